@@ -65,6 +65,12 @@ class SecurityClient(AsyncClosable):
         """
         await self.__security.RemoveBond(facade_pb2.RemoveBondRequest(address=address))
 
+    async def force_disconnect(self, address, hci_reason):
+        """
+        Disconnects the ACL to a given address and reports it with the given HCI reason
+        """
+        await self.__security.ForceDisconnect(facade_pb2.ForceDisconnectRequest(address=address, hci_reason=hci_reason))
+
     async def generate_local_oob_data(self, transport):
         await self.__security.GenerateLocalOobData(facade_pb2.GenerateOobDataRequest(transport=transport))
         future = await self.__adapter._listen_for_event(facade_pb2.EventType.GENERATE_LOCAL_OOB_DATA)
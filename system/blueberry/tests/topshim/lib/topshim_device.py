@@ -181,6 +181,12 @@ class TopshimDevice(AsyncClosable):
         """
         self.__post(self.__security.remove_bond(address))
 
+    def force_disconnect(self, address, hci_reason):
+        """
+        Disconnects the ACL to a given address as if it was lost with the given HCI reason.
+        """
+        self.__post(self.__security.force_disconnect(address, hci_reason))
+
     async def __bond_change_waiter(self, f):
         params = await f
         state, address = params["bond_state"].data[0], params["address"].data[0]
@@ -417,6 +417,7 @@ static tBTM_STATUS bta_dm_sp_cback(tBTM_SP_EVT event, tBTM_SP_EVT_DATA* p_data)
   switch (event) {
     case BTM_SP_IO_REQ_EVT:
       /* translate auth_req */
+      btif_dm_set_io_cap_for_io_req(&p_data->io_req.io_cap);
       btif_dm_set_oob_for_io_req(&p_data->io_req.oob_data);
       btif_dm_proc_io_req(&p_data->io_req.auth_req, p_data->io_req.is_orig);
       log::verbose("io mitm: {} oob_data:{}", p_data->io_req.auth_req, p_data->io_req.oob_data);
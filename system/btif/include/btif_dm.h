@@ -25,6 +25,7 @@
 #include "internal_include/bt_target.h"
 #include "internal_include/bte_appl.h"
 #include "stack/include/acl_api_types.h"
+#include "stack/include/hci_error_code.h"
 #include "types/raw_address.h"
 
 /*******************************************************************************
@@ -84,6 +85,12 @@ void btif_dm_proc_io_rsp(const RawAddress& bd_addr, tBTM_IO_CAP io_cap, tBTM_OOB
  */
 DEV_CLASS btif_dm_get_local_class_of_device();
 
+/**
+ * Local IO capability used for BR/EDR pairing. Overrides the default until cleanup.
+ */
+void btif_dm_set_local_io_caps(tBTM_IO_CAP io_cap);
+void btif_dm_set_io_cap_for_io_req(tBTM_IO_CAP* p_io_cap);
+
 /**
  * Out-of-band functions
  */
@@ -105,6 +112,10 @@ void btif_dm_clear_event_mask();
 void btif_dm_clear_filter_accept_list();
 void btif_dm_disconnect_all_acls();
 
+// Test hook: reports |reason| instead of the actual disconnect reason the next time the ACL to
+// |bd_addr| goes down. The override is dropped if a new ACL to |bd_addr| comes up first.
+void btif_dm_override_next_disc_reason(const RawAddress& bd_addr, tHCI_REASON reason);
+
 void btif_dm_le_rand(bluetooth::hci::LeRandCallback callback);
 void btif_dm_set_event_filter_connection_setup_all_devices();
 void btif_dm_allow_wake_by_hid(std::vector<RawAddress> classic_addrs,
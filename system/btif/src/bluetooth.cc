@@ -570,6 +570,9 @@ static int set_adapter_property(const bt_property_t* property) {
     case BT_PROPERTY_BDNAME:
     case BT_PROPERTY_ADAPTER_DISCOVERABLE_TIMEOUT:
     case BT_PROPERTY_CLASS_OF_DEVICE:
+#ifdef TARGET_FLOSS
+    case BT_PROPERTY_LOCAL_IO_CAPS:
+#endif
       break;
     default:
       return BT_STATUS_UNHANDLED;
@@ -581,6 +581,16 @@ void btif_set_adapter_property(bt_property_t* property) {
          if required */
       btif_core_storage_adapter_write(property);
     } break;
+
+#ifdef TARGET_FLOSS
+    case BT_PROPERTY_LOCAL_IO_CAPS: {
+      if (property->len != sizeof(bt_io_cap_t)) {
+        log::warn("Invalid local io caps length {}", property->len);
+        break;
+      }
+      btif_dm_set_local_io_caps(static_cast<tBTM_IO_CAP>(*(bt_io_cap_t*)property->val));
+    } break;
+#endif
     default:
       break;
   }
@@ -45,6 +45,7 @@
 #include <time.h>
 #include <unistd.h>
 
+#include <map>
 #include <mutex>
 #include <optional>
 
@@ -273,6 +274,11 @@ static btif_dm_metadata_cb_t metadata_cb{.le_audio_cache{40}};
 static void btif_dm_cb_create_bond(const RawAddress bd_addr, tBT_TRANSPORT transport);
 static void btif_dm_cb_create_bond_le(const RawAddress bd_addr, tBLE_ADDR_TYPE addr_type);
 static btif_dm_local_key_cb_t ble_local_key_cb;
+// Disconnect reasons to report for the next link down of each device, set by tests. Dropped when
+// a new link to the device comes up.
+static std::map<RawAddress, tHCI_REASON> disc_reason_overrides;
+// Local IO capability to use for BR/EDR pairing instead of the stack default.
+static std::optional<tBTM_IO_CAP> local_io_caps;
 static void btif_dm_ble_passkey_notif_evt(tBTA_DM_SP_KEY_NOTIF* p_ssp_key_notif);
 static void btif_dm_ble_auth_cmpl_evt(tBTA_DM_AUTH_CMPL* p_auth_cmpl);
 static void btif_dm_ble_passkey_req_evt(tBTA_DM_PIN_REQ* p_pin_req);
@@ -314,6 +320,7 @@ void btif_dm_cleanup(void) {
     uid_set_destroy(uid_set);
     uid_set = NULL;
   }
+  local_io_caps.reset();
 }
 
 static bt_status_t btif_in_execute_service_request(tBTA_SERVICE_ID service_id, bool b_enable) {
@@ -1512,6 +1519,16 @@ static void btif_dm_search_devices_evt(tBTA_DM_SEARCH_EVT event, tBTA_DM_SEARCH*
         status = btif_storage_set_remote_addr_type(&bdaddr, addr_type);
         ASSERTC(status == BT_STATUS_SUCCESS, "failed to save remote addr type (inquiry)", status);
 
+#if TARGET_FLOSS
+        // The raw EIR is only reported, not stored, to help debugging name resolution and
+        // service discovery issues.
+        if (p_search_data->inq_res.p_eir && p_search_data->inq_res.eir_len > 0) {
+          bt_properties.push_back(bt_property_t{BT_PROPERTY_REMOTE_EIR,
+                                                static_cast<int>(p_search_data->inq_res.eir_len),
+                                                (void*)p_search_data->inq_res.p_eir});
+        }
+#endif
+
         bool restrict_report =
                 osi_property_get_bool("bluetooth.restrict_discovered_device.enabled", false);
         if (restrict_report && p_search_data->inq_res.device_type == BT_DEVICE_TYPE_BLE &&
@@ -1732,6 +1749,10 @@ static void btif_on_service_discovery_results(RawAddress bd_addr,
     /* Send the event to the BTIF */
     GetInterfaceToProfiles()->events->invoke_remote_device_properties_cb(BT_STATUS_SUCCESS, bd_addr,
                                                                          1, &prop);
+  } else if (result != BTA_SUCCESS) {
+    /* Nothing to report, but let the upper layers know the service discovery is over */
+    GetInterfaceToProfiles()->events->invoke_remote_device_properties_cb(BT_STATUS_FAIL, bd_addr, 0,
+                                                                         nullptr);
   }
 }
 
@@ -2242,6 +2263,9 @@ void btif_dm_acl_evt(tBTA_DM_ACL_EVT event, tBTA_DM_ACL* p_data) {
 
       btif_update_remote_version_property(&bd_addr);
 
+      // An override left over from a disconnect that never completed must not apply to this link.
+      disc_reason_overrides.erase(bd_addr);
+
       GetInterfaceToProfiles()->events->invoke_acl_state_changed_cb(
               BT_STATUS_SUCCESS, bd_addr, BT_ACL_STATE_CONNECTED,
               (int)p_data->link_up.transport_link_type, HCI_SUCCESS,
@@ -2270,8 +2294,17 @@ void btif_dm_acl_evt(tBTA_DM_ACL_EVT event, tBTA_DM_ACL* p_data) {
       btm_set_bond_type_dev(p_data->link_down.bd_addr, BOND_TYPE_UNKNOWN);
       GetInterfaceToProfiles()->onLinkDown(bd_addr, p_data->link_down.transport_link_type);
 
+      tHCI_REASON reason = btm_get_acl_disc_reason_code();
+      auto reason_override = disc_reason_overrides.find(bd_addr);
+      if (reason_override != disc_reason_overrides.end()) {
+        log::info("Overriding disconnect reason of {} with {}", bd_addr,
+                  hci_reason_code_text(reason_override->second));
+        reason = reason_override->second;
+        disc_reason_overrides.erase(reason_override);
+      }
+
       bt_conn_direction_t direction;
-      switch (btm_get_acl_disc_reason_code()) {
+      switch (reason) {
         case HCI_ERR_PEER_USER:
         case HCI_ERR_REMOTE_LOW_RESOURCE:
         case HCI_ERR_REMOTE_POWER_OFF:
@@ -2286,14 +2319,12 @@ void btif_dm_acl_evt(tBTA_DM_ACL_EVT event, tBTA_DM_ACL* p_data) {
       }
       GetInterfaceToProfiles()->events->invoke_acl_state_changed_cb(
               BT_STATUS_SUCCESS, bd_addr, BT_ACL_STATE_DISCONNECTED,
-              (int)p_data->link_down.transport_link_type,
-              static_cast<bt_hci_error_code_t>(btm_get_acl_disc_reason_code()), direction,
-              INVALID_ACL_HANDLE);
+              (int)p_data->link_down.transport_link_type, static_cast<bt_hci_error_code_t>(reason),
+              direction, INVALID_ACL_HANDLE);
       log::debug(
               "Sent BT_ACL_STATE_DISCONNECTED upward as ACL link down event "
               "device:{} reason:{}",
-              bd_addr,
-              hci_reason_code_text(static_cast<tHCI_REASON>(btm_get_acl_disc_reason_code())));
+              bd_addr, hci_reason_code_text(reason));
     } break;
     case BTA_DM_LE_FEATURES_READ:
       btif_get_adapter_property(BT_PROPERTY_LOCAL_LE_FEATURES);
@@ -2927,6 +2958,22 @@ void btif_dm_proc_io_rsp(const RawAddress& /* bd_addr */, tBTM_IO_CAP io_cap,
   }
 }
 
+void btif_dm_set_local_io_caps(tBTM_IO_CAP io_cap) {
+  if (io_cap >= BTM_IO_CAP_MAX) {
+    log::warn("Ignoring invalid local io caps {}", io_cap);
+    return;
+  }
+  log::info("Local io caps set to {}", io_cap);
+  local_io_caps = io_cap;
+}
+
+void btif_dm_set_io_cap_for_io_req(tBTM_IO_CAP* p_io_cap) {
+  if (local_io_caps.has_value()) {
+    *p_io_cap = local_io_caps.value();
+  }
+  log::verbose("*p_io_cap={}", *p_io_cap);
+}
+
 void btif_dm_set_oob_for_io_req(tBTM_OOB_DATA* p_has_oob_data) {
   if (is_empty_128bit(oob_cb.p192_data.c)) {
     *p_has_oob_data = false;
@@ -3930,6 +3977,10 @@ void btif_dm_clear_filter_accept_list() { BTA_DmClearFilterAcceptList(); }
 
 void btif_dm_disconnect_all_acls() { BTA_DmDisconnectAllAcls(); }
 
+void btif_dm_override_next_disc_reason(const RawAddress& bd_addr, tHCI_REASON reason) {
+  disc_reason_overrides[bd_addr] = reason;
+}
+
 void btif_dm_le_rand(bluetooth::hci::LeRandCallback callback) { BTA_DmLeRand(std::move(callback)); }
 
 void btif_dm_set_event_filter_connection_setup_all_devices() {
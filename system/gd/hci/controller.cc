@@ -1354,6 +1354,9 @@ LOCAL_FEATURE_ACCESSOR(SupportsSimultaneousLeBrEdr, 0, 49)
 LOCAL_FEATURE_ACCESSOR(SupportsSimplePairing, 0, 51)
 LOCAL_FEATURE_ACCESSOR(SupportsNonFlushablePb, 0, 54)
 
+// The LMP feature bit is "BR/EDR Not Supported", set by LE only controllers.
+bool Controller::SupportsBrEdr() const { return !(GetLocalFeatures(0) & BIT(53)); }
+
 LOCAL_FEATURE_ACCESSOR(SupportsSecureConnections, 2, 8)
 
 #define LOCAL_LE_FEATURE_ACCESSOR(name, bit) \
@@ -54,6 +54,7 @@ public:
   virtual bool SupportsSimplePairing() const override;
   virtual bool SupportsSecureConnections() const override;
   virtual bool SupportsSimultaneousLeBrEdr() const override;
+  virtual bool SupportsBrEdr() const override;
   virtual bool SupportsInterlacedInquiryScan() const override;
   virtual bool SupportsRssiWithInquiryResults() const override;
   virtual bool SupportsExtendedInquiryResponse() const override;
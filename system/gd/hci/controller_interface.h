@@ -49,6 +49,7 @@ public:
   virtual bool SupportsSimplePairing() const = 0;
   virtual bool SupportsSecureConnections() const = 0;
   virtual bool SupportsSimultaneousLeBrEdr() const = 0;
+  virtual bool SupportsBrEdr() const = 0;
   virtual bool SupportsInterlacedInquiryScan() const = 0;
   virtual bool SupportsRssiWithInquiryResults() const = 0;
   virtual bool SupportsExtendedInquiryResponse() const = 0;
@@ -40,6 +40,7 @@ public:
   MOCK_METHOD(bool, SupportsSimplePairing, (), (const));
   MOCK_METHOD(bool, SupportsSecureConnections, (), (const));
   MOCK_METHOD(bool, SupportsSimultaneousLeBrEdr, (), (const));
+  MOCK_METHOD(bool, SupportsBrEdr, (), (const));
   MOCK_METHOD(bool, SupportsInterlacedInquiryScan, (), (const));
   MOCK_METHOD(bool, SupportsRssiWithInquiryResults, (), (const));
   MOCK_METHOD(bool, SupportsExtendedInquiryResponse, (), (const));
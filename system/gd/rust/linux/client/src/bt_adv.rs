@@ -9,12 +9,95 @@ use bt_topshim::profiles::gatt::{LeDiscMode, LePhy};
 use btstack::bluetooth_adv::{AdvertiseData, AdvertiserId, AdvertisingSetParameters};
 use btstack::bluetooth_gatt::IBluetoothGatt;
 
+/// TX power level value that lets the controller pick the power on its own.
+const TX_POWER_NO_PREFERENCE: i32 = 0x7f;
+
+/// Longest advertising duration the controller accepts, in ms.
+const MAX_ADV_DURATION_MS: i32 = 655350;
+
+/// Largest number of extended advertising events the controller accepts.
+const MAX_EXT_ADV_EVENTS: i32 = 255;
+
+/// Largest legacy advertising data, in bytes.
+const MAX_LEGACY_ADV_DATA_LEN: usize = 31;
+
+/// Largest extended advertising data the spec allows, in bytes.
+const MAX_EXT_ADV_DATA_LEN: usize = 1650;
+
+/// Checks that |data| is a well-formed sequence of AD elements, each made of a length byte
+/// followed by that many bytes of type and value, and that it fits in legacy or extended
+/// advertising. A zero length ends the significant part of the data.
+pub(crate) fn validate_raw_adv_data(data: &[u8], is_legacy: bool) -> Result<(), String> {
+    let max_len = if is_legacy { MAX_LEGACY_ADV_DATA_LEN } else { MAX_EXT_ADV_DATA_LEN };
+    if data.len() > max_len {
+        return Err(format!(
+            "Advertising data is {} bytes, exceeding the {} bytes allowed for {} advertising",
+            data.len(),
+            max_len,
+            if is_legacy { "legacy" } else { "extended" }
+        ));
+    }
+
+    let mut offset = 0;
+    while offset < data.len() {
+        let len = data[offset] as usize;
+        if len == 0 {
+            break;
+        }
+        if offset + 1 + len > data.len() {
+            return Err(format!(
+                "AD element at offset {} claims length {} exceeding buffer",
+                offset, len
+            ));
+        }
+        offset += 1 + len;
+    }
+    Ok(())
+}
+
+/// Standard advertising TX power levels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum AdvTxPower {
+    UltraLow,
+    Low,
+    Medium,
+    High,
+}
+
+impl AdvTxPower {
+    pub(crate) fn from_level_name(level: &str) -> Option<Self> {
+        match level {
+            "ultra-low" => Some(AdvTxPower::UltraLow),
+            "low" => Some(AdvTxPower::Low),
+            "medium" => Some(AdvTxPower::Medium),
+            "high" => Some(AdvTxPower::High),
+            _ => None,
+        }
+    }
+
+    /// Returns the requested power in dBm.
+    pub(crate) fn to_dbm(self) -> i32 {
+        match self {
+            AdvTxPower::UltraLow => -21,
+            AdvTxPower::Low => -15,
+            AdvTxPower::Medium => -7,
+            AdvTxPower::High => 1,
+        }
+    }
+}
+
 /// Avertisement parameter and data for a BLE advertising set.
 #[derive(Debug, Clone)]
 pub(crate) struct AdvSet {
     /// ID for the advertising set if it's being started successfully, None otherwise.
     pub(crate) adv_id: Option<AdvertiserId>,
 
+    /// Name given to the set by the user, if any, to refer to it instead of its ID.
+    pub(crate) name: Option<String>,
+
+    /// Whether the set is currently advertising.
+    pub(crate) enabled: bool,
+
     /// Advertising parameters.
     pub(crate) params: AdvertisingSetParameters,
 
@@ -23,10 +106,19 @@ pub(crate) struct AdvSet {
 
     /// Scan response data.
     pub(crate) scan_rsp: AdvertiseData,
+
+    /// Advertising duration, in 10 ms unit. 0 means no timeout.
+    pub(crate) duration: i32,
+
+    /// Maximum number of extended advertising events before stopping. 0 means no limit.
+    pub(crate) max_ext_adv_events: i32,
+
+    /// Raw advertising data last set, which replaces |data|.
+    pub(crate) raw_data: Option<Vec<u8>>,
 }
 
 impl AdvSet {
-    pub(crate) fn new(is_legacy: bool) -> Self {
+    pub(crate) fn new(is_legacy: bool, name: Option<String>) -> Self {
         let params = AdvertisingSetParameters {
             discoverable: LeDiscMode::GeneralDiscoverable,
             connectable: false,
@@ -37,8 +129,11 @@ impl AdvSet {
             primary_phy: LePhy::Phy1m,
             secondary_phy: LePhy::Phy1m,
             interval: 100,
-            tx_power_level: 0x7f, // no preference
-            own_address_type: 1,  // random
+            tx_power_level: TX_POWER_NO_PREFERENCE,
+            own_address_type: 1, // random
+            directed_mode: None,
+            peer_address: None,
+            peer_address_type: None,
         };
 
         let data = AdvertiseData {
@@ -67,7 +162,70 @@ impl AdvSet {
             include_device_name: false,
         };
 
-        AdvSet { adv_id: None, params, data, scan_rsp }
+        AdvSet {
+            adv_id: None,
+            name,
+            enabled: false,
+            params,
+            data,
+            scan_rsp,
+            duration: 0,
+            max_ext_adv_events: 0,
+            raw_data: None,
+        }
+    }
+
+    /// Returns the ID of the started set referred to by |key|, either its ID or its name.
+    pub(crate) fn find_adv_id(adv_sets: &HashMap<i32, AdvSet>, key: &str) -> Option<AdvertiserId> {
+        let id = key.parse::<AdvertiserId>().ok();
+        adv_sets
+            .values()
+            .filter_map(|s| s.adv_id.map(|adv_id| (adv_id, s)))
+            .find(|(adv_id, s)| Some(*adv_id) == id || s.name.as_deref() == Some(key))
+            .map(|(adv_id, _)| adv_id)
+    }
+
+    /// Describes the set and its parameters on a single line.
+    pub(crate) fn describe(&self) -> String {
+        format!(
+            "{} name: {}, {}, {}, {}, {}, interval: {} ms, tx power: {}, data: {}",
+            self.adv_id.map_or(String::from("(starting)"), |id| id.to_string()),
+            self.name.as_deref().unwrap_or("-"),
+            if self.enabled { "enabled" } else { "disabled" },
+            if self.params.is_legacy { "legacy" } else { "extended" },
+            if self.params.connectable { "connectable" } else { "non-connectable" },
+            if self.params.scannable { "scannable" } else { "non-scannable" },
+            self.params.interval * 5 / 8,
+            self.params.tx_power_level,
+            self.raw_data.as_ref().map_or(String::from("default"), hex::encode),
+        )
+    }
+
+    /// Sets the limits after which the advertising set stops on its own. The duration is
+    /// rounded up to the 10 ms unit the controller uses.
+    pub(crate) fn set_limits(&mut self, duration_ms: i32, max_events: i32) -> Result<(), String> {
+        if !(0..=MAX_ADV_DURATION_MS).contains(&duration_ms) {
+            return Err(format!("Duration must be within 0 and {} ms", MAX_ADV_DURATION_MS));
+        }
+        if !(0..=MAX_EXT_ADV_EVENTS).contains(&max_events) {
+            return Err(format!("Max events must be within 0 and {}", MAX_EXT_ADV_EVENTS));
+        }
+        self.duration = (duration_ms + 9) / 10;
+        self.max_ext_adv_events = max_events;
+        Ok(())
+    }
+
+    /// Reports if the controller selected a TX power other than the one requested, e.g. when
+    /// the requested level is not supported and got clamped.
+    pub(crate) fn check_selected_tx_power(&self, tx_power: i32) {
+        let requested = self.params.tx_power_level;
+        if requested != TX_POWER_NO_PREFERENCE && requested != tx_power {
+            print_info!(
+                "Requested TX power {} dBm is not supported, controller selected {} dBm",
+                requested,
+                tx_power
+            );
+        }
     }
 
     pub(crate) fn start(context: Arc<Mutex<ClientContext>>, s: AdvSet, callback_id: u32) {
@@ -79,14 +237,23 @@ impl AdvSet {
             None,
             None,
             None,
-            0,
-            0,
+            s.duration,
+            s.max_ext_adv_events,
             callback_id,
         );
         print_info!("Starting advertising set for reg_id = {}", reg_id);
         context.adv_sets.insert(reg_id, s);
     }
 
+    /// Stops and unregisters the advertising set |adv_id|.
+    pub(crate) fn stop(context: Arc<Mutex<ClientContext>>, adv_id: AdvertiserId) {
+        let mut context = context.lock().unwrap();
+
+        print_info!("Stopping advertising set {}", adv_id);
+        context.gatt_dbus.as_mut().unwrap().stop_advertising_set(adv_id);
+        context.adv_sets.retain(|_, s| s.adv_id != Some(adv_id));
+    }
+
     pub(crate) fn stop_all(context: Arc<Mutex<ClientContext>>) {
         let mut context = context.lock().unwrap();
 
@@ -1,5 +1,83 @@
-use bt_topshim::btif::BtTransport;
+use std::collections::{HashMap, HashSet};
+
+use bt_topshim::btif::{BtTransport, RawAddress, Uuid};
 use bt_topshim::profiles::gatt::LePhy;
+use btstack::bluetooth_gatt::{
+    BluetoothGattCharacteristic, BluetoothGattService, GattDbElementType, ScanFilter,
+    ScanFilterCondition, ScanFilterPattern,
+};
+use btstack::uuid::UuidHelper;
+
+// Advertising data types carrying lists of service UUIDs, by UUID width.
+const SERVICE_UUID_16_AD_TYPES: [u8; 2] = [0x02, 0x03];
+const SERVICE_UUID_32_AD_TYPES: [u8; 2] = [0x04, 0x05];
+const SERVICE_UUID_128_AD_TYPES: [u8; 2] = [0x06, 0x07];
+
+// Largest AD structure data in legacy advertising, excluding its length and type bytes.
+const MAX_AD_DATA_LEN: usize = 29;
+
+// Room left for patterns in the 255-byte parameters of the MSFT LE Monitor Advertisement
+// command, after the subcommand opcode, RSSI parameters, condition type and pattern count.
+const MAX_SCAN_FILTER_PATTERNS_LEN: usize = 255 - 7;
+
+// Bytes taken by a pattern besides its content: length, AD type and start position.
+const SCAN_FILTER_PATTERN_OVERHEAD: usize = 3;
+
+// Names of commonly seen GATT services, characteristics and descriptors, by 16-bit UUID.
+const GATT_UUID_NAMES: &[(u16, &str)] = &[
+    (0x1800, "Generic Access"),
+    (0x1801, "Generic Attribute"),
+    (0x180A, "Device Information"),
+    (0x180F, "Battery Service"),
+    (0x1812, "Human Interface Device"),
+    (0x1813, "Scan Parameters"),
+    (0x2800, "Primary Service"),
+    (0x2801, "Secondary Service"),
+    (0x2802, "Include"),
+    (0x2803, "Characteristic"),
+    (0x2900, "Characteristic Extended Properties"),
+    (0x2901, "Characteristic User Description"),
+    (0x2902, "Client Characteristic Configuration"),
+    (0x2903, "Server Characteristic Configuration"),
+    (0x2904, "Characteristic Presentation Format"),
+    (0x2908, "Report Reference"),
+    (0x2A00, "Device Name"),
+    (0x2A01, "Appearance"),
+    (0x2A04, "Peripheral Preferred Connection Parameters"),
+    (0x2A05, "Service Changed"),
+    (0x2A19, "Battery Level"),
+    (0x2A22, "Boot Keyboard Input Report"),
+    (0x2A24, "Model Number String"),
+    (0x2A25, "Serial Number String"),
+    (0x2A26, "Firmware Revision String"),
+    (0x2A27, "Hardware Revision String"),
+    (0x2A28, "Software Revision String"),
+    (0x2A29, "Manufacturer Name String"),
+    (0x2A32, "Boot Keyboard Output Report"),
+    (0x2A33, "Boot Mouse Input Report"),
+    (0x2A4A, "HID Information"),
+    (0x2A4B, "Report Map"),
+    (0x2A4C, "HID Control Point"),
+    (0x2A4D, "Report"),
+    (0x2A4E, "Protocol Mode"),
+    (0x2A50, "PnP ID"),
+    (0x2AA6, "Central Address Resolution"),
+    (0x2B29, "Client Supported Features"),
+    (0x2B2A, "Database Hash"),
+    (0x2B3A, "Server Supported Features"),
+];
+
+// Names of the characteristic property bits, in bit order.
+const CHARACTERISTIC_PROPERTY_NAMES: [(i32, &str); 8] = [
+    (BluetoothGattCharacteristic::PROPERTY_BROADCAST, "Broadcast"),
+    (BluetoothGattCharacteristic::PROPERTY_READ, "Read"),
+    (BluetoothGattCharacteristic::PROPERTY_WRITE_NO_RESPONSE, "WriteNoRsp"),
+    (BluetoothGattCharacteristic::PROPERTY_WRITE, "Write"),
+    (BluetoothGattCharacteristic::PROPERTY_NOTIFY, "Notify"),
+    (BluetoothGattCharacteristic::PROPERTY_INDICATE, "Indicate"),
+    (BluetoothGattCharacteristic::PROPERTY_SIGNED_WRITE, "SignedWrite"),
+    (BluetoothGattCharacteristic::PROPERTY_EXTENDED_PROPS, "ExtendedProps"),
+];
 
 #[repr(i32)]
 #[derive(Debug, Copy, Clone)]
@@ -20,8 +98,12 @@ impl From<AuthReq> for i32 {
 
 /// User preferenece of GATT operations
 pub(crate) struct GattClientContext {
-    /// If set, the registered GATT client id. None otherwise.
-    pub(crate) client_id: Option<i32>,
+    /// Registered GATT client ids, keyed by the user supplied tag.
+    clients: HashMap<String, i32>,
+    /// Tag of the most recently registered client. Used when no tag is given.
+    last_registered_client: Option<String>,
+    /// Number of client registrations requested so far. Used to derive unique app UUIDs.
+    pub(crate) num_client_registrations: u32,
     /// Type of authentication requirement
     pub(crate) auth_req: AuthReq,
     /// Is connection going to be directed?
@@ -32,23 +114,58 @@ pub(crate) struct GattClientContext {
     pub(crate) connect_opportunistic: bool,
     /// Type of connect phy
     pub(crate) connect_phy: LePhy,
+    /// Services found by the most recent discovery on each remote device.
+    discovered_services: HashMap<RawAddress, Vec<BluetoothGattService>>,
 }
 
 impl GattClientContext {
     pub(crate) fn new() -> Self {
         GattClientContext {
-            client_id: None,
+            clients: HashMap::new(),
+            last_registered_client: None,
+            num_client_registrations: 0,
             auth_req: AuthReq::NoEnc,
             is_connect_direct: false,
             connect_transport: BtTransport::Le,
             connect_opportunistic: false,
             connect_phy: LePhy::Phy1m,
+            discovered_services: HashMap::new(),
         }
     }
 
     pub(crate) fn get_auth_req(&self) -> AuthReq {
         self.auth_req
     }
+
+    /// Records the client id registered for `tag`, which becomes the default client.
+    pub(crate) fn add_client(&mut self, tag: String, client_id: i32) {
+        self.clients.insert(tag.clone(), client_id);
+        self.last_registered_client = Some(tag);
+    }
+
+    /// Gets the client id registered for `tag`, or for the most recently registered client if no
+    /// tag is given.
+    pub(crate) fn get_client_id(&self, tag: Option<&str>) -> Option<i32> {
+        let tag = tag.or(self.last_registered_client.as_deref())?;
+        self.clients.get(tag).copied()
+    }
+
+    /// Caches the services discovered on `addr`, replacing those of any previous discovery.
+    pub(crate) fn set_discovered_services(
+        &mut self,
+        addr: RawAddress,
+        services: Vec<BluetoothGattService>,
+    ) {
+        self.discovered_services.insert(addr, services);
+    }
+
+    /// Gets the services discovered on `addr`, if a discovery has completed on it.
+    pub(crate) fn get_discovered_services(
+        &self,
+        addr: &RawAddress,
+    ) -> Option<&Vec<BluetoothGattService>> {
+        self.discovered_services.get(addr)
+    }
 }
 
 /// User preference of GATT server operations
@@ -57,10 +174,160 @@ pub(crate) struct GattServerContext {
     pub(crate) is_connect_direct: bool,
     /// Transport of connection
     pub(crate) connect_transport: BtTransport,
+    /// Clients connected to each server, in the order they connected.
+    connected_clients: HashMap<i32, Vec<RawAddress>>,
 }
 
 impl GattServerContext {
     pub(crate) fn new() -> Self {
-        GattServerContext { is_connect_direct: false, connect_transport: BtTransport::Le }
+        GattServerContext {
+            is_connect_direct: false,
+            connect_transport: BtTransport::Le,
+            connected_clients: HashMap::new(),
+        }
+    }
+
+    /// Tracks the connection state of `addr` to the server `server_id`.
+    pub(crate) fn set_client_connected(
+        &mut self,
+        server_id: i32,
+        addr: RawAddress,
+        connected: bool,
+    ) {
+        let clients = self.connected_clients.entry(server_id).or_default();
+        clients.retain(|&client| client != addr);
+        if connected {
+            clients.push(addr);
+        }
+    }
+
+    /// Gets the client most recently connected to the server `server_id`, if any is connected.
+    pub(crate) fn get_target_client(&self, server_id: i32) -> Option<RawAddress> {
+        self.connected_clients.get(&server_id)?.last().copied()
     }
 }
+
+/// Builds a scan filter matching advertisements that contain any of the given service UUIDs. An
+/// empty set yields a filter without patterns.
+///
+/// Each UUID is matched against both the incomplete and complete service UUID lists of its
+/// shortest representation, at every position of the list it can occupy. The resulting patterns
+/// are "OR"-ed by the scanner. Fails if the patterns don't fit in a single monitor command.
+/// Controllers without filter offload report all advertisements, so results are also filtered in
+/// |ScannerCallback|.
+pub(crate) fn build_service_uuid_scan_filter(uuids: &HashSet<Uuid>) -> Result<ScanFilter, String> {
+    let patterns: Vec<ScanFilterPattern> = uuids
+        .iter()
+        .flat_map(|uuid| {
+            let slice = uuid.get_shortest_slice();
+            let ad_types = match slice.len() {
+                2 => SERVICE_UUID_16_AD_TYPES,
+                4 => SERVICE_UUID_32_AD_TYPES,
+                _ => SERVICE_UUID_128_AD_TYPES,
+            };
+            // Advertising data carries UUIDs in little endian.
+            let content: Vec<u8> = slice.iter().rev().cloned().collect();
+            let positions = (0..=MAX_AD_DATA_LEN - content.len()).step_by(content.len());
+            ad_types
+                .iter()
+                .flat_map(|&ad_type| {
+                    positions.clone().map(move |position| (ad_type, position as u8))
+                })
+                .map(|(ad_type, start_position)| ScanFilterPattern {
+                    start_position,
+                    ad_type,
+                    content: content.clone(),
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    let patterns_len: usize =
+        patterns.iter().map(|p| SCAN_FILTER_PATTERN_OVERHEAD + p.content.len()).sum();
+    if patterns_len > MAX_SCAN_FILTER_PATTERNS_LEN {
+        return Err(format!(
+            "Scan filter needs {} bytes of patterns, at most {} fit",
+            patterns_len, MAX_SCAN_FILTER_PATTERNS_LEN
+        ));
+    }
+
+    Ok(ScanFilter {
+        rssi_high_threshold: 0,
+        rssi_low_threshold: 0,
+        rssi_low_timeout: 0,
+        rssi_sampling_period: 0,
+        condition: ScanFilterCondition::Patterns(patterns),
+    })
+}
+
+/// Formats a GATT attribute UUID, appending its name if it is a known GATT or profile UUID.
+fn gatt_uuid_to_string(uuid: &Uuid) -> String {
+    let name = match uuid.get_shortest_slice() {
+        [hi, lo] => {
+            let short = u16::from_be_bytes([*hi, *lo]);
+            GATT_UUID_NAMES.iter().find(|(u, _)| *u == short).map(|(_, name)| *name)
+        }
+        _ => None,
+    };
+
+    match name {
+        Some(name) => format!("{}: {}", uuid, name),
+        None => UuidHelper::known_uuid_to_string(uuid),
+    }
+}
+
+fn characteristic_properties_to_string(properties: i32) -> String {
+    CHARACTERISTIC_PROPERTY_NAMES
+        .iter()
+        .filter(|(bit, _)| properties & bit != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join("|")
+}
+
+/// Renders a GATT database as an indented tree of services, included services, characteristics
+/// and descriptors, one attribute per line.
+pub(crate) fn format_gatt_db(services: &[BluetoothGattService]) -> Vec<String> {
+    let mut lines = vec![];
+
+    for service in services {
+        let kind = if service.service_type == GattDbElementType::SecondaryService as i32 {
+            "Secondary Service"
+        } else {
+            "Primary Service"
+        };
+        lines.push(format!(
+            "{} 0x{:04X} {}",
+            kind,
+            service.instance_id,
+            gatt_uuid_to_string(&service.uuid)
+        ));
+
+        for included in &service.included_services {
+            lines.push(format!(
+                "  Included Service 0x{:04X} {}",
+                included.instance_id,
+                gatt_uuid_to_string(&included.uuid)
+            ));
+        }
+
+        for characteristic in &service.characteristics {
+            lines.push(format!(
+                "  Characteristic 0x{:04X} {} [{}]",
+                characteristic.instance_id,
+                gatt_uuid_to_string(&characteristic.uuid),
+                characteristic_properties_to_string(characteristic.properties)
+            ));
+
+            for descriptor in &characteristic.descriptors {
+                lines.push(format!(
+                    "    Descriptor 0x{:04X} {}",
+                    descriptor.instance_id,
+                    gatt_uuid_to_string(&descriptor.uuid)
+                ));
+            }
+        }
+    }
+
+    lines
+}
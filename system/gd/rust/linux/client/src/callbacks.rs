@@ -1,4 +1,4 @@
-use crate::command_handler::SocketSchedule;
+use crate::command_handler::{CommandExitCode, SocketSchedule};
 use crate::dbus_iface::{
     export_admin_policy_callback_dbus_intf, export_advertising_set_callback_dbus_intf,
     export_battery_manager_callback_dbus_intf, export_bluetooth_callback_dbus_intf,
@@ -8,9 +8,12 @@ use crate::dbus_iface::{
     export_qa_callback_dbus_intf, export_scanner_callback_dbus_intf,
     export_socket_callback_dbus_intf, export_suspend_callback_dbus_intf,
 };
+use crate::pairing_events::PairingEvent;
 use crate::{console_red, console_yellow, print_error, print_info};
 use crate::{ClientContext, GattRequest};
-use bt_topshim::btif::{BtBondState, BtPropertyType, BtSspVariant, BtStatus, RawAddress, Uuid};
+use bt_topshim::btif::{
+    BtBondState, BtHciErrorCode, BtPropertyType, BtSspVariant, BtStatus, RawAddress, Uuid,
+};
 use bt_topshim::profiles::gatt::{AdvertisingStatus, GattStatus, LePhy};
 use bt_topshim::profiles::hfp::HfpCodecId;
 use bt_topshim::profiles::le_audio::{
@@ -18,9 +21,11 @@ use bt_topshim::profiles::le_audio::{
     BtLeAudioUnicastMonitorModeStatus,
 };
 use bt_topshim::profiles::sdp::BtSdpRecord;
+use bt_topshim::profiles::socket::SocketType;
 use btstack::battery_manager::{BatterySet, IBatteryManagerCallback};
 use btstack::bluetooth::{
-    BluetoothDevice, IBluetooth, IBluetoothCallback, IBluetoothConnectionCallback,
+    BluetoothDevice, ConnectionQuality, IBluetooth, IBluetoothCallback,
+    IBluetoothConnectionCallback,
 };
 use btstack::bluetooth_admin::{IBluetoothAdminPolicyCallback, PolicyEffect};
 use btstack::bluetooth_adv::IAdvertisingSetCallback;
@@ -31,7 +36,7 @@ use btstack::bluetooth_gatt::{
 use btstack::bluetooth_media::{
     BluetoothAudioDevice, IBluetoothMediaCallback, IBluetoothTelephonyCallback,
 };
-use btstack::bluetooth_qa::IBluetoothQACallback;
+use btstack::bluetooth_qa::{IBluetoothQACallback, LinkErrorCounters, LinkPowerMode};
 use btstack::socket_manager::{
     BluetoothServerSocket, BluetoothSocket, IBluetoothSocketManager,
     IBluetoothSocketManagerCallbacks, SocketId,
@@ -200,13 +205,25 @@ impl IBluetoothCallback for BtCallback {
         print_info!("Discovering: {}", discovering);
     }
 
+    fn on_discovery_timeout(&mut self) {
+        print_info!("Discovery stopped after its timeout");
+    }
+
     fn on_ssp_request(
         &mut self,
         remote_device: BluetoothDevice,
-        _cod: u32,
+        cod: u32,
         variant: BtSspVariant,
         passkey: u32,
     ) {
+        self.context.lock().unwrap().report_pairing_event(PairingEvent::SspRequest {
+            address: remote_device.address,
+            name: remote_device.name.clone(),
+            cod,
+            variant: variant.clone(),
+            passkey,
+        });
+
         match variant {
             BtSspVariant::PasskeyNotification | BtSspVariant::PasskeyConfirmation => {
                 print_info!(
@@ -241,7 +258,14 @@ impl IBluetoothCallback for BtCallback {
         }
     }
 
-    fn on_pin_request(&mut self, remote_device: BluetoothDevice, _cod: u32, min_16_digit: bool) {
+    fn on_pin_request(&mut self, remote_device: BluetoothDevice, cod: u32, min_16_digit: bool) {
+        self.context.lock().unwrap().report_pairing_event(PairingEvent::PinRequest {
+            address: remote_device.address,
+            name: remote_device.name.clone(),
+            cod,
+            min_16_digit,
+        });
+
         print_info!(
             "Device [{}: {:?}] would like to pair, enter pin code {}",
             remote_device.address.to_string(),
@@ -254,6 +278,12 @@ impl IBluetoothCallback for BtCallback {
     }
 
     fn on_pin_display(&mut self, remote_device: BluetoothDevice, pincode: String) {
+        self.context.lock().unwrap().report_pairing_event(PairingEvent::PinDisplay {
+            address: remote_device.address,
+            name: remote_device.name.clone(),
+            pincode: pincode.clone(),
+        });
+
         print_info!(
             "Device [{}: {:?}] would like to pair, enter pin code {} on the remote",
             remote_device.address.to_string(),
@@ -286,15 +316,41 @@ impl IBluetoothCallback for BtCallback {
 
         let device = BluetoothDevice { address, name: String::from("Classic device") };
 
+        // Complete a pending `device pair-and-connect` once the bonding is done.
+        let pair_and_connect = BtBondState::Bonding != state.into()
+            && matches!(
+                self.context.lock().unwrap().pending_pair_and_connect,
+                Some((pending, _)) if pending == address
+            );
+        if pair_and_connect {
+            let mut context = self.context.lock().unwrap();
+            context.pending_pair_and_connect = None;
+            if BtBondState::Bonded == state.into() {
+                context.run_callback(Box::new(move |context| {
+                    context.lock().unwrap().connect_and_report_from_callback(device.clone());
+                }));
+            } else {
+                context.run_callback(Box::new(move |context| {
+                    print_error!(
+                        "Failed to pair with {}, status = {}",
+                        address.to_string(),
+                        status
+                    );
+                    context.lock().unwrap().callback_exit_code = Some(CommandExitCode::Failed);
+                }));
+            }
+            return;
+        }
+
         // If bonded, we should also automatically connect all enabled profiles
         if BtBondState::Bonded == state.into() {
-            self.context.lock().unwrap().bonded_devices.insert(address.to_string(), device.clone());
             self.context.lock().unwrap().connect_all_enabled_profiles(device.clone());
         }
+    }
 
-        if BtBondState::NotBonded == state.into() {
-            self.context.lock().unwrap().bonded_devices.remove(&address.to_string());
-        }
+    fn on_bonded_devices_changed(&mut self, devices: Vec<BluetoothDevice>) {
+        self.context.lock().unwrap().bonded_devices =
+            devices.into_iter().map(|device| (device.address.to_string(), device)).collect();
     }
 
     fn on_sdp_search_complete(
@@ -328,6 +384,24 @@ impl IBluetoothCallback for BtCallback {
             });
         }
     }
+
+    fn on_remote_uuids_fetched(
+        &mut self,
+        remote_device: BluetoothDevice,
+        uuids: Vec<Uuid>,
+        status: BtStatus,
+    ) {
+        // Go through run_callback so that a pending `device fetch-uuids` completes.
+        self.context.lock().unwrap().run_callback(Box::new(move |_context| {
+            print_info!(
+                "Fetched UUIDs of [{}: {:?}] with status {:?}: {}",
+                remote_device.address.to_string(),
+                remote_device.name,
+                status,
+                uuids.iter().map(|uuid| uuid.to_string()).collect::<Vec<_>>().join(", ")
+            );
+        }));
+    }
 }
 
 impl RPCProxy for BtCallback {
@@ -378,13 +452,30 @@ impl IBluetoothConnectionCallback for BtConnectionCallback {
         );
     }
 
-    fn on_device_connection_failed(&mut self, remote_device: BluetoothDevice, status: BtStatus) {
+    fn on_device_connection_failed(
+        &mut self,
+        remote_device: BluetoothDevice,
+        status: BtStatus,
+        hci_reason: BtHciErrorCode,
+    ) {
         print_info!(
-            "Connection to [{}] failed, status = {:?}",
+            "Connection to [{}] failed, status = {:?}, hci reason = {:#04x}",
             remote_device.address.to_string(),
-            status
+            status,
+            hci_reason
         );
     }
+
+    fn on_connection_quality(
+        &mut self,
+        remote_device: BluetoothDevice,
+        quality: ConnectionQuality,
+    ) {
+        let show = |value: Option<String>| value.unwrap_or(String::from("unavailable"));
+        print_info!("Connection quality of [{}]:", remote_device.address.to_string());
+        print_info!("  RSSI: {}", show(quality.rssi.map(|rssi| format!("{} dBm", rssi))));
+        print_info!("  Link quality: {}", show(quality.link_quality.map(|lq| lq.to_string())));
+    }
 }
 
 impl RPCProxy for BtConnectionCallback {
@@ -433,19 +524,35 @@ impl IScannerCallback for ScannerCallback {
     }
 
     fn on_scan_result(&mut self, scan_result: ScanResult) {
-        if !self.context.lock().unwrap().active_scanner_ids.is_empty() {
+        let mut context = self.context.lock().unwrap();
+        if !context.active_scanner_ids.is_empty()
+            && context.scan_result_matches_filter(&scan_result)
+        {
             print_info!("Scan result: {:#?}", scan_result);
+
+            // Stop the one-shot scan once it has collected enough results from the foreground.
+            if let Some(scanner_id) = context.count_one_shot_scan_result() {
+                context.run_callback(Box::new(move |context| {
+                    context.lock().unwrap().stop_one_shot_scan(scanner_id);
+                }));
+            }
         }
     }
 
     fn on_advertisement_found(&mut self, scanner_id: u8, scan_result: ScanResult) {
-        if !self.context.lock().unwrap().active_scanner_ids.is_empty() {
+        let context = self.context.lock().unwrap();
+        if !context.active_scanner_ids.is_empty()
+            && context.scan_result_matches_filter(&scan_result)
+        {
             print_info!("Advertisement found for scanner_id {} : {:#?}", scanner_id, scan_result);
         }
     }
 
     fn on_advertisement_lost(&mut self, scanner_id: u8, scan_result: ScanResult) {
-        if !self.context.lock().unwrap().active_scanner_ids.is_empty() {
+        let context = self.context.lock().unwrap();
+        if !context.active_scanner_ids.is_empty()
+            && context.scan_result_matches_filter(&scan_result)
+        {
             print_info!("Advertisement lost for scanner_id {} : {:#?}", scanner_id, scan_result);
         }
     }
@@ -574,6 +681,8 @@ impl IAdvertisingSetCallback for AdvertisingSetCallback {
         }
         if let Some(s) = context.adv_sets.get_mut(&reg_id) {
             s.adv_id = Some(advertiser_id);
+            s.enabled = true;
+            s.check_selected_tx_power(tx_power);
         } else {
             print_error!("on_advertising_set_started: invalid callback for reg_id={}", reg_id);
         }
@@ -590,6 +699,7 @@ impl IAdvertisingSetCallback for AdvertisingSetCallback {
 
     fn on_advertising_set_stopped(&mut self, advertiser_id: i32) {
         print_info!("on_advertising_set_stopped: advertiser_id = {}", advertiser_id);
+        self.context.lock().unwrap().adv_sets.retain(|_, s| s.adv_id != Some(advertiser_id));
     }
 
     fn on_advertising_enabled(
@@ -604,6 +714,14 @@ impl IAdvertisingSetCallback for AdvertisingSetCallback {
             enable,
             status
         );
+
+        if status != AdvertisingStatus::Success {
+            return;
+        }
+        let mut context = self.context.lock().unwrap();
+        if let Some(s) = context.adv_sets.values_mut().find(|s| s.adv_id == Some(advertiser_id)) {
+            s.enabled = enable;
+        }
     }
 
     fn on_advertising_data_set(&mut self, advertiser_id: i32, status: AdvertisingStatus) {
@@ -634,6 +752,14 @@ impl IAdvertisingSetCallback for AdvertisingSetCallback {
             tx_power,
             status
         );
+
+        if status != AdvertisingStatus::Success {
+            return;
+        }
+        let context = self.context.lock().unwrap();
+        if let Some(s) = context.adv_sets.values().find(|s| s.adv_id == Some(advertiser_id)) {
+            s.check_selected_tx_power(tx_power);
+        }
     }
 
     fn on_periodic_advertising_parameters_updated(
@@ -693,6 +819,8 @@ impl RPCProxy for AdvertisingSetCallback {
 
 pub(crate) struct BtGattCallback {
     objpath: String,
+    /// Tag identifying the GATT client this callback was registered for.
+    client_tag: String,
     context: Arc<Mutex<ClientContext>>,
 
     dbus_connection: Arc<SyncConnection>,
@@ -702,18 +830,28 @@ pub(crate) struct BtGattCallback {
 impl BtGattCallback {
     pub(crate) fn new(
         objpath: String,
+        client_tag: String,
         context: Arc<Mutex<ClientContext>>,
         dbus_connection: Arc<SyncConnection>,
         dbus_crossroads: Arc<Mutex<Crossroads>>,
     ) -> Self {
-        Self { objpath, context, dbus_connection, dbus_crossroads }
+        Self { objpath, client_tag, context, dbus_connection, dbus_crossroads }
     }
 }
 
 impl IBluetoothGattCallback for BtGattCallback {
     fn on_client_registered(&mut self, status: GattStatus, client_id: i32) {
-        print_info!("GATT Client registered status = {}, client_id = {}", status, client_id);
-        self.context.lock().unwrap().gatt_client_context.client_id = Some(client_id);
+        print_info!(
+            "GATT Client {} registered status = {}, client_id = {}",
+            self.client_tag,
+            status,
+            client_id
+        );
+        self.context
+            .lock()
+            .unwrap()
+            .gatt_client_context
+            .add_client(self.client_tag.clone(), client_id);
     }
 
     fn on_client_connection_state(
@@ -758,6 +896,22 @@ impl IBluetoothGattCallback for BtGattCallback {
         );
     }
 
+    fn on_remote_phy_capabilities(
+        &mut self,
+        addr: RawAddress,
+        le_2m: bool,
+        le_coded: bool,
+        status: GattStatus,
+    ) {
+        print_info!(
+            "Remote phy capabilities: addr = {}, le_2m = {}, le_coded = {}, status = {:?}",
+            addr.to_string(),
+            le_2m,
+            le_coded,
+            status
+        );
+    }
+
     fn on_search_complete(
         &mut self,
         addr: RawAddress,
@@ -770,6 +924,14 @@ impl IBluetoothGattCallback for BtGattCallback {
             services,
             status
         );
+
+        if status == GattStatus::Success {
+            self.context
+                .lock()
+                .unwrap()
+                .gatt_client_context
+                .set_discovered_services(addr, services);
+        }
     }
 
     fn on_characteristic_read(
@@ -924,6 +1086,11 @@ impl IBluetoothGattServerCallback for BtGattServerCallback {
             connected,
             addr.to_string()
         );
+        self.context
+            .lock()
+            .unwrap()
+            .gatt_server_context
+            .set_client_connected(server_id, addr, connected);
     }
 
     fn on_service_added(&mut self, status: GattStatus, service: BluetoothGattService) {
@@ -1247,6 +1414,9 @@ impl IBluetoothSocketManagerCallbacks for BtSocketManagerCallback {
             socket.name,
             socket.uuid,
         );
+        if let (SocketType::Rfcomm, Some(channel)) = (&socket.sock_type, socket.channel) {
+            print_info!("Socket {} listening on RFCOMM channel {}", socket.id, channel);
+        }
 
         let callback_id = self.context.lock().unwrap().socket_manager_callback_id.unwrap();
 
@@ -1290,6 +1460,14 @@ impl IBluetoothSocketManagerCallbacks for BtSocketManagerCallback {
             print_info!("Connection failed on {}: {:?}", connecting_id, result);
         }
     }
+
+    fn on_l2cap_ping_result(&mut self, addr: RawAddress, status: BtStatus, rtt_ms: u32) {
+        if status == BtStatus::Success {
+            print_info!("L2CAP ping to {}: time={} ms", addr.to_string(), rtt_ms);
+        } else {
+            print_info!("L2CAP ping to {} failed: {:?}", addr.to_string(), status);
+        }
+    }
 }
 
 impl RPCProxy for BtSocketManagerCallback {
@@ -1311,6 +1489,7 @@ impl RPCProxy for BtSocketManagerCallback {
 /// Callback container for suspend interface callbacks.
 pub(crate) struct SuspendCallback {
     objpath: String,
+    context: Arc<Mutex<ClientContext>>,
 
     dbus_connection: Arc<SyncConnection>,
     dbus_crossroads: Arc<Mutex<Crossroads>>,
@@ -1319,16 +1498,20 @@ pub(crate) struct SuspendCallback {
 impl SuspendCallback {
     pub(crate) fn new(
         objpath: String,
+        context: Arc<Mutex<ClientContext>>,
         dbus_connection: Arc<SyncConnection>,
         dbus_crossroads: Arc<Mutex<Crossroads>>,
     ) -> Self {
-        Self { objpath, dbus_connection, dbus_crossroads }
+        Self { objpath, context, dbus_connection, dbus_crossroads }
     }
 }
 
 impl ISuspendCallback for SuspendCallback {
+    fn on_callback_registered(&mut self, callback_id: u32) {
+        self.context.lock().unwrap().suspend_callback_id = Some(callback_id);
+    }
+
     // TODO(b/224606285): Implement suspend utils in btclient.
-    fn on_callback_registered(&mut self, _callback_id: u32) {}
     fn on_suspend_ready(&mut self, _suspend_id: i32) {}
     fn on_resumed(&mut self, _suspend_id: i32) {}
 }
@@ -1406,6 +1589,68 @@ impl IBluetoothQACallback for QACallback {
     fn on_send_hid_virtual_unplug_completed(&mut self, status: BtStatus) {
         print_info!("Send HID virtual unplug: {:?}", status);
     }
+
+    fn on_get_link_error_counters_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        counters: LinkErrorCounters,
+    ) {
+        if status != BtStatus::Success {
+            print_error!("Read link errors of {} failed: {:?}", addr.to_string(), status);
+            return;
+        }
+        print_info!("Link errors of {}:", addr.to_string());
+        print_info!("  Failed contact counter: {}", counters.failed_contact_counter);
+        match counters.link_quality {
+            Some(link_quality) => print_info!("  Link quality: {}", link_quality),
+            None => print_info!("  Link quality: unavailable"),
+        }
+    }
+
+    fn on_reset_failed_contact_counter_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        print_info!("Reset failed contact counter of {}: {:?}", addr.to_string(), status);
+    }
+
+    fn on_get_link_power_mode_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+    ) {
+        if status != BtStatus::Success {
+            print_error!("Read power mode of {} failed: {:?}", addr.to_string(), status);
+            return;
+        }
+        print_info!("Power mode of {}: {:?}", addr.to_string(), mode);
+    }
+
+    fn on_link_power_mode_changed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+        interval: u16,
+    ) {
+        if status != BtStatus::Success {
+            print_error!("Set power mode of {} failed: {:?}", addr.to_string(), status);
+            return;
+        }
+        print_info!(
+            "Power mode of {} changed: {:?}, interval {}",
+            addr.to_string(),
+            mode,
+            interval
+        );
+    }
+
+    fn on_add_to_accept_list_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        if status != BtStatus::Success {
+            print_error!("Add {} to accept list failed: {:?}", addr.to_string(), status);
+            return;
+        }
+        print_info!("Added {} to accept list", addr.to_string());
+    }
 }
 
 impl RPCProxy for QACallback {
@@ -1450,6 +1695,10 @@ fn timestamp_to_string(ts_in_us: u64) -> String {
 }
 
 impl IBluetoothMediaCallback for MediaCallback {
+    fn on_callback_registered(&mut self, callback_id: u32) {
+        self.context.lock().unwrap().media_callback_id = Some(callback_id);
+    }
+
     // TODO(b/333341411): implement callbacks for client as necessary
     fn on_lea_group_connected(&mut self, _group_id: i32, _name: String) {}
     fn on_lea_group_disconnected(&mut self, _group_id: i32) {}
@@ -1569,7 +1818,7 @@ impl RPCProxy for MediaCallback {
 
 pub(crate) struct TelephonyCallback {
     objpath: String,
-    _context: Arc<Mutex<ClientContext>>,
+    context: Arc<Mutex<ClientContext>>,
 
     dbus_connection: Arc<SyncConnection>,
     dbus_crossroads: Arc<Mutex<Crossroads>>,
@@ -1582,11 +1831,15 @@ impl TelephonyCallback {
         dbus_connection: Arc<SyncConnection>,
         dbus_crossroads: Arc<Mutex<Crossroads>>,
     ) -> Self {
-        Self { objpath, _context: context, dbus_connection, dbus_crossroads }
+        Self { objpath, context, dbus_connection, dbus_crossroads }
     }
 }
 
 impl IBluetoothTelephonyCallback for TelephonyCallback {
+    fn on_callback_registered(&mut self, callback_id: u32) {
+        self.context.lock().unwrap().telephony_callback_id = Some(callback_id);
+    }
+
     fn on_telephony_event(&mut self, addr: RawAddress, event: u8, call_state: u8) {
         print_info!(
             "Telephony event changed: [{}] event {} state: {}",
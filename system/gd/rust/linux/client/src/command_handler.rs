@@ -4,28 +4,34 @@ use std::slice::SliceIndex;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
-use crate::bt_adv::AdvSet;
-use crate::bt_gatt::AuthReq;
+use crate::bt_adv::{validate_raw_adv_data, AdvSet, AdvTxPower};
+use crate::bt_gatt::{build_service_uuid_scan_filter, format_gatt_db, AuthReq};
 use crate::callbacks::{BtGattCallback, BtGattServerCallback};
 use crate::ClientContext;
 use crate::{console_red, console_yellow, print_error, print_info};
 use bt_topshim::btif::{
-    BtConnectionState, BtDiscMode, BtStatus, BtTransport, RawAddress, Uuid, INVALID_RSSI,
+    BluetoothProperty, BtAddrType, BtBondState, BtConnectionState, BtDeviceType, BtDiscMode,
+    BtIoCap, BtStatus, BtTransport, RawAddress, Uuid, INVALID_RSSI,
 };
 use bt_topshim::profiles::gatt::{GattStatus, LePhy};
 use bt_topshim::profiles::hid_host::BthhReportType;
 use bt_topshim::profiles::sdp::{BtSdpMpsRecord, BtSdpRecord};
 use bt_topshim::profiles::ProfileConnectionState;
 use bt_topshim::syslog::Level;
+use btstack::appearance::DisplayAppearance;
 use btstack::battery_manager::IBatteryManager;
-use btstack::bluetooth::{BluetoothDevice, IBluetooth};
+use btstack::bluetooth::{BluetoothDevice, BtAdapterRole, IBluetooth};
+use btstack::bluetooth_adv::DirectedAdvertisingMode;
 use btstack::bluetooth_gatt::{
     BluetoothGattCharacteristic, BluetoothGattDescriptor, BluetoothGattService, GattDbElementType,
     GattWriteType, IBluetoothGatt,
 };
 use btstack::bluetooth_logging::IBluetoothLogging;
 use btstack::bluetooth_media::{IBluetoothMedia, IBluetoothTelephony};
-use btstack::bluetooth_qa::IBluetoothQA;
+use btstack::bluetooth_qa::{
+    AdapterMode, ClassicScanType, IBluetoothQA, LinkPowerMode, SniffParams,
+};
+use btstack::company::DisplayCompany;
 use btstack::socket_manager::{IBluetoothSocketManager, SocketResult};
 use btstack::uuid::{Profile, UuidHelper};
 use manager_service::iface_bluetooth_manager::IBluetoothManager;
@@ -36,18 +42,65 @@ const BAR2_CHAR: &str = "-";
 const MAX_MENU_CHAR_WIDTH: usize = 72;
 
 const GATT_CLIENT_APP_UUID: &str = "12345678123456781234567812345678";
+const GATT_CLIENT_CALLBACK_OBJPATH: &str = "/org/chromium/bluetooth/client/bluetooth_gatt_callback";
+const DEFAULT_GATT_CLIENT_TAG: &str = "default";
 const GATT_SERVER_APP_UUID: &str = "12345678123456781234567812345679";
 const HEART_RATE_SERVICE_UUID: &str = "0000180D-0000-1000-8000-00805F9B34FB";
 const HEART_RATE_MEASUREMENT_UUID: &str = "00002A37-0000-1000-8000-00805F9B34FB";
 const GENERIC_UUID: &str = "00000000-0000-1000-8000-00805F9B34FB";
 const CCC_DESCRIPTOR_UUID: &str = "00002902-0000-1000-8000-00805F9B34FB";
 const BATTERY_SERVICE_UUID: &str = "0000180F-0000-1000-8000-00805F9B34FB";
+/// Payload size of an L2CAP ping when none is given, the same as l2ping.
+const DEFAULT_L2CAP_PING_SIZE: u16 = 44;
+/// How long `adapter reset` waits for the adapter to be ready again.
+const ADAPTER_RESET_TIMEOUT: Duration = Duration::from_secs(20);
+/// How long `device pair-and-connect` waits for the bonding to complete, which leaves time to
+/// confirm the pairing on both devices.
+const PAIR_AND_CONNECT_TIMEOUT: Duration = Duration::from_secs(60);
 
 enum CommandError {
     // Command not handled due to invalid arguments.
     InvalidArgs,
     // Command handled but failed with the given reason.
     Failed(String),
+    // Command not handled as the adapter isn't enabled or ready.
+    AdapterNotReady(String),
+    // Command handled but the object it targets doesn't exist.
+    NotFound(String),
+    // Command not handled as the adapter doesn't support it.
+    Unsupported(String),
+}
+
+/// Exit code of btclient when a non-interactive command fails, per failure category. Timeout is
+/// for a command that doesn't complete within --timeout, or within `adapter wait-ready`'s timeout.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CommandExitCode {
+    Failed = 1,
+    InvalidArgs = 2,
+    AdapterNotReady = 3,
+    NotFound = 4,
+    Timeout = 5,
+    Unsupported = 6,
+}
+
+impl std::fmt::Display for CommandExitCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "command failed ({:?})", self)
+    }
+}
+
+impl std::error::Error for CommandExitCode {}
+
+impl CommandError {
+    fn exit_code(&self) -> CommandExitCode {
+        match self {
+            CommandError::InvalidArgs => CommandExitCode::InvalidArgs,
+            CommandError::Failed(_) => CommandExitCode::Failed,
+            CommandError::AdapterNotReady(_) => CommandExitCode::AdapterNotReady,
+            CommandError::NotFound(_) => CommandExitCode::NotFound,
+            CommandError::Unsupported(_) => CommandExitCode::Unsupported,
+        }
+    }
 }
 
 impl From<&str> for CommandError {
@@ -140,15 +193,26 @@ fn build_commands() -> HashMap<String, CommandOption> {
             rules: vec![
                 String::from("adapter enable"),
                 String::from("adapter disable"),
+                String::from("adapter reset"),
                 String::from("adapter show"),
+                String::from("adapter controller-info"),
+                String::from("adapter roles"),
+                String::from("adapter profile <hid|hogp> <on|off>"),
                 String::from("adapter discoverable <on|limited|off> <duration>"),
+                String::from("adapter set-discoverable-timeout <secs>"),
                 String::from("adapter connectable <on|off>"),
                 String::from("adapter set-name <name>"),
+                String::from("adapter set-class <cod-hex>"),
+                String::from(
+                    "adapter set-io-cap <DisplayOnly|DisplayYesNo|KeyboardOnly|NoIO|KeyboardDisplay>",
+                ),
+                String::from("adapter wait-ready <timeout_secs>"),
             ],
             description: String::from(
                 "Enable/Disable/Show default bluetooth adapter. (e.g. adapter enable)\n
                  Discoverable On/Limited/Off (e.g. adapter discoverable on 60)\n
-                 Connectable On/Off (e.g. adapter connectable on)",
+                 Connectable On/Off (e.g. adapter connectable on)\n
+                 Wait until the adapter is ready, failing after a timeout (e.g. adapter wait-ready 10)",
             ),
             function_pointer: CommandHandler::cmd_adapter,
         },
@@ -184,11 +248,23 @@ fn build_commands() -> HashMap<String, CommandOption> {
         CommandOption {
             rules: vec![
                 String::from("device <connect|disconnect|info> <address>"),
+                String::from("device cancel-connect <address>"),
+                String::from("device pair-and-connect <address> <auto|bredr|le>"),
                 String::from("device set-pairing-confirmation <address> <accept|reject>"),
                 String::from("device set-pairing-pin <address> <pin|reject>"),
                 String::from("device set-pairing-passkey <address> <passkey|reject>"),
                 String::from("device set-alias <address> <new-alias>"),
+                String::from("device set-wake-allowed <address> <true|false>"),
+                String::from("device set-type <address> <bredr|le|dual>"),
+                String::from("device forget <address>"),
+                String::from("device connectable-profiles <address>"),
+                String::from("device handles <address>"),
                 String::from("device get-rssi <address>"),
+                String::from("device quality <address>"),
+                String::from("device dump-props <address>"),
+                String::from("device adv-data <address>"),
+                String::from("device get-eir <address>"),
+                String::from("device fetch-uuids <address>"),
             ],
             description: String::from("Take action on a remote device. (i.e. info)"),
             function_pointer: CommandHandler::cmd_device,
@@ -217,10 +293,17 @@ fn build_commands() -> HashMap<String, CommandOption> {
                 String::from("gatt register-client"),
                 String::from("gatt client-connect <address>"),
                 String::from("gatt client-read-phy <address>"),
+                String::from("gatt client-read-phy-caps <address>"),
                 String::from("gatt client-discover-services <address>"),
                 String::from("gatt client-discover-service-by-uuid-pts <address> <uuid>"),
+                String::from("gatt has-service <address> <uuid>"),
+                String::from("gatt print-db <address>"),
                 String::from("gatt client-disconnect <address>"),
+                String::from("gatt auto-connect <address> <on|off>"),
                 String::from("gatt configure-mtu <address> <mtu>"),
+                String::from(
+                    "gatt conn-update <address> <min_interval> <max_interval> <latency> <timeout>",
+                ),
                 String::from("gatt set-direct-connect <true|false>"),
                 String::from("gatt set-connect-transport <Bredr|LE|Auto>"),
                 String::from("gatt set-connect-opportunistic <true|false>"),
@@ -233,7 +316,11 @@ fn build_commands() -> HashMap<String, CommandOption> {
                 String::from(
                     "gatt read-characteristic-by-uuid <address> <uuid> <start_handle> <end_handle>",
                 ),
+                String::from("gatt write-descriptor <address> <handle> <hex-value>"),
+                String::from("gatt read-descriptor <address> <handle>"),
                 String::from("gatt register-notification <address> <handle> <enable|disable>"),
+                String::from("gatt persist-notification <address> <handle> <true|false>"),
+                String::from("gatt list-persisted-notifications <address>"),
                 String::from("gatt register-server"),
                 String::from("gatt unregister-server <server_id>"),
                 String::from("gatt server-connect <server_id> <client_address>"),
@@ -243,11 +330,17 @@ fn build_commands() -> HashMap<String, CommandOption> {
                 String::from("gatt server-remove-service <server_id> <service_handle>"),
                 String::from("gatt server-clear-all-services <server_id>"),
                 String::from("gatt server-send-response <server_id> <success|fail>"),
+                String::from(
+                    "gatt server-notify <server_id> <handle> <confirm:true|false> <hex-value>",
+                ),
                 String::from("gatt server-set-direct-connect <true|false>"),
                 String::from("gatt server-set-connect-transport <Bredr|LE|Auto>"),
             ],
             description: String::from(
                 "GATT tools\n\n
+                Using multiple GATT Clients:\n
+                Client commands accept an optional `--client <tag>` to select which registered\n
+                client to use. Without it, the most recently registered client is used.\n\n
                 Creating a GATT Server:\n
                 Register a server, then add a basic (battery) service. After, a more complex\n
                 (heartrate) service can be created with previously created services included.",
@@ -261,10 +354,21 @@ fn build_commands() -> HashMap<String, CommandOption> {
             rules: vec![
                 String::from("le-scan register-scanner"),
                 String::from("le-scan unregister-scanner <scanner-id>"),
-                String::from("le-scan start-scan <scanner-id>"),
+                String::from(
+                    "le-scan start-scan <scanner-id> [--duration <ms>] [--max-results <n>]",
+                ),
                 String::from("le-scan stop-scan <scanner-id>"),
+                String::from("le-scan set-filter <uuid>"),
+                String::from("le-scan clear-filter"),
             ],
-            description: String::from("LE scanning utilities."),
+            description: String::from(
+                "LE scanning utilities.\n
+                 start-scan: With --duration or --max-results, the scan stops on its own. Such a\n
+                 scan can't run alongside other scans.\n
+                 set-filter: Only report advertisements containing the service UUID. Multiple\n
+                 filters are OR-ed and apply to scans started afterwards. UUIDs that don't fit\n
+                 in the controller filter are refused.",
+            ),
             function_pointer: CommandHandler::cmd_le_scan,
         },
     );
@@ -272,13 +376,25 @@ fn build_commands() -> HashMap<String, CommandOption> {
         String::from("advertise"),
         CommandOption {
             rules: vec![
-                String::from("advertise <on|off|ext>"),
+                String::from("advertise <on|ext> [name]"),
+                String::from("advertise off"),
+                String::from("advertise list"),
+                String::from("advertise <enable|disable> <adv-id|name>"),
+                String::from("advertise stop <adv-id|name>"),
                 String::from("advertise set-interval <ms>"),
                 String::from("advertise set-scan-rsp <enable|disable>"),
                 String::from("advertise set-raw-data <raw-adv-data> <adv-id>"),
                 String::from("advertise set-connectable <on|off> <adv-id>"),
+                String::from("advertise set-tx-power <ultra-low|low|medium|high>"),
+                String::from(
+                    "advertise start-directed <adv-id> <peer-address> <public|random> [low-duty|high-duty]",
+                ),
+                String::from("advertise set-limits <adv-id> <duration_ms> <max-events>"),
             ],
-            description: String::from("Advertising utilities."),
+            description: String::from(
+                "Advertising utilities. Several sets can be started if the controller supports \
+                 multi-advertisement, and given a name to refer to them instead of their ID.",
+            ),
             function_pointer: CommandHandler::cmd_advertise,
         },
     );
@@ -295,13 +411,17 @@ fn build_commands() -> HashMap<String, CommandOption> {
         CommandOption {
             rules: vec![
                 String::from("socket listen <auth-required> <Bredr|LE>"),
-                String::from("socket listen-rfcomm <scn>"),
+                String::from("socket listen-rfcomm <scn|auto>"),
                 String::from("socket send-msc <dlci> <address>"),
                 String::from(
                     "socket connect <address> <l2cap|rfcomm> <psm|uuid> <auth-required> <Bredr|LE>",
                 ),
                 String::from("socket close <socket_id>"),
+                String::from("socket list"),
+                String::from("socket ping <address> [size]"),
                 String::from("socket set-on-connect-schedule <send|resend|dump>"),
+                String::from("socket show-schedule"),
+                String::from("socket clear-schedule"),
             ],
             description: String::from("Socket manager utilities."),
             function_pointer: CommandHandler::cmd_socket,
@@ -315,6 +435,7 @@ fn build_commands() -> HashMap<String, CommandOption> {
                 String::from("hid set-report <address> <Input|Output|Feature> <report_value>"),
                 String::from("hid send-data <address> <data>"),
                 String::from("hid virtual-unplug <address>"),
+                String::from("hid disconnect <address> <allow-reconnect:true|false>"),
             ],
             description: String::from("Socket manager utilities."),
             function_pointer: CommandHandler::cmd_hid,
@@ -331,7 +452,31 @@ fn build_commands() -> HashMap<String, CommandOption> {
     command_options.insert(
         String::from("qa"),
         CommandOption {
-            rules: vec![String::from("qa add-media-player <name> <browsing_supported>")],
+            rules: vec![
+                String::from("qa add-media-player <name> <browsing_supported>"),
+                String::from("qa callback-counts"),
+                String::from("qa metrics-dump"),
+                String::from("qa dropped-messages"),
+                String::from("qa link-errors <address>"),
+                String::from("qa reset-link-errors <address>"),
+                String::from("qa set-power-mode <address> active"),
+                String::from(
+                    "qa set-power-mode <address> sniff <max_interval> <min_interval> <attempt> <timeout>",
+                ),
+                String::from("qa get-power-mode <address>"),
+                String::from("qa accept-list add <address> <public|random>"),
+                String::from("qa accept-list remove <address>"),
+                String::from("qa accept-list show"),
+                String::from("qa set-adapter-mode <dual|bredr|le>"),
+                String::from("qa get-adapter-mode"),
+                String::from("qa scan-params get"),
+                String::from("qa scan-params set <interval> <window> [standard|interlaced]"),
+                String::from("qa page-scan-params get"),
+                String::from(
+                    "qa page-scan-params set <interval> <window> [standard|interlaced]",
+                ),
+                String::from("qa auto-accept-just-works <on|off>"),
+            ],
             description: String::from("Methods for testing purposes"),
             function_pointer: CommandHandler::cmd_qa,
         },
@@ -347,7 +492,11 @@ fn build_commands() -> HashMap<String, CommandOption> {
     command_options.insert(
         String::from("list"),
         CommandOption {
-            rules: vec![String::from("list <bonded|found|connected>")],
+            rules: vec![
+                String::from("list <bonded|found|connected>"),
+                String::from("list found <bredr|le|dual>"),
+                String::from("list found [bredr|le|dual] --out <file>"),
+            ],
             description: String::from(
                 "List bonded or found remote devices. Use: list <bonded|found>",
             ),
@@ -371,6 +520,7 @@ fn build_commands() -> HashMap<String, CommandOption> {
                     "telephony <release-held|release-active-accept-held|hold-active-accept-held>",
                 ),
                 String::from("telephony <audio-connect|audio-disconnect> <address>"),
+                String::from("telephony clear-calls"),
             ],
             description: String::from("Set device telephony status."),
             function_pointer: CommandHandler::cmd_telephony,
@@ -429,6 +579,147 @@ where
     args.get(index).ok_or(CommandError::InvalidArgs)
 }
 
+// Extracts the optional `--client <tag>` of the gatt commands. Returns the tag, if any, along with
+// the remaining arguments.
+fn split_gatt_client_tag(args: &[String]) -> Result<(Option<String>, Vec<String>), CommandError> {
+    let mut tag = None;
+    let mut rest = vec![];
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg != "--client" {
+            rest.push(arg.clone());
+            continue;
+        }
+
+        let value = iter.next().ok_or(CommandError::InvalidArgs)?;
+        // The tag is used as part of the callback object path.
+        if value.is_empty() || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid client tag: {}", value).into());
+        }
+        tag = Some(value.clone());
+    }
+
+    Ok((tag, rest))
+}
+
+// Parses the optional `--duration <ms>` and `--max-results <n>` that make a scan stop on its own.
+fn parse_scan_limits(args: &[String]) -> Result<(Option<Duration>, Option<usize>), CommandError> {
+    let mut duration = None;
+    let mut max_results = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = iter.next().ok_or(CommandError::InvalidArgs)?;
+        match &arg[..] {
+            "--duration" => {
+                let ms = value.parse::<u64>().or(Err("Failed parsing duration"))?;
+                duration = Some(Duration::from_millis(ms));
+            }
+            "--max-results" => {
+                let n = value.parse::<usize>().or(Err("Failed parsing max results"))?;
+                if n == 0 {
+                    return Err("Max results must be at least 1".into());
+                }
+                max_results = Some(n);
+            }
+            other => return Err(format!("Invalid option '{}'", other).into()),
+        }
+    }
+
+    Ok((duration, max_results))
+}
+
+// Derives a distinct app UUID for every GATT client registration, starting from
+// GATT_CLIENT_APP_UUID. The stack identifies clients by their app UUID.
+fn gatt_client_app_uuid(index: u32) -> String {
+    let first = u32::from_str_radix(&GATT_CLIENT_APP_UUID[..8], 16).unwrap();
+    format!("{:08x}{}", first.wrapping_add(index), &GATT_CLIENT_APP_UUID[8..])
+}
+
+// Parses a Class of Device given in hex, with or without the 0x prefix. The COD is 3 bytes long,
+// so values that don't fit in 24 bits are rejected.
+fn parse_class_of_device(arg: &str) -> Result<u32, CommandError> {
+    let hex = arg.strip_prefix("0x").or_else(|| arg.strip_prefix("0X")).unwrap_or(arg);
+    let cod = u32::from_str_radix(hex, 16)
+        .map_err(|_| format!("Failed parsing class of device: {}", arg))?;
+    if cod > 0xff_ffff {
+        return Err(format!("Class of device {:#x} doesn't fit in 24 bits", cod).into());
+    }
+
+    Ok(cod)
+}
+
+// Checks LE connection parameters against the ranges of Core 5.3, Vol 4, Part E, 7.8.18. The
+// intervals are in 1.25 ms units and the supervision timeout in 10 ms units, and the timeout
+// must be longer than (1 + latency) * max_interval * 2.
+fn check_conn_params(
+    min_interval: i32,
+    max_interval: i32,
+    latency: i32,
+    timeout: i32,
+) -> Result<(), String> {
+    const INTERVAL_RANGE: std::ops::RangeInclusive<i32> = 0x0006..=0x0C80;
+    const MAX_LATENCY: i32 = 0x01F3;
+    const TIMEOUT_RANGE: std::ops::RangeInclusive<i32> = 0x000A..=0x0C80;
+
+    if !INTERVAL_RANGE.contains(&min_interval) || !INTERVAL_RANGE.contains(&max_interval) {
+        return Err(format!("Intervals must be within {:?}", INTERVAL_RANGE));
+    }
+    if min_interval > max_interval {
+        return Err(format!(
+            "min_interval {} is greater than max_interval {}",
+            min_interval, max_interval
+        ));
+    }
+    if !(0..=MAX_LATENCY).contains(&latency) {
+        return Err(format!("Latency must be within 0 and {}", MAX_LATENCY));
+    }
+    if !TIMEOUT_RANGE.contains(&timeout) {
+        return Err(format!("Timeout must be within {:?}", TIMEOUT_RANGE));
+    }
+    // timeout * 10 ms > (1 + latency) * max_interval * 1.25 ms * 2
+    if timeout * 4 <= (1 + latency) * max_interval {
+        return Err(format!(
+            "Timeout {} is too short for latency {} and max_interval {}",
+            timeout, latency, max_interval
+        ));
+    }
+
+    Ok(())
+}
+
+// Names the Core Specification version of an HCI or LMP version number.
+fn core_spec_version_name(version: u8) -> &'static str {
+    match version {
+        0 => "1.0b",
+        1 => "1.1",
+        2 => "1.2",
+        3 => "2.0 + EDR",
+        4 => "2.1 + EDR",
+        5 => "3.0 + HS",
+        6 => "4.0",
+        7 => "4.1",
+        8 => "4.2",
+        9 => "5.0",
+        10 => "5.1",
+        11 => "5.2",
+        12 => "5.3",
+        13 => "5.4",
+        14 => "6.0",
+        _ => "Unknown",
+    }
+}
+
+// Quotes a CSV field if it contains a separator, a quote or a line break, doubling its quotes.
+fn csv_escape(field: &str) -> String {
+    if field.contains(|c: char| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
 impl CommandHandler {
     /// Creates a new CommandHandler.
     pub fn new(context: Arc<Mutex<ClientContext>>) -> CommandHandler {
@@ -436,29 +727,38 @@ impl CommandHandler {
     }
 
     /// Entry point for command and arguments
-    pub fn process_cmd_line(&mut self, command: &str, args: &[String]) -> bool {
+    /// Runs the command. On failure, returns the exit code of its failure category, which is the
+    /// exit code of btclient when running the command non-interactively.
+    pub fn process_cmd_line(
+        &mut self,
+        command: &str,
+        args: &[String],
+    ) -> Result<(), CommandExitCode> {
         // Ignore empty line
         match command {
-            "" => false,
+            "" => Err(CommandExitCode::InvalidArgs),
             _ => match self.command_options.get(command) {
                 Some(cmd) => {
                     let rules = cmd.rules.clone();
-                    match (cmd.function_pointer)(self, args) {
-                        Ok(()) => true,
-                        Err(CommandError::InvalidArgs) => {
-                            print_error!("Invalid arguments. Usage:\n{}", rules.join("\n"));
-                            false
-                        }
-                        Err(CommandError::Failed(msg)) => {
-                            print_error!("Command failed: {}", msg);
-                            false
+                    (cmd.function_pointer)(self, args).map_err(|err| {
+                        match &err {
+                            CommandError::InvalidArgs => {
+                                print_error!("Invalid arguments. Usage:\n{}", rules.join("\n"));
+                            }
+                            CommandError::Failed(msg)
+                            | CommandError::AdapterNotReady(msg)
+                            | CommandError::NotFound(msg)
+                            | CommandError::Unsupported(msg) => {
+                                print_error!("Command failed: {}", msg);
+                            }
                         }
-                    }
+                        err.exit_code()
+                    })
                 }
                 None => {
                     println!("'{}' is an invalid command!", command);
                     self.cmd_help(args).ok();
-                    false
+                    Err(CommandExitCode::InvalidArgs)
                 }
             },
         }
@@ -468,13 +768,22 @@ impl CommandHandler {
         self.context.lock().unwrap()
     }
 
+    // Gets the GATT client id for the given tag, or for the most recently registered client.
+    fn get_gatt_client_id(&self, tag: Option<&str>) -> Result<i32, CommandError> {
+        self.lock_context().gatt_client_context.get_client_id(tag).ok_or_else(|| match tag {
+            Some(tag) => {
+                CommandError::NotFound(format!("GATT client {} is not yet registered.", tag))
+            }
+            None => CommandError::NotFound("GATT client is not yet registered.".into()),
+        })
+    }
+
     // Common message for when the adapter isn't ready
     fn adapter_not_ready(&self) -> CommandError {
-        format!(
+        CommandError::AdapterNotReady(format!(
             "Default adapter {} is not enabled. Enable the adapter before using this command.",
             self.lock_context().default_adapter
-        )
-        .into()
+        ))
     }
 
     fn cmd_help(&mut self, args: &[String]) -> CommandResult {
@@ -537,7 +846,18 @@ impl CommandHandler {
 
         let command = get_arg(args, 0)?;
 
-        if matches!(&command[..], "show" | "discoverable" | "connectable" | "set-name") {
+        if matches!(
+            &command[..],
+            "show"
+                | "controller-info"
+                | "roles"
+                | "profile"
+                | "discoverable"
+                | "set-discoverable-timeout"
+                | "connectable"
+                | "set-name"
+                | "set-class"
+        ) {
             if !self.lock_context().adapter_ready {
                 return Err(self.adapter_not_ready());
             }
@@ -556,6 +876,16 @@ impl CommandHandler {
                 }
                 self.lock_context().manager_dbus.stop(default_adapter);
             }
+            "reset" => {
+                if self.lock_context().is_restricted {
+                    return Err("You are not allowed to toggle adapter power".into());
+                }
+                print_info!(
+                    "Resetting the adapter, waiting up to {}s for it to be ready",
+                    ADAPTER_RESET_TIMEOUT.as_secs()
+                );
+                self.lock_context().reset_adapter(ADAPTER_RESET_TIMEOUT);
+            }
             "show" => {
                 let enabled = self.lock_context().enabled;
                 let address = self.lock_context().adapter_address.unwrap_or_default();
@@ -563,6 +893,7 @@ impl CommandHandler {
                 let adapter_dbus = context.adapter_dbus.as_ref().unwrap();
                 let qa_dbus = context.qa_dbus.as_ref().unwrap();
                 let name = adapter_dbus.get_name();
+                let le_address_info = adapter_dbus.get_local_le_address_info();
                 let modalias = qa_dbus.get_modalias();
                 let uuids = adapter_dbus.get_uuids();
                 let is_discoverable = adapter_dbus.get_discoverable();
@@ -572,6 +903,7 @@ impl CommandHandler {
                 let le_ext_adv_supported = adapter_dbus.is_le_extended_advertising_supported();
                 let wbs_supported = adapter_dbus.is_wbs_supported();
                 let le_audio_supported = adapter_dbus.is_le_audio_supported();
+                let le_audio_caps = adapter_dbus.get_le_audio_capabilities();
                 let supported_profiles = UuidHelper::get_supported_profiles();
                 let connected_profiles: Vec<(Profile, ProfileConnectionState)> = supported_profiles
                     .iter()
@@ -588,6 +920,11 @@ impl CommandHandler {
                 qa_dbus.fetch_alias();
                 qa_dbus.fetch_discoverable_mode();
                 print_info!("Address: {}", address.to_string());
+                print_info!(
+                    "LeAddress: {} ({:?})",
+                    le_address_info.address.to_string(),
+                    le_address_info.address_type
+                );
                 print_info!("Name: {}", name);
                 print_info!("Modalias: {}", modalias);
                 print_info!("State: {}", if enabled { "enabled" } else { "disabled" });
@@ -599,6 +936,7 @@ impl CommandHandler {
                 print_info!("Connected profiles: {:?}", connected_profiles);
                 print_info!("IsWbsSupported: {}", wbs_supported);
                 print_info!("IsLeAudioSupported: {}", le_audio_supported);
+                print_info!("LeAudioCapabilities: {:?}", le_audio_caps);
                 print_info!(
                     "Uuids: {}",
                     DisplayList(
@@ -658,6 +996,25 @@ impl CommandHandler {
                 }
                 other => println!("Invalid argument for adapter discoverable '{}'", other),
             },
+            "set-discoverable-timeout" => {
+                let duration = String::from(get_arg(args, 1)?)
+                    .parse::<u32>()
+                    .or(Err("Failed parsing duration."))?;
+
+                let success = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_discoverable_timeout(duration);
+                if !success {
+                    return Err("Failed to set the discoverable timeout".into());
+                }
+                match duration {
+                    0 => print_info!("Discoverable until turned off"),
+                    _ => print_info!("Discoverable for {} more seconds", duration),
+                }
+            }
             "connectable" => match &get_arg(args, 1)?[..] {
                 "on" => {
                     self.lock_context().qa_dbus.as_mut().unwrap().set_connectable(true);
@@ -674,6 +1031,108 @@ impl CommandHandler {
                     println!("usage: adapter set-name <name>");
                 }
             }
+            "set-class" => {
+                let cod = parse_class_of_device(get_arg(args, 1)?)?;
+                if self.lock_context().adapter_dbus.as_ref().unwrap().set_bluetooth_class(cod) {
+                    print_info!("Class of device set to {:#08x}", cod);
+                } else {
+                    return Err(format!("Failed to set class of device to {:#08x}", cod).into());
+                }
+            }
+            "profile" => {
+                let name = get_arg(args, 1)?;
+                let profile = UuidHelper::get_supported_profiles()
+                    .into_iter()
+                    .find(|p| p.to_string().eq_ignore_ascii_case(name))
+                    .ok_or_else(|| format!("Unknown or unsupported profile '{}'", name))?;
+                let enabled = match &get_arg(args, 2)?[..] {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(CommandError::InvalidArgs),
+                };
+                if !self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_profile_enabled(profile, enabled)
+                {
+                    return Err(
+                        format!("Failed to set {} to {}", profile, get_arg(args, 2)?).into()
+                    );
+                }
+                print_info!("{} is {}", profile, if enabled { "enabled" } else { "disabled" });
+            }
+            "roles" => {
+                let roles =
+                    self.lock_context().adapter_dbus.as_ref().unwrap().get_supported_roles();
+                for role in [
+                    BtAdapterRole::Central,
+                    BtAdapterRole::Peripheral,
+                    BtAdapterRole::CentralPeripheral,
+                ] {
+                    print_info!(
+                        "{:?}: {}",
+                        role,
+                        if roles.contains(&role) { "supported" } else { "not supported" }
+                    );
+                }
+            }
+            "controller-info" => {
+                let info = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_controller_info()
+                    .ok_or("Controller information isn't available")?;
+                print_info!(
+                    "HCI Version: {} (0x{:02x}), Revision: 0x{:04x}",
+                    core_spec_version_name(info.hci_version),
+                    info.hci_version,
+                    info.hci_revision
+                );
+                print_info!(
+                    "LMP Version: {} (0x{:02x}), Subversion: 0x{:04x}",
+                    core_spec_version_name(info.lmp_version),
+                    info.lmp_version,
+                    info.subversion
+                );
+                print_info!("Manufacturer: {}", DisplayCompany(info.manufacturer));
+            }
+            "set-io-cap" => {
+                let io_cap = match &get_arg(args, 1)?[..] {
+                    "DisplayOnly" => BtIoCap::Out,
+                    "DisplayYesNo" => BtIoCap::InOut,
+                    "KeyboardOnly" => BtIoCap::In,
+                    "NoIO" => BtIoCap::None_,
+                    "KeyboardDisplay" => BtIoCap::KbDisp,
+                    _ => return Err(CommandError::InvalidArgs),
+                };
+                if self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_io_capability(io_cap.clone())
+                {
+                    print_info!("IO capability set to {:?}", io_cap);
+                } else {
+                    return Err(format!("Failed to set IO capability to {:?}", io_cap).into());
+                }
+            }
+            "wait-ready" => {
+                let timeout_secs = String::from(get_arg(args, 1)?)
+                    .parse::<u64>()
+                    .or(Err("Failed parsing timeout"))?;
+
+                if self.lock_context().adapter_ready {
+                    print_info!("Adapter is ready");
+                } else {
+                    print_info!("Waiting up to {}s for the adapter to be ready", timeout_secs);
+                    self.lock_context().wait_adapter_ready(Duration::from_secs(timeout_secs));
+                }
+            }
 
             _ => return Err(CommandError::InvalidArgs),
         };
@@ -813,8 +1272,12 @@ impl CommandHandler {
                     .unwrap()
                     .create_bond(device.clone(), BtTransport::Auto);
 
-                if status == BtStatus::Success {
-                    self.lock_context().bonding_attempt = Some(device);
+                match status {
+                    BtStatus::Success => self.lock_context().bonding_attempt = Some(device),
+                    BtStatus::Busy => {
+                        println!("Can't bond now, the adapter is busy or suspended. Retry later.")
+                    }
+                    _ => {}
                 }
             }
             "remove" => {
@@ -855,20 +1318,79 @@ impl CommandHandler {
                     name: String::from("Classic Device"),
                 };
 
-                let status = self
+                self.lock_context().connect_and_report(device)?;
+            }
+            "pair-and-connect" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from("Classic Device"),
+                };
+                let transport = match &get_arg(args, 2)?[..] {
+                    "auto" => BtTransport::Auto,
+                    "bredr" => BtTransport::Bredr,
+                    "le" => BtTransport::Le,
+                    other => return Err(format!("Invalid transport '{}'", other).into()),
+                };
+
+                let mut context = self.lock_context();
+                if context.adapter_dbus.as_ref().unwrap().get_bond_state(device.clone())
+                    == BtBondState::Bonded
+                {
+                    print_info!("{} is already bonded", device.address.to_string());
+                    // Go through run_callback so that the command completes.
+                    context.run_callback(Box::new(move |context| {
+                        context.lock().unwrap().connect_and_report_from_callback(device.clone());
+                    }));
+                    return Ok(());
+                }
+
+                if let Some(bonding) = context.bonding_attempt.as_ref() {
+                    return Err(format!(
+                        "Already bonding [{}]. Cancel bonding first.",
+                        bonding.address.to_string()
+                    )
+                    .into());
+                }
+
+                let status =
+                    context.adapter_dbus.as_mut().unwrap().create_bond(device.clone(), transport);
+                if status != BtStatus::Success {
+                    return Err(format!(
+                        "Failed to pair with {}: {:?}",
+                        device.address.to_string(),
+                        status
+                    )
+                    .into());
+                }
+
+                print_info!(
+                    "Pairing with {}, waiting up to {}s before connecting",
+                    device.address.to_string(),
+                    PAIR_AND_CONNECT_TIMEOUT.as_secs()
+                );
+                context.wait_pair_and_connect(device.address, PAIR_AND_CONNECT_TIMEOUT);
+                context.bonding_attempt = Some(device);
+            }
+            "disconnect" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from("Classic Device"),
+                };
+
+                let success = self
                     .lock_context()
                     .adapter_dbus
                     .as_mut()
                     .unwrap()
-                    .connect_all_enabled_profiles(device.clone());
+                    .disconnect_all_enabled_profiles(device.clone());
 
-                if status == BtStatus::Success {
-                    println!("Connecting to {}", &device.address.to_string());
+                if success {
+                    println!("Disconnecting from {}", &device.address.to_string());
                 } else {
-                    println!("Can't connect to {}", &device.address.to_string());
+                    println!("Can't disconnect from {}", &device.address.to_string());
                 }
             }
-            "disconnect" => {
+            "cancel-connect" => {
                 let device = BluetoothDevice {
                     address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
                     name: String::from("Classic Device"),
@@ -879,12 +1401,12 @@ impl CommandHandler {
                     .adapter_dbus
                     .as_mut()
                     .unwrap()
-                    .disconnect_all_enabled_profiles(device.clone());
+                    .cancel_connect_all_enabled_profiles(device.clone());
 
                 if success {
-                    println!("Disconnecting from {}", &device.address.to_string());
+                    println!("Cancelled connecting to {}", &device.address.to_string());
                 } else {
-                    println!("Can't disconnect from {}", &device.address.to_string());
+                    println!("Can't cancel connecting to {}", &device.address.to_string());
                 }
             }
             "info" => {
@@ -905,6 +1427,8 @@ impl CommandHandler {
                     connection_state,
                     uuids,
                     wake_allowed,
+                    connection_direction,
+                    discovery_sources,
                     dual_mode_audio,
                 ) = {
                     let ctx = self.lock_context();
@@ -926,6 +1450,9 @@ impl CommandHandler {
                     };
                     let uuids = adapter.get_remote_uuids(device.clone());
                     let wake_allowed = adapter.get_remote_wake_allowed(device.clone());
+                    let connection_direction =
+                        adapter.get_remote_connection_direction(device.clone());
+                    let discovery_sources = adapter.get_remote_discovery_sources(device.clone());
                     let dual_mode_audio = adapter.is_dual_mode_audio_sink_device(device.clone());
 
                     (
@@ -940,6 +1467,8 @@ impl CommandHandler {
                         connection_state,
                         uuids,
                         wake_allowed,
+                        connection_direction,
+                        discovery_sources,
                         dual_mode_audio,
                     )
                 };
@@ -950,11 +1479,13 @@ impl CommandHandler {
                 print_info!("Device Type: {:?}", device_type);
                 print_info!("Address Type: {:?}", addr_type);
                 print_info!("Class: {}", class);
-                print_info!("Appearance: {}", appearance);
+                print_info!("Appearance: {}", DisplayAppearance(appearance));
                 print_info!("Modalias: {}", modalias);
                 print_info!("Wake Allowed: {}", wake_allowed);
                 print_info!("Bond State: {:?}", bonded);
                 print_info!("Connection State: {}", connection_state);
+                print_info!("Last Connection Direction: {:?}", connection_direction);
+                print_info!("Found Via: {:?}", discovery_sources);
                 print_info!("Dual Mode Audio Device: {}", dual_mode_audio);
                 print_info!(
                     "Uuids: {}",
@@ -984,11 +1515,116 @@ impl CommandHandler {
                     old_alias,
                     new_alias
                 );
-                self.lock_context()
+                let success = self
+                    .lock_context()
                     .adapter_dbus
                     .as_mut()
                     .unwrap()
                     .set_remote_alias(device.clone(), new_alias.clone());
+                if !success {
+                    return Err(format!(
+                        "Failed to set alias of {}, is the device known?",
+                        device.address.to_string()
+                    )
+                    .into());
+                }
+            }
+            "set-wake-allowed" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+                let allowed = match &get_arg(args, 2)?[..] {
+                    "true" => true,
+                    "false" => false,
+                    other => {
+                        return Err(format!("Invalid wake allowed value '{}'", other).into());
+                    }
+                };
+                self.lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_remote_wake_allowed(device, allowed);
+            }
+            "connectable-profiles" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+                let profiles = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_connectable_profiles(device.clone());
+
+                print_info!(
+                    "Profiles that would be connected for {}: {}",
+                    device.address.to_string(),
+                    DisplayList(profiles)
+                );
+            }
+            "handles" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+                let handles = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_connection_handles(device.clone());
+
+                print_info!(
+                    "Connection handles for {}: {}",
+                    device.address.to_string(),
+                    DisplayList(handles.iter().map(|h| format!("0x{:04X}", h)).collect())
+                );
+            }
+            "forget" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+                if !self.lock_context().adapter_dbus.as_mut().unwrap().forget_device(device.clone())
+                {
+                    return Err(format!(
+                        "Failed to forget {}, it must be known, not bonded and not connected",
+                        device.address.to_string()
+                    )
+                    .into());
+                }
+                print_info!("Forgot {}", device.address.to_string());
+            }
+            "set-type" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+                let device_type = match &get_arg(args, 2)?[..] {
+                    "bredr" => BtDeviceType::Bredr,
+                    "le" => BtDeviceType::Ble,
+                    "dual" => BtDeviceType::Dual,
+                    other => {
+                        return Err(format!("Invalid device type '{}'", other).into());
+                    }
+                };
+                if !self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_remote_device_type_override(device.clone(), device_type.clone())
+                {
+                    return Err(format!(
+                        "Failed to set type of {}, is the device known?",
+                        device.address.to_string()
+                    )
+                    .into());
+                }
+                print_info!("Type of {} set to {:?}", device.address.to_string(), device_type);
             }
             "set-pairing-confirmation" => {
                 let device = BluetoothDevice {
@@ -1047,12 +1683,30 @@ impl CommandHandler {
                     passkey,
                 );
             }
-            "get-rssi" => {
+            "fetch-uuids" => {
                 let device = BluetoothDevice {
                     address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
                     name: String::from(""),
                 };
-
+                let started = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .fetch_remote_uuids(device.clone());
+                if !started {
+                    return Err(
+                        format!("Failed to fetch UUIDs of {}", device.address.to_string()).into()
+                    );
+                }
+                print_info!("Fetching UUIDs of {}", device.address.to_string());
+            }
+            "get-rssi" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+
                 match self
                     .lock_context()
                     .adapter_dbus
@@ -1067,6 +1721,111 @@ impl CommandHandler {
                         println!("RSSI: {}", rssi);
                     }
                 };
+                let smoothed = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_remote_rssi_smoothed(device);
+                if smoothed != INVALID_RSSI {
+                    println!("Smoothed RSSI: {}", smoothed);
+                }
+            }
+            "quality" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+
+                let requested = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .get_connection_quality(device.clone());
+                if !requested {
+                    return Err(format!("{} is not connected", device.address.to_string()).into());
+                }
+            }
+            "dump-props" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+
+                let mut props = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_remote_all_properties(device.clone());
+                props.sort_by_key(|prop| u32::from(prop.get_type()));
+
+                print_info!("Cached properties of {}:", device.address.to_string());
+                for prop in props {
+                    print_info!("  {:?}: {:?}", prop.get_type(), prop);
+                }
+            }
+            "get-eir" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+
+                let eir = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_remote_all_properties(device.clone())
+                    .into_iter()
+                    .find_map(|prop| match prop {
+                        BluetoothProperty::RemoteEir(eir) => Some(eir),
+                        _ => None,
+                    })
+                    .ok_or(format!(
+                        "No EIR received from {}, is it in range of a BR/EDR inquiry?",
+                        device.address.to_string()
+                    ))?;
+
+                print_info!("EIR of {}: {}", device.address.to_string(), hex::encode(eir));
+            }
+            "adv-data" => {
+                let device = BluetoothDevice {
+                    address: RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
+                    name: String::from(""),
+                };
+
+                let adv_data = self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_remote_advertising_data(device.clone());
+
+                print_info!("Advertising data of {}:", device.address.to_string());
+                print_info!("  Flags: {:#04x}", adv_data.flags);
+                print_info!("  TxPower: {:?}", adv_data.tx_power);
+                print_info!(
+                    "  ServiceUuids: {}",
+                    DisplayList(
+                        adv_data
+                            .service_uuids
+                            .iter()
+                            .map(|&x| UuidHelper::known_uuid_to_string(&x))
+                            .collect::<Vec<String>>()
+                    )
+                );
+                for (uuid, data) in adv_data.service_data.iter() {
+                    print_info!("  ServiceData: {}: {}", uuid, hex::encode(data));
+                }
+                for (manf_id, data) in adv_data.manufacturer_data.iter() {
+                    print_info!("  ManufacturerData: {:#06x}: {}", manf_id, hex::encode(data));
+                }
+                print_info!(
+                    "  Appearance: {:?}",
+                    adv_data.appearance.map(|a| format!("{:#06x}", a))
+                );
             }
             other => {
                 println!("Invalid argument '{}'", other);
@@ -1105,6 +1864,9 @@ impl CommandHandler {
             return Err(self.adapter_not_ready());
         }
 
+        let (client_tag, args) = split_gatt_client_tag(args)?;
+        let args = &args[..];
+
         let command = get_arg(args, 0)?;
 
         match &command[..] {
@@ -1112,10 +1874,24 @@ impl CommandHandler {
                 let dbus_connection = self.lock_context().dbus_connection.clone();
                 let dbus_crossroads = self.lock_context().dbus_crossroads.clone();
 
+                let tag = client_tag.unwrap_or_else(|| String::from(DEFAULT_GATT_CLIENT_TAG));
+                let objpath = if tag == DEFAULT_GATT_CLIENT_TAG {
+                    String::from(GATT_CLIENT_CALLBACK_OBJPATH)
+                } else {
+                    format!("{}_{}", GATT_CLIENT_CALLBACK_OBJPATH, tag)
+                };
+                let app_uuid = {
+                    let gatt_client_context = &mut self.lock_context().gatt_client_context;
+                    let index = gatt_client_context.num_client_registrations;
+                    gatt_client_context.num_client_registrations += 1;
+                    gatt_client_app_uuid(index)
+                };
+
                 self.lock_context().gatt_dbus.as_mut().unwrap().register_client(
-                    String::from(GATT_CLIENT_APP_UUID),
+                    app_uuid,
                     Box::new(BtGattCallback::new(
-                        String::from("/org/chromium/bluetooth/client/bluetooth_gatt_callback"),
+                        objpath,
+                        tag,
                         self.context.clone(),
                         dbus_connection,
                         dbus_crossroads,
@@ -1124,11 +1900,7 @@ impl CommandHandler {
                 );
             }
             "client-connect" => {
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 let is_direct = self.lock_context().gatt_client_context.is_connect_direct;
@@ -1147,40 +1919,58 @@ impl CommandHandler {
                 );
             }
             "client-disconnect" => {
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 self.lock_context().gatt_dbus.as_ref().unwrap().client_disconnect(client_id, addr);
             }
-            "client-read-phy" => {
-                let client_id = self
+            "auto-connect" => {
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let enabled = match &get_arg(args, 2)?[..] {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(CommandError::InvalidArgs),
+                };
+
+                if !self
                     .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                    .gatt_dbus
+                    .as_mut()
+                    .unwrap()
+                    .client_set_auto_connect(client_id, addr, enabled)
+                {
+                    return Err("Failed to set auto-connect".into());
+                }
+                print_info!(
+                    "Auto-connect to {} {}",
+                    addr.to_string(),
+                    if enabled { "enabled" } else { "disabled" }
+                );
+            }
+            "client-read-phy" => {
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 self.lock_context().gatt_dbus.as_mut().unwrap().client_read_phy(client_id, addr);
             }
+            "client-read-phy-caps" => {
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                self.lock_context()
+                    .gatt_dbus
+                    .as_mut()
+                    .unwrap()
+                    .client_read_remote_phy_capabilities(client_id, addr);
+            }
             "client-discover-services" => {
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 self.lock_context().gatt_dbus.as_ref().unwrap().discover_services(client_id, addr);
             }
             "client-discover-service-by-uuid-pts" => {
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 let uuid = String::from(get_arg(args, 2)?);
                 self.lock_context()
@@ -1189,12 +1979,37 @@ impl CommandHandler {
                     .unwrap()
                     .btif_gattc_discover_service_by_uuid(client_id, addr, uuid);
             }
-            "configure-mtu" => {
-                let client_id = self
-                    .lock_context()
+            "print-db" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let context = self.lock_context();
+                let services = context
                     .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                    .get_discovered_services(&addr)
+                    .ok_or_else(|| {
+                        format!(
+                            "No services discovered on {}, run client-discover-services first",
+                            addr.to_string()
+                        )
+                    })?;
+
+                print_info!("GATT DB of {}:", addr.to_string());
+                for line in format_gatt_db(services) {
+                    print_info!("  {}", line);
+                }
+            }
+            "has-service" => {
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let uuid = String::from(get_arg(args, 2)?);
+                let has_service = self.lock_context().gatt_dbus.as_ref().unwrap().has_service(
+                    client_id,
+                    addr,
+                    uuid.clone(),
+                );
+                print_info!("Service {} found on {}: {}", uuid, addr.to_string(), has_service);
+            }
+            "configure-mtu" => {
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 let mtu =
@@ -1202,6 +2017,36 @@ impl CommandHandler {
 
                 self.lock_context().gatt_dbus.as_ref().unwrap().configure_mtu(client_id, addr, mtu)
             }
+            "conn-update" => {
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let min_interval = String::from(get_arg(args, 2)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing min_interval"))?;
+                let max_interval = String::from(get_arg(args, 3)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing max_interval"))?;
+                let latency = String::from(get_arg(args, 4)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing latency"))?;
+                let timeout = String::from(get_arg(args, 5)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing timeout"))?;
+                check_conn_params(min_interval, max_interval, latency, timeout)?;
+
+                // The result is reported by |on_connection_updated|.
+                self.lock_context().gatt_dbus.as_ref().unwrap().connection_parameter_update(
+                    client_id,
+                    addr,
+                    min_interval,
+                    max_interval,
+                    latency,
+                    timeout,
+                    0,
+                    0,
+                );
+            }
             "set-direct-connect" => {
                 let is_direct = String::from(get_arg(args, 1)?)
                     .parse::<bool>()
@@ -1271,11 +2116,7 @@ impl CommandHandler {
 
                 let value = hex::decode(get_arg(args, 4)?).or(Err("Failed to parse value"))?;
 
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let auth_req = self.lock_context().gatt_client_context.get_auth_req().into();
 
@@ -1290,11 +2131,7 @@ impl CommandHandler {
                 let handle = String::from(get_arg(args, 2)?)
                     .parse::<i32>()
                     .or(Err("Failed to parse handle"))?;
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let auth_req = self.lock_context().gatt_client_context.get_auth_req().into();
 
@@ -1314,11 +2151,7 @@ impl CommandHandler {
                     .parse::<i32>()
                     .or(Err("Failed to parse end handle"))?;
 
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 let auth_req = self.lock_context().gatt_client_context.get_auth_req().into();
 
@@ -1331,6 +2164,38 @@ impl CommandHandler {
                     auth_req,
                 );
             }
+            "write-descriptor" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let handle = String::from(get_arg(args, 2)?)
+                    .parse::<i32>()
+                    .or(Err("Failed to parse handle"))?;
+                let value = hex::decode(get_arg(args, 3)?).or(Err("Failed to parse value"))?;
+
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+
+                let auth_req = self.lock_context().gatt_client_context.get_auth_req().into();
+
+                self.lock_context()
+                    .gatt_dbus
+                    .as_ref()
+                    .unwrap()
+                    .write_descriptor(client_id, addr, handle, auth_req, value);
+            }
+            "read-descriptor" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let handle = String::from(get_arg(args, 2)?)
+                    .parse::<i32>()
+                    .or(Err("Failed to parse handle"))?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+
+                let auth_req = self.lock_context().gatt_client_context.get_auth_req().into();
+
+                self.lock_context()
+                    .gatt_dbus
+                    .as_ref()
+                    .unwrap()
+                    .read_descriptor(client_id, addr, handle, auth_req);
+            }
             "register-notification" => {
                 let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
                 let handle = String::from(get_arg(args, 2)?)
@@ -1344,11 +2209,7 @@ impl CommandHandler {
                     }
                 };
 
-                let client_id = self
-                    .lock_context()
-                    .gatt_client_context
-                    .client_id
-                    .ok_or("GATT client is not yet registered.")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
 
                 self.lock_context()
                     .gatt_dbus
@@ -1356,6 +2217,39 @@ impl CommandHandler {
                     .unwrap()
                     .register_for_notification(client_id, addr, handle, enable);
             }
+            "persist-notification" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let handle = String::from(get_arg(args, 2)?)
+                    .parse::<i32>()
+                    .or(Err("Failed to parse handle"))?;
+                let persist = String::from(get_arg(args, 3)?)
+                    .parse::<bool>()
+                    .or(Err("Failed to parse persist"))?;
+
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+
+                if !self
+                    .lock_context()
+                    .gatt_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_persist_notifications(client_id, addr, handle, persist)
+                {
+                    return Err("Failed to set notification persistence".into());
+                }
+            }
+            "list-persisted-notifications" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let client_id = self.get_gatt_client_id(client_tag.as_deref())?;
+
+                let handles = self
+                    .lock_context()
+                    .gatt_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_persisted_notifications(client_id, addr);
+                print_info!("Persisted notifications of {}: {:?}", addr.to_string(), handles);
+            }
             "register-server" => {
                 let dbus_connection = self.lock_context().dbus_connection.clone();
                 let dbus_crossroads = self.lock_context().dbus_crossroads.clone();
@@ -1529,6 +2423,38 @@ impl CommandHandler {
 
                 self.lock_context().pending_gatt_request = None;
             }
+            "server-notify" => {
+                let server_id = String::from(get_arg(args, 1)?)
+                    .parse::<i32>()
+                    .or(Err("Failed to parse server_id"))?;
+                let handle = String::from(get_arg(args, 2)?)
+                    .parse::<i32>()
+                    .or(Err("Failed to parse handle"))?;
+                let confirm = String::from(get_arg(args, 3)?)
+                    .parse::<bool>()
+                    .or(Err("Failed to parse confirm"))?;
+                let value = hex::decode(get_arg(args, 4)?).or(Err("Failed to parse value"))?;
+
+                let client_addr = self
+                    .lock_context()
+                    .gatt_server_context
+                    .get_target_client(server_id)
+                    .ok_or("No client is connected to the server")?;
+                if !self.lock_context().gatt_dbus.as_ref().unwrap().send_notification(
+                    server_id,
+                    client_addr,
+                    handle,
+                    confirm,
+                    value,
+                ) {
+                    return Err("Failed to send notification".into());
+                }
+                print_info!(
+                    "Sent {} to {}",
+                    if confirm { "indication" } else { "notification" },
+                    client_addr.to_string()
+                );
+            }
             "server-set-direct-connect" => {
                 let is_direct = String::from(get_arg(args, 1)?)
                     .parse::<bool>()
@@ -1586,22 +2512,39 @@ impl CommandHandler {
                 let scanner_id = String::from(get_arg(args, 1)?)
                     .parse::<u8>()
                     .or(Err("Failed parsing scanner id"))?;
+                let (duration, max_results) = parse_scan_limits(&args[2..])?;
+                let is_one_shot = duration.is_some() || max_results.is_some();
+
+                // Scan results don't tell which scanner found them, so they can only be counted
+                // toward a one-shot scan that runs alone.
+                if let Some(scan) = self.lock_context().one_shot_scan.as_ref() {
+                    return Err(format!(
+                        "One-shot scan on scanner {} is running, stop it first",
+                        scan.scanner_id
+                    )
+                    .into());
+                }
+                if is_one_shot && !self.lock_context().active_scanner_ids.is_empty() {
+                    return Err("A one-shot scan can't run alongside other scans".into());
+                }
+
+                let filter =
+                    build_service_uuid_scan_filter(&self.lock_context().scan_filter_uuids)?;
 
                 self.lock_context().gatt_dbus.as_mut().unwrap().start_scan(
                     scanner_id,
-                    // TODO(b/254870159): Construct real settings and filters depending on
-                    // command line options.
+                    // TODO(b/254870159): Construct real settings depending on command line
+                    // options.
                     None,
-                    Some(btstack::bluetooth_gatt::ScanFilter {
-                        rssi_high_threshold: 0,
-                        rssi_low_threshold: 0,
-                        rssi_low_timeout: 0,
-                        rssi_sampling_period: 0,
-                        condition: btstack::bluetooth_gatt::ScanFilterCondition::Patterns(vec![]),
-                    }),
+                    Some(filter),
                 );
 
                 self.lock_context().active_scanner_ids.insert(scanner_id);
+
+                // The scanner itself doesn't stop; the client stops it once a limit is reached.
+                if is_one_shot {
+                    self.lock_context().track_one_shot_scan(scanner_id, duration, max_results);
+                }
             }
             "stop-scan" => {
                 let scanner_id = String::from(get_arg(args, 1)?)
@@ -1610,6 +2553,34 @@ impl CommandHandler {
 
                 self.lock_context().gatt_dbus.as_mut().unwrap().stop_scan(scanner_id);
                 self.lock_context().active_scanner_ids.remove(&scanner_id);
+                let mut context = self.lock_context();
+                if matches!(&context.one_shot_scan, Some(scan) if scan.scanner_id == scanner_id) {
+                    context.one_shot_scan = None;
+                }
+            }
+            "set-filter" => {
+                let uuid = Uuid::from_string(get_arg(args, 1)?).ok_or("Invalid UUID")?;
+
+                let mut context = self.lock_context();
+                let mut uuids = context.scan_filter_uuids.clone();
+                uuids.insert(uuid);
+                // Refuse UUIDs that would make the filter too large to offload.
+                build_service_uuid_scan_filter(&uuids)?;
+                context.scan_filter_uuids = uuids;
+                print_info!(
+                    "Scan filter service UUIDs: {}",
+                    DisplayList(
+                        context
+                            .scan_filter_uuids
+                            .iter()
+                            .map(|&x| UuidHelper::known_uuid_to_string(&x))
+                            .collect::<Vec<String>>()
+                    )
+                );
+            }
+            "clear-filter" => {
+                self.lock_context().scan_filter_uuids.clear();
+                print_info!("Scan filter cleared");
             }
             _ => return Err(CommandError::InvalidArgs),
         }
@@ -1632,18 +2603,83 @@ impl CommandHandler {
 
         let command = get_arg(args, 0)?;
 
+        if matches!(&command[..], "on" | "ext") {
+            let context = self.lock_context();
+            if let Some(name) = args.get(1) {
+                if context.adv_sets.values().any(|s| s.name.as_ref() == Some(name)) {
+                    return Err(format!("Advertising set '{}' already exists", name).into());
+                }
+            }
+            if !context.adv_sets.is_empty()
+                && !context.adapter_dbus.as_ref().unwrap().is_multi_advertisement_supported()
+            {
+                return Err(CommandError::Unsupported(
+                    "Multiple advertising sets are not supported".into(),
+                ));
+            }
+        }
+
         match &command[..] {
             "on" => {
                 print_info!("Creating legacy advertising set...");
-                let s = AdvSet::new(true); // legacy advertising
+                let s = AdvSet::new(true, args.get(1).cloned()); // legacy advertising
                 AdvSet::start(self.context.clone(), s, callback_id);
             }
             "off" => {
                 AdvSet::stop_all(self.context.clone());
             }
+            "list" => {
+                let context = self.lock_context();
+                if context.adv_sets.is_empty() {
+                    print_info!("No advertising sets");
+                }
+                for s in context.adv_sets.values() {
+                    print_info!("{}", s.describe());
+                }
+            }
+            "enable" | "disable" => {
+                let enable = command == "enable";
+                let mut context = self.lock_context();
+                let adv_id = AdvSet::find_adv_id(&context.adv_sets, get_arg(args, 1)?)
+                    .ok_or(CommandError::NotFound("Failed to find advertising set".into()))?;
+                let (duration, max_ext_adv_events) = context
+                    .adv_sets
+                    .values()
+                    .find(|s| s.adv_id == Some(adv_id))
+                    .map(|s| (s.duration, s.max_ext_adv_events))
+                    .unwrap_or_default();
+
+                print_info!(
+                    "{} advertising set {}",
+                    if enable { "Enabling" } else { "Disabling" },
+                    adv_id
+                );
+                context.gatt_dbus.as_mut().unwrap().enable_advertising_set(
+                    adv_id,
+                    enable,
+                    duration,
+                    max_ext_adv_events,
+                );
+            }
+            "stop" => {
+                let adv_id = AdvSet::find_adv_id(&self.lock_context().adv_sets, get_arg(args, 1)?)
+                    .ok_or(CommandError::NotFound("Failed to find advertising set".into()))?;
+                AdvSet::stop(self.context.clone(), adv_id);
+            }
             "ext" => {
+                if !self
+                    .lock_context()
+                    .adapter_dbus
+                    .as_ref()
+                    .unwrap()
+                    .is_le_extended_advertising_supported()
+                {
+                    return Err(CommandError::Unsupported(
+                        "LE extended advertising is not supported".into(),
+                    ));
+                }
                 print_info!("Creating extended advertising set...");
-                let s = AdvSet::new(false); // extended advertising
+                let s = AdvSet::new(false, args.get(1).cloned()); // extended advertising
                 AdvSet::start(self.context.clone(), s, callback_id);
             }
             "set-interval" => {
@@ -1669,6 +2705,28 @@ impl CommandHandler {
                     context.gatt_dbus.as_mut().unwrap().set_advertising_parameters(adv_id, params);
                 }
             }
+            "set-tx-power" => {
+                let level = AdvTxPower::from_level_name(get_arg(args, 1)?)
+                    .ok_or("Failed parsing TX power")?;
+
+                let mut context = self.lock_context();
+                context
+                    .adv_sets
+                    .iter_mut()
+                    .for_each(|(_, s)| s.params.tx_power_level = level.to_dbm());
+
+                // Active sets are restarted by the stack with the new parameters. The power the
+                // controller actually selected is reported through the advertising callbacks.
+                let advs: Vec<(_, _)> = context
+                    .adv_sets
+                    .iter()
+                    .filter_map(|(_, s)| s.adv_id.map(|adv_id| (adv_id, s.params.clone())))
+                    .collect();
+                for (adv_id, params) in advs {
+                    print_info!("Setting advertising parameters for {}", adv_id);
+                    context.gatt_dbus.as_mut().unwrap().set_advertising_parameters(adv_id, params);
+                }
+            }
             "set-connectable" => {
                 let connectable = match &get_arg(args, 1)?[..] {
                     "on" => true,
@@ -1703,6 +2761,90 @@ impl CommandHandler {
                     context.gatt_dbus.as_mut().unwrap().set_advertising_data(adv_id, data);
                 }
             }
+            "start-directed" => {
+                let adv_id = String::from(get_arg(args, 1)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing adv_id"))?;
+                let peer_address =
+                    RawAddress::from_string(get_arg(args, 2)?).ok_or("Invalid Address")?;
+                let peer_address_type = match &get_arg(args, 3)?[..] {
+                    "public" => 0,
+                    "random" => 1,
+                    other => return Err(format!("Invalid address type '{}'", other).into()),
+                };
+                // Low duty cycle keeps advertising at the set interval until stopped. High duty
+                // cycle reconnects fastest but only lasts 1.28 s and needs a legacy set.
+                let directed_mode = match args.get(4).map(|s| &s[..]) {
+                    None | Some("low-duty") => DirectedAdvertisingMode::LowDutyCycle,
+                    Some("high-duty") => DirectedAdvertisingMode::HighDutyCycle,
+                    Some(other) => return Err(format!("Invalid duty cycle '{}'", other).into()),
+                };
+
+                let mut context = self.context.lock().unwrap();
+
+                let device = BluetoothDevice { address: peer_address, name: String::from("") };
+                if context.adapter_dbus.as_ref().unwrap().get_bond_state(device)
+                    != BtBondState::Bonded
+                {
+                    return Err(format!("{} is not bonded", peer_address.to_string()).into());
+                }
+
+                let s = context
+                    .adv_sets
+                    .values_mut()
+                    .find(|s| s.adv_id == Some(adv_id))
+                    .ok_or(CommandError::NotFound("Failed to find advertising set".into()))?;
+                if directed_mode == DirectedAdvertisingMode::HighDutyCycle && !s.params.is_legacy {
+                    return Err("High duty cycle directed advertising needs a legacy set".into());
+                }
+                s.params.connectable = true;
+                s.params.directed_mode = Some(directed_mode);
+                s.params.peer_address = Some(peer_address);
+                s.params.peer_address_type = Some(peer_address_type);
+                let params = s.params.clone();
+
+                print_info!(
+                    "Directing advertising set {} to {} ({:?})",
+                    adv_id,
+                    peer_address.to_string(),
+                    directed_mode
+                );
+                context.gatt_dbus.as_mut().unwrap().set_advertising_parameters(adv_id, params);
+            }
+            "set-limits" => {
+                let adv_id = String::from(get_arg(args, 1)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing adv_id"))?;
+                let duration_ms = String::from(get_arg(args, 2)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing duration"))?;
+                let max_events = String::from(get_arg(args, 3)?)
+                    .parse::<i32>()
+                    .or(Err("Failed parsing max events"))?;
+
+                let mut context = self.context.lock().unwrap();
+
+                let s = context
+                    .adv_sets
+                    .values_mut()
+                    .find(|s| s.adv_id == Some(adv_id))
+                    .ok_or(CommandError::NotFound("Failed to find advertising set".into()))?;
+                s.set_limits(duration_ms, max_events)?;
+                let (duration, max_ext_adv_events) = (s.duration, s.max_ext_adv_events);
+
+                print_info!(
+                    "Limiting advertising set {} to {} ms and {} events",
+                    adv_id,
+                    duration_ms,
+                    max_events
+                );
+                context.gatt_dbus.as_mut().unwrap().enable_advertising_set(
+                    adv_id,
+                    true,
+                    duration,
+                    max_ext_adv_events,
+                );
+            }
             "set-scan-rsp" => {
                 let enable = match &get_arg(args, 1)?[..] {
                     "enable" => true,
@@ -1735,10 +2877,13 @@ impl CommandHandler {
                     .or(Err("Failed parsing adv_id"))?;
 
                 let mut context = self.context.lock().unwrap();
-                if !context.adv_sets.iter().any(|(_, s)| s.adv_id.map_or(false, |id| id == adv_id))
-                {
-                    return Err("Failed to find advertising set".into());
-                }
+                let s = context
+                    .adv_sets
+                    .values_mut()
+                    .find(|s| s.adv_id == Some(adv_id))
+                    .ok_or(CommandError::NotFound("Failed to find advertising set".into()))?;
+                validate_raw_adv_data(&data, s.params.is_legacy)?;
+                s.raw_data = Some(data.clone());
 
                 print_info!("Setting advertising data for {}", adv_id);
                 context.gatt_dbus.as_mut().unwrap().set_raw_adv_data(adv_id, data);
@@ -1813,6 +2958,20 @@ impl CommandHandler {
 
                 self.context.lock().unwrap().socket_test_schedule = Some(schedule);
             }
+            "show-schedule" => match self.context.lock().unwrap().socket_test_schedule {
+                Some(SocketSchedule { num_frame, send_interval, disconnect_delay }) => {
+                    print_info!(
+                        "Socket schedule: num_frame={}, send_interval={:?}, disconnect_delay={:?}",
+                        num_frame,
+                        send_interval,
+                        disconnect_delay
+                    );
+                }
+                None => print_info!("No socket schedule set"),
+            },
+            "clear-schedule" => {
+                self.context.lock().unwrap().socket_test_schedule = None;
+            }
             "send-msc" => {
                 let dlci =
                     String::from(get_arg(args, 1)?).parse::<u8>().or(Err("Failed parsing DLCI"))?;
@@ -1820,9 +2979,14 @@ impl CommandHandler {
                 self.context.lock().unwrap().qa_dbus.as_mut().unwrap().rfcomm_send_msc(dlci, addr);
             }
             "listen-rfcomm" => {
-                let scn = String::from(get_arg(args, 1)?)
-                    .parse::<i32>()
-                    .or(Err("Failed parsing Service Channel Number"))?;
+                // A free channel is allocated if none is given, and reported once the socket is
+                // ready.
+                let scn = match &get_arg(args, 1)?[..] {
+                    "auto" => None,
+                    scn => {
+                        Some(scn.parse::<i32>().or(Err("Failed parsing Service Channel Number"))?)
+                    }
+                };
                 let SocketResult { status, id } = self
                     .context
                     .lock()
@@ -1830,7 +2994,7 @@ impl CommandHandler {
                     .socket_manager_dbus
                     .as_mut()
                     .unwrap()
-                    .listen_using_rfcomm(callback_id, Some(scn), None, None, None);
+                    .listen_using_rfcomm(callback_id, scn, None, None, None);
                 if status != BtStatus::Success {
                     return Err(format!(
                         "Failed to request for listening using rfcomm, status = {:?}",
@@ -1986,6 +3150,34 @@ impl CommandHandler {
                     .into());
                 }
             }
+            "list" => {
+                let sockets = self
+                    .context
+                    .lock()
+                    .unwrap()
+                    .socket_manager_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_active_sockets(callback_id);
+                print_info!("Active sockets: {}", DisplayList(sockets));
+            }
+            "ping" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let size = match args.get(2) {
+                    Some(size) => size.parse::<u16>().or(Err("Failed parsing size"))?,
+                    None => DEFAULT_L2CAP_PING_SIZE,
+                };
+                let started =
+                    self.context.lock().unwrap().socket_manager_dbus.as_mut().unwrap().l2cap_ping(
+                        callback_id,
+                        addr,
+                        size,
+                    );
+                if !started {
+                    return Err(format!("Failed to ping {}", addr.to_string()).into());
+                }
+                print_info!("Pinging {} with {} bytes", addr.to_string(), size);
+            }
 
             _ => return Err(CommandError::InvalidArgs),
         };
@@ -2055,6 +3247,28 @@ impl CommandHandler {
                     .unwrap()
                     .send_hid_virtual_unplug(addr);
             }
+            "disconnect" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let reconnect_allowed = match &get_arg(args, 2)?[..] {
+                    "true" => true,
+                    "false" => false,
+                    other => {
+                        return Err(format!("Invalid allow-reconnect value '{}'", other).into());
+                    }
+                };
+                let device = BluetoothDevice { address: addr, name: String::from("") };
+                let status = self
+                    .context
+                    .lock()
+                    .unwrap()
+                    .adapter_dbus
+                    .as_mut()
+                    .unwrap()
+                    .disconnect_hid(device, reconnect_allowed);
+                if status != BtStatus::Success {
+                    return Err(format!("Failed to disconnect HID: {:?}", status).into());
+                }
+            }
             _ => return Err(CommandError::InvalidArgs),
         };
 
@@ -2083,8 +3297,68 @@ impl CommandHandler {
                 }
             }
             "found" => {
+                // Split off the file to export to, if any.
+                let (args, out) = match args.iter().position(|a| a == "--out") {
+                    Some(i) => {
+                        let out = get_arg(args, i + 1)?.clone();
+                        let mut rest = args.to_vec();
+                        rest.drain(i..=i + 1);
+                        (rest, Some(out))
+                    }
+                    None => (args.to_vec(), None),
+                };
+
+                let device_type = match args.get(1).map(String::as_str) {
+                    None => None,
+                    Some("bredr") => Some(BtDeviceType::Bredr),
+                    Some("le") => Some(BtDeviceType::Ble),
+                    Some("dual") => Some(BtDeviceType::Dual),
+                    Some(other) => {
+                        return Err(format!("Invalid device type '{}'", other).into());
+                    }
+                };
+
+                if let Some(out) = out {
+                    let context = self.lock_context();
+                    let adapter_dbus = context.adapter_dbus.as_ref().unwrap();
+                    let mut csv = String::from("address,name,type,rssi,last_seen_ms_ago\n");
+                    let mut count = 0;
+                    for found in adapter_dbus.get_found_devices_with_age() {
+                        let remote_type = adapter_dbus.get_remote_type(found.device.clone());
+                        if device_type.as_ref().map_or(false, |t| *t != remote_type) {
+                            continue;
+                        }
+                        let rssi = adapter_dbus.get_remote_rssi(found.device.clone());
+                        csv.push_str(&format!(
+                            "{},{},{:?},{},{}\n",
+                            found.device.address.to_string(),
+                            csv_escape(&found.device.name),
+                            remote_type,
+                            rssi,
+                            found.age_ms
+                        ));
+                        count += 1;
+                    }
+                    std::fs::write(&out, csv)
+                        .map_err(|e| format!("Failed to write to {}: {}", out, e))?;
+                    print_info!("Exported {} found devices to {}", count, out);
+                    return Ok(());
+                }
+
                 print_info!("Devices found in most recent discovery session:");
-                for (key, val) in self.lock_context().found_devices.iter() {
+                let found_devices = self.lock_context().found_devices.clone();
+                for (key, val) in found_devices.iter() {
+                    if let Some(device_type) = &device_type {
+                        let remote_type = self
+                            .lock_context()
+                            .adapter_dbus
+                            .as_ref()
+                            .unwrap()
+                            .get_remote_type(val.clone());
+                        if remote_type != *device_type {
+                            continue;
+                        }
+                    }
                     print_info!("[{:17}] {}", key, val.name);
                 }
             }
@@ -2321,6 +3595,9 @@ impl CommandHandler {
                     RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?,
                 );
             }
+            "clear-calls" => {
+                self.context.lock().unwrap().telephony_dbus.as_mut().unwrap().clear_calls();
+            }
             other => {
                 return Err(format!("Invalid argument '{}'", other).into());
             }
@@ -2349,6 +3626,233 @@ impl CommandHandler {
                     .unwrap()
                     .add_media_player(name, browsing_supported);
             }
+            "callback-counts" => {
+                let mut counts: Vec<(String, u32)> = self
+                    .context
+                    .lock()
+                    .unwrap()
+                    .qa_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_callback_counts()
+                    .into_iter()
+                    .collect();
+                counts.sort();
+                for (name, count) in counts {
+                    print_info!("{}: {}", name, count);
+                }
+            }
+            "link-errors" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                self.context
+                    .lock()
+                    .unwrap()
+                    .qa_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_link_error_counters(addr);
+            }
+            "reset-link-errors" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                self.context
+                    .lock()
+                    .unwrap()
+                    .qa_dbus
+                    .as_ref()
+                    .unwrap()
+                    .reset_failed_contact_counter(addr);
+            }
+            "set-power-mode" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                let (mode, params) = match &get_arg(args, 2)?[..] {
+                    "active" => (LinkPowerMode::Active, SniffParams::default()),
+                    "sniff" => {
+                        let mut values = [0u16; 4];
+                        for (i, value) in values.iter_mut().enumerate() {
+                            *value = String::from(get_arg(args, 3 + i)?)
+                                .parse::<u16>()
+                                .or(Err("Failed parsing sniff parameters"))?;
+                        }
+                        let [max_interval, min_interval, attempt, timeout] = values;
+                        (
+                            LinkPowerMode::Sniff,
+                            SniffParams { max_interval, min_interval, attempt, timeout },
+                        )
+                    }
+                    other => {
+                        return Err(format!("Invalid power mode '{}'", other).into());
+                    }
+                };
+                let status = self
+                    .context
+                    .lock()
+                    .unwrap()
+                    .qa_dbus
+                    .as_ref()
+                    .unwrap()
+                    .set_link_power_mode(addr, mode, params);
+                if status != BtStatus::Success {
+                    return Err(format!("Failed to set power mode: {:?}", status).into());
+                }
+            }
+            "get-power-mode" => {
+                let addr = RawAddress::from_string(get_arg(args, 1)?).ok_or("Invalid Address")?;
+                self.context.lock().unwrap().qa_dbus.as_ref().unwrap().get_link_power_mode(addr);
+            }
+            "accept-list" => match &get_arg(args, 1)?[..] {
+                "add" => {
+                    let addr =
+                        RawAddress::from_string(get_arg(args, 2)?).ok_or("Invalid Address")?;
+                    let addr_type = match &get_arg(args, 3)?[..] {
+                        "public" => BtAddrType::Public,
+                        "random" => BtAddrType::Random,
+                        other => {
+                            return Err(format!("Invalid address type '{}'", other).into());
+                        }
+                    };
+                    let status = self
+                        .context
+                        .lock()
+                        .unwrap()
+                        .qa_dbus
+                        .as_mut()
+                        .unwrap()
+                        .add_to_accept_list(addr, addr_type);
+                    if status != BtStatus::Success {
+                        return Err(format!("Failed to add to accept list: {:?}", status).into());
+                    }
+                }
+                "remove" => {
+                    let addr =
+                        RawAddress::from_string(get_arg(args, 2)?).ok_or("Invalid Address")?;
+                    let status = self
+                        .context
+                        .lock()
+                        .unwrap()
+                        .qa_dbus
+                        .as_mut()
+                        .unwrap()
+                        .remove_from_accept_list(addr);
+                    if status != BtStatus::Success {
+                        return Err(
+                            format!("Failed to remove from accept list: {:?}", status).into()
+                        );
+                    }
+                    print_info!("Removed {} from accept list", addr.to_string());
+                }
+                "show" => {
+                    let entries =
+                        self.context.lock().unwrap().qa_dbus.as_ref().unwrap().get_accept_list();
+                    print_info!("Accept list ({} devices):", entries.len());
+                    for entry in entries {
+                        print_info!("  {} ({:?})", entry.address.to_string(), entry.addr_type);
+                    }
+                }
+                other => {
+                    return Err(format!("Invalid accept-list command '{}'", other).into());
+                }
+            },
+            "set-adapter-mode" => {
+                let mode = match &get_arg(args, 1)?[..] {
+                    "dual" => AdapterMode::DualMode,
+                    "bredr" => AdapterMode::BrEdrOnly,
+                    "le" => AdapterMode::LeOnly,
+                    other => {
+                        return Err(format!("Invalid adapter mode '{}'", other).into());
+                    }
+                };
+                let status =
+                    self.context.lock().unwrap().qa_dbus.as_mut().unwrap().set_adapter_mode(mode);
+                if status != BtStatus::Success {
+                    return Err(format!("Failed to set adapter mode: {:?}", status).into());
+                }
+            }
+            "get-adapter-mode" => {
+                let mode =
+                    self.context.lock().unwrap().qa_dbus.as_ref().unwrap().get_adapter_mode();
+                print_info!("Adapter mode: {:?}", mode);
+            }
+            "scan-params" | "page-scan-params" => {
+                let is_page = command == "page-scan-params";
+                let scan = if is_page { "Page" } else { "Inquiry" };
+                match &get_arg(args, 1)?[..] {
+                    "get" => {
+                        let context = self.context.lock().unwrap();
+                        let qa = context.qa_dbus.as_ref().unwrap();
+                        let params =
+                            if is_page { qa.get_page_scan_params() } else { qa.get_scan_params() };
+                        print_info!("{} scan interval: {} slots", scan, params.interval);
+                        print_info!("{} scan window: {} slots", scan, params.window);
+                        print_info!("{} scan type: {:?}", scan, params.scan_type);
+                    }
+                    "set" => {
+                        let interval = String::from(get_arg(args, 2)?)
+                            .parse::<u16>()
+                            .or(Err("Failed parsing interval"))?;
+                        let window = String::from(get_arg(args, 3)?)
+                            .parse::<u16>()
+                            .or(Err("Failed parsing window"))?;
+                        let mut context = self.context.lock().unwrap();
+                        let qa = context.qa_dbus.as_mut().unwrap();
+                        let scan_type = match args.get(4).map(String::as_str) {
+                            None if is_page => qa.get_page_scan_params().scan_type,
+                            None => qa.get_scan_params().scan_type,
+                            Some("standard") => ClassicScanType::Standard,
+                            Some("interlaced") => ClassicScanType::Interlaced,
+                            Some(other) => {
+                                return Err(format!("Invalid scan type '{}'", other).into());
+                            }
+                        };
+                        let status = if is_page {
+                            qa.set_page_scan_params(interval, window, scan_type)
+                        } else {
+                            qa.set_scan_params(interval, window, scan_type)
+                        };
+                        if status != BtStatus::Success {
+                            return Err(format!(
+                                "Failed to set {} scan params: {:?}",
+                                scan.to_lowercase(),
+                                status
+                            )
+                            .into());
+                        }
+                    }
+                    _ => return Err(CommandError::InvalidArgs),
+                }
+            }
+            "auto-accept-just-works" => {
+                let enabled = match &get_arg(args, 1)?[..] {
+                    "on" => true,
+                    "off" => false,
+                    _ => return Err(CommandError::InvalidArgs),
+                };
+                self.context
+                    .lock()
+                    .unwrap()
+                    .qa_dbus
+                    .as_mut()
+                    .unwrap()
+                    .set_auto_accept_just_works(enabled);
+            }
+            "metrics-dump" => {
+                let events =
+                    self.context.lock().unwrap().qa_dbus.as_ref().unwrap().get_recent_metrics();
+                print_info!("Recent metrics events (oldest first):");
+                for event in events {
+                    print_info!("  {}", event);
+                }
+            }
+            "dropped-messages" => {
+                let count = self
+                    .context
+                    .lock()
+                    .unwrap()
+                    .qa_dbus
+                    .as_ref()
+                    .unwrap()
+                    .get_dropped_message_count();
+                print_info!("Stack events dropped: {}", count);
+            }
             _ => return Err(CommandError::InvalidArgs),
         };
 
@@ -2422,6 +3926,58 @@ impl CommandHandler {
 mod tests {
 
     use super::*;
+    use btstack::bluetooth_gatt::{ScanFilterCondition, ScanFilterPattern};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_split_gatt_client_tag() {
+        let args: Vec<String> = ["client-connect", "--client", "second", "11:22:33:44:55:66"]
+            .map(String::from)
+            .to_vec();
+        let (tag, rest) = split_gatt_client_tag(&args).ok().unwrap();
+        assert_eq!(tag.as_deref(), Some("second"));
+        assert_eq!(rest, vec!["client-connect", "11:22:33:44:55:66"]);
+
+        let args: Vec<String> = ["client-connect", "11:22:33:44:55:66"].map(String::from).to_vec();
+        let (tag, rest) = split_gatt_client_tag(&args).ok().unwrap();
+        assert_eq!(tag, None);
+        assert_eq!(rest, args);
+
+        let args: Vec<String> = ["register-client", "--client"].map(String::from).to_vec();
+        assert!(split_gatt_client_tag(&args).is_err());
+
+        let args: Vec<String> = ["register-client", "--client", "a/b"].map(String::from).to_vec();
+        assert!(split_gatt_client_tag(&args).is_err());
+    }
+
+    #[test]
+    fn test_parse_scan_limits() {
+        let args: Vec<String> =
+            ["--max-results", "5", "--duration", "1500"].map(String::from).to_vec();
+        let (duration, max_results) = parse_scan_limits(&args).ok().unwrap();
+        assert_eq!(duration, Some(Duration::from_millis(1500)));
+        assert_eq!(max_results, Some(5));
+
+        let (duration, max_results) = parse_scan_limits(&[]).ok().unwrap();
+        assert_eq!(duration, None);
+        assert_eq!(max_results, None);
+
+        let args: Vec<String> = ["--duration"].map(String::from).to_vec();
+        assert!(parse_scan_limits(&args).is_err());
+
+        let args: Vec<String> = ["--max-results", "0"].map(String::from).to_vec();
+        assert!(parse_scan_limits(&args).is_err());
+
+        let args: Vec<String> = ["--count", "5"].map(String::from).to_vec();
+        assert!(parse_scan_limits(&args).is_err());
+    }
+
+    #[test]
+    fn test_gatt_client_app_uuid() {
+        assert_eq!(gatt_client_app_uuid(0), GATT_CLIENT_APP_UUID);
+        assert_eq!(gatt_client_app_uuid(1), "12345679123456781234567812345678");
+        assert_ne!(gatt_client_app_uuid(1), GATT_SERVER_APP_UUID);
+    }
 
     #[test]
     fn test_wrap_help_text() {
@@ -2452,4 +4008,166 @@ mod tests {
         assert_eq!("|  |", wrap_help_text("", 1, 1));
         assert_eq!("| |", wrap_help_text("", 0, 1));
     }
+
+    #[test]
+    fn test_format_gatt_db() {
+        let mut service = BluetoothGattService::new(
+            Uuid::from_string("00001800-0000-1000-8000-00805f9b34fb").unwrap(),
+            1,
+            0,
+        );
+        let mut characteristic = BluetoothGattCharacteristic::new(
+            Uuid::from_string("00002a00-0000-1000-8000-00805f9b34fb").unwrap(),
+            3,
+            BluetoothGattCharacteristic::PROPERTY_READ
+                | BluetoothGattCharacteristic::PROPERTY_NOTIFY,
+            0,
+        );
+        characteristic.descriptors.push(BluetoothGattDescriptor::new(
+            Uuid::from_string("00002902-0000-1000-8000-00805f9b34fb").unwrap(),
+            4,
+            0,
+        ));
+        service.characteristics.push(characteristic);
+
+        assert_eq!(
+            format_gatt_db(&[service]),
+            vec![
+                "Primary Service 0x0001 00001800-0000-1000-8000-00805f9b34fb: Generic Access",
+                "  Characteristic 0x0003 00002a00-0000-1000-8000-00805f9b34fb: Device Name [Read|Notify]",
+                "    Descriptor 0x0004 00002902-0000-1000-8000-00805f9b34fb: Client Characteristic Configuration",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csv_escape() {
+        assert_eq!(csv_escape("Speaker"), "Speaker");
+        assert_eq!(csv_escape(""), "");
+        assert_eq!(csv_escape("Living room, left"), "\"Living room, left\"");
+        assert_eq!(csv_escape("My \"Mouse\""), "\"My \"\"Mouse\"\"\"");
+        assert_eq!(csv_escape("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_build_service_uuid_scan_filter() {
+        let uuid = |uuid: &str| Uuid::from_string(uuid).unwrap();
+        let patterns = |uuids: &[Uuid]| {
+            let uuids: HashSet<Uuid> = uuids.iter().cloned().collect();
+            match build_service_uuid_scan_filter(&uuids).unwrap().condition {
+                ScanFilterCondition::Patterns(patterns) => patterns,
+                _ => panic!("Unexpected scan filter condition"),
+            }
+        };
+        // Matches an AD structure the way the controller does.
+        let matches = |patterns: &[ScanFilterPattern], ad_type: u8, data: &[u8]| {
+            patterns.iter().any(|p| {
+                let start = p.start_position as usize;
+                p.ad_type == ad_type
+                    && data.get(start..start + p.content.len()) == Some(&p.content[..])
+            })
+        };
+        let battery = uuid("0000180f-0000-1000-8000-00805f9b34fb");
+
+        // 16-bit UUIDs are matched at every even position, in both service UUID lists.
+        let patterns_16 = patterns(&[battery]);
+        assert_eq!(patterns_16.len(), 28);
+        assert!(patterns_16.iter().all(|p| p.content == vec![0x0f, 0x18]));
+        assert!(matches(&patterns_16, 0x03, &[0x0f, 0x18]));
+        // A UUID listed after others is still matched.
+        assert!(matches(&patterns_16, 0x02, &[0x0a, 0x18, 0x12, 0x18, 0x0f, 0x18]));
+        assert!(!matches(&patterns_16, 0x03, &[0x0a, 0x18, 0x12, 0x18]));
+
+        // 32-bit UUIDs are matched at every 4-byte aligned position.
+        let patterns_32 = patterns(&[uuid("1234180f-0000-1000-8000-00805f9b34fb")]);
+        assert_eq!(patterns_32.len(), 14);
+        assert!(matches(&patterns_32, 0x05, &[0x01, 0x02, 0x03, 0x04, 0x0f, 0x18, 0x34, 0x12]));
+
+        // Only a single 128-bit UUID fits in an AD structure.
+        let uuid_128 = |i: u8| uuid(&format!("12345678-9abc-def0-1234-56789abcde{:02x}", i));
+        let patterns_128 = patterns(&[uuid_128(0)]);
+        assert_eq!(patterns_128.len(), 2);
+        assert!(patterns_128.iter().all(|p| p.start_position == 0 && p.content.len() == 16));
+
+        // Filters that don't fit in a single monitor command are refused.
+        let uuids: HashSet<Uuid> = (0..6).map(uuid_128).collect();
+        assert!(build_service_uuid_scan_filter(&uuids).is_ok());
+        let uuids: HashSet<Uuid> = (0..7).map(uuid_128).collect();
+        assert!(build_service_uuid_scan_filter(&uuids).is_err());
+        let uuids = HashSet::from([battery, uuid("0000180a-0000-1000-8000-00805f9b34fb")]);
+        assert!(build_service_uuid_scan_filter(&uuids).is_err());
+    }
+
+    #[test]
+    fn test_find_adv_id() {
+        let mut named = AdvSet::new(true, Some(String::from("beacon")));
+        named.adv_id = Some(3);
+        let mut unnamed = AdvSet::new(false, None);
+        unnamed.adv_id = Some(5);
+        let starting = AdvSet::new(true, Some(String::from("pending")));
+        let adv_sets = HashMap::from([(1, named), (2, unnamed), (4, starting)]);
+
+        assert_eq!(AdvSet::find_adv_id(&adv_sets, "beacon"), Some(3));
+        assert_eq!(AdvSet::find_adv_id(&adv_sets, "3"), Some(3));
+        assert_eq!(AdvSet::find_adv_id(&adv_sets, "5"), Some(5));
+        // Sets that haven't started yet have no ID to refer to.
+        assert_eq!(AdvSet::find_adv_id(&adv_sets, "pending"), None);
+        assert_eq!(AdvSet::find_adv_id(&adv_sets, "1"), None);
+        assert_eq!(AdvSet::find_adv_id(&adv_sets, "unknown"), None);
+    }
+
+    #[test]
+    fn test_validate_raw_adv_data() {
+        // Flags and a complete local name.
+        assert!(
+            validate_raw_adv_data(&[0x02, 0x01, 0x06, 0x04, 0x09, 0x61, 0x62, 0x63], true).is_ok()
+        );
+        // Zero length ends the significant part.
+        assert!(validate_raw_adv_data(&[0x02, 0x01, 0x06, 0x00, 0xff], true).is_ok());
+        assert!(validate_raw_adv_data(&[], true).is_ok());
+
+        assert_eq!(
+            validate_raw_adv_data(&[0x02, 0x01, 0x06, 0x05, 0x09, 0x61], true),
+            Err(String::from("AD element at offset 3 claims length 5 exceeding buffer"))
+        );
+
+        let long = [[0x1f, 0xff].as_slice(), &[0; 30]].concat();
+        assert!(validate_raw_adv_data(&long, false).is_ok());
+        assert!(validate_raw_adv_data(&long, true).is_err());
+    }
+
+    #[test]
+    fn test_check_conn_params() {
+        // 7.5 ms to 15 ms intervals, no latency, 2 s timeout.
+        assert!(check_conn_params(6, 12, 0, 200).is_ok());
+        assert!(check_conn_params(0x0C80, 0x0C80, 0, 0x0C80).is_ok());
+
+        assert!(check_conn_params(5, 12, 0, 200).is_err());
+        assert!(check_conn_params(6, 0x0C81, 0, 200).is_err());
+        assert!(check_conn_params(12, 6, 0, 200).is_err());
+        assert!(check_conn_params(6, 12, 0x01F4, 200).is_err());
+        assert!(check_conn_params(6, 12, 0, 9).is_err());
+
+        // The timeout must cover (1 + latency) * max_interval * 2.
+        assert!(check_conn_params(6, 40, 4, 50).is_err());
+        assert!(check_conn_params(6, 40, 4, 51).is_ok());
+    }
+
+    #[test]
+    fn test_parse_class_of_device() {
+        assert_eq!(parse_class_of_device("0x2a010c").ok(), Some(0x2a010c));
+        assert_eq!(parse_class_of_device("2A010C").ok(), Some(0x2a010c));
+        assert_eq!(parse_class_of_device("0").ok(), Some(0));
+        assert_eq!(parse_class_of_device("ffffff").ok(), Some(0xffffff));
+
+        // More than 3 bytes.
+        assert!(parse_class_of_device("0x1000000").is_err());
+        assert!(parse_class_of_device("ffffffff").is_err());
+
+        // Not hex.
+        assert!(parse_class_of_device("").is_err());
+        assert!(parse_class_of_device("0x").is_err());
+        assert!(parse_class_of_device("2a01zz").is_err());
+        assert!(parse_class_of_device("-1").is_err());
+    }
 }
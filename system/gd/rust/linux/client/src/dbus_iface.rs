@@ -1,8 +1,9 @@
 //! D-Bus proxy implementations of the APIs.
 
 use bt_topshim::btif::{
-    BtAddrType, BtBondState, BtConnectionState, BtDeviceType, BtDiscMode, BtPropertyType,
-    BtSspVariant, BtStatus, BtTransport, BtVendorProductInfo, DisplayAddress, RawAddress, Uuid,
+    BluetoothProperty, BtAddrType, BtBondState, BtConnectionDirection, BtConnectionState,
+    BtDeviceType, BtDiscMode, BtHciErrorCode, BtIoCap, BtPropertyType, BtSspVariant, BtStatus,
+    BtTransport, BtVendorProductInfo, DisplayAddress, RawAddress, Uuid,
 };
 use bt_topshim::profiles::a2dp::{
     A2dpCodecBitsPerSample, A2dpCodecChannelMode, A2dpCodecConfig, A2dpCodecIndex,
@@ -28,13 +29,14 @@ use bt_topshim::syslog::Level;
 
 use btstack::battery_manager::{Battery, BatterySet, IBatteryManager, IBatteryManagerCallback};
 use btstack::bluetooth::{
-    BluetoothDevice, BtAdapterRole, IBluetooth, IBluetoothCallback, IBluetoothConnectionCallback,
-    IBluetoothQALegacy,
+    AdvertisingData, BluetoothDevice, BtAdapterRole, ConnectionQuality, ControllerInfo,
+    DiscoverySource, FoundDevice, IBluetooth, IBluetoothCallback, IBluetoothConnectionCallback,
+    IBluetoothQALegacy, LeAddressInfo, LeAudioCaps, PairingPolicy,
 };
 use btstack::bluetooth_admin::{IBluetoothAdmin, IBluetoothAdminPolicyCallback, PolicyEffect};
 use btstack::bluetooth_adv::{
-    AdvertiseData, AdvertisingSetParameters, IAdvertisingSetCallback, ManfId,
-    PeriodicAdvertisingParameters,
+    AdvertiseData, AdvertisingSetParameters, DirectedAdvertisingMode, IAdvertisingSetCallback,
+    ManfId, PeriodicAdvertisingParameters,
 };
 use btstack::bluetooth_gatt::{
     BluetoothGattCharacteristic, BluetoothGattDescriptor, BluetoothGattService,
@@ -46,11 +48,15 @@ use btstack::bluetooth_media::{
     BluetoothAudioDevice, IBluetoothMedia, IBluetoothMediaCallback, IBluetoothTelephony,
     IBluetoothTelephonyCallback,
 };
-use btstack::bluetooth_qa::IBluetoothQA;
+use btstack::bluetooth_qa::{
+    AcceptListEntry, AdapterMode, ClassicScanParams, ClassicScanType, IBluetoothQA,
+    LinkErrorCounters, LinkPowerMode, SniffParams,
+};
 use btstack::socket_manager::{
     BluetoothServerSocket, BluetoothSocket, CallbackId, IBluetoothSocketManager,
-    IBluetoothSocketManagerCallbacks, SocketId, SocketResult,
+    IBluetoothSocketManagerCallbacks, SocketId, SocketInfo, SocketResult,
 };
+use btstack::uuid::Profile;
 use btstack::{RPCProxy, SuspendMode};
 
 use btstack::bluetooth_logging::IBluetoothLogging;
@@ -107,6 +113,8 @@ impl_dbus_arg_enum!(GattWriteRequestStatus);
 impl_dbus_arg_enum!(GattWriteType);
 impl_dbus_arg_enum!(LeDiscMode);
 impl_dbus_arg_enum!(LePhy);
+impl_dbus_arg_enum!(DirectedAdvertisingMode);
+impl_dbus_arg_enum!(Profile);
 impl_dbus_arg_enum!(ProfileConnectionState);
 impl_dbus_arg_enum!(ScanType);
 impl_dbus_arg_enum!(SocketType);
@@ -115,9 +123,59 @@ impl_dbus_arg_enum!(SuspendType);
 impl_dbus_arg_from_into!(Uuid, Vec<u8>);
 impl_dbus_arg_enum!(BthhReportType);
 impl_dbus_arg_enum!(BtAdapterRole);
+impl_dbus_arg_enum!(DiscoverySource);
+impl_dbus_arg_enum!(PairingPolicy);
 
 impl_dbus_arg_enum!(BtSdpType);
 impl_dbus_arg_enum!(Level);
+impl_dbus_arg_enum!(LinkPowerMode);
+impl_dbus_arg_enum!(ClassicScanType);
+impl_dbus_arg_enum!(AdapterMode);
+
+#[dbus_propmap(LeAudioCaps)]
+pub struct LeAudioCapsDBus {
+    cis_central: bool,
+    cis_peripheral: bool,
+    iso_broadcaster: bool,
+    iso_sync_receiver: bool,
+}
+
+#[dbus_propmap(LeAddressInfo)]
+pub struct LeAddressInfoDBus {
+    address: RawAddress,
+    address_type: BtAddrType,
+}
+
+#[dbus_propmap(ControllerInfo)]
+pub struct ControllerInfoDBus {
+    hci_version: u8,
+    hci_revision: u16,
+    lmp_version: u8,
+    subversion: u16,
+    manufacturer: u16,
+}
+
+#[dbus_propmap(AdvertisingData)]
+pub struct AdvertisingDataDBus {
+    flags: u8,
+    tx_power: Option<i32>,
+    service_uuids: Vec<Uuid>,
+    service_data: HashMap<String, Vec<u8>>,
+    manufacturer_data: HashMap<u16, Vec<u8>>,
+    appearance: Option<u16>,
+}
+
+#[dbus_propmap(FoundDevice)]
+pub struct FoundDeviceDBus {
+    device: BluetoothDevice,
+    age_ms: u64,
+}
+
+#[dbus_propmap(ConnectionQuality)]
+pub struct ConnectionQualityDBus {
+    rssi: Option<i32>,
+    link_quality: Option<u32>,
+}
 
 #[dbus_propmap(BtSdpHeaderOverlay)]
 struct BtSdpHeaderOverlayDBus {
@@ -355,6 +413,33 @@ impl DBusArg for BtSdpRecord {
     }
 }
 
+impl DBusArg for BluetoothProperty {
+    type DBusType = dbus::arg::PropMap;
+    fn from_dbus(
+        data: dbus::arg::PropMap,
+        _conn: Option<std::sync::Arc<dbus::nonblock::SyncConnection>>,
+        _remote: Option<dbus::strings::BusName<'static>>,
+        _disconnect_watcher: Option<
+            std::sync::Arc<std::sync::Mutex<dbus_projection::DisconnectWatcher>>,
+        >,
+    ) -> Result<BluetoothProperty, Box<dyn std::error::Error>> {
+        let prop_type = read_propmap_value::<u32>(&data, &String::from("type"))?;
+        let value = parse_propmap_value::<Vec<u8>>(&data, &String::from("value"))?;
+        Ok(BluetoothProperty::from_raw_value(BtPropertyType::from(prop_type), value))
+    }
+
+    fn to_dbus(prop: BluetoothProperty) -> Result<dbus::arg::PropMap, Box<dyn std::error::Error>> {
+        let mut map: dbus::arg::PropMap = std::collections::HashMap::new();
+        write_propmap_value::<u32>(&mut map, prop.get_type().into(), &String::from("type"))?;
+        write_propmap_value::<Vec<u8>>(&mut map, prop.to_raw_value(), &String::from("value"))?;
+        Ok(map)
+    }
+
+    fn log(prop: &BluetoothProperty) -> String {
+        format!("{:?}", prop)
+    }
+}
+
 impl DBusArg for RawAddress {
     type DBusType = String;
     fn from_dbus(
@@ -630,6 +715,9 @@ impl IBluetoothCallback for IBluetoothCallbackDBus {
     #[dbus_method("OnDiscoveringChanged", DBusLog::Disable)]
     fn on_discovering_changed(&mut self, discovering: bool) {}
 
+    #[dbus_method("OnDiscoveryTimeout", DBusLog::Disable)]
+    fn on_discovery_timeout(&mut self) {}
+
     #[dbus_method("OnSspRequest", DBusLog::Disable)]
     fn on_ssp_request(
         &mut self,
@@ -649,6 +737,9 @@ impl IBluetoothCallback for IBluetoothCallbackDBus {
     #[dbus_method("OnBondStateChanged", DBusLog::Disable)]
     fn on_bond_state_changed(&mut self, status: u32, address: RawAddress, state: u32) {}
 
+    #[dbus_method("OnBondedDevicesChanged", DBusLog::Disable)]
+    fn on_bonded_devices_changed(&mut self, devices: Vec<BluetoothDevice>) {}
+
     #[dbus_method("OnSdpSearchComplete", DBusLog::Disable)]
     fn on_sdp_search_complete(
         &mut self,
@@ -660,6 +751,15 @@ impl IBluetoothCallback for IBluetoothCallbackDBus {
 
     #[dbus_method("OnSdpRecordCreated", DBusLog::Disable)]
     fn on_sdp_record_created(&mut self, record: BtSdpRecord, handle: i32) {}
+
+    #[dbus_method("OnRemoteUuidsFetched", DBusLog::Disable)]
+    fn on_remote_uuids_fetched(
+        &mut self,
+        remote_device: BluetoothDevice,
+        uuids: Vec<Uuid>,
+        status: BtStatus,
+    ) {
+    }
 }
 
 #[allow(dead_code)]
@@ -679,7 +779,21 @@ impl IBluetoothConnectionCallback for IBluetoothConnectionCallbackDBus {
     fn on_device_disconnected(&mut self, remote_device: BluetoothDevice) {}
 
     #[dbus_method("OnDeviceConnectionFailed", DBusLog::Disable)]
-    fn on_device_connection_failed(&mut self, remote_device: BluetoothDevice, status: BtStatus) {}
+    fn on_device_connection_failed(
+        &mut self,
+        remote_device: BluetoothDevice,
+        status: BtStatus,
+        hci_reason: BtHciErrorCode,
+    ) {
+    }
+
+    #[dbus_method("OnConnectionQuality", DBusLog::Disable)]
+    fn on_connection_quality(
+        &mut self,
+        remote_device: BluetoothDevice,
+        quality: ConnectionQuality,
+    ) {
+    }
 }
 
 #[allow(dead_code)]
@@ -718,7 +832,9 @@ impl IScannerCallback for IScannerCallbackDBus {
     }
 }
 
+impl_dbus_arg_enum!(BtConnectionDirection);
 impl_dbus_arg_enum!(BtDiscMode);
+impl_dbus_arg_enum!(BtIoCap);
 
 // Implements RPC-friendly wrapper methods for calling IBluetooth, generated by
 // `generate_dbus_interface_client` below.
@@ -799,11 +915,26 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetAdapterProperty")]
+    fn get_adapter_property(&self, prop_type: BtPropertyType) -> Option<BluetoothProperty> {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetUuids")]
     fn get_uuids(&self) -> Vec<Uuid> {
         dbus_generated!()
     }
 
+    #[dbus_method("GetLocalLeAddressInfo")]
+    fn get_local_le_address_info(&self) -> LeAddressInfo {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetControllerInfo")]
+    fn get_controller_info(&self) -> Option<ControllerInfo> {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetName")]
     fn get_name(&self) -> String {
         dbus_generated!()
@@ -839,6 +970,11 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("SetDiscoverableTimeout")]
+    fn set_discoverable_timeout(&mut self, duration: u32) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("IsMultiAdvertisementSupported")]
     fn is_multi_advertisement_supported(&self) -> bool {
         dbus_generated!()
@@ -884,11 +1020,21 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("ForgetDevice")]
+    fn forget_device(&mut self, device: BluetoothDevice) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetBondedDevices")]
     fn get_bonded_devices(&self) -> Vec<BluetoothDevice> {
         dbus_generated!()
     }
 
+    #[dbus_method("GetFoundDevicesWithAge")]
+    fn get_found_devices_with_age(&self) -> Vec<FoundDevice> {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetBondState")]
     fn get_bond_state(&self, device: BluetoothDevice) -> BtBondState {
         dbus_generated!()
@@ -909,6 +1055,31 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("SetPairingPolicy")]
+    fn set_pairing_policy(&mut self, policy: PairingPolicy) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetForceUuidFetchOnBond")]
+    fn set_force_uuid_fetch_on_bond(&mut self, force: bool) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetPropertyChangeCoalescing")]
+    fn set_property_change_coalescing(&mut self, enabled: bool) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetConnectRetryPolicy")]
+    fn set_connect_retry_policy(&mut self, max_retries: u32, backoff_ms: u32) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetIoCapability")]
+    fn set_io_capability(&mut self, io_cap: BtIoCap) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetRemoteName")]
     fn get_remote_name(&self, device: BluetoothDevice) -> String {
         dbus_generated!()
@@ -925,7 +1096,16 @@ impl IBluetooth for BluetoothDBus {
     }
 
     #[dbus_method("SetRemoteAlias")]
-    fn set_remote_alias(&mut self, device: BluetoothDevice, new_alias: String) {
+    fn set_remote_alias(&mut self, device: BluetoothDevice, new_alias: String) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetRemoteDeviceTypeOverride")]
+    fn set_remote_device_type_override(
+        &mut self,
+        device: BluetoothDevice,
+        device_type: BtDeviceType,
+    ) -> bool {
         dbus_generated!()
     }
 
@@ -949,6 +1129,26 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetRemoteConnectionDirection")]
+    fn get_remote_connection_direction(&self, _device: BluetoothDevice) -> BtConnectionDirection {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetRemoteDiscoverySources")]
+    fn get_remote_discovery_sources(&self, _device: BluetoothDevice) -> Vec<DiscoverySource> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetConnectionHandles")]
+    fn get_connection_handles(&self, _device: BluetoothDevice) -> Vec<u16> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetRemoteWakeAllowed")]
+    fn set_remote_wake_allowed(&mut self, _device: BluetoothDevice, _allowed: bool) {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetRemoteVendorProductInfo")]
     fn get_remote_vendor_product_info(&self, _device: BluetoothDevice) -> BtVendorProductInfo {
         dbus_generated!()
@@ -964,6 +1164,31 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetRemoteRSSISmoothed")]
+    fn get_remote_rssi_smoothed(&self, device: BluetoothDevice) -> i8 {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetRSSISmoothingFactor")]
+    fn set_rssi_smoothing_factor(&mut self, factor: f64) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetDeviceFoundMinInterval")]
+    fn set_device_found_min_interval(&mut self, interval_ms: u32) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetRemoteAllProperties")]
+    fn get_remote_all_properties(&self, device: BluetoothDevice) -> Vec<BluetoothProperty> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetRemoteAdvertisingData")]
+    fn get_remote_advertising_data(&self, device: BluetoothDevice) -> AdvertisingData {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetConnectedDevices")]
     fn get_connected_devices(&self) -> Vec<BluetoothDevice> {
         dbus_generated!()
@@ -985,7 +1210,7 @@ impl IBluetooth for BluetoothDBus {
     }
 
     #[dbus_method("FetchRemoteUuids")]
-    fn fetch_remote_uuids(&self, device: BluetoothDevice) -> bool {
+    fn fetch_remote_uuids(&mut self, device: BluetoothDevice) -> bool {
         dbus_generated!()
     }
 
@@ -1009,11 +1234,31 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetConnectableProfiles")]
+    fn get_connectable_profiles(&self, device: BluetoothDevice) -> Vec<Profile> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("CancelConnectAllEnabledProfiles")]
+    fn cancel_connect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("DisconnectAllEnabledProfiles")]
     fn disconnect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
         dbus_generated!()
     }
 
+    #[dbus_method("SetProfileEnabled")]
+    fn set_profile_enabled(&mut self, profile: Profile, enabled: bool) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("DisconnectHid")]
+    fn disconnect_hid(&mut self, device: BluetoothDevice, reconnect_allowed: bool) -> BtStatus {
+        dbus_generated!()
+    }
+
     #[dbus_method("IsWbsSupported")]
     fn is_wbs_supported(&self) -> bool {
         dbus_generated!()
@@ -1039,11 +1284,21 @@ impl IBluetooth for BluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetLeAudioCapabilities")]
+    fn get_le_audio_capabilities(&self) -> LeAudioCaps {
+        dbus_generated!()
+    }
+
     #[dbus_method("IsDualModeAudioSinkDevice")]
     fn is_dual_mode_audio_sink_device(&self, device: BluetoothDevice) -> bool {
         dbus_generated!()
     }
 
+    #[dbus_method("GetConnectionQuality")]
+    fn get_connection_quality(&mut self, device: BluetoothDevice) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetDumpsys")]
     fn get_dumpsys(&self) -> String {
         dbus_generated!()
@@ -1319,6 +1574,9 @@ struct AdvertisingSetParametersDBus {
     interval: i32,
     tx_power_level: i32,
     own_address_type: i32,
+    directed_mode: Option<DirectedAdvertisingMode>,
+    peer_address: Option<RawAddress>,
+    peer_address_type: Option<i32>,
 }
 
 #[dbus_propmap(AdvertiseData)]
@@ -1649,6 +1907,11 @@ impl IBluetoothGatt for BluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("ClientSetAutoConnect")]
+    fn client_set_auto_connect(&mut self, client_id: i32, addr: RawAddress, enabled: bool) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("ClientSetPreferredPhy")]
     fn client_set_preferred_phy(
         &self,
@@ -1666,6 +1929,11 @@ impl IBluetoothGatt for BluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("ClientReadRemotePhyCapabilities")]
+    fn client_read_remote_phy_capabilities(&mut self, client_id: i32, addr: RawAddress) {
+        dbus_generated!()
+    }
+
     #[dbus_method("RefreshDevice")]
     fn refresh_device(&self, client_id: i32, addr: RawAddress) {
         dbus_generated!()
@@ -1681,6 +1949,11 @@ impl IBluetoothGatt for BluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("HasService")]
+    fn has_service(&self, client_id: i32, addr: RawAddress, service_uuid: String) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("BtifGattcDiscoverServiceByUuid")]
     fn btif_gattc_discover_service_by_uuid(&self, client_id: i32, addr: RawAddress, uuid: String) {
         dbus_generated!()
@@ -1745,6 +2018,22 @@ impl IBluetoothGatt for BluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("SetPersistNotifications")]
+    fn set_persist_notifications(
+        &mut self,
+        client_id: i32,
+        addr: RawAddress,
+        handle: i32,
+        persist: bool,
+    ) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetPersistedNotifications")]
+    fn get_persisted_notifications(&self, client_id: i32, addr: RawAddress) -> Vec<i32> {
+        dbus_generated!()
+    }
+
     #[dbus_method("BeginReliableWrite")]
     fn begin_reliable_write(&mut self, client_id: i32, addr: RawAddress) {
         dbus_generated!()
@@ -1908,6 +2197,16 @@ impl IBluetoothGattCallback for IBluetoothGattCallbackDBus {
     #[dbus_method("OnPhyRead", DBusLog::Disable)]
     fn on_phy_read(&mut self, addr: RawAddress, tx_phy: LePhy, rx_phy: LePhy, status: GattStatus) {}
 
+    #[dbus_method("OnRemotePhyCapabilities", DBusLog::Disable)]
+    fn on_remote_phy_capabilities(
+        &mut self,
+        addr: RawAddress,
+        le_2m: bool,
+        le_coded: bool,
+        status: GattStatus,
+    ) {
+    }
+
     #[dbus_method("OnSearchComplete", DBusLog::Disable)]
     fn on_search_complete(
         &mut self,
@@ -2107,6 +2406,17 @@ pub struct BluetoothSocketDBus {
     max_tx_size: i32,
 }
 
+#[dbus_propmap(SocketInfo)]
+pub struct SocketInfoDBus {
+    id: SocketId,
+    sock_type: SocketType,
+    is_listening: bool,
+    remote_address: Option<RawAddress>,
+    psm: Option<i32>,
+    channel: Option<i32>,
+    uuid: Option<Uuid>,
+}
+
 #[dbus_propmap(SocketResult)]
 pub struct SocketResultDBus {
     status: BtStatus,
@@ -2279,6 +2589,16 @@ impl IBluetoothSocketManager for BluetoothSocketManagerDBus {
     fn close(&mut self, callback: CallbackId, id: SocketId) -> BtStatus {
         dbus_generated!()
     }
+
+    #[dbus_method("GetActiveSockets")]
+    fn get_active_sockets(&self, callback: CallbackId) -> Vec<SocketInfo> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("L2capPing")]
+    fn l2cap_ping(&mut self, callback: CallbackId, addr: RawAddress, size: u16) -> bool {
+        dbus_generated!()
+    }
 }
 
 #[allow(dead_code)]
@@ -2319,6 +2639,11 @@ impl IBluetoothSocketManagerCallbacks for IBluetoothSocketManagerCallbacksDBus {
     ) {
         dbus_generated!()
     }
+
+    #[dbus_method("OnL2capPingResult", DBusLog::Disable)]
+    fn on_l2cap_ping_result(&mut self, addr: RawAddress, status: BtStatus, rtt_ms: u32) {
+        dbus_generated!()
+    }
 }
 
 pub(crate) struct SuspendDBus {
@@ -2419,6 +2744,11 @@ impl IBluetoothTelephony for BluetoothTelephonyDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("UnregisterTelephonyCallback")]
+    fn unregister_telephony_callback(&mut self, callback_id: u32) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("SetNetworkAvailable")]
     fn set_network_available(&mut self, network_available: bool) {
         dbus_generated!()
@@ -2487,6 +2817,10 @@ impl IBluetoothTelephony for BluetoothTelephonyDBus {
     fn audio_disconnect(&mut self, address: RawAddress) {
         dbus_generated!()
     }
+    #[dbus_method("ClearCalls")]
+    fn clear_calls(&mut self) {
+        dbus_generated!()
+    }
 }
 
 #[allow(dead_code)]
@@ -2499,6 +2833,11 @@ impl RPCProxy for IBluetoothTelephonyCallbackDBus {}
     "org.chromium.bluetooth.BluetoothTelephonyCallback"
 )]
 impl IBluetoothTelephonyCallback for IBluetoothTelephonyCallbackDBus {
+    #[dbus_method("OnCallbackRegistered")]
+    fn on_callback_registered(&mut self, callback_id: u32) {
+        dbus_generated!()
+    }
+
     #[dbus_method("OnTelephonyEvent")]
     fn on_telephony_event(&mut self, addr: RawAddress, event: u8, call_state: u8) {
         dbus_generated!()
@@ -2587,6 +2926,120 @@ impl IBluetoothQA for BluetoothQADBus {
     fn send_hid_virtual_unplug(&self, addr: RawAddress) {
         dbus_generated!()
     }
+    #[dbus_method("GetCallbackCounts")]
+    fn get_callback_counts(&self) -> HashMap<String, u32> {
+        dbus_generated!()
+    }
+    #[dbus_method("GetRecentMetrics")]
+    fn get_recent_metrics(&self) -> Vec<String> {
+        dbus_generated!()
+    }
+    #[dbus_method("GetDroppedMessageCount")]
+    fn get_dropped_message_count(&self) -> u64 {
+        dbus_generated!()
+    }
+    #[dbus_method("GetLinkErrorCounters")]
+    fn get_link_error_counters(&self, addr: RawAddress) {
+        dbus_generated!()
+    }
+    #[dbus_method("ResetFailedContactCounter")]
+    fn reset_failed_contact_counter(&self, addr: RawAddress) {
+        dbus_generated!()
+    }
+    #[dbus_method("SetLinkPowerMode")]
+    fn set_link_power_mode(
+        &self,
+        addr: RawAddress,
+        mode: LinkPowerMode,
+        params: SniffParams,
+    ) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetLinkPowerMode")]
+    fn get_link_power_mode(&self, addr: RawAddress) {
+        dbus_generated!()
+    }
+    #[dbus_method("AddToAcceptList")]
+    fn add_to_accept_list(&mut self, addr: RawAddress, addr_type: BtAddrType) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("RemoveFromAcceptList")]
+    fn remove_from_accept_list(&mut self, addr: RawAddress) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetAcceptList")]
+    fn get_accept_list(&self) -> Vec<AcceptListEntry> {
+        dbus_generated!()
+    }
+    #[dbus_method("SetAdapterMode")]
+    fn set_adapter_mode(&mut self, mode: AdapterMode) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetAdapterMode")]
+    fn get_adapter_mode(&self) -> AdapterMode {
+        dbus_generated!()
+    }
+    #[dbus_method("GetScanParams")]
+    fn get_scan_params(&self) -> ClassicScanParams {
+        dbus_generated!()
+    }
+    #[dbus_method("SetScanParams")]
+    fn set_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetPageScanParams")]
+    fn get_page_scan_params(&self) -> ClassicScanParams {
+        dbus_generated!()
+    }
+    #[dbus_method("SetPageScanParams")]
+    fn set_page_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetCancellingDevices")]
+    fn get_cancelling_devices(&self) -> Vec<RawAddress> {
+        dbus_generated!()
+    }
+    #[dbus_method("SetAutoAcceptJustWorks")]
+    fn set_auto_accept_just_works(&mut self, enabled: bool) {
+        dbus_generated!()
+    }
+}
+
+#[dbus_propmap(LinkErrorCounters)]
+pub struct LinkErrorCountersDBus {
+    failed_contact_counter: u16,
+    link_quality: Option<u8>,
+}
+
+#[dbus_propmap(SniffParams)]
+pub struct SniffParamsDBus {
+    max_interval: u16,
+    min_interval: u16,
+    attempt: u16,
+    timeout: u16,
+}
+
+#[dbus_propmap(ClassicScanParams)]
+pub struct ClassicScanParamsDBus {
+    interval: u16,
+    window: u16,
+    scan_type: ClassicScanType,
+}
+
+#[dbus_propmap(AcceptListEntry)]
+pub struct AcceptListEntryDBus {
+    address: RawAddress,
+    addr_type: BtAddrType,
 }
 
 #[allow(dead_code)]
@@ -2632,6 +3085,42 @@ impl IBluetoothQACallback for IBluetoothQACallbackDBus {
     fn on_send_hid_virtual_unplug_completed(&mut self, status: BtStatus) {
         dbus_generated!()
     }
+    #[dbus_method("OnGetLinkErrorCountersComplete", DBusLog::Disable)]
+    fn on_get_link_error_counters_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        counters: LinkErrorCounters,
+    ) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnResetFailedContactCounterComplete", DBusLog::Disable)]
+    fn on_reset_failed_contact_counter_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnGetLinkPowerModeComplete", DBusLog::Disable)]
+    fn on_get_link_power_mode_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+    ) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnLinkPowerModeChanged", DBusLog::Disable)]
+    fn on_link_power_mode_changed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+        interval: u16,
+    ) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnAddToAcceptListComplete", DBusLog::Disable)]
+    fn on_add_to_accept_list_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        dbus_generated!()
+    }
 }
 
 #[derive(Clone)]
@@ -2671,6 +3160,11 @@ impl IBluetoothMedia for BluetoothMediaDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("UnregisterCallback")]
+    fn unregister_callback(&mut self, callback_id: u32) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("Initialize")]
     fn initialize(&mut self) -> bool {
         dbus_generated!()
@@ -2931,6 +3425,9 @@ impl RPCProxy for IBluetoothMediaCallbackDBus {}
     "org.chromium.bluetooth.BluetoothMediaCallback"
 )]
 impl IBluetoothMediaCallback for IBluetoothMediaCallbackDBus {
+    #[dbus_method("OnCallbackRegistered", DBusLog::Disable)]
+    fn on_callback_registered(&mut self, callback_id: u32) {}
+
     #[dbus_method("OnBluetoothAudioDeviceAdded", DBusLog::Disable)]
     fn on_bluetooth_audio_device_added(&mut self, device: BluetoothAudioDevice) {}
 
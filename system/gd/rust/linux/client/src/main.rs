@@ -18,15 +18,23 @@ use crate::callbacks::{
     BtConnectionCallback, BtManagerCallback, BtSocketManagerCallback, MediaCallback, QACallback,
     ScannerCallback, SuspendCallback, TelephonyCallback,
 };
-use crate::command_handler::{CommandHandler, SocketSchedule};
+use crate::command_handler::{CommandExitCode, CommandHandler, SocketSchedule};
 use crate::dbus_iface::{
     BatteryManagerDBus, BluetoothAdminDBus, BluetoothDBus, BluetoothGattDBus, BluetoothLoggingDBus,
     BluetoothManagerDBus, BluetoothMediaDBus, BluetoothQADBus, BluetoothQALegacyDBus,
     BluetoothSocketManagerDBus, BluetoothTelephonyDBus, SuspendDBus,
 };
 use crate::editor::AsyncEditor;
-use bt_topshim::{btif::RawAddress, topstack};
+use crate::pairing_events::{PairingEvent, PairingEventWriter};
+use bt_topshim::btif::{BtStatus, RawAddress, Uuid};
+use bt_topshim::topstack;
+use btstack::battery_manager::IBatteryManager;
 use btstack::bluetooth::{BluetoothDevice, IBluetooth};
+use btstack::bluetooth_admin::IBluetoothAdmin;
+use btstack::bluetooth_gatt::{IBluetoothGatt, ScanResult};
+use btstack::bluetooth_media::{IBluetoothMedia, IBluetoothTelephony};
+use btstack::bluetooth_qa::IBluetoothQA;
+use btstack::socket_manager::IBluetoothSocketManager;
 use btstack::suspend::ISuspend;
 use manager_service::iface_bluetooth_manager::IBluetoothManager;
 
@@ -38,6 +46,7 @@ mod console;
 mod dbus_arg;
 mod dbus_iface;
 mod editor;
+mod pairing_events;
 
 #[derive(Clone)]
 pub(crate) struct GattRequest {
@@ -47,6 +56,14 @@ pub(crate) struct GattRequest {
     value: Vec<u8>,
 }
 
+/// A scan that the client stops once it has run for its duration or collected enough results.
+pub(crate) struct OneShotScan {
+    scanner_id: u8,
+    id: u32,
+    max_results: Option<usize>,
+    results: usize,
+}
+
 /// Context structure for the client. Used to keep track details about the active adapter and its
 /// state.
 pub(crate) struct ClientContext {
@@ -125,6 +142,12 @@ pub(crate) struct ClientContext {
     /// Internal DBus crossroads object.
     dbus_crossroads: Arc<Mutex<Crossroads>>,
 
+    /// Identifies the callback to receive IBluetoothCallback method calls.
+    adapter_callback_id: Option<u32>,
+
+    /// Identifies the callback to receive IBluetoothConnectionCallback method calls.
+    connection_callback_id: Option<u32>,
+
     /// Identifies the callback to receive IScannerCallback method calls.
     scanner_callback_id: Option<u32>,
 
@@ -137,6 +160,9 @@ pub(crate) struct ClientContext {
     /// Keeps track of active LE scanners.
     active_scanner_ids: HashSet<u8>,
 
+    /// Service UUIDs that scan results must contain to be reported. Empty if not filtering.
+    scan_filter_uuids: HashSet<Uuid>,
+
     /// Keeps track of advertising sets registered. Map from reg_id to AdvSet.
     adv_sets: HashMap<i32, AdvSet>,
 
@@ -146,12 +172,24 @@ pub(crate) struct ClientContext {
     /// Identifies the callback to receive IBluetoothQACallback method calls.
     qa_callback_id: Option<u32>,
 
+    /// Identifies the callback to receive IBatteryManagerCallback method calls.
+    battery_callback_id: Option<u32>,
+
+    /// Whether the ISuspendCallback, IBluetoothMediaCallback and IBluetoothTelephonyCallback are
+    /// registered. These APIs don't return a callback id.
+    suspend_callback_registered: bool,
+    media_callback_registered: bool,
+    telephony_callback_registered: bool,
+
+    /// Identify the ISuspendCallback, IBluetoothMediaCallback and IBluetoothTelephonyCallback.
+    /// These are given through `on_callback_registered` after the registration.
+    suspend_callback_id: Option<u32>,
+    media_callback_id: Option<u32>,
+    telephony_callback_id: Option<u32>,
+
     /// Is btclient running in restricted mode?
     is_restricted: bool,
 
-    /// Is btclient running in interactive mode?
-    is_interactive: bool,
-
     /// Data of GATT client preference.
     gatt_client_context: GattClientContext,
 
@@ -172,6 +210,34 @@ pub(crate) struct ClientContext {
 
     /// A request from a GATT client that is still being processed.
     pending_gatt_request: Option<GattRequest>,
+
+    /// Identifies the pending `adapter wait-ready`, if any.
+    pending_adapter_ready_wait: Option<u32>,
+
+    /// Id given to the most recent `adapter wait-ready`.
+    adapter_ready_wait_id: u32,
+
+    /// Whether an `adapter reset` is waiting for the adapter to stop before starting it again.
+    pending_adapter_reset: bool,
+
+    /// The device of the pending `device pair-and-connect` and the id of its timeout, if any.
+    pub(crate) pending_pair_and_connect: Option<(RawAddress, u32)>,
+
+    /// Id given to the most recent `device pair-and-connect`.
+    pair_and_connect_id: u32,
+
+    /// Exit code of the non-interactive command whose callback failed, if any.
+    pub(crate) callback_exit_code: Option<CommandExitCode>,
+
+    /// Scan started with a `--duration` or `--max-results` limit, if any. Scan results don't tell
+    /// which scanner found them, so such a scan can't run alongside other scans.
+    pub(crate) one_shot_scan: Option<OneShotScan>,
+
+    /// Id given to the most recent one-shot scan.
+    one_shot_scan_id: u32,
+
+    /// Where pairing requests are written for an external pairing agent, if anywhere.
+    pairing_events: Option<PairingEventWriter>,
 }
 
 impl ClientContext {
@@ -180,7 +246,6 @@ impl ClientContext {
         dbus_crossroads: Arc<Mutex<Crossroads>>,
         tx: mpsc::Sender<ForegroundActions>,
         is_restricted: bool,
-        is_interactive: bool,
         client_commands_with_callbacks: Vec<String>,
     ) -> ClientContext {
         // Manager interface is almost always available but adapter interface
@@ -212,15 +277,24 @@ impl ClientContext {
             fg: tx,
             dbus_connection,
             dbus_crossroads,
+            adapter_callback_id: None,
+            connection_callback_id: None,
             scanner_callback_id: None,
             advertiser_callback_id: None,
             admin_callback_id: None,
             active_scanner_ids: HashSet::new(),
+            scan_filter_uuids: HashSet::new(),
             adv_sets: HashMap::new(),
             socket_manager_callback_id: None,
             qa_callback_id: None,
+            battery_callback_id: None,
+            suspend_callback_registered: false,
+            media_callback_registered: false,
+            telephony_callback_registered: false,
+            suspend_callback_id: None,
+            media_callback_id: None,
+            telephony_callback_id: None,
             is_restricted,
-            is_interactive,
             gatt_client_context: GattClientContext::new(),
             gatt_server_context: GattServerContext::new(),
             socket_test_schedule: None,
@@ -228,6 +302,15 @@ impl ClientContext {
             client_commands_with_callbacks,
             battery_address_filter: HashSet::new(),
             pending_gatt_request: None,
+            pending_adapter_ready_wait: None,
+            adapter_ready_wait_id: 0,
+            pending_adapter_reset: false,
+            pending_pair_and_connect: None,
+            pair_and_connect_id: 0,
+            callback_exit_code: None,
+            one_shot_scan: None,
+            one_shot_scan_id: 0,
+            pairing_events: None,
         }
     }
 
@@ -248,6 +331,12 @@ impl ClientContext {
                 self.create_adapter_proxy(hci_interface);
             } else {
                 self.adapter_dbus = None;
+
+                if self.pending_adapter_reset {
+                    self.pending_adapter_reset = false;
+                    print_info!("Adapter stopped, starting it again");
+                    self.manager_dbus.start(default_adapter);
+                }
             }
         }
     }
@@ -280,25 +369,242 @@ impl ClientContext {
 
         self.logging_dbus = Some(BluetoothLoggingDBus::new(conn.clone(), idx));
 
-        // Trigger callback registration in the foreground
+        // Callbacks registered with a previous adapter are gone along with it.
+        self.clear_callback_registrations();
+
+        // Trigger callback registration in the foreground. Floss won't export the interface until
+        // it is ready to be used, so the registration is retried with backoff if it isn't there yet.
+        self.schedule_adapter_callback_registration(format!("adapter{}", idx), 0, None);
+    }
+
+    // Forgets which callbacks are registered, so that all of them are registered again.
+    fn clear_callback_registrations(&mut self) {
+        self.adapter_callback_id = None;
+        self.connection_callback_id = None;
+        self.scanner_callback_id = None;
+        self.advertiser_callback_id = None;
+        self.admin_callback_id = None;
+        self.socket_manager_callback_id = None;
+        self.qa_callback_id = None;
+        self.battery_callback_id = None;
+        self.suspend_callback_registered = false;
+        self.media_callback_registered = false;
+        self.telephony_callback_registered = false;
+        self.suspend_callback_id = None;
+        self.media_callback_id = None;
+        self.telephony_callback_id = None;
+    }
+
+    // Queues an attempt to register the adapter callbacks, optionally after a delay.
+    fn schedule_adapter_callback_registration(
+        &mut self,
+        adapter: String,
+        attempt: u32,
+        delay: Option<Duration>,
+    ) {
         let fg = self.fg.clone();
-        let is_interactive = self.is_interactive;
         tokio::spawn(async move {
-            let adapter = format!("adapter{}", idx);
-
-            // Floss won't export the interface until it is ready to be used.
-            // Wait 1 second before registering the callbacks.
-            // Only introduce such delay on interactive mode. This is because we expect the user to
-            // ensure the adapter interface is ready when they issue the command in non-interactive
-            // mode. Otherwise, there will always have 1 second delay and in most of the case it is
-            // not needed.
-            if is_interactive {
-                sleep(Duration::from_millis(1000)).await;
+            if let Some(delay) = delay {
+                sleep(delay).await;
             }
-            let _ = fg.send(ForegroundActions::RegisterAdapterCallback(adapter)).await;
+            let _ = fg.send(ForegroundActions::RegisterAdapterCallback(adapter, attempt)).await;
         });
     }
 
+    // Stops the default adapter and starts it again once it is stopped. The reset is reported as
+    // failed if the adapter isn't ready again after |timeout|.
+    fn reset_adapter(&mut self, timeout: Duration) {
+        let default_adapter = self.default_adapter;
+        if self.enabled {
+            self.pending_adapter_reset = true;
+            self.manager_dbus.stop(default_adapter);
+        } else {
+            self.manager_dbus.start(default_adapter);
+        }
+        self.wait_adapter_ready(timeout);
+    }
+
+    // Waits for the adapter to become ready. The wait is reported as failed if the adapter isn't
+    // ready after |timeout|.
+    fn wait_adapter_ready(&mut self, timeout: Duration) {
+        self.adapter_ready_wait_id = self.adapter_ready_wait_id.wrapping_add(1);
+        let id = self.adapter_ready_wait_id;
+        self.pending_adapter_ready_wait = Some(id);
+
+        let fg = self.fg.clone();
+        tokio::spawn(async move {
+            sleep(timeout).await;
+            let _ = fg.send(ForegroundActions::AdapterReadyTimeout(id)).await;
+        });
+    }
+
+    // Waits for the bonding with |address| to complete before connecting its profiles. The
+    // bonding is cancelled if it hasn't completed after |timeout|.
+    fn wait_pair_and_connect(&mut self, address: RawAddress, timeout: Duration) {
+        self.pair_and_connect_id = self.pair_and_connect_id.wrapping_add(1);
+        let id = self.pair_and_connect_id;
+        self.pending_pair_and_connect = Some((address, id));
+
+        let fg = self.fg.clone();
+        tokio::spawn(async move {
+            sleep(timeout).await;
+            let _ = fg.send(ForegroundActions::PairAndConnectTimeout(id)).await;
+        });
+    }
+
+    // Stops the scan of |scanner_id| once it has run for |duration| or reported |max_results|
+    // results, whichever comes first.
+    fn track_one_shot_scan(
+        &mut self,
+        scanner_id: u8,
+        duration: Option<Duration>,
+        max_results: Option<usize>,
+    ) {
+        self.one_shot_scan_id = self.one_shot_scan_id.wrapping_add(1);
+        let id = self.one_shot_scan_id;
+        self.one_shot_scan = Some(OneShotScan { scanner_id, id, max_results, results: 0 });
+
+        if let Some(duration) = duration {
+            let fg = self.fg.clone();
+            tokio::spawn(async move {
+                sleep(duration).await;
+                let _ = fg.send(ForegroundActions::OneShotScanTimeout(scanner_id, id)).await;
+            });
+        }
+    }
+
+    // Counts a scan result for the one-shot scan, if any. Returns its scanner if the scan just
+    // reached its maximum number of results.
+    fn count_one_shot_scan_result(&mut self) -> Option<u8> {
+        let scan = self.one_shot_scan.as_mut()?;
+        if scan.max_results.map_or(false, |max| scan.results >= max) {
+            return None;
+        }
+        scan.results += 1;
+        if Some(scan.results) == scan.max_results {
+            Some(scan.scanner_id)
+        } else {
+            None
+        }
+    }
+
+    // Stops the one-shot scan of |scanner_id|, if still running, and reports how many results it
+    // collected.
+    fn stop_one_shot_scan(&mut self, scanner_id: u8) {
+        let scan = match self.one_shot_scan.take() {
+            Some(scan) if scan.scanner_id == scanner_id => scan,
+            other => {
+                self.one_shot_scan = other;
+                return;
+            }
+        };
+
+        if let Some(gatt) = self.gatt_dbus.as_mut() {
+            gatt.stop_scan(scanner_id);
+        }
+        self.active_scanner_ids.remove(&scanner_id);
+        print_info!("Scanner {} stopped after collecting {} results", scanner_id, scan.results);
+    }
+
+    // Queues |event| for the external pairing agent, if any. This doesn't block on the agent, so
+    // it is fine to call with the context locked. The agent is dropped once its writer stopped,
+    // e.g. after it went away; the writer already reported why.
+    fn report_pairing_event(&mut self, event: PairingEvent) {
+        if let Some(writer) = self.pairing_events.as_mut() {
+            if writer.write(&event).is_err() {
+                self.pairing_events = None;
+            }
+        }
+    }
+
+    // Connects all the enabled profiles of |device|. Reports that the connection started, or
+    // returns why it didn't.
+    fn connect_and_report(&mut self, device: BluetoothDevice) -> Result<(), String> {
+        let address = device.address.to_string();
+        match self.adapter_dbus.as_mut().unwrap().connect_all_enabled_profiles(device) {
+            BtStatus::Success => {
+                println!("Connecting to {}", address);
+                Ok(())
+            }
+            BtStatus::Busy => {
+                Err(format!("Can't connect to {} while the adapter is suspended", address))
+            }
+            _ => Err(format!("Can't connect to {}", address)),
+        }
+    }
+
+    // Connects |device| from a callback of a non-interactive command, which fails the command if
+    // the connection doesn't start.
+    pub(crate) fn connect_and_report_from_callback(&mut self, device: BluetoothDevice) {
+        if let Err(e) = self.connect_and_report(device) {
+            print_error!("{}", e);
+            self.callback_exit_code = Some(CommandExitCode::Failed);
+        }
+    }
+
+    // Unregisters the callbacks whose ids we keep, so the daemon doesn't have to wait for the
+    // disconnect to clean them up. Callbacks that were never registered are skipped.
+    fn unregister_callbacks(&mut self) {
+        if let Some(id) = self.adapter_callback_id.take() {
+            if let Some(adapter) = self.adapter_dbus.as_mut() {
+                adapter.unregister_callback(id);
+            }
+        }
+        if let Some(id) = self.connection_callback_id.take() {
+            if let Some(adapter) = self.adapter_dbus.as_mut() {
+                adapter.unregister_connection_callback(id);
+            }
+        }
+        if let Some(id) = self.scanner_callback_id.take() {
+            if let Some(gatt) = self.gatt_dbus.as_mut() {
+                gatt.unregister_scanner_callback(id);
+            }
+        }
+        if let Some(id) = self.advertiser_callback_id.take() {
+            if let Some(gatt) = self.gatt_dbus.as_mut() {
+                gatt.unregister_advertiser_callback(id);
+            }
+        }
+        if let Some(id) = self.admin_callback_id.take() {
+            if let Some(admin) = self.admin_dbus.as_mut() {
+                admin.unregister_admin_policy_callback(id);
+            }
+        }
+        if let Some(id) = self.socket_manager_callback_id.take() {
+            if let Some(socket_manager) = self.socket_manager_dbus.as_mut() {
+                socket_manager.unregister_callback(id);
+            }
+        }
+        if let Some(id) = self.qa_callback_id.take() {
+            if let Some(qa) = self.qa_dbus.as_mut() {
+                qa.unregister_qa_callback(id);
+            }
+        }
+        if let Some(id) = self.battery_callback_id.take() {
+            if let Some(battery_manager) = self.battery_manager_dbus.as_mut() {
+                battery_manager.unregister_battery_callback(id);
+            }
+        }
+        if let Some(id) = self.suspend_callback_id.take() {
+            if let Some(suspend) = self.suspend_dbus.as_mut() {
+                suspend.unregister_callback(id);
+            }
+        }
+        if let Some(id) = self.media_callback_id.take() {
+            if let Some(media) = self.media_dbus.as_mut() {
+                media.unregister_callback(id);
+            }
+        }
+        if let Some(id) = self.telephony_callback_id.take() {
+            if let Some(telephony) = self.telephony_dbus.as_mut() {
+                telephony.unregister_telephony_callback(id);
+            }
+        }
+        self.suspend_callback_registered = false;
+        self.media_callback_registered = false;
+        self.telephony_callback_registered = false;
+    }
+
     // Foreground-only: Updates the adapter address.
     fn update_adapter_address(&mut self) -> RawAddress {
         let address = self.adapter_dbus.as_ref().unwrap().get_address();
@@ -323,6 +629,13 @@ impl ClientContext {
         });
     }
 
+    /// Returns whether |command| has to wait for a callback before it completes.
+    fn needs_callback(&self, command: &str) -> bool {
+        self.client_commands_with_callbacks
+            .iter()
+            .any(|c| command == c || command.starts_with(&format!("{} ", c)))
+    }
+
     fn run_callback(&mut self, callback: Box<dyn Fn(Arc<Mutex<ClientContext>>) + Send>) {
         let fg = self.fg.clone();
         tokio::spawn(async move {
@@ -345,6 +658,12 @@ impl ClientContext {
         result
     }
 
+    // Checks whether a scan result contains one of the filtered service UUIDs, if any.
+    fn scan_result_matches_filter(&self, scan_result: &ScanResult) -> bool {
+        self.scan_filter_uuids.is_empty()
+            || scan_result.service_uuids.iter().any(|uuid| self.scan_filter_uuids.contains(uuid))
+    }
+
     fn get_floss_api_version(&mut self) -> (u32, u32) {
         let ver = self.manager_dbus.get_floss_api_version();
         let major = (ver & 0xFFFF_0000) >> 16;
@@ -358,8 +677,11 @@ impl ClientContext {
 enum ForegroundActions {
     ConnectAllEnabledProfiles(BluetoothDevice), // Connect all enabled profiles for this device
     RunCallback(Box<dyn Fn(Arc<Mutex<ClientContext>>) + Send>), // Run callback in foreground
-    RegisterAdapterCallback(String),            // Register callbacks for this adapter
-    Readline(rustyline::Result<String>),        // Readline result from rustyline
+    RegisterAdapterCallback(String, u32), // Register callbacks for this adapter (with attempt count)
+    AdapterReadyTimeout(u32),             // Timeout of the `adapter wait-ready` with this id
+    PairAndConnectTimeout(u32),           // Timeout of the `device pair-and-connect` with this id
+    OneShotScanTimeout(u8, u32),          // Duration of the one-shot scan with this scanner and id
+    Readline(rustyline::Result<String>),  // Readline result from rustyline
 }
 
 /// Runs a command line program that interacts with a Bluetooth stack.
@@ -380,13 +702,25 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .takes_value(true)
                 .help("Specify a timeout in seconds for a non-interactive command"),
         )
+        .arg(
+            Arg::with_name("pairing-events")
+                .long("pairing-events")
+                .takes_value(true)
+                .help("Writes pairing requests as JSON lines to a Unix socket, FIFO or file"),
+        )
         .get_matches();
     let command = value_t!(matches, "command", String).ok();
     let is_restricted = matches.is_present("restricted");
     let is_interactive = command.is_none();
     let timeout_secs = value_t!(matches, "timeout", u64);
 
-    topstack::get_runtime().block_on(async move {
+    // Opened before the runtime starts since opening a FIFO blocks until there is a reader.
+    let pairing_events = match matches.value_of("pairing-events") {
+        Some(path) => Some(PairingEventWriter::open(path)?),
+        None => None,
+    };
+
+    let result = topstack::get_runtime().block_on(async move {
         // Connect to D-Bus system bus.
         let (resource, conn) = dbus_tokio::connection::new_system_sync()?;
 
@@ -420,7 +754,11 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         // Include the commands
         // (1) that will be run as non-interactive client commands, and
         // (2) that will need to wait for the callbacks to complete.
-        let client_commands_with_callbacks = vec!["media".to_string()];
+        let client_commands_with_callbacks = vec![
+            "media".to_string(),
+            "device fetch-uuids".to_string(),
+            "device pair-and-connect".to_string(),
+        ];
 
         // Create the context needed for handling commands
         let context = Arc::new(Mutex::new(ClientContext::new(
@@ -428,9 +766,9 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             cr.clone(),
             tx.clone(),
             is_restricted,
-            is_interactive,
             client_commands_with_callbacks,
         )));
+        context.lock().unwrap().pairing_events = pairing_events;
 
         // Check if manager interface is valid. We only print some help text before failing on the
         // first actual access to the interface (so we can also capture the actual reason the
@@ -470,38 +808,303 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         };
 
         let handler = CommandHandler::new(context.clone());
-        if !is_interactive {
+        let client_command = handle_client_command(
+            handler,
+            tx,
+            rx,
+            context.clone(),
+            command,
+            default_adapter_enabled,
+        );
+        let result = match timeout_secs {
             // Timeout applies only to non-interactive commands.
-            if let Ok(timeout_secs) = timeout_secs {
-                let timeout_duration = Duration::from_secs(timeout_secs);
-                match timeout(
-                    timeout_duration,
-                    handle_client_command(
-                        handler,
-                        tx,
-                        rx,
-                        context,
-                        command,
-                        default_adapter_enabled,
-                    ),
-                )
-                .await
-                {
-                    Ok(_) => {
-                        return Result::Ok(());
-                    }
+            Ok(timeout_secs) if !is_interactive => {
+                match timeout(Duration::from_secs(timeout_secs), client_command).await {
+                    Ok(result) => result,
                     Err(_) => {
-                        return Result::Err("btclient timeout".into());
+                        print_error!("btclient timeout");
+                        Result::Err(CommandExitCode::Timeout.into())
                     }
-                };
+                }
             }
-        }
-        // There are two scenarios in which handle_client_command is run without a timeout.
-        // - Interactive commands: none of these commands require a timeout.
-        // - Non-interactive commands that have not specified a timeout.
-        handle_client_command(handler, tx, rx, context, command, default_adapter_enabled).await?;
-        Result::Ok(())
-    })
+            // There are two scenarios in which handle_client_command is run without a timeout.
+            // - Interactive commands: none of these commands require a timeout.
+            // - Non-interactive commands that have not specified a timeout.
+            _ => client_command.await,
+        };
+
+        // Cleans up on every exit, including failed and timed out commands.
+        context.lock().unwrap().unregister_callbacks();
+        result
+    });
+
+    // The failure of a non-interactive command is already reported, so only its category is left
+    // to report through the exit code.
+    if let Some(exit_code) = result.as_ref().err().and_then(|e| e.downcast_ref::<CommandExitCode>())
+    {
+        std::process::exit(*exit_code as i32);
+    }
+    result
+}
+
+/// Maximum number of times adapter callback registration is retried while the adapter interface
+/// isn't exported yet.
+const ADAPTER_CALLBACK_REGISTRATION_MAX_RETRIES: u32 = 5;
+
+/// Delay before the first retry of adapter callback registration. Doubles on every retry.
+const ADAPTER_CALLBACK_REGISTRATION_INITIAL_BACKOFF_MS: u64 = 250;
+
+fn adapter_callback_registration_backoff(attempt: u32) -> Duration {
+    Duration::from_millis(ADAPTER_CALLBACK_REGISTRATION_INITIAL_BACKOFF_MS << attempt.min(16))
+}
+
+// Whether the D-Bus error means the adapter interface isn't exported (yet).
+fn is_interface_not_ready_error(e: &dbus::Error) -> bool {
+    matches!(
+        e.name(),
+        Some("org.freedesktop.DBus.Error.UnknownObject")
+            | Some("org.freedesktop.DBus.Error.ServiceUnknown")
+    )
+}
+
+// Registers all callbacks for the adapter. On failure, returns the failing method along with the
+// D-Bus error.
+async fn register_adapter_callbacks(
+    context: &Arc<Mutex<ClientContext>>,
+    adapter: &str,
+) -> Result<(), (&'static str, dbus::Error)> {
+    let cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/bluetooth_callback", adapter);
+    let conn_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/bluetooth_conn_callback", adapter);
+    let suspend_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/suspend_callback", adapter);
+    let scanner_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/scanner_callback", adapter);
+    let advertiser_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/advertising_set_callback", adapter);
+    let admin_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/admin_callback", adapter);
+    let socket_manager_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/socket_manager_callback", adapter);
+    let qa_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/qa_manager_callback", adapter);
+    let media_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/bluetooth_media_callback", adapter);
+    let telephony_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/bluetooth_telephony_callback", adapter);
+    let battery_cb_objpath: String =
+        format!("/org/chromium/bluetooth/client/{}/battery_manager_callback", adapter);
+
+    let dbus_connection = context.lock().unwrap().dbus_connection.clone();
+    let dbus_crossroads = context.lock().unwrap().dbus_crossroads.clone();
+
+    // A retry only registers the callbacks that didn't succeed in a previous attempt.
+    if context.lock().unwrap().adapter_callback_id.is_none() {
+        let adapter_callback_id = context
+            .lock()
+            .unwrap()
+            .adapter_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_callback(Box::new(BtCallback::new(
+                cb_objpath.clone(),
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetooth::RegisterCallback", e))?;
+        context.lock().unwrap().adapter_callback_id = Some(adapter_callback_id);
+    }
+
+    if context.lock().unwrap().connection_callback_id.is_none() {
+        let connection_callback_id = context
+            .lock()
+            .unwrap()
+            .adapter_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_connection_callback(Box::new(BtConnectionCallback::new(
+                conn_cb_objpath,
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetooth::RegisterConnectionCallback", e))?;
+        context.lock().unwrap().connection_callback_id = Some(connection_callback_id);
+    }
+
+    // Register callback listener for le-scan`commands.
+    if context.lock().unwrap().scanner_callback_id.is_none() {
+        let scanner_callback_id = context
+            .lock()
+            .unwrap()
+            .gatt_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_scanner_callback(Box::new(ScannerCallback::new(
+                scanner_cb_objpath.clone(),
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothGatt::RegisterScannerCallback", e))?;
+        context.lock().unwrap().scanner_callback_id = Some(scanner_callback_id);
+    }
+
+    if context.lock().unwrap().advertiser_callback_id.is_none() {
+        let advertiser_callback_id = context
+            .lock()
+            .unwrap()
+            .gatt_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_advertiser_callback(Box::new(AdvertisingSetCallback::new(
+                advertiser_cb_objpath.clone(),
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothGatt::RegisterAdvertiserCallback", e))?;
+        context.lock().unwrap().advertiser_callback_id = Some(advertiser_callback_id);
+    }
+
+    if context.lock().unwrap().admin_callback_id.is_none() {
+        let admin_callback_id = context
+            .lock()
+            .unwrap()
+            .admin_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_admin_policy_callback(Box::new(AdminCallback::new(
+                admin_cb_objpath.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothAdmin::RegisterAdminCallback", e))?;
+        context.lock().unwrap().admin_callback_id = Some(admin_callback_id);
+    }
+
+    if context.lock().unwrap().socket_manager_callback_id.is_none() {
+        let socket_manager_callback_id = context
+            .lock()
+            .unwrap()
+            .socket_manager_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_callback(Box::new(BtSocketManagerCallback::new(
+                socket_manager_cb_objpath.clone(),
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothSocketManager::RegisterCallback", e))?;
+        context.lock().unwrap().socket_manager_callback_id = Some(socket_manager_callback_id);
+    }
+
+    if context.lock().unwrap().qa_callback_id.is_none() {
+        let qa_callback_id = context
+            .lock()
+            .unwrap()
+            .qa_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_qa_callback(Box::new(QACallback::new(
+                qa_cb_objpath.clone(),
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothQA::RegisterCallback", e))?;
+        context.lock().unwrap().qa_callback_id = Some(qa_callback_id);
+    }
+
+    // When adapter is ready, Suspend API is also ready. Register as an observer.
+    // TODO(b/224606285): Implement suspend debug utils in btclient.
+    if !context.lock().unwrap().suspend_callback_registered {
+        context.lock().unwrap().suspend_dbus.as_mut().unwrap().register_callback(Box::new(
+            SuspendCallback::new(
+                suspend_cb_objpath,
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            ),
+        ));
+        context.lock().unwrap().suspend_callback_registered = true;
+    }
+
+    if !context.lock().unwrap().media_callback_registered {
+        context
+            .lock()
+            .unwrap()
+            .media_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_callback(Box::new(MediaCallback::new(
+                media_cb_objpath,
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothMedia::RegisterCallback", e))?;
+        context.lock().unwrap().media_callback_registered = true;
+    }
+
+    if !context.lock().unwrap().telephony_callback_registered {
+        context
+            .lock()
+            .unwrap()
+            .telephony_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_telephony_callback(Box::new(TelephonyCallback::new(
+                telephony_cb_objpath,
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBluetoothMedia::RegisterTelephonyCallback", e))?;
+        context.lock().unwrap().telephony_callback_registered = true;
+    }
+
+    if context.lock().unwrap().battery_callback_id.is_none() {
+        let battery_callback_id = context
+            .lock()
+            .unwrap()
+            .battery_manager_dbus
+            .as_mut()
+            .unwrap()
+            .rpc
+            .register_battery_callback(Box::new(BatteryManagerCallback::new(
+                battery_cb_objpath,
+                context.clone(),
+                dbus_connection.clone(),
+                dbus_crossroads.clone(),
+            )))
+            .await
+            .map_err(|e| ("IBatteryManagerDBus::RegisterBatteryCallback", e))?;
+        context.lock().unwrap().battery_callback_id = Some(battery_callback_id);
+    }
+
+    Ok(())
 }
 
 // If btclient runs without command arguments, the interactive shell actions are performed.
@@ -524,16 +1127,21 @@ async fn handle_client_command(
     command: Option<String>,
     run_command_on_ready: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    // Whether the command has been run. It is only run once, even if the adapter becomes ready
+    // again, e.g. after an `adapter reset`.
+    let mut command_processed = !run_command_on_ready;
+
     if !run_command_on_ready {
         if let Some(command) = command.as_ref() {
             let mut iter = command.split(' ').map(String::from);
             let first = iter.next().unwrap_or(String::from(""));
             // Return immediately if the command fails to execute.
-            if !handler.process_cmd_line(&first, &iter.collect::<Vec<String>>()) {
-                return Err("failed process command".into());
-            }
+            handler.process_cmd_line(&first, &iter.collect::<Vec<String>>())?;
             // If there is no callback to wait for, we're done.
-            if !context.lock().unwrap().client_commands_with_callbacks.contains(&first) {
+            let context_locked = context.lock().unwrap();
+            if !context_locked.needs_callback(command)
+                && context_locked.pending_adapter_ready_wait.is_none()
+            {
                 return Ok(());
             }
         }
@@ -593,213 +1201,42 @@ async fn handle_client_command(
 
                 // Break the loop as a non-interactive command is completed.
                 if command.is_some() {
+                    if let Some(exit_code) = context.lock().unwrap().callback_exit_code.take() {
+                        return Err(exit_code.into());
+                    }
                     break;
                 }
             }
             // Once adapter is ready, register callbacks, get the address and mark it as ready
-            ForegroundActions::RegisterAdapterCallback(adapter) => {
-                let cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/bluetooth_callback", adapter);
-                let conn_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/bluetooth_conn_callback", adapter);
-                let suspend_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/suspend_callback", adapter);
-                let scanner_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/scanner_callback", adapter);
-                let advertiser_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/advertising_set_callback", adapter);
-                let admin_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/admin_callback", adapter);
-                let socket_manager_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/socket_manager_callback", adapter);
-                let qa_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/qa_manager_callback", adapter);
-                let media_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/bluetooth_media_callback", adapter);
-                let telephony_cb_objpath: String = format!(
-                    "/org/chromium/bluetooth/client/{}/bluetooth_telephony_callback",
-                    adapter
-                );
-                let battery_cb_objpath: String =
-                    format!("/org/chromium/bluetooth/client/{}/battery_manager_callback", adapter);
-
-                let dbus_connection = context.lock().unwrap().dbus_connection.clone();
-                let dbus_crossroads = context.lock().unwrap().dbus_crossroads.clone();
-
-                context
-                    .lock()
-                    .unwrap()
-                    .adapter_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_callback(Box::new(BtCallback::new(
-                        cb_objpath.clone(),
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetooth::RegisterCallback");
-                context
-                    .lock()
-                    .unwrap()
-                    .adapter_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_connection_callback(Box::new(BtConnectionCallback::new(
-                        conn_cb_objpath,
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetooth::RegisterConnectionCallback");
-
-                // Register callback listener for le-scan`commands.
-                let scanner_callback_id = context
-                    .lock()
-                    .unwrap()
-                    .gatt_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_scanner_callback(Box::new(ScannerCallback::new(
-                        scanner_cb_objpath.clone(),
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothGatt::RegisterScannerCallback");
-                context.lock().unwrap().scanner_callback_id = Some(scanner_callback_id);
-
-                let advertiser_callback_id = context
-                    .lock()
-                    .unwrap()
-                    .gatt_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_advertiser_callback(Box::new(AdvertisingSetCallback::new(
-                        advertiser_cb_objpath.clone(),
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothGatt::RegisterAdvertiserCallback");
-                context.lock().unwrap().advertiser_callback_id = Some(advertiser_callback_id);
-
-                let admin_callback_id = context
-                    .lock()
-                    .unwrap()
-                    .admin_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_admin_policy_callback(Box::new(AdminCallback::new(
-                        admin_cb_objpath.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothAdmin::RegisterAdminCallback");
-                context.lock().unwrap().admin_callback_id = Some(admin_callback_id);
-
-                let socket_manager_callback_id = context
-                    .lock()
-                    .unwrap()
-                    .socket_manager_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_callback(Box::new(BtSocketManagerCallback::new(
-                        socket_manager_cb_objpath.clone(),
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothSocketManager::RegisterCallback");
-                context.lock().unwrap().socket_manager_callback_id =
-                    Some(socket_manager_callback_id);
-
-                let qa_callback_id = context
-                    .lock()
-                    .unwrap()
-                    .qa_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_qa_callback(Box::new(QACallback::new(
-                        qa_cb_objpath.clone(),
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothQA::RegisterCallback");
-                context.lock().unwrap().qa_callback_id = Some(qa_callback_id);
-
-                // When adapter is ready, Suspend API is also ready. Register as an observer.
-                // TODO(b/224606285): Implement suspend debug utils in btclient.
-                context.lock().unwrap().suspend_dbus.as_mut().unwrap().register_callback(Box::new(
-                    SuspendCallback::new(
-                        suspend_cb_objpath,
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    ),
-                ));
-
-                context
-                    .lock()
-                    .unwrap()
-                    .media_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_callback(Box::new(MediaCallback::new(
-                        media_cb_objpath,
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothMedia::RegisterCallback");
-
-                context
-                    .lock()
-                    .unwrap()
-                    .telephony_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_telephony_callback(Box::new(TelephonyCallback::new(
-                        telephony_cb_objpath,
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBluetoothMedia::RegisterTelephonyCallback");
-
-                context
-                    .lock()
-                    .unwrap()
-                    .battery_manager_dbus
-                    .as_mut()
-                    .unwrap()
-                    .rpc
-                    .register_battery_callback(Box::new(BatteryManagerCallback::new(
-                        battery_cb_objpath,
-                        context.clone(),
-                        dbus_connection.clone(),
-                        dbus_crossroads.clone(),
-                    )))
-                    .await
-                    .expect("D-Bus error on IBatteryManagerDBus::RegisterBatteryCallback");
+            ForegroundActions::RegisterAdapterCallback(adapter, attempt) => {
+                if let Err((method, e)) = register_adapter_callbacks(&context, &adapter).await {
+                    if is_interface_not_ready_error(&e)
+                        && attempt < ADAPTER_CALLBACK_REGISTRATION_MAX_RETRIES
+                    {
+                        let delay = adapter_callback_registration_backoff(attempt);
+                        print_info!(
+                            "{} isn't ready yet ({}), retrying in {}ms",
+                            adapter,
+                            e.name().unwrap_or_default(),
+                            delay.as_millis()
+                        );
+                        context.lock().unwrap().schedule_adapter_callback_registration(
+                            adapter,
+                            attempt + 1,
+                            Some(delay),
+                        );
+                        continue;
+                    }
+
+                    print_error!("D-Bus error on {} for {}: {}", method, adapter, e);
+                    print_error!("Giving up on registering callbacks for {}", adapter);
+
+                    // A non-interactive command can't be run without the adapter.
+                    if command.is_some() {
+                        return Err("failed to register adapter callbacks".into());
+                    }
+                    continue;
+                }
 
                 context.lock().unwrap().adapter_ready = true;
                 let adapter_address = context.lock().unwrap().update_adapter_address();
@@ -807,24 +1244,75 @@ async fn handle_client_command(
 
                 print_info!("Adapter {} is ready", adapter_address.to_string());
 
-                if run_command_on_ready {
+                // This completes a pending `adapter wait-ready` or `adapter reset`.
+                if context.lock().unwrap().pending_adapter_ready_wait.take().is_some()
+                    && command.is_some()
+                    && command_processed
+                {
+                    break;
+                }
+
+                if !command_processed {
+                    command_processed = true;
                     if let Some(command) = command.as_ref() {
                         let mut iter = command.split(' ').map(String::from);
                         let first = iter.next().unwrap_or(String::from(""));
-                        if !handler.process_cmd_line(&first, &iter.collect::<Vec<String>>()) {
-                            // Break immediately if the command fails to execute.
-                            break;
+                        if let Err(exit_code) =
+                            handler.process_cmd_line(&first, &iter.collect::<Vec<String>>())
+                        {
+                            // Return immediately if the command fails to execute.
+                            return Err(exit_code.into());
                         }
 
                         // Break the loop immediately if there is no callback
                         // to wait for.
-                        if !context.lock().unwrap().client_commands_with_callbacks.contains(&first)
+                        let context_locked = context.lock().unwrap();
+                        if !context_locked.needs_callback(command)
+                            && context_locked.pending_adapter_ready_wait.is_none()
                         {
                             break;
                         }
                     }
                 }
             }
+            ForegroundActions::AdapterReadyTimeout(id) => {
+                let mut context_locked = context.lock().unwrap();
+                if context_locked.pending_adapter_ready_wait != Some(id) {
+                    continue;
+                }
+                context_locked.pending_adapter_ready_wait = None;
+                context_locked.pending_adapter_reset = false;
+
+                print_error!("Timed out waiting for the adapter to be ready");
+                if command.is_some() {
+                    return Err(CommandExitCode::Timeout.into());
+                }
+            }
+            ForegroundActions::PairAndConnectTimeout(id) => {
+                let mut context_locked = context.lock().unwrap();
+                let address = match context_locked.pending_pair_and_connect {
+                    Some((address, pending_id)) if pending_id == id => address,
+                    _ => continue,
+                };
+                context_locked.pending_pair_and_connect = None;
+
+                print_error!("Timed out pairing with {}", address.to_string());
+                let device = BluetoothDevice { address, name: String::from("") };
+                context_locked.adapter_dbus.as_mut().unwrap().cancel_bond_process(device);
+                if command.is_some() {
+                    return Err(CommandExitCode::Timeout.into());
+                }
+            }
+            ForegroundActions::OneShotScanTimeout(scanner_id, id) => {
+                let mut context_locked = context.lock().unwrap();
+                if !matches!(
+                    &context_locked.one_shot_scan,
+                    Some(scan) if scan.scanner_id == scanner_id && scan.id == id
+                ) {
+                    continue;
+                }
+                context_locked.stop_one_shot_scan(scanner_id);
+            }
             ForegroundActions::Readline(result) => match result {
                 Err(rustyline::error::ReadlineError::Interrupted) => {
                     // Ctrl-C cancels the currently typed line, do nothing and ready to do next
@@ -853,7 +1341,8 @@ async fn handle_client_command(
                             break 'foreground_actions;
                         }
 
-                        handler.process_cmd_line(cmd, rest);
+                        // Failures are already reported to the user.
+                        let _ = handler.process_cmd_line(cmd, rest);
                         break 'readline;
                     }
 
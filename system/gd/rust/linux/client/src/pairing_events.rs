@@ -0,0 +1,148 @@
+//! Pairing requests written as newline-delimited JSON for external pairing agents.
+//!
+//! An agent reads the events from a Unix socket, a FIFO or a plain file given with
+//! `--pairing-events <path>`, and answers them with the `device set-pairing-*` commands.
+
+use std::fs::OpenOptions;
+use std::io::{Error, ErrorKind, Result, Write};
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+
+use bt_topshim::btif::{BtSspVariant, RawAddress};
+
+use crate::{console_red, print_error};
+
+/// A pairing request from a remote device.
+pub(crate) enum PairingEvent {
+    SspRequest { address: RawAddress, name: String, cod: u32, variant: BtSspVariant, passkey: u32 },
+    PinRequest { address: RawAddress, name: String, cod: u32, min_16_digit: bool },
+    PinDisplay { address: RawAddress, name: String, pincode: String },
+}
+
+// Quotes |s| as a JSON string.
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+impl PairingEvent {
+    /// Formats the event as a single line JSON object, without the line break.
+    pub(crate) fn to_json(&self) -> String {
+        match self {
+            PairingEvent::SspRequest { address, name, cod, variant, passkey } => format!(
+                "{{\"event\":\"ssp_request\",\"address\":{},\"name\":{},\"cod\":{},\
+                 \"variant\":{},\"passkey\":{}}}",
+                json_string(&address.to_string()),
+                json_string(name),
+                cod,
+                json_string(&format!("{:?}", variant)),
+                passkey
+            ),
+            PairingEvent::PinRequest { address, name, cod, min_16_digit } => format!(
+                "{{\"event\":\"pin_request\",\"address\":{},\"name\":{},\"cod\":{},\
+                 \"min_16_digit\":{}}}",
+                json_string(&address.to_string()),
+                json_string(name),
+                cod,
+                min_16_digit
+            ),
+            PairingEvent::PinDisplay { address, name, pincode } => format!(
+                "{{\"event\":\"pin_display\",\"address\":{},\"name\":{},\"pincode\":{}}}",
+                json_string(&address.to_string()),
+                json_string(name),
+                json_string(pincode)
+            ),
+        }
+    }
+}
+
+/// Destination of the pairing events. The events are written from a separate thread, so that a
+/// slow or stuck agent never blocks the caller.
+pub(crate) struct PairingEventWriter {
+    tx: Sender<String>,
+}
+
+impl PairingEventWriter {
+    /// Connects to the Unix socket at |path|, or opens it for writing otherwise. Opening a FIFO
+    /// waits for its reader, and a regular file is created if needed and appended to.
+    pub(crate) fn open(path: &str) -> Result<PairingEventWriter> {
+        let is_socket = std::fs::metadata(path).map_or(false, |m| m.file_type().is_socket());
+        let out: Box<dyn Write + Send> = if is_socket {
+            Box::new(UnixStream::connect(path)?)
+        } else {
+            Box::new(OpenOptions::new().append(true).create(true).open(path)?)
+        };
+
+        Ok(PairingEventWriter::spawn(out))
+    }
+
+    // Starts the thread writing the queued events to |out|. The thread stops once writing fails.
+    fn spawn(mut out: Box<dyn Write + Send>) -> PairingEventWriter {
+        let (tx, rx) = channel::<String>();
+        thread::spawn(move || {
+            for line in rx {
+                if let Err(e) = writeln!(out, "{}", line).and_then(|_| out.flush()) {
+                    print_error!("Failed to write pairing event, no longer writing them: {}", e);
+                    return;
+                }
+            }
+        });
+
+        PairingEventWriter { tx }
+    }
+
+    /// Queues |event| to be written as one line. Fails once the writer has stopped.
+    pub(crate) fn write(&mut self, event: &PairingEvent) -> Result<()> {
+        self.tx
+            .send(event.to_json())
+            .map_err(|_| Error::new(ErrorKind::BrokenPipe, "pairing event writer stopped"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pairing_event_to_json() {
+        let address = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let event = PairingEvent::SspRequest {
+            address,
+            name: String::from("Keyboard"),
+            cod: 0x002540,
+            variant: BtSspVariant::PasskeyConfirmation,
+            passkey: 123456,
+        };
+        assert_eq!(
+            event.to_json(),
+            "{\"event\":\"ssp_request\",\"address\":\"11:22:33:44:55:66\",\"name\":\"Keyboard\",\
+             \"cod\":9536,\"variant\":\"PasskeyConfirmation\",\"passkey\":123456}"
+        );
+
+        // Names come from the remote device and are escaped.
+        let event = PairingEvent::PinDisplay {
+            address,
+            name: String::from("My \"Mouse\"\n"),
+            pincode: String::from("0000"),
+        };
+        assert_eq!(
+            event.to_json(),
+            "{\"event\":\"pin_display\",\"address\":\"11:22:33:44:55:66\",\
+             \"name\":\"My \\\"Mouse\\\"\\n\",\"pincode\":\"0000\"}"
+        );
+    }
+}
@@ -601,6 +601,9 @@ fn copy_without_attributes(item: &TokenStream) -> TokenStream {
 ///     }
 /// ]
 /// ```
+///
+/// Fields of type `Option<T>` may be left out of the dictionary, in which case they are `None`.
+/// This lets fields be added to a struct without breaking the callers that don't know about them.
 // TODO: Support more data types of struct fields (currently only supports integers and enums).
 #[proc_macro_attribute]
 pub fn dbus_propmap(attr: TokenStream, item: TokenStream) -> TokenStream {
@@ -637,6 +640,9 @@ pub fn dbus_propmap(attr: TokenStream, item: TokenStream) -> TokenStream {
             #field_idents #field_ident,
         };
 
+        let is_optional =
+            field_type.path.segments.last().map_or(false, |segment| segment.ident == "Option");
+
         let field_type_name = format_ident! {"{}_type_", field_str};
         let make_field = quote! {
             match #field_ident.arg_type() {
@@ -662,19 +668,33 @@ pub fn dbus_propmap(attr: TokenStream, item: TokenStream) -> TokenStream {
             )?;
         };
 
-        make_fields = quote! {
-            #make_fields
+        make_fields = if is_optional {
+            quote! {
+                #make_fields
 
-            let #field_ident = match data__.get(#field_str) {
-                Some(data) => data,
-                None => {
-                    return Err(Box::new(DBusArgError::new(format!(
-                        "{}.{} is required",
-                        #struct_str, #field_str
-                    ))));
-                }
-            };
-            #make_field
+                let #field_ident = match data__.get(#field_str) {
+                    Some(#field_ident) => {
+                        #make_field
+                        #field_ident
+                    }
+                    None => None,
+                };
+            }
+        } else {
+            quote! {
+                #make_fields
+
+                let #field_ident = match data__.get(#field_str) {
+                    Some(data) => data,
+                    None => {
+                        return Err(Box::new(DBusArgError::new(format!(
+                            "{}.{} is required",
+                            #struct_str, #field_str
+                        ))));
+                    }
+                };
+                #make_field
+            }
         };
 
         insert_map_fields = quote! {
@@ -1,7 +1,7 @@
 use bt_topshim::btif::{
-    BtAddrType, BtBondState, BtConnectionState, BtDeviceType, BtDiscMode, BtPropertyType,
-    BtSspVariant, BtStatus, BtTransport, BtVendorProductInfo, DisplayAddress, DisplayUuid,
-    RawAddress, Uuid,
+    BluetoothProperty, BtAddrType, BtBondState, BtConnectionDirection, BtConnectionState,
+    BtDeviceType, BtDiscMode, BtHciErrorCode, BtIoCap, BtPropertyType, BtSspVariant, BtStatus,
+    BtTransport, BtVendorProductInfo, DisplayAddress, DisplayUuid, RawAddress, Uuid,
 };
 use bt_topshim::profiles::socket::SocketType;
 use bt_topshim::profiles::ProfileConnectionState;
@@ -17,14 +17,16 @@ use bt_topshim::profiles::sdp::{
 };
 
 use btstack::bluetooth::{
-    Bluetooth, BluetoothDevice, BtAdapterRole, IBluetooth, IBluetoothCallback,
-    IBluetoothConnectionCallback, IBluetoothQALegacy,
+    AdvertisingData, Bluetooth, BluetoothDevice, BtAdapterRole, ConnectionQuality, ControllerInfo,
+    DiscoverySource, FoundDevice, IBluetooth, IBluetoothCallback, IBluetoothConnectionCallback,
+    IBluetoothQALegacy, LeAddressInfo, LeAudioCaps, PairingPolicy,
 };
 use btstack::socket_manager::{
     BluetoothServerSocket, BluetoothSocket, BluetoothSocketManager, CallbackId,
-    IBluetoothSocketManager, IBluetoothSocketManagerCallbacks, SocketId, SocketResult,
+    IBluetoothSocketManager, IBluetoothSocketManagerCallbacks, SocketId, SocketInfo, SocketResult,
 };
 use btstack::suspend::{ISuspend, ISuspendCallback, Suspend, SuspendType};
+use btstack::uuid::Profile;
 use btstack::RPCProxy;
 
 use dbus::arg::RefArg;
@@ -36,6 +38,7 @@ use dbus_projection::prelude::*;
 
 use num_traits::cast::{FromPrimitive, ToPrimitive};
 
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 use std::sync::{Arc, Mutex};
 
@@ -123,6 +126,10 @@ impl IBluetoothCallback for BluetoothCallbackDBus {
     fn on_discovering_changed(&mut self, discovering: bool) {
         dbus_generated!()
     }
+    #[dbus_method("OnDiscoveryTimeout")]
+    fn on_discovery_timeout(&mut self) {
+        dbus_generated!()
+    }
     #[dbus_method(
         "OnSspRequest",
         DBusLog::Enable(DBusLogOptions::LogAll, DBusLogVerbosity::Verbose)
@@ -151,6 +158,10 @@ impl IBluetoothCallback for BluetoothCallbackDBus {
     fn on_bond_state_changed(&mut self, status: u32, address: RawAddress, state: u32) {
         dbus_generated!()
     }
+    #[dbus_method("OnBondedDevicesChanged")]
+    fn on_bonded_devices_changed(&mut self, devices: Vec<BluetoothDevice>) {
+        dbus_generated!()
+    }
     #[dbus_method("OnSdpSearchComplete")]
     fn on_sdp_search_complete(
         &mut self,
@@ -164,6 +175,15 @@ impl IBluetoothCallback for BluetoothCallbackDBus {
     fn on_sdp_record_created(&mut self, record: BtSdpRecord, handle: i32) {
         dbus_generated!()
     }
+    #[dbus_method("OnRemoteUuidsFetched")]
+    fn on_remote_uuids_fetched(
+        &mut self,
+        remote_device: BluetoothDevice,
+        uuids: Vec<Uuid>,
+        status: BtStatus,
+    ) {
+        dbus_generated!()
+    }
 }
 
 impl_dbus_arg_enum!(BtBondState);
@@ -173,8 +193,56 @@ impl_dbus_arg_enum!(BtAddrType);
 impl_dbus_arg_enum!(BtPropertyType);
 impl_dbus_arg_enum!(BtSspVariant);
 impl_dbus_arg_enum!(BtTransport);
+impl_dbus_arg_enum!(Profile);
 impl_dbus_arg_enum!(ProfileConnectionState);
 impl_dbus_arg_enum!(BtAdapterRole);
+impl_dbus_arg_enum!(DiscoverySource);
+impl_dbus_arg_enum!(PairingPolicy);
+
+#[dbus_propmap(LeAudioCaps)]
+pub struct LeAudioCapsDBus {
+    cis_central: bool,
+    cis_peripheral: bool,
+    iso_broadcaster: bool,
+    iso_sync_receiver: bool,
+}
+
+#[dbus_propmap(LeAddressInfo)]
+pub struct LeAddressInfoDBus {
+    address: RawAddress,
+    address_type: BtAddrType,
+}
+
+#[dbus_propmap(ControllerInfo)]
+pub struct ControllerInfoDBus {
+    hci_version: u8,
+    hci_revision: u16,
+    lmp_version: u8,
+    subversion: u16,
+    manufacturer: u16,
+}
+
+#[dbus_propmap(AdvertisingData)]
+pub struct AdvertisingDataDBus {
+    flags: u8,
+    tx_power: Option<i32>,
+    service_uuids: Vec<Uuid>,
+    service_data: HashMap<String, Vec<u8>>,
+    manufacturer_data: HashMap<u16, Vec<u8>>,
+    appearance: Option<u16>,
+}
+
+#[dbus_propmap(FoundDevice)]
+pub struct FoundDeviceDBus {
+    device: BluetoothDevice,
+    age_ms: u64,
+}
+
+#[dbus_propmap(ConnectionQuality)]
+pub struct ConnectionQualityDBus {
+    rssi: Option<i32>,
+    link_quality: Option<u32>,
+}
 
 #[allow(dead_code)]
 struct BluetoothConnectionCallbackDBus {}
@@ -201,7 +269,24 @@ impl IBluetoothConnectionCallback for BluetoothConnectionCallbackDBus {
         "OnDeviceConnectionFailed",
         DBusLog::Enable(DBusLogOptions::LogAll, DBusLogVerbosity::Verbose)
     )]
-    fn on_device_connection_failed(&mut self, remote_device: BluetoothDevice, status: BtStatus) {
+    fn on_device_connection_failed(
+        &mut self,
+        remote_device: BluetoothDevice,
+        status: BtStatus,
+        hci_reason: BtHciErrorCode,
+    ) {
+        dbus_generated!()
+    }
+
+    #[dbus_method(
+        "OnConnectionQuality",
+        DBusLog::Enable(DBusLogOptions::LogAll, DBusLogVerbosity::Verbose)
+    )]
+    fn on_connection_quality(
+        &mut self,
+        remote_device: BluetoothDevice,
+        quality: ConnectionQuality,
+    ) {
         dbus_generated!()
     }
 }
@@ -443,6 +528,33 @@ impl DBusArg for BtSdpRecord {
     }
 }
 
+impl DBusArg for BluetoothProperty {
+    type DBusType = dbus::arg::PropMap;
+    fn from_dbus(
+        data: dbus::arg::PropMap,
+        _conn: Option<std::sync::Arc<dbus::nonblock::SyncConnection>>,
+        _remote: Option<dbus::strings::BusName<'static>>,
+        _disconnect_watcher: Option<
+            std::sync::Arc<std::sync::Mutex<dbus_projection::DisconnectWatcher>>,
+        >,
+    ) -> Result<BluetoothProperty, Box<dyn std::error::Error>> {
+        let prop_type = read_propmap_value::<u32>(&data, &String::from("type"))?;
+        let value = parse_propmap_value::<Vec<u8>>(&data, &String::from("value"))?;
+        Ok(BluetoothProperty::from_raw_value(BtPropertyType::from(prop_type), value))
+    }
+
+    fn to_dbus(prop: BluetoothProperty) -> Result<dbus::arg::PropMap, Box<dyn std::error::Error>> {
+        let mut map: dbus::arg::PropMap = std::collections::HashMap::new();
+        write_propmap_value::<u32>(&mut map, prop.get_type().into(), &String::from("type"))?;
+        write_propmap_value::<Vec<u8>>(&mut map, prop.to_raw_value(), &String::from("value"))?;
+        Ok(map)
+    }
+
+    fn log(prop: &BluetoothProperty) -> String {
+        format!("{:?}", prop)
+    }
+}
+
 impl DBusArg for RawAddress {
     type DBusType = String;
     fn from_dbus(
@@ -470,7 +582,9 @@ impl DBusArg for RawAddress {
     }
 }
 
+impl_dbus_arg_enum!(BtConnectionDirection);
 impl_dbus_arg_enum!(BtDiscMode);
+impl_dbus_arg_enum!(BtIoCap);
 impl_dbus_arg_from_into!(EscoCodingFormat, u8);
 
 #[allow(dead_code)]
@@ -531,11 +645,26 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetAdapterProperty", DBusLog::Disable)]
+    fn get_adapter_property(&self, prop_type: BtPropertyType) -> Option<BluetoothProperty> {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetUuids", DBusLog::Disable)]
     fn get_uuids(&self) -> Vec<Uuid> {
         dbus_generated!()
     }
 
+    #[dbus_method("GetLocalLeAddressInfo", DBusLog::Disable)]
+    fn get_local_le_address_info(&self) -> LeAddressInfo {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetControllerInfo")]
+    fn get_controller_info(&self) -> Option<ControllerInfo> {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetName", DBusLog::Disable)]
     fn get_name(&self) -> String {
         dbus_generated!()
@@ -571,6 +700,11 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("SetDiscoverableTimeout")]
+    fn set_discoverable_timeout(&mut self, duration: u32) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("IsMultiAdvertisementSupported", DBusLog::Disable)]
     fn is_multi_advertisement_supported(&self) -> bool {
         dbus_generated!()
@@ -616,11 +750,21 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("ForgetDevice")]
+    fn forget_device(&mut self, device: BluetoothDevice) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetBondedDevices", DBusLog::Disable)]
     fn get_bonded_devices(&self) -> Vec<BluetoothDevice> {
         dbus_generated!()
     }
 
+    #[dbus_method("GetFoundDevicesWithAge", DBusLog::Disable)]
+    fn get_found_devices_with_age(&self) -> Vec<FoundDevice> {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetBondState", DBusLog::Disable)]
     fn get_bond_state(&self, device: BluetoothDevice) -> BtBondState {
         dbus_generated!()
@@ -641,6 +785,31 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("SetPairingPolicy")]
+    fn set_pairing_policy(&mut self, policy: PairingPolicy) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetForceUuidFetchOnBond")]
+    fn set_force_uuid_fetch_on_bond(&mut self, force: bool) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetPropertyChangeCoalescing")]
+    fn set_property_change_coalescing(&mut self, enabled: bool) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetConnectRetryPolicy")]
+    fn set_connect_retry_policy(&mut self, max_retries: u32, backoff_ms: u32) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetIoCapability")]
+    fn set_io_capability(&mut self, io_cap: BtIoCap) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetRemoteName", DBusLog::Disable)]
     fn get_remote_name(&self, _device: BluetoothDevice) -> String {
         dbus_generated!()
@@ -657,7 +826,16 @@ impl IBluetooth for IBluetoothDBus {
     }
 
     #[dbus_method("SetRemoteAlias")]
-    fn set_remote_alias(&mut self, _device: BluetoothDevice, new_alias: String) {
+    fn set_remote_alias(&mut self, _device: BluetoothDevice, new_alias: String) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetRemoteDeviceTypeOverride")]
+    fn set_remote_device_type_override(
+        &mut self,
+        _device: BluetoothDevice,
+        device_type: BtDeviceType,
+    ) -> bool {
         dbus_generated!()
     }
 
@@ -681,6 +859,26 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetRemoteConnectionDirection", DBusLog::Disable)]
+    fn get_remote_connection_direction(&self, _device: BluetoothDevice) -> BtConnectionDirection {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetRemoteDiscoverySources", DBusLog::Disable)]
+    fn get_remote_discovery_sources(&self, _device: BluetoothDevice) -> Vec<DiscoverySource> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetConnectionHandles")]
+    fn get_connection_handles(&self, _device: BluetoothDevice) -> Vec<u16> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetRemoteWakeAllowed")]
+    fn set_remote_wake_allowed(&mut self, _device: BluetoothDevice, _allowed: bool) {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetRemoteVendorProductInfo", DBusLog::Disable)]
     fn get_remote_vendor_product_info(&self, _device: BluetoothDevice) -> BtVendorProductInfo {
         dbus_generated!()
@@ -696,6 +894,31 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetRemoteRSSISmoothed", DBusLog::Disable)]
+    fn get_remote_rssi_smoothed(&self, device: BluetoothDevice) -> i8 {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetRSSISmoothingFactor")]
+    fn set_rssi_smoothing_factor(&mut self, factor: f64) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("SetDeviceFoundMinInterval")]
+    fn set_device_found_min_interval(&mut self, interval_ms: u32) {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetRemoteAllProperties", DBusLog::Disable)]
+    fn get_remote_all_properties(&self, device: BluetoothDevice) -> Vec<BluetoothProperty> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetRemoteAdvertisingData", DBusLog::Disable)]
+    fn get_remote_advertising_data(&self, device: BluetoothDevice) -> AdvertisingData {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetConnectedDevices", DBusLog::Disable)]
     fn get_connected_devices(&self) -> Vec<BluetoothDevice> {
         dbus_generated!()
@@ -717,7 +940,7 @@ impl IBluetooth for IBluetoothDBus {
     }
 
     #[dbus_method("FetchRemoteUuids", DBusLog::Disable)]
-    fn fetch_remote_uuids(&self, device: BluetoothDevice) -> bool {
+    fn fetch_remote_uuids(&mut self, device: BluetoothDevice) -> bool {
         dbus_generated!()
     }
 
@@ -741,11 +964,31 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetConnectableProfiles")]
+    fn get_connectable_profiles(&self, device: BluetoothDevice) -> Vec<Profile> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("CancelConnectAllEnabledProfiles")]
+    fn cancel_connect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("DisconnectAllEnabledProfiles")]
     fn disconnect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
         dbus_generated!()
     }
 
+    #[dbus_method("SetProfileEnabled")]
+    fn set_profile_enabled(&mut self, profile: Profile, enabled: bool) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("DisconnectHid")]
+    fn disconnect_hid(&mut self, device: BluetoothDevice, reconnect_allowed: bool) -> BtStatus {
+        dbus_generated!()
+    }
+
     #[dbus_method("IsWbsSupported", DBusLog::Disable)]
     fn is_wbs_supported(&self) -> bool {
         dbus_generated!()
@@ -771,11 +1014,21 @@ impl IBluetooth for IBluetoothDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("GetLeAudioCapabilities", DBusLog::Disable)]
+    fn get_le_audio_capabilities(&self) -> LeAudioCaps {
+        dbus_generated!()
+    }
+
     #[dbus_method("IsDualModeAudioSinkDevice", DBusLog::Disable)]
     fn is_dual_mode_audio_sink_device(&self, device: BluetoothDevice) -> bool {
         dbus_generated!()
     }
 
+    #[dbus_method("GetConnectionQuality")]
+    fn get_connection_quality(&mut self, device: BluetoothDevice) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("GetDumpsys", DBusLog::Disable)]
     fn get_dumpsys(&self) -> String {
         dbus_generated!()
@@ -808,6 +1061,17 @@ pub struct BluetoothSocketDBus {
     max_tx_size: i32,
 }
 
+#[dbus_propmap(SocketInfo)]
+pub struct SocketInfoDBus {
+    id: SocketId,
+    sock_type: SocketType,
+    is_listening: bool,
+    remote_address: Option<RawAddress>,
+    psm: Option<i32>,
+    channel: Option<i32>,
+    uuid: Option<Uuid>,
+}
+
 #[dbus_propmap(SocketResult)]
 pub struct SocketResultDBus {
     status: BtStatus,
@@ -847,6 +1111,11 @@ impl IBluetoothSocketManagerCallbacks for IBluetoothSocketManagerCallbacksDBus {
     ) {
         dbus_generated!()
     }
+
+    #[dbus_method("OnL2capPingResult")]
+    fn on_l2cap_ping_result(&mut self, addr: RawAddress, status: BtStatus, rtt_ms: u32) {
+        dbus_generated!()
+    }
 }
 
 #[allow(dead_code)]
@@ -993,6 +1262,16 @@ impl IBluetoothSocketManager for IBluetoothSocketManagerDBus {
     fn close(&mut self, callback: CallbackId, id: SocketId) -> BtStatus {
         dbus_generated!()
     }
+
+    #[dbus_method("GetActiveSockets")]
+    fn get_active_sockets(&self, callback: CallbackId) -> Vec<SocketInfo> {
+        dbus_generated!()
+    }
+
+    #[dbus_method("L2capPing")]
+    fn l2cap_ping(&mut self, callback: CallbackId, addr: RawAddress, size: u16) -> bool {
+        dbus_generated!()
+    }
 }
 
 impl_dbus_arg_enum!(SuspendType);
@@ -2,8 +2,8 @@ use bt_topshim::btif::{BtStatus, BtTransport, RawAddress, Uuid};
 use bt_topshim::profiles::gatt::{AdvertisingStatus, GattStatus, LeDiscMode, LePhy};
 
 use btstack::bluetooth_adv::{
-    AdvertiseData, AdvertisingSetParameters, IAdvertisingSetCallback, ManfId,
-    PeriodicAdvertisingParameters,
+    AdvertiseData, AdvertisingSetParameters, DirectedAdvertisingMode, IAdvertisingSetCallback,
+    ManfId, PeriodicAdvertisingParameters,
 };
 use btstack::bluetooth_gatt::{
     BluetoothGattCharacteristic, BluetoothGattDescriptor, BluetoothGattService,
@@ -66,6 +66,17 @@ impl IBluetoothGattCallback for BluetoothGattCallbackDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("OnRemotePhyCapabilities")]
+    fn on_remote_phy_capabilities(
+        &mut self,
+        addr: RawAddress,
+        le_2m: bool,
+        le_coded: bool,
+        status: GattStatus,
+    ) {
+        dbus_generated!()
+    }
+
     #[dbus_method("OnSearchComplete")]
     fn on_search_complete(
         &mut self,
@@ -372,6 +383,7 @@ impl_dbus_arg_enum!(GattWriteRequestStatus);
 impl_dbus_arg_enum!(GattWriteType);
 impl_dbus_arg_enum!(LeDiscMode);
 impl_dbus_arg_enum!(LePhy);
+impl_dbus_arg_enum!(DirectedAdvertisingMode);
 impl_dbus_arg_enum!(ScanType);
 impl_dbus_arg_enum!(SuspendMode);
 
@@ -568,6 +580,9 @@ struct AdvertisingSetParametersDBus {
     interval: i32,
     tx_power_level: i32,
     own_address_type: i32,
+    directed_mode: Option<DirectedAdvertisingMode>,
+    peer_address: Option<RawAddress>,
+    peer_address_type: Option<i32>,
 }
 
 #[dbus_propmap(AdvertiseData)]
@@ -774,6 +789,11 @@ impl IBluetoothGatt for IBluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("ClientSetAutoConnect")]
+    fn client_set_auto_connect(&mut self, client_id: i32, addr: RawAddress, enabled: bool) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("RefreshDevice")]
     fn refresh_device(&self, client_id: i32, addr: RawAddress) {
         dbus_generated!()
@@ -789,6 +809,11 @@ impl IBluetoothGatt for IBluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("HasService", DBusLog::Disable)]
+    fn has_service(&self, client_id: i32, addr: RawAddress, service_uuid: String) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("BtifGattcDiscoverServiceByUuid", DBusLog::Disable)]
     fn btif_gattc_discover_service_by_uuid(&self, client_id: i32, addr: RawAddress, uuid: String) {
         dbus_generated!()
@@ -853,6 +878,22 @@ impl IBluetoothGatt for IBluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("SetPersistNotifications", DBusLog::Disable)]
+    fn set_persist_notifications(
+        &mut self,
+        client_id: i32,
+        addr: RawAddress,
+        handle: i32,
+        persist: bool,
+    ) -> bool {
+        dbus_generated!()
+    }
+
+    #[dbus_method("GetPersistedNotifications", DBusLog::Disable)]
+    fn get_persisted_notifications(&self, client_id: i32, addr: RawAddress) -> Vec<i32> {
+        dbus_generated!()
+    }
+
     #[dbus_method("BeginReliableWrite", DBusLog::Disable)]
     fn begin_reliable_write(&mut self, client_id: i32, addr: RawAddress) {
         dbus_generated!()
@@ -905,6 +946,11 @@ impl IBluetoothGatt for IBluetoothGattDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("ClientReadRemotePhyCapabilities")]
+    fn client_read_remote_phy_capabilities(&mut self, client_id: i32, addr: RawAddress) {
+        dbus_generated!()
+    }
+
     // GATT Server
 
     #[dbus_method("RegisterServer")]
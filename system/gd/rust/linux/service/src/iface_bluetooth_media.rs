@@ -80,6 +80,11 @@ impl_dbus_arg_from_into!(A2dpCodecChannelMode, i32);
 
 #[dbus_proxy_obj(BluetoothMediaCallback, "org.chromium.bluetooth.BluetoothMediaCallback")]
 impl IBluetoothMediaCallback for BluetoothMediaCallbackDBus {
+    #[dbus_method("OnCallbackRegistered")]
+    fn on_callback_registered(&mut self, callback_id: u32) {
+        dbus_generated!()
+    }
+
     #[dbus_method("OnBluetoothAudioDeviceAdded")]
     fn on_bluetooth_audio_device_added(&mut self, device: BluetoothAudioDevice) {
         dbus_generated!()
@@ -263,6 +268,11 @@ impl IBluetoothMedia for IBluetoothMediaDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("UnregisterCallback")]
+    fn unregister_callback(&mut self, callback_id: u32) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("Initialize")]
     fn initialize(&mut self) -> bool {
         dbus_generated!()
@@ -1,14 +1,51 @@
-use btstack::bluetooth_qa::{IBluetoothQA, IBluetoothQACallback};
+use btstack::bluetooth_qa::{
+    AcceptListEntry, AdapterMode, ClassicScanParams, ClassicScanType, IBluetoothQA,
+    IBluetoothQACallback, LinkErrorCounters, LinkPowerMode, SniffParams,
+};
 
-use bt_topshim::btif::{BtDiscMode, RawAddress};
-use dbus_macros::{dbus_method, dbus_proxy_obj, generate_dbus_exporter};
+use bt_topshim::btif::{BtAddrType, BtDiscMode, RawAddress};
+use dbus_macros::{dbus_method, dbus_propmap, dbus_proxy_obj, generate_dbus_exporter};
 use dbus_projection::prelude::*;
 
-use crate::dbus_arg::DBusArg;
+use crate::dbus_arg::{DBusArg, DBusArgError, RefArgToRust};
 use bt_topshim::btif::BtStatus;
 use bt_topshim::profiles::hid_host::BthhReportType;
 use btstack::RPCProxy;
+use dbus::arg::RefArg;
 use dbus::Path;
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use std::collections::HashMap;
+
+#[dbus_propmap(LinkErrorCounters)]
+pub struct LinkErrorCountersDBus {
+    failed_contact_counter: u16,
+    link_quality: Option<u8>,
+}
+
+#[dbus_propmap(SniffParams)]
+pub struct SniffParamsDBus {
+    max_interval: u16,
+    min_interval: u16,
+    attempt: u16,
+    timeout: u16,
+}
+
+impl_dbus_arg_enum!(LinkPowerMode);
+impl_dbus_arg_enum!(AdapterMode);
+impl_dbus_arg_enum!(ClassicScanType);
+
+#[dbus_propmap(ClassicScanParams)]
+pub struct ClassicScanParamsDBus {
+    interval: u16,
+    window: u16,
+    scan_type: ClassicScanType,
+}
+
+#[dbus_propmap(AcceptListEntry)]
+pub struct AcceptListEntryDBus {
+    address: RawAddress,
+    addr_type: BtAddrType,
+}
 
 #[allow(dead_code)]
 struct IBluetoothQACallbackDBus {}
@@ -69,6 +106,93 @@ impl IBluetoothQA for IBluetoothQADBus {
     fn send_hid_virtual_unplug(&self, addr: RawAddress) {
         dbus_generated!()
     }
+    #[dbus_method("GetCallbackCounts")]
+    fn get_callback_counts(&self) -> HashMap<String, u32> {
+        dbus_generated!()
+    }
+    #[dbus_method("GetRecentMetrics")]
+    fn get_recent_metrics(&self) -> Vec<String> {
+        dbus_generated!()
+    }
+    #[dbus_method("GetDroppedMessageCount")]
+    fn get_dropped_message_count(&self) -> u64 {
+        dbus_generated!()
+    }
+    #[dbus_method("GetLinkErrorCounters")]
+    fn get_link_error_counters(&self, addr: RawAddress) {
+        dbus_generated!()
+    }
+    #[dbus_method("ResetFailedContactCounter")]
+    fn reset_failed_contact_counter(&self, addr: RawAddress) {
+        dbus_generated!()
+    }
+    #[dbus_method("SetLinkPowerMode")]
+    fn set_link_power_mode(
+        &self,
+        addr: RawAddress,
+        mode: LinkPowerMode,
+        params: SniffParams,
+    ) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetLinkPowerMode")]
+    fn get_link_power_mode(&self, addr: RawAddress) {
+        dbus_generated!()
+    }
+    #[dbus_method("AddToAcceptList")]
+    fn add_to_accept_list(&mut self, addr: RawAddress, addr_type: BtAddrType) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("RemoveFromAcceptList")]
+    fn remove_from_accept_list(&mut self, addr: RawAddress) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetAcceptList")]
+    fn get_accept_list(&self) -> Vec<AcceptListEntry> {
+        dbus_generated!()
+    }
+    #[dbus_method("SetAdapterMode")]
+    fn set_adapter_mode(&mut self, mode: AdapterMode) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetAdapterMode")]
+    fn get_adapter_mode(&self) -> AdapterMode {
+        dbus_generated!()
+    }
+    #[dbus_method("GetScanParams")]
+    fn get_scan_params(&self) -> ClassicScanParams {
+        dbus_generated!()
+    }
+    #[dbus_method("SetScanParams")]
+    fn set_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetPageScanParams")]
+    fn get_page_scan_params(&self) -> ClassicScanParams {
+        dbus_generated!()
+    }
+    #[dbus_method("SetPageScanParams")]
+    fn set_page_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus {
+        dbus_generated!()
+    }
+    #[dbus_method("GetCancellingDevices")]
+    fn get_cancelling_devices(&self) -> Vec<RawAddress> {
+        dbus_generated!()
+    }
+    #[dbus_method("SetAutoAcceptJustWorks")]
+    fn set_auto_accept_just_works(&mut self, enabled: bool) {
+        dbus_generated!()
+    }
 }
 
 #[dbus_proxy_obj(QACallback, "org.chromium.bluetooth.QACallback")]
@@ -105,4 +229,40 @@ impl IBluetoothQACallback for IBluetoothQACallbackDBus {
     fn on_send_hid_virtual_unplug_completed(&mut self, status: BtStatus) {
         dbus_generated!()
     }
+    #[dbus_method("OnGetLinkErrorCountersComplete")]
+    fn on_get_link_error_counters_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        counters: LinkErrorCounters,
+    ) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnResetFailedContactCounterComplete")]
+    fn on_reset_failed_contact_counter_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnGetLinkPowerModeComplete")]
+    fn on_get_link_power_mode_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+    ) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnLinkPowerModeChanged")]
+    fn on_link_power_mode_changed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+        interval: u16,
+    ) {
+        dbus_generated!()
+    }
+    #[dbus_method("OnAddToAcceptListComplete")]
+    fn on_add_to_accept_list_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        dbus_generated!()
+    }
 }
@@ -15,6 +15,11 @@ struct BluetoothTelephonyCallbackDBus {}
 
 #[dbus_proxy_obj(BluetoothTelephonyCallback, "org.chromium.bluetooth.BluetoothTelephonyCallback")]
 impl IBluetoothTelephonyCallback for BluetoothTelephonyCallbackDBus {
+    #[dbus_method("OnCallbackRegistered")]
+    fn on_callback_registered(&mut self, callback_id: u32) {
+        dbus_generated!()
+    }
+
     #[dbus_method("OnTelephonyEvent")]
     fn on_telephony_event(&mut self, addr: RawAddress, event: u8, call_state: u8) {
         dbus_generated!()
@@ -37,6 +42,11 @@ impl IBluetoothTelephony for IBluetoothTelephonyDBus {
         dbus_generated!()
     }
 
+    #[dbus_method("UnregisterTelephonyCallback")]
+    fn unregister_telephony_callback(&mut self, callback_id: u32) -> bool {
+        dbus_generated!()
+    }
+
     #[dbus_method("SetNetworkAvailable")]
     fn set_network_available(&mut self, network_available: bool) {
         dbus_generated!()
@@ -105,4 +115,8 @@ impl IBluetoothTelephony for IBluetoothTelephonyDBus {
     fn audio_disconnect(&mut self, address: RawAddress) {
         dbus_generated!()
     }
+    #[dbus_method("ClearCalls")]
+    fn clear_calls(&mut self) {
+        dbus_generated!()
+    }
 }
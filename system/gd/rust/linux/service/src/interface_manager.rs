@@ -52,6 +52,7 @@ impl InterfaceManager {
         mut rx: Receiver<APIMessage>,
         tx: Sender<Message>,
         virt_index: i32,
+        graceful_shutdown: bool,
         conn: Arc<SyncConnection>,
         conn_join_handle: tokio::task::JoinHandle<()>,
         disconnect_watcher: Arc<Mutex<DisconnectWatcher>>,
@@ -249,7 +250,12 @@ impl InterfaceManager {
 
                     let tx = tx.clone();
                     tokio::spawn(async move {
-                        let _ = tx.send(Message::AdapterShutdown).await;
+                        let msg = if graceful_shutdown {
+                            Message::AdapterShutdownGraceful
+                        } else {
+                            Message::AdapterShutdown
+                        };
+                        let _ = tx.send(msg).await;
                     });
                     break;
                 }
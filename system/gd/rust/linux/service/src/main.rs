@@ -1,11 +1,12 @@
 use clap::{App, AppSettings, Arg};
+use dbus::nonblock::{Proxy, SyncConnection};
 use dbus_projection::DisconnectWatcher;
 use dbus_tokio::connection;
 use futures::future;
 use nix::sys::signal;
 use std::error::Error;
 use std::sync::{Arc, Condvar, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::runtime::Builder;
 use tokio::sync::mpsc::Sender;
 
@@ -17,7 +18,7 @@ use btstack::{
     bluetooth::{Bluetooth, IBluetooth, SigData},
     bluetooth_admin::BluetoothAdmin,
     bluetooth_gatt::BluetoothGatt,
-    bluetooth_logging::BluetoothLogging,
+    bluetooth_logging::{BluetoothLogging, LogOutput},
     bluetooth_media::BluetoothMedia,
     bluetooth_qa::BluetoothQA,
     dis::DeviceInformation,
@@ -40,6 +41,8 @@ mod interface_manager;
 
 const DBUS_SERVICE_NAME: &str = "org.chromium.bluetooth";
 const ADMIN_SETTINGS_FILE_PATH: &str = "/var/lib/bluetooth/admin_policy.json";
+const ADAPTER_MODE_FILE_PATH: &str = "/var/lib/bluetooth/adapter_mode.json";
+const IO_CAPABILITY_FILE_PATH: &str = "/var/lib/bluetooth/io_capability.json";
 // The maximum ACL disconnect timeout is 3.5s defined by BTA_DM_DISABLE_TIMER_MS
 // and BTA_DM_DISABLE_TIMER_RETRIAL_MS
 const STACK_TURN_OFF_TIMEOUT_MS: Duration = Duration::from_millis(4000);
@@ -51,6 +54,16 @@ const STACK_CLEANUP_PROFILES_TIMEOUT_MS: Duration = Duration::from_millis(100);
 const EXTRA_WAIT_BEFORE_KILL_MS: Duration = Duration::from_millis(1000);
 
 const INIT_LOGGING_MAX_RETRY: u8 = 3;
+const DEFAULT_LOG_FILE_PATH: &str = "/var/log/btadapterd.log";
+const DEFAULT_LOG_FILE_MAX_KB: &str = "10240";
+const DEFAULT_LOG_FILE_ROTATIONS: &str = "3";
+
+// Interval between the checks that our own objects are still served over D-Bus
+const DBUS_LIVENESS_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// Time a liveness check waits for the reply
+const DBUS_LIVENESS_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+// Consecutive failed liveness checks after which the D-Bus connection is considered broken
+const DBUS_LIVENESS_MAX_FAILURES: u32 = 3;
 
 /// Runs the Bluetooth daemon serving D-Bus IPC.
 fn main() -> Result<(), Box<dyn Error>> {
@@ -81,18 +94,74 @@ fn main() -> Result<(), Box<dyn Error>> {
             Arg::with_name("log-output")
                 .long("log-output")
                 .takes_value(true)
-                .possible_values(&["syslog", "stderr"])
+                .possible_values(&["syslog", "stderr", "file"])
                 .default_value("syslog")
                 .help("Select log output"),
         )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .value_name("PATH")
+                .takes_value(true)
+                .default_value(DEFAULT_LOG_FILE_PATH)
+                .help("The file logs are written to with --log-output file"),
+        )
+        .arg(
+            Arg::with_name("log-file-max-kb")
+                .long("log-file-max-kb")
+                .value_name("KB")
+                .takes_value(true)
+                .default_value(DEFAULT_LOG_FILE_MAX_KB)
+                .help("Size after which the log file is rotated"),
+        )
+        .arg(
+            Arg::with_name("log-file-rotations")
+                .long("log-file-rotations")
+                .value_name("COUNT")
+                .takes_value(true)
+                .default_value(DEFAULT_LOG_FILE_ROTATIONS)
+                .help("Number of rotated log files kept"),
+        )
+        .arg(
+            Arg::with_name("graceful-shutdown")
+                .long("graceful-shutdown")
+                .help("Disconnects connected devices before disabling the adapter on shutdown"),
+        )
+        .arg(
+            Arg::with_name("suspend-watchdog-timeout-ms")
+                .long("suspend-watchdog-timeout-ms")
+                .value_name("MS")
+                .takes_value(true)
+                .help("Resets scan and discovery stuck suspending or resuming after this long"),
+        )
         .get_matches();
 
     let is_debug = matches.is_present("debug");
     let is_verbose_debug = matches.is_present("verbose-debug");
-    let log_output = matches.value_of("log-output").unwrap_or("syslog");
+    let log_output = match matches.value_of("log-output").unwrap_or("syslog") {
+        "stderr" => LogOutput::Stderr,
+        "file" => LogOutput::File {
+            path: matches.value_of("log-file").unwrap_or(DEFAULT_LOG_FILE_PATH).into(),
+            max_size: matches
+                .value_of("log-file-max-kb")
+                .and_then(|kb| kb.parse::<u64>().ok())
+                .ok_or("Invalid --log-file-max-kb")?
+                * 1024,
+            max_rotated: matches
+                .value_of("log-file-rotations")
+                .and_then(|count| count.parse::<u32>().ok())
+                .ok_or("Invalid --log-file-rotations")?,
+        },
+        _ => LogOutput::Syslog,
+    };
+    let graceful_shutdown = matches.is_present("graceful-shutdown");
 
     let virt_index = matches.value_of("index").map_or(0, |idx| idx.parse::<i32>().unwrap_or(0));
     let hci_index = matches.value_of("hci").map_or(0, |idx| idx.parse::<i32>().unwrap_or(0));
+    let suspend_watchdog_timeout = matches
+        .value_of("suspend-watchdog-timeout-ms")
+        .and_then(|ms| ms.parse::<u64>().ok())
+        .map(Duration::from_millis);
 
     let logging = Arc::new(Mutex::new(Box::new(BluetoothLogging::new(
         is_debug,
@@ -161,11 +230,15 @@ fn main() -> Result<(), Box<dyn Error>> {
             api_tx.clone(),
             sig_notifier.clone(),
             intf.clone(),
+            String::from(IO_CAPABILITY_FILE_PATH),
         ))));
-        let bluetooth_qa = Arc::new(Mutex::new(Box::new(BluetoothQA::new(tx.clone()))));
         let battery_provider_manager =
             Arc::new(Mutex::new(Box::new(BatteryProviderManager::new(tx.clone()))));
 
+        if let Some(timeout) = suspend_watchdog_timeout {
+            bluetooth.lock().unwrap().set_suspend_mode_watchdog_timeout(timeout);
+        }
+        bluetooth.lock().unwrap().set_logging(logging.clone());
         bluetooth.lock().unwrap().init(hci_index);
         bluetooth.lock().unwrap().enable();
 
@@ -185,6 +258,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         ))));
         let bluetooth_gatt =
             Arc::new(Mutex::new(Box::new(BluetoothGatt::new(intf.clone(), tx.clone()))));
+        let bluetooth_qa = Arc::new(Mutex::new(Box::new(BluetoothQA::new(
+            tx.clone(),
+            bluetooth.clone(),
+            bluetooth_gatt.clone(),
+            bt_sock_mgr.clone(),
+            String::from(ADAPTER_MODE_FILE_PATH),
+        ))));
 
         // These constructions don't need |intf| to be init-ed, but just depend on those who need.
         let bluetooth_admin = Arc::new(Mutex::new(Box::new(BluetoothAdmin::new(
@@ -239,10 +319,13 @@ fn main() -> Result<(), Box<dyn Error>> {
         disconnect_watcher.setup_watch(conn.clone()).await;
         let disconnect_watcher = Arc::new(Mutex::new(disconnect_watcher));
 
+        tokio::spawn(check_dbus_liveness(conn.clone(), tx.clone()));
+
         tokio::spawn(interface_manager::InterfaceManager::dispatch(
             api_rx,
             tx.clone(),
             virt_index,
+            graceful_shutdown,
             conn,
             conn_join_handle,
             disconnect_watcher.clone(),
@@ -264,9 +347,72 @@ fn main() -> Result<(), Box<dyn Error>> {
     })
 }
 
+/// Periodically introspects our own root object through the bus, and shuts the interfaces down
+/// once that failed several times in a row. A connection that is up but doesn't process messages
+/// would otherwise keep holding the service name without answering anything.
+async fn check_dbus_liveness(conn: Arc<SyncConnection>, tx: Sender<Message>) {
+    let proxy = Proxy::new(DBUS_SERVICE_NAME, "/", DBUS_LIVENESS_CHECK_TIMEOUT, conn);
+    let mut failures = 0;
+
+    loop {
+        tokio::time::sleep(DBUS_LIVENESS_CHECK_INTERVAL).await;
+
+        let result: Result<(String,), dbus::Error> =
+            proxy.method_call("org.freedesktop.DBus.Introspectable", "Introspect", ()).await;
+        match result {
+            Ok(_) => failures = 0,
+            Err(e) => {
+                failures += 1;
+                log::warn!(
+                    "D-Bus liveness check failed ({}/{}): {}",
+                    failures,
+                    DBUS_LIVENESS_MAX_FAILURES,
+                    e
+                );
+
+                if failures >= DBUS_LIVENESS_MAX_FAILURES {
+                    log::error!("D-Bus connection is unresponsive, shutting down the interfaces");
+                    let _ = tx.send(Message::InterfaceShutdown).await;
+                    return;
+                }
+            }
+        }
+    }
+}
+
 /// Data needed for signal handling.
 static SIG_DATA: Mutex<Option<(Sender<Message>, Arc<SigData>)>> = Mutex::new(None);
 
+/// Waits up to |timeout| for |pending| to be cleared and logs how the shutdown phase ended.
+///
+/// |pending| is true while the phase is still in progress; a phase that isn't in progress is
+/// skipped.
+fn wait_for_shutdown_phase(
+    phase: &str,
+    pending: &Mutex<bool>,
+    notify: &Condvar,
+    timeout: Duration,
+) {
+    let guard = pending.lock().unwrap();
+    if !*guard {
+        log::debug!("SIGTERM: {} not in progress, skipped", phase);
+        return;
+    }
+
+    log::debug!("SIGTERM: waiting for {} for {:?}", phase, timeout);
+    let start = Instant::now();
+    let (guard, result) = notify.wait_timeout(guard, timeout).unwrap();
+    let elapsed = start.elapsed();
+
+    if !*guard {
+        log::debug!("SIGTERM: {} completed after {:?}", phase, elapsed);
+    } else if result.timed_out() {
+        log::error!("SIGTERM: {} timed out after {:?}", phase, elapsed);
+    } else {
+        log::warn!("SIGTERM: woken up after {:?} but {} hasn't completed", elapsed, phase);
+    }
+}
+
 extern "C" fn handle_sigterm(_signum: i32) {
     let guard = SIG_DATA.lock().unwrap();
     if let Some((tx, notifier)) = guard.as_ref() {
@@ -277,11 +423,12 @@ extern "C" fn handle_sigterm(_signum: i32) {
             let _ = txl.send(Message::InterfaceShutdown).await;
         });
 
-        let guard = notifier.enabled.lock().unwrap();
-        if *guard {
-            log::debug!("Waiting for stack to turn off for {:?}", STACK_TURN_OFF_TIMEOUT_MS);
-            let _ = notifier.enabled_notify.wait_timeout(guard, STACK_TURN_OFF_TIMEOUT_MS);
-        }
+        wait_for_shutdown_phase(
+            "stack turn off",
+            &notifier.enabled,
+            &notifier.enabled_notify,
+            STACK_TURN_OFF_TIMEOUT_MS,
+        );
 
         log::debug!("SIGTERM cleaning up the stack.");
         let txl = tx.clone();
@@ -295,11 +442,12 @@ extern "C" fn handle_sigterm(_signum: i32) {
             let _ = txl.send(Message::Cleanup).await;
         });
 
-        let guard = notifier.thread_attached.lock().unwrap();
-        if *guard {
-            log::debug!("Waiting for stack to clean up for {:?}", STACK_CLEANUP_TIMEOUT_MS);
-            let _ = notifier.thread_notify.wait_timeout(guard, STACK_CLEANUP_TIMEOUT_MS);
-        }
+        wait_for_shutdown_phase(
+            "stack cleanup",
+            &notifier.thread_attached,
+            &notifier.thread_notify,
+            STACK_CLEANUP_TIMEOUT_MS,
+        );
 
         // Extra delay to give the rest of the cleanup processes some time to finish after
         // finishing btif cleanup.
@@ -9,7 +9,10 @@ use std::path::Path;
 use syn::parse::Parser;
 use syn::punctuated::Punctuated;
 use syn::token::Comma;
-use syn::{Expr, FnArg, ItemTrait, Meta, Pat, TraitItem};
+use syn::{
+    Attribute, Data, DeriveInput, Expr, FnArg, Ident, ItemTrait, Lit, Meta, NestedMeta, Pat,
+    TraitItem,
+};
 
 use crate::proc_macro::TokenStream;
 
@@ -47,7 +50,7 @@ pub fn btif_callback(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// `bt_topshim::btif::BaseCallbacks` to the functions in the defined trait.
 ///
 /// ```ignore
-/// #[btif_callbacks_dispatcher(dispatch_base_callbacks, BaseCallbacks)]
+/// #[btif_callbacks_dispatcher(dispatch_base_callbacks, BaseCallbacks, [Foo, Bar])]
 /// trait BtifBluetoothCallbacks {
 ///     #[btif_callback(Foo)]
 ///     fn foo(&mut self, param1: u32, param2: bool);
@@ -78,6 +81,11 @@ pub fn btif_callback(_attr: TokenStream, item: TokenStream) -> TokenStream {
 /// dispatch_base_callbacks(&mut struct1, BaseCallbacks::Foo(1, true));
 /// dispatch_base_callbacks(&mut struct2, BaseCallbacks::Foo(2, false));
 /// ```
+///
+/// The third argument lists the variants of the callbacks enum handled by the trait. Every
+/// `#[btif_callback(X)]` is checked against this list and an unknown `X` is reported as a compile
+/// error pointing at the offending name. The list itself is checked against the enum, so it can't
+/// silently drift from the topshim definition.
 #[proc_macro_attribute]
 pub fn btif_callbacks_dispatcher(attr: TokenStream, item: TokenStream) -> TokenStream {
     let args = Punctuated::<Expr, Comma>::parse_separated_nonempty.parse(attr.clone()).unwrap();
@@ -94,7 +102,24 @@ pub fn btif_callbacks_dispatcher(attr: TokenStream, item: TokenStream) -> TokenS
         panic!("callbacks struct ident must be specified");
     };
 
+    let known_variants = match args.iter().nth(2) {
+        Some(expr) => parse_known_variants(expr),
+        None => Err(syn::Error::new_spanned(
+            &args,
+            "expected a list of callback variants as the third argument, e.g. `[Foo, Bar]`",
+        )),
+    };
+    let known_variants = match known_variants {
+        Ok(v) => v,
+        Err(e) => {
+            let ori_item = proc_macro2::TokenStream::from(item);
+            let e = e.to_compile_error();
+            return quote! { #ori_item #e }.into();
+        }
+    };
+
     let mut dispatch_arms = quote! {};
+    let mut errors = quote! {};
 
     let ast: ItemTrait = syn::parse(item.clone()).unwrap();
     let trait_ident = ast.ident;
@@ -111,14 +136,20 @@ pub fn btif_callbacks_dispatcher(attr: TokenStream, item: TokenStream) -> TokenS
                 continue;
             }
 
-            let attr_args = attr.parse_meta().unwrap();
-            let btif_callback = if let Meta::List(meta_list) = attr_args {
-                Some(meta_list.nested[0].clone())
-            } else {
-                None
+            let btif_callback = match parse_btif_callback(attr) {
+                Ok(ident) => ident,
+                Err(e) => {
+                    let e = e.to_compile_error();
+                    errors = quote! { #errors #e };
+                    continue;
+                }
             };
 
-            if btif_callback.is_none() {
+            if let Err(e) =
+                validate_callback_variant(&btif_callback, callbacks_struct_ident, &known_variants)
+            {
+                let e = e.to_compile_error();
+                errors = quote! { #errors #e };
                 continue;
             }
 
@@ -147,10 +178,23 @@ pub fn btif_callbacks_dispatcher(attr: TokenStream, item: TokenStream) -> TokenS
         }
     }
 
+    // Make sure every listed variant really exists in the callbacks enum.
+    let variants_check = if known_variants.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            const _: fn(&#callbacks_struct_ident) -> bool = |cb| {
+                matches!(cb, #(#callbacks_struct_ident::#known_variants { .. })|*)
+            };
+        }
+    };
+
     let ori_item = proc_macro2::TokenStream::from(item.clone());
 
     let gen = quote! {
         #ori_item
+        #errors
+        #variants_check
         pub(crate) fn #fn_ident<T: #trait_ident>(obj: &mut T, cb: #callbacks_struct_ident) {
             match cb {
                 #dispatch_arms
@@ -165,3 +209,286 @@ pub fn btif_callbacks_dispatcher(attr: TokenStream, item: TokenStream) -> TokenS
 
     gen.into()
 }
+
+/// Parses the list of known callback variants, e.g. `[Foo, Bar]`.
+fn parse_known_variants(expr: &Expr) -> syn::Result<Vec<Ident>> {
+    let array = match expr {
+        Expr::Array(array) => array,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                expr,
+                "expected a list of callback variants, e.g. `[Foo, Bar]`",
+            ))
+        }
+    };
+
+    array
+        .elems
+        .iter()
+        .map(|elem| match elem {
+            Expr::Path(p) if p.path.get_ident().is_some() => {
+                Ok(p.path.get_ident().unwrap().clone())
+            }
+            _ => Err(syn::Error::new_spanned(elem, "expected a callback variant name")),
+        })
+        .collect()
+}
+
+/// Extracts the variant name `X` from `#[btif_callback(X)]`.
+fn parse_btif_callback(attr: &Attribute) -> syn::Result<Ident> {
+    let meta_list = match attr.parse_meta()? {
+        Meta::List(meta_list) => meta_list,
+        meta => {
+            return Err(syn::Error::new_spanned(
+                meta,
+                "expected a callback variant, e.g. `#[btif_callback(Foo)]`",
+            ))
+        }
+    };
+
+    if meta_list.nested.len() != 1 {
+        return Err(syn::Error::new_spanned(
+            &meta_list.nested,
+            "expected exactly one callback variant",
+        ));
+    }
+
+    match &meta_list.nested[0] {
+        NestedMeta::Meta(Meta::Path(p)) if p.get_ident().is_some() => {
+            Ok(p.get_ident().unwrap().clone())
+        }
+        nested => Err(syn::Error::new_spanned(nested, "expected a callback variant name")),
+    }
+}
+
+/// Checks that `variant` is one of the variants listed for `callbacks_enum`.
+fn validate_callback_variant(
+    variant: &Ident,
+    callbacks_enum: &Ident,
+    known_variants: &[Ident],
+) -> syn::Result<()> {
+    if known_variants.contains(variant) {
+        return Ok(());
+    }
+
+    Err(syn::Error::new_spanned(
+        variant,
+        format!("`{}` is not a variant of `{}`", variant, callbacks_enum),
+    ))
+}
+
+/// Derives `RPCProxy` for a callback struct that doesn't need any RPC specific behavior.
+///
+/// `get_object_id` returns either the struct field marked with `#[rpc_proxy(object_id)]` or the
+/// fixed string given with `#[rpc_proxy(object_id = "...")]` on the struct. The other methods use
+/// the defaults of `RPCProxy`, which must be in scope.
+///
+/// Example usage:
+/// ```ignore
+/// #[derive(RPCProxyStub)]
+/// #[rpc_proxy(object_id = "BLE Discovery Callback")]
+/// struct BleDiscoveryCallbacks {
+///     tx: Sender<Message>,
+/// }
+///
+/// #[derive(RPCProxyStub)]
+/// struct ClientCallback {
+///     #[rpc_proxy(object_id)]
+///     objpath: String,
+/// }
+/// ```
+#[proc_macro_derive(RPCProxyStub, attributes(rpc_proxy))]
+pub fn derive_rpc_proxy_stub(item: TokenStream) -> TokenStream {
+    let ast: DeriveInput = syn::parse(item).unwrap();
+
+    let object_id = match parse_rpc_proxy_object_id(&ast) {
+        Ok(object_id) => object_id,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let struct_ident = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let gen = quote! {
+        impl #impl_generics RPCProxy for #struct_ident #ty_generics #where_clause {
+            fn get_object_id(&self) -> String {
+                #object_id
+            }
+        }
+    };
+
+    debug_output_to_file(&gen, format!("out-rpc-proxy-{}.rs", struct_ident));
+
+    gen.into()
+}
+
+/// Builds the expression returned by the derived `get_object_id`.
+fn parse_rpc_proxy_object_id(ast: &DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut object_id = None;
+
+    for attr in ast.attrs.iter().filter(|attr| attr.path.is_ident("rpc_proxy")) {
+        match parse_rpc_proxy_attr(attr)? {
+            Some(Lit::Str(s)) if object_id.is_none() => {
+                object_id = Some(quote! { String::from(#s) });
+            }
+            Some(Lit::Str(_)) => {
+                return Err(syn::Error::new_spanned(attr, "object_id is specified more than once"))
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected `#[rpc_proxy(object_id = \"...\")]` on the struct",
+                ))
+            }
+        }
+    }
+
+    let fields = match &ast.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "RPCProxyStub can only be derived for structs",
+            ))
+        }
+    };
+
+    for field in fields.iter() {
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("rpc_proxy")) {
+            if parse_rpc_proxy_attr(attr)?.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "expected `#[rpc_proxy(object_id)]` on the field",
+                ));
+            }
+
+            let field_ident = match &field.ident {
+                Some(ident) => ident,
+                None => {
+                    return Err(syn::Error::new_spanned(field, "object_id must be a named field"))
+                }
+            };
+
+            if object_id.is_some() {
+                return Err(syn::Error::new_spanned(attr, "object_id is specified more than once"));
+            }
+            object_id = Some(quote! { self.#field_ident.to_string() });
+        }
+    }
+
+    object_id.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &ast.ident,
+            "missing `#[rpc_proxy(object_id)]` field or `#[rpc_proxy(object_id = \"...\")]`",
+        )
+    })
+}
+
+/// Parses `#[rpc_proxy(object_id)]` into `None` and `#[rpc_proxy(object_id = lit)]` into `lit`.
+fn parse_rpc_proxy_attr(attr: &Attribute) -> syn::Result<Option<Lit>> {
+    let meta_list = match attr.parse_meta()? {
+        Meta::List(meta_list) if meta_list.nested.len() == 1 => meta_list,
+        meta => return Err(syn::Error::new_spanned(meta, "expected `rpc_proxy(object_id)`")),
+    };
+
+    match &meta_list.nested[0] {
+        NestedMeta::Meta(Meta::Path(p)) if p.is_ident("object_id") => Ok(None),
+        NestedMeta::Meta(Meta::NameValue(nv)) if nv.path.is_ident("object_id") => {
+            Ok(Some(nv.lit.clone()))
+        }
+        nested => Err(syn::Error::new_spanned(nested, "expected `object_id`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    #[test]
+    fn test_parse_known_variants() {
+        let expr: Expr = parse_quote!([Foo, Bar]);
+        let variants = parse_known_variants(&expr).unwrap();
+        assert_eq!(variants, vec![parse_quote!(Foo), parse_quote!(Bar)] as Vec<Ident>);
+
+        let expr: Expr = parse_quote!(Foo);
+        assert!(parse_known_variants(&expr).is_err());
+
+        let expr: Expr = parse_quote!([Foo, 1]);
+        assert!(parse_known_variants(&expr).is_err());
+    }
+
+    #[test]
+    fn test_parse_btif_callback() {
+        let attr: Attribute = parse_quote!(#[btif_callback(Foo)]);
+        assert_eq!(parse_btif_callback(&attr).unwrap(), "Foo");
+
+        let attr: Attribute = parse_quote!(#[btif_callback]);
+        assert!(parse_btif_callback(&attr).is_err());
+
+        let attr: Attribute = parse_quote!(#[btif_callback(Foo, Bar)]);
+        assert!(parse_btif_callback(&attr).is_err());
+
+        let attr: Attribute = parse_quote!(#[btif_callback("Foo")]);
+        assert!(parse_btif_callback(&attr).is_err());
+    }
+
+    #[test]
+    fn test_validate_callback_variant() {
+        let callbacks_enum: Ident = parse_quote!(BaseCallbacks);
+        let known_variants: Vec<Ident> = vec![parse_quote!(Foo), parse_quote!(Bar)];
+
+        let variant: Ident = parse_quote!(Foo);
+        assert!(validate_callback_variant(&variant, &callbacks_enum, &known_variants).is_ok());
+
+        let variant: Ident = parse_quote!(Fooo);
+        let err =
+            validate_callback_variant(&variant, &callbacks_enum, &known_variants).unwrap_err();
+        assert_eq!(err.to_string(), "`Fooo` is not a variant of `BaseCallbacks`");
+    }
+
+    #[test]
+    fn test_parse_rpc_proxy_object_id() {
+        let ast: DeriveInput = parse_quote! {
+            struct Foo {
+                #[rpc_proxy(object_id)]
+                objpath: String,
+            }
+        };
+        assert_eq!(
+            parse_rpc_proxy_object_id(&ast).unwrap().to_string(),
+            quote! { self.objpath.to_string() }.to_string()
+        );
+
+        let ast: DeriveInput = parse_quote! {
+            #[rpc_proxy(object_id = "Foo Callback")]
+            struct Foo {}
+        };
+        assert_eq!(
+            parse_rpc_proxy_object_id(&ast).unwrap().to_string(),
+            quote! { String::from("Foo Callback") }.to_string()
+        );
+
+        let ast: DeriveInput = parse_quote! {
+            struct Foo {
+                objpath: String,
+            }
+        };
+        assert!(parse_rpc_proxy_object_id(&ast).is_err());
+
+        let ast: DeriveInput = parse_quote! {
+            #[rpc_proxy(object_id = "Foo Callback")]
+            struct Foo {
+                #[rpc_proxy(object_id)]
+                objpath: String,
+            }
+        };
+        assert!(parse_rpc_proxy_object_id(&ast).is_err());
+
+        let ast: DeriveInput = parse_quote! {
+            #[rpc_proxy(object_id = 1)]
+            struct Foo {}
+        };
+        assert!(parse_rpc_proxy_object_id(&ast).is_err());
+    }
+}
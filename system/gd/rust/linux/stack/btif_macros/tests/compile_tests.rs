@@ -0,0 +1,8 @@
+#[test]
+fn compile_tests() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/dispatcher.rs");
+    t.compile_fail("tests/ui/dispatcher_unknown_variant.rs");
+    t.compile_fail("tests/ui/dispatcher_missing_variants.rs");
+    t.compile_fail("tests/ui/dispatcher_invalid_callback.rs");
+}
@@ -0,0 +1,38 @@
+use btif_macros::{btif_callback, btif_callbacks_dispatcher};
+
+#[derive(Debug)]
+pub enum BaseCallbacks {
+    Foo(u32, bool),
+    Bar(String),
+}
+
+#[btif_callbacks_dispatcher(dispatch_base_callbacks, BaseCallbacks, [Foo, Bar])]
+trait BtifBluetoothCallbacks {
+    #[btif_callback(Foo)]
+    fn foo(&mut self, param1: u32, param2: bool);
+
+    #[btif_callback(Bar)]
+    fn bar(&mut self, name: String);
+}
+
+#[derive(Default)]
+struct Callbacks {
+    calls: Vec<String>,
+}
+
+impl BtifBluetoothCallbacks for Callbacks {
+    fn foo(&mut self, param1: u32, param2: bool) {
+        self.calls.push(format!("foo({}, {})", param1, param2));
+    }
+
+    fn bar(&mut self, name: String) {
+        self.calls.push(format!("bar({})", name));
+    }
+}
+
+fn main() {
+    let mut callbacks = Callbacks::default();
+    dispatch_base_callbacks(&mut callbacks, BaseCallbacks::Foo(1, true));
+    dispatch_base_callbacks(&mut callbacks, BaseCallbacks::Bar(String::from("baz")));
+    assert_eq!(callbacks.calls, vec!["foo(1, true)", "bar(baz)"]);
+}
@@ -0,0 +1,18 @@
+use btif_macros::{btif_callback, btif_callbacks_dispatcher};
+
+#[derive(Debug)]
+pub enum BaseCallbacks {
+    Foo(u32, bool),
+    Bar(String),
+}
+
+#[btif_callbacks_dispatcher(dispatch_base_callbacks, BaseCallbacks, [Foo, Bar])]
+trait BtifBluetoothCallbacks {
+    #[btif_callback(Foo, Bar)]
+    fn foo(&mut self, param1: u32, param2: bool);
+
+    #[btif_callback("Bar")]
+    fn bar(&mut self, name: String);
+}
+
+fn main() {}
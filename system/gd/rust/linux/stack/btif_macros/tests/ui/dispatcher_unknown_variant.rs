@@ -0,0 +1,16 @@
+use btif_macros::{btif_callback, btif_callbacks_dispatcher};
+
+#[derive(Debug)]
+pub enum BaseCallbacks {
+    Foo(u32, bool),
+    Bar(String),
+}
+
+// The variant of each callback must be one of the listed variants of the callbacks enum.
+#[btif_callbacks_dispatcher(dispatch_base_callbacks, BaseCallbacks, [Foo, Bar])]
+trait BtifBluetoothCallbacks {
+    #[btif_callback(Fooo)]
+    fn foo(&mut self, param1: u32, param2: bool);
+}
+
+fn main() {}
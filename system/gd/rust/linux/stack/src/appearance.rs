@@ -0,0 +1,233 @@
+//! Decoding of the GAP Appearance characteristic value.
+//!
+//! The appearance is a 16-bit value where the upper 10 bits are the category and the lower 6 bits
+//! are the subcategory, as listed in the Bluetooth Assigned Numbers.
+
+use std::fmt::{Display, Formatter, Result};
+
+const SUBCATEGORY_BITS: u16 = 6;
+const SUBCATEGORY_MASK: u16 = (1 << SUBCATEGORY_BITS) - 1;
+
+/// Categories with their generic name and the names of their known subcategories.
+const CATEGORIES: &[(u16, &str, &[(u16, &str)])] = &[
+    (0x001, "Phone", &[]),
+    (
+        0x002,
+        "Computer",
+        &[
+            (0x01, "Desktop Workstation"),
+            (0x02, "Server-class Computer"),
+            (0x03, "Laptop"),
+            (0x04, "Handheld PC/PDA"),
+            (0x05, "Palm-size PC/PDA"),
+            (0x06, "Wearable Computer"),
+            (0x07, "Tablet"),
+            (0x08, "Docking Station"),
+            (0x09, "All in One"),
+            (0x0A, "Blade Server"),
+            (0x0B, "Convertible"),
+            (0x0C, "Detachable"),
+            (0x0D, "IoT Gateway"),
+            (0x0E, "Mini PC"),
+            (0x0F, "Stick PC"),
+        ],
+    ),
+    (0x003, "Watch", &[(0x01, "Sports Watch"), (0x02, "Smartwatch")]),
+    (0x004, "Clock", &[]),
+    (0x005, "Display", &[]),
+    (0x006, "Remote Control", &[]),
+    (0x007, "Eye-glasses", &[]),
+    (0x008, "Tag", &[]),
+    (0x009, "Keyring", &[]),
+    (0x00A, "Media Player", &[]),
+    (0x00B, "Barcode Scanner", &[]),
+    (0x00C, "Thermometer", &[(0x01, "Ear Thermometer")]),
+    (0x00D, "Heart Rate Sensor", &[(0x01, "Heart Rate Belt")]),
+    (0x00E, "Blood Pressure", &[(0x01, "Arm Blood Pressure"), (0x02, "Wrist Blood Pressure")]),
+    (
+        0x00F,
+        "Human Interface Device",
+        &[
+            (0x01, "Keyboard"),
+            (0x02, "Mouse"),
+            (0x03, "Joystick"),
+            (0x04, "Gamepad"),
+            (0x05, "Digitizer Tablet"),
+            (0x06, "Card Reader"),
+            (0x07, "Digital Pen"),
+            (0x08, "Barcode Scanner"),
+            (0x09, "Touchpad"),
+            (0x0A, "Presentation Remote"),
+        ],
+    ),
+    (0x010, "Glucose Meter", &[]),
+    (
+        0x011,
+        "Running Walking Sensor",
+        &[
+            (0x01, "In-Shoe Running Walking Sensor"),
+            (0x02, "On-Shoe Running Walking Sensor"),
+            (0x03, "On-Hip Running Walking Sensor"),
+        ],
+    ),
+    (
+        0x012,
+        "Cycling",
+        &[
+            (0x01, "Cycling Computer"),
+            (0x02, "Speed Sensor"),
+            (0x03, "Cadence Sensor"),
+            (0x04, "Power Sensor"),
+            (0x05, "Speed and Cadence Sensor"),
+        ],
+    ),
+    (0x013, "Control Device", &[]),
+    (0x014, "Network Device", &[]),
+    (0x015, "Sensor", &[]),
+    (0x016, "Light Fixtures", &[]),
+    (0x017, "Fan", &[]),
+    (0x018, "HVAC", &[]),
+    (0x019, "Air Conditioning", &[]),
+    (0x01A, "Humidifier", &[]),
+    (0x01B, "Heating", &[]),
+    (0x01C, "Access Control", &[]),
+    (0x01D, "Motorized Device", &[]),
+    (0x01E, "Power Device", &[]),
+    (0x01F, "Light Source", &[]),
+    (0x020, "Window Covering", &[]),
+    (
+        0x021,
+        "Audio Sink",
+        &[
+            (0x01, "Standalone Speaker"),
+            (0x02, "Soundbar"),
+            (0x03, "Bookshelf Speaker"),
+            (0x04, "Standmounted Speaker"),
+            (0x05, "Speakerphone"),
+        ],
+    ),
+    (
+        0x022,
+        "Audio Source",
+        &[
+            (0x01, "Microphone"),
+            (0x02, "Alarm"),
+            (0x03, "Bell"),
+            (0x04, "Horn"),
+            (0x05, "Broadcasting Device"),
+            (0x06, "Service Desk"),
+            (0x07, "Kiosk"),
+            (0x08, "Broadcasting Room"),
+            (0x09, "Auditorium"),
+        ],
+    ),
+    (0x023, "Motorized Vehicle", &[]),
+    (0x024, "Domestic Appliance", &[]),
+    (
+        0x025,
+        "Wearable Audio Device",
+        &[(0x01, "Earbud"), (0x02, "Headset"), (0x03, "Headphones"), (0x04, "Neck Band")],
+    ),
+    (0x026, "Aircraft", &[]),
+    (0x027, "AV Equipment", &[]),
+    (0x028, "Display Equipment", &[]),
+    (
+        0x029,
+        "Hearing Aid",
+        &[
+            (0x01, "In-ear Hearing Aid"),
+            (0x02, "Behind-ear Hearing Aid"),
+            (0x03, "Cochlear Implant"),
+        ],
+    ),
+    (0x02A, "Gaming", &[(0x01, "Home Video Game Console"), (0x02, "Portable Handheld Console")]),
+    (0x02B, "Signage", &[]),
+    (
+        0x031,
+        "Pulse Oximeter",
+        &[(0x01, "Fingertip Pulse Oximeter"), (0x02, "Wrist Worn Pulse Oximeter")],
+    ),
+    (0x032, "Weight Scale", &[]),
+    (0x033, "Personal Mobility Device", &[]),
+    (0x034, "Continuous Glucose Monitor", &[]),
+    (0x035, "Insulin Pump", &[]),
+    (0x036, "Medication Delivery", &[]),
+    (0x037, "Spirometer", &[]),
+    (
+        0x051,
+        "Outdoor Sports Activity",
+        &[
+            (0x01, "Location Display"),
+            (0x02, "Location and Navigation Display"),
+            (0x03, "Location Pod"),
+            (0x04, "Location and Navigation Pod"),
+        ],
+    ),
+];
+
+/// Returns the name of |appearance|. A subcategory that isn't known is named after its category,
+/// and None is returned if the category isn't known either.
+pub fn appearance_name(appearance: u16) -> Option<&'static str> {
+    let category = appearance >> SUBCATEGORY_BITS;
+    let subcategory = appearance & SUBCATEGORY_MASK;
+
+    let (_, name, subcategories) = CATEGORIES.iter().find(|(c, _, _)| *c == category)?;
+    Some(
+        subcategories
+            .iter()
+            .find(|(s, _)| *s == subcategory)
+            .map_or(*name, |(_, subcategory_name)| *subcategory_name),
+    )
+}
+
+/// Wrapper for printing an appearance value with its name, e.g. "Mouse (0x03C2)".
+pub struct DisplayAppearance(pub u16);
+
+impl Display for DisplayAppearance {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match appearance_name(self.0) {
+            Some(name) => write!(f, "{} (0x{:04X})", name, self.0),
+            None => write!(f, "0x{:04X}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_appearance_name() {
+        assert_eq!(appearance_name(0x03C1), Some("Keyboard"));
+        assert_eq!(appearance_name(0x03C2), Some("Mouse"));
+        assert_eq!(appearance_name(0x0942), Some("Headset"));
+        assert_eq!(appearance_name(0x0341), Some("Heart Rate Belt"));
+        assert_eq!(appearance_name(0x0340), Some("Heart Rate Sensor"));
+    }
+
+    #[test]
+    fn test_appearance_category_boundaries() {
+        // The generic Unknown category.
+        assert_eq!(appearance_name(0x0000), None);
+        assert_eq!(appearance_name(0x003F), None);
+        // First and last subcategory of the first known category.
+        assert_eq!(appearance_name(0x0040), Some("Phone"));
+        assert_eq!(appearance_name(0x007F), Some("Phone"));
+        // Unknown subcategories fall back to the category.
+        assert_eq!(appearance_name(0x03CB), Some("Human Interface Device"));
+        assert_eq!(appearance_name(0x03FF), Some("Human Interface Device"));
+        // The next category starts right after.
+        assert_eq!(appearance_name(0x0400), Some("Glucose Meter"));
+        // Gaps between and after the assigned categories.
+        assert_eq!(appearance_name(0x0B00), None);
+        assert_eq!(appearance_name(0xFFFF), None);
+        // The last assigned category.
+        assert_eq!(appearance_name(0x1441), Some("Location Display"));
+    }
+
+    #[test]
+    fn test_display_appearance() {
+        assert_eq!(DisplayAppearance(0x03C2).to_string(), "Mouse (0x03C2)");
+        assert_eq!(DisplayAppearance(0xFFFF).to_string(), "0xFFFF");
+    }
+}
@@ -11,6 +11,7 @@ use crate::RPCProxy;
 use crate::{uuid, APIMessage, BluetoothAPI};
 use bt_topshim::btif::{BtTransport, DisplayAddress, RawAddress, Uuid};
 use bt_topshim::profiles::gatt::{GattStatus, LePhy};
+use btif_macros::RPCProxyStub;
 use log::{debug, info};
 use std::collections::HashMap;
 use std::convert::TryInto;
@@ -373,6 +374,8 @@ impl IBatteryService for BatteryService {
     }
 }
 
+#[derive(RPCProxyStub)]
+#[rpc_proxy(object_id = "BAS BatteryProvider Callback")]
 struct BatteryProviderCallback {
     tx: Sender<Message>,
 }
@@ -392,12 +395,8 @@ impl IBatteryProviderCallback for BatteryProviderCallback {
     }
 }
 
-impl RPCProxy for BatteryProviderCallback {
-    fn get_object_id(&self) -> String {
-        "BAS BatteryProvider Callback".to_string()
-    }
-}
-
+#[derive(RPCProxyStub)]
+#[rpc_proxy(object_id = "BAS Gatt Callback")]
 struct GattCallback {
     tx: Sender<Message>,
     api_tx: Sender<APIMessage>,
@@ -504,6 +503,15 @@ impl IBluetoothGattCallback for GattCallback {
     ) {
     }
 
+    fn on_remote_phy_capabilities(
+        &mut self,
+        _addr: RawAddress,
+        _le_2m: bool,
+        _le_coded: bool,
+        _status: GattStatus,
+    ) {
+    }
+
     fn on_characteristic_write(&mut self, _addr: RawAddress, _status: GattStatus, _handle: i32) {}
 
     fn on_execute_write(&mut self, _addr: RawAddress, _status: GattStatus) {}
@@ -535,9 +543,3 @@ impl IBluetoothGattCallback for GattCallback {
 
     fn on_service_changed(&mut self, _addr: RawAddress) {}
 }
-
-impl RPCProxy for GattCallback {
-    fn get_object_id(&self) -> String {
-        "BAS Gatt Callback".to_string()
-    }
-}
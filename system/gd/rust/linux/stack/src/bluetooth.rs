@@ -3,9 +3,9 @@
 use bt_topshim::btif::{
     BaseCallbacks, BaseCallbacksDispatcher, BluetoothInterface, BluetoothProperty, BtAclState,
     BtAddrType, BtBondState, BtConnectionDirection, BtConnectionState, BtDeviceType, BtDiscMode,
-    BtDiscoveryState, BtHciErrorCode, BtPinCode, BtPropertyType, BtScanMode, BtSspVariant, BtState,
-    BtStatus, BtThreadEvent, BtTransport, BtVendorProductInfo, DisplayAddress, DisplayUuid,
-    RawAddress, ToggleableProfile, Uuid, INVALID_RSSI,
+    BtDiscoveryState, BtHciErrorCode, BtIoCap, BtPinCode, BtPropertyType, BtScanMode, BtSspVariant,
+    BtState, BtStatus, BtThreadEvent, BtTransport, BtVendorProductInfo, DisplayAddress,
+    DisplayUuid, RawAddress, ToggleableProfile, Uuid, INVALID_RSSI,
 };
 use bt_topshim::{
     controller, metrics,
@@ -17,24 +17,29 @@ use bt_topshim::{
     },
     profiles::sdp::{BtSdpRecord, Sdp, SdpCallbacks, SdpCallbacksDispatcher},
     profiles::ProfileConnectionState,
+    syslog::Level,
     topstack,
 };
 
+use bt_utils::adv_parser;
 use bt_utils::array_utils;
 use bt_utils::cod::{is_cod_hid_combo, is_cod_hid_keyboard};
 use bt_utils::uhid::UHid;
-use btif_macros::{btif_callback, btif_callbacks_dispatcher};
+use btif_macros::{btif_callback, btif_callbacks_dispatcher, RPCProxyStub};
 
-use log::{debug, error, warn};
+use log::{debug, error, info, warn};
+use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
 use num_derive::{FromPrimitive, ToPrimitive};
-use num_traits::cast::ToPrimitive;
+use num_traits::cast::{FromPrimitive, ToPrimitive};
 use num_traits::pow;
+use serde_json::{json, Value};
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
-use std::fs::{File, OpenOptions};
+use std::ffi::CString;
+use std::fs::File;
 use std::hash::Hash;
-use std::io::Write;
-use std::os::fd::AsRawFd;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
 use std::process;
 use std::sync::{Arc, Condvar, Mutex};
 use std::time::Duration;
@@ -47,8 +52,9 @@ use crate::bluetooth_admin::BluetoothAdminPolicyHelper;
 use crate::bluetooth_gatt::{
     BluetoothGatt, GattActions, IBluetoothGatt, IScannerCallback, ScanResult,
 };
+use crate::bluetooth_logging::{BluetoothLogging, IBluetoothLogging};
 use crate::bluetooth_media::{BluetoothMedia, MediaActions, LEA_UNKNOWN_GROUP_ID};
-use crate::callbacks::Callbacks;
+use crate::callbacks::{CallbackCount, Callbacks};
 use crate::socket_manager::SocketActions;
 use crate::uuid::{Profile, UuidHelper};
 use crate::{make_message_dispatcher, APIMessage, BluetoothAPI, Message, RPCProxy, SuspendMode};
@@ -62,22 +68,441 @@ const MIN_ADV_INSTANCES_FOR_MULTI_ADV: u8 = 5;
 /// clear event should be sent to clients.
 const FOUND_DEVICE_FRESHNESS: Duration = Duration::from_secs(30);
 
+/// How long a graceful disable waits for connected devices to disconnect before the adapter is
+/// disabled anyway. This must stay well within the time the daemon is given to turn off.
+const GRACEFUL_DISABLE_TIMEOUT: Duration = Duration::from_millis(1500);
+
+/// Default time the scan and discovery suspend modes may stay in Suspending or Resuming before
+/// they are forcibly reset to Normal. Until then discovery and scan mode changes are ignored.
+const DEFAULT_SUSPEND_MODE_WATCHDOG_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long property changes of a remote device are accumulated before they are reported at once.
+const PROPERTY_CHANGE_COALESCING_WINDOW: Duration = Duration::from_millis(50);
+
+/// How long a UUID fetch may take before it is reported as failed to the callbacks.
+const REMOTE_UUIDS_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Default weight of a new RSSI sample in the smoothed RSSI of a remote device.
+const DEFAULT_RSSI_SMOOTHING_FACTOR: f64 = 0.25;
+
+/// Default minimum interval between two |on_device_found| of the same device, unless its name or
+/// UUIDs changed.
+const DEFAULT_DEVICE_FOUND_MIN_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns true if either suspend mode shows that suspend is underway or complete.
+fn is_suspend_active(scan_mode: &SuspendMode, discovery_mode: &SuspendMode) -> bool {
+    [scan_mode, discovery_mode]
+        .iter()
+        .any(|mode| matches!(mode, SuspendMode::Suspending | SuspendMode::Suspended))
+}
+
+/// Returns true if a discovery stopping after running for |elapsed| ran into its timeout. Stops
+/// that were requested, that come while the adapter is turning off, or that come earlier, e.g.
+/// when the stack stops discovery for bonding or an ACL setup, aren't timeouts.
+fn is_discovery_timeout(cancel_requested: bool, adapter_on: bool, elapsed: Duration) -> bool {
+    !cancel_requested
+        && adapter_on
+        && elapsed >= Duration::from_millis(DEFAULT_DISCOVERY_TIMEOUT_MS)
+}
+
+/// Reads the IO capability stored at |path|.
+fn read_io_capability(path: &str) -> std::io::Result<BtIoCap> {
+    let json = serde_json::from_str::<Value>(&std::fs::read_to_string(path)?)?;
+    json.get("io_capability")
+        .and_then(Value::as_u64)
+        .and_then(BtIoCap::from_u64)
+        .filter(|io_cap| *io_cap < BtIoCap::Max)
+        .ok_or(Error::new(ErrorKind::Other, "Invalid IO capability"))
+}
+
+fn write_io_capability(path: &str, io_cap: &BtIoCap) -> std::io::Result<()> {
+    std::fs::write(path, json!({ "io_capability": io_cap.to_u32() }).to_string())
+}
+
+/// Removes the remote devices that aren't fresh anymore and returns them. A bond cancel still
+/// pending for a removed device is dropped as well, since its bond state will never be reported
+/// and would otherwise suppress the metrics of a later bond with the same address.
+fn remove_stale_devices(
+    remote_devices: &mut HashMap<RawAddress, BluetoothDeviceContext>,
+    cancelling_devices: &mut HashSet<RawAddress>,
+    now: Instant,
+) -> Vec<BluetoothDevice> {
+    // A remote device is considered fresh if:
+    // * It was last seen less than |FOUND_DEVICE_FRESHNESS| ago.
+    // * It is bonded / bonding (i.e., not NotBonded)
+    // * It is currently connected.
+    let is_fresh = |d: &BluetoothDeviceContext| {
+        let fresh_at = d.last_seen + FOUND_DEVICE_FRESHNESS;
+        now < fresh_at || d.is_connected() || d.bond_state != BtBondState::NotBonded
+    };
+
+    let stale_devices: Vec<BluetoothDevice> =
+        remote_devices.values().filter(|d| !is_fresh(*d)).map(|d| d.info.clone()).collect();
+
+    // Retain only devices that are fresh.
+    remote_devices.retain(|_, d| is_fresh(d));
+
+    for d in &stale_devices {
+        if cancelling_devices.remove(&d.address) {
+            debug!("Dropped pending bond cancel of stale device {}", DisplayAddress(&d.address));
+        }
+    }
+
+    stale_devices
+}
+
 /// This is the value returned from Bluetooth Interface calls.
 // TODO(241930383): Add enum to topshim
 const BTM_SUCCESS: i32 = 0;
 
 const PID_DIR: &str = "/var/run/bluetooth";
 
-const DUMPSYS_LOG: &str = "/tmp/dumpsys.log";
-
 /// Represents various roles the adapter supports.
-#[derive(Debug, FromPrimitive, ToPrimitive)]
+#[derive(Debug, PartialEq, FromPrimitive, ToPrimitive)]
 #[repr(u32)]
 pub enum BtAdapterRole {
     Central = 0,
     Peripheral,
     CentralPeripheral,
 }
+
+impl BtAdapterRole {
+    // See Core 5.3, Vol 4, Part E, 7.8.27 for detailed state information
+    const CENTRAL_STATE_BIT: u64 = 35;
+    const PERIPHERAL_STATE_BIT: u64 = 38;
+    const CENTRAL_PERIPHERAL_STATE_BIT: u64 = 28;
+
+    /// Decodes the roles supported according to the LE supported states of the controller.
+    pub fn from_le_supported_states(states: u64) -> Vec<Self> {
+        [
+            (Self::CENTRAL_STATE_BIT, BtAdapterRole::Central),
+            (Self::PERIPHERAL_STATE_BIT, BtAdapterRole::Peripheral),
+            (Self::CENTRAL_PERIPHERAL_STATE_BIT, BtAdapterRole::CentralPeripheral),
+        ]
+        .into_iter()
+        .filter(|(bit, _)| states >> bit & 1 == 1u64)
+        .map(|(_, role)| role)
+        .collect()
+    }
+}
+
+/// Mechanisms through which a remote device can be found. A device may be found through several
+/// of them, which are recorded as a bitmask of these values.
+#[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum DiscoverySource {
+    /// Found by the stack's device discovery, i.e. a BR/EDR inquiry result.
+    Inquiry = 1 << 0,
+    /// Found by the BLE discovery scanner.
+    LeScan = 1 << 1,
+}
+
+impl DiscoverySource {
+    /// Decodes the sources recorded in |bits|.
+    pub fn from_bits(bits: u32) -> Vec<Self> {
+        [DiscoverySource::Inquiry, DiscoverySource::LeScan]
+            .into_iter()
+            .filter(|source| bits & (*source as u32) != 0)
+            .collect()
+    }
+}
+
+/// How the adapter answers SSP requests without an agent.
+#[derive(Clone, Copy, Debug, PartialEq, FromPrimitive, ToPrimitive)]
+#[repr(u32)]
+pub enum PairingPolicy {
+    /// Accepts the Just-Works pairing initiated by us and lets the agent handle the rest.
+    RequireAgent = 0,
+    /// Accepts all the Just-Works pairing and lets the agent handle the rest.
+    AutoAcceptJustWorks,
+    /// Rejects all the SSP requests.
+    RejectAll,
+}
+
+impl PairingPolicy {
+    /// Returns the reply to a SSP request of |variant| that doesn't need an agent, or None if the
+    /// request should be forwarded to the agent.
+    fn auto_ssp_reply(&self, variant: &BtSspVariant, initiated_by_us: bool) -> Option<bool> {
+        match (self, variant) {
+            (PairingPolicy::RejectAll, _) => Some(false),
+            (PairingPolicy::AutoAcceptJustWorks, BtSspVariant::Consent) => Some(true),
+            (PairingPolicy::RequireAgent, BtSspVariant::Consent) => Some(initiated_by_us),
+            _ => None,
+        }
+    }
+}
+
+/// LE Audio related capabilities of the controller.
+///
+/// See Core 5.3, Vol 6, Part B, 4.6 FEATURE SUPPORT.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LeAudioCaps {
+    pub cis_central: bool,
+    pub cis_peripheral: bool,
+    pub iso_broadcaster: bool,
+    pub iso_sync_receiver: bool,
+}
+
+impl LeAudioCaps {
+    const CIS_CENTRAL_BIT: u64 = 28;
+    const CIS_PERIPHERAL_BIT: u64 = 29;
+    const ISO_BROADCASTER_BIT: u64 = 30;
+    const ISO_SYNC_RECEIVER_BIT: u64 = 31;
+
+    /// Builds the capabilities from the LE local supported features.
+    pub fn from_le_features(features: u64) -> Self {
+        let has = |bit: u64| features >> bit & 1 == 1u64;
+        LeAudioCaps {
+            cis_central: has(Self::CIS_CENTRAL_BIT),
+            cis_peripheral: has(Self::CIS_PERIPHERAL_BIT),
+            iso_broadcaster: has(Self::ISO_BROADCASTER_BIT),
+            iso_sync_receiver: has(Self::ISO_SYNC_RECEIVER_BIT),
+        }
+    }
+
+    /// Returns whether the controller supports LE Audio, which is determined by CIS Central
+    /// support. A broadcast-only controller isn't reported as supporting LE Audio.
+    pub fn is_le_audio_supported(&self) -> bool {
+        self.cis_central
+    }
+}
+
+/// Decides which scan mode writes reach the controller. Once the adapter starts disabling, only a
+/// single write turning the scan off is let through so that the controller doesn't flap between
+/// connectable and discoverable during teardown. See b/361510982.
+#[derive(Debug, Default)]
+struct ScanModeGuard {
+    disabling: bool,
+    turned_off: bool,
+}
+
+impl ScanModeGuard {
+    fn set_disabling(&mut self, disabling: bool) {
+        self.disabling = disabling;
+        self.turned_off = false;
+    }
+
+    fn is_disabling(&self) -> bool {
+        self.disabling
+    }
+
+    /// Returns whether |mode| should be written to the controller.
+    fn allow(&mut self, mode: &BtScanMode) -> bool {
+        if !self.disabling {
+            return true;
+        }
+        if *mode != BtScanMode::None_ || self.turned_off {
+            return false;
+        }
+        self.turned_off = true;
+        true
+    }
+}
+
+/// Accumulates the property types changed per remote device until they are reported. Bursts of
+/// property changes, e.g. on SDP completion, are then reported with a single callback.
+#[derive(Debug, Default)]
+struct PropertyChangeCoalescer {
+    pending: HashMap<RawAddress, Vec<BtPropertyType>>,
+}
+
+impl PropertyChangeCoalescer {
+    /// Adds the changed |types| of |addr|. Returns true if nothing was pending for |addr| yet, in
+    /// which case a flush needs to be scheduled.
+    fn add(&mut self, addr: RawAddress, types: Vec<BtPropertyType>) -> bool {
+        let is_new = !self.pending.contains_key(&addr);
+        let pending = self.pending.entry(addr).or_default();
+        for prop_type in types {
+            if !pending.contains(&prop_type) {
+                pending.push(prop_type);
+            }
+        }
+        is_new
+    }
+
+    /// Takes the property types pending for |addr|, in the order they first changed.
+    fn take(&mut self, addr: RawAddress) -> Vec<BtPropertyType> {
+        self.pending.remove(&addr).unwrap_or_default()
+    }
+}
+
+/// HCI reasons of connection failures that may not happen again on a retry.
+const HCI_ERR_PAGE_TIMEOUT: BtHciErrorCode = 0x04;
+const HCI_ERR_CONNECTION_TIMEOUT: BtHciErrorCode = 0x08;
+const HCI_ERR_CONNECTION_FAILED_ESTABLISHMENT: BtHciErrorCode = 0x3E;
+
+/// Retries outgoing connections that failed for a transient reason, e.g. a page timeout on a flaky
+/// link. Retrying is disabled until a policy with retries is set.
+#[derive(Debug, Default)]
+struct ConnectRetry {
+    max_retries: u32,
+    backoff: Duration,
+    retries: HashMap<RawAddress, u32>,
+}
+
+impl ConnectRetry {
+    fn is_retriable(reason: BtHciErrorCode) -> bool {
+        matches!(
+            reason,
+            HCI_ERR_PAGE_TIMEOUT
+                | HCI_ERR_CONNECTION_TIMEOUT
+                | HCI_ERR_CONNECTION_FAILED_ESTABLISHMENT
+        )
+    }
+
+    /// Returns whether the connection to |addr| that failed with |reason| should be retried, and
+    /// counts the retry if so. Once this returns false the count starts over.
+    fn on_failure(&mut self, addr: RawAddress, reason: BtHciErrorCode) -> bool {
+        let retries = self.retries.get(&addr).copied().unwrap_or(0);
+        if !ConnectRetry::is_retriable(reason) || retries >= self.max_retries {
+            self.retries.remove(&addr);
+            return false;
+        }
+        self.retries.insert(addr, retries + 1);
+        true
+    }
+
+    /// Forgets the retries of |addr|, e.g. once it connected.
+    fn reset(&mut self, addr: &RawAddress) {
+        self.retries.remove(addr);
+    }
+}
+
+/// Tracks the devices a graceful disable is still waiting on. The adapter is disabled once the
+/// last of them disconnects or the wait times out, whichever comes first.
+#[derive(Debug, Default)]
+struct GracefulDisable {
+    pending: Option<HashSet<RawAddress>>,
+}
+
+impl GracefulDisable {
+    /// Starts waiting on |devices|. Returns false if there is nothing to wait for.
+    fn start(&mut self, devices: HashSet<RawAddress>) -> bool {
+        if devices.is_empty() {
+            return false;
+        }
+        self.pending = Some(devices);
+        true
+    }
+
+    fn is_in_progress(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Returns true if |addr| was the last device being waited on.
+    fn on_disconnected(&mut self, addr: &RawAddress) -> bool {
+        match self.pending.as_mut() {
+            Some(pending) => pending.remove(addr) && pending.is_empty(),
+            None => false,
+        }
+    }
+
+    /// Stops waiting. Returns false if the graceful disable was already finished.
+    fn finish(&mut self) -> bool {
+        self.pending.take().is_some()
+    }
+}
+
+/// A found (i.e. not bonded) remote device along with how long ago it was last seen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FoundDevice {
+    pub device: BluetoothDevice,
+    /// Milliseconds elapsed since the device was last seen.
+    pub age_ms: u64,
+}
+
+/// The address the adapter currently uses over LE.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeAddressInfo {
+    pub address: RawAddress,
+    /// Random if LE privacy is enabled, in which case the address is rotated periodically.
+    pub address_type: BtAddrType,
+}
+
+/// Version information of the local controller, as read with HCI Read Local Version Information.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ControllerInfo {
+    pub hci_version: u8,
+    pub hci_revision: u16,
+    pub lmp_version: u8,
+    pub subversion: u16,
+    /// The Bluetooth SIG company identifier of the manufacturer.
+    pub manufacturer: u16,
+}
+
+/// Advertising data parsed from the last advertisement received from a remote device.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AdvertisingData {
+    pub flags: u8,
+    /// Advertised TX power level in dBm.
+    pub tx_power: Option<i32>,
+    pub service_uuids: Vec<Uuid>,
+    /// A map of 128-bit UUID and its corresponding service data.
+    pub service_data: HashMap<String, Vec<u8>>,
+    pub manufacturer_data: HashMap<u16, Vec<u8>>,
+    pub appearance: Option<u16>,
+}
+
+impl AdvertisingData {
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        AdvertisingData {
+            flags: adv_parser::extract_flags(bytes),
+            tx_power: adv_parser::extract_tx_power(bytes).map(i32::from),
+            service_uuids: adv_parser::extract_service_uuids(bytes),
+            service_data: adv_parser::extract_service_data(bytes),
+            manufacturer_data: adv_parser::extract_manufacturer_data(bytes),
+            appearance: adv_parser::extract_appearance(bytes),
+        }
+    }
+}
+
+/// Link quality metrics of a connected remote device. Values that could not be obtained are
+/// left empty.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ConnectionQuality {
+    /// Received signal strength in dBm.
+    pub rssi: Option<i32>,
+    /// HCI Link Quality, where 255 is the best. See Core 5.3, Vol 4, Part E, 7.5.3.
+    pub link_quality: Option<u32>,
+}
+
+/// Collects the sub-values of a connection quality request, which may arrive separately and in
+/// any order, until all requested values have been reported.
+#[derive(Debug)]
+pub(crate) struct ConnectionQualityAggregator {
+    quality: ConnectionQuality,
+    pending_rssi: bool,
+    pending_link_quality: bool,
+}
+
+impl ConnectionQualityAggregator {
+    pub(crate) fn new(rssi: bool, link_quality: bool) -> Self {
+        ConnectionQualityAggregator {
+            quality: ConnectionQuality::default(),
+            pending_rssi: rssi,
+            pending_link_quality: link_quality,
+        }
+    }
+
+    pub(crate) fn on_rssi(&mut self, rssi: i8) {
+        self.pending_rssi = false;
+        self.quality.rssi = if rssi == INVALID_RSSI { None } else { Some(rssi.into()) };
+    }
+
+    pub(crate) fn on_link_quality(&mut self, link_quality: Option<u8>) {
+        self.pending_link_quality = false;
+        self.quality.link_quality = link_quality.map(|lq| lq.into());
+    }
+
+    pub(crate) fn is_complete(&self) -> bool {
+        !self.pending_rssi && !self.pending_link_quality
+    }
+
+    pub(crate) fn quality(&self) -> ConnectionQuality {
+        self.quality.clone()
+    }
+}
+
 /// Defines the adapter API.
 pub trait IBluetooth {
     /// Adds a callback from a client who wishes to observe adapter events.
@@ -114,9 +539,21 @@ pub trait IBluetooth {
     /// Returns the Bluetooth address of the local adapter.
     fn get_address(&self) -> RawAddress;
 
+    /// Gets the cached value of a local adapter property, or None if the adapter hasn't reported
+    /// it. The getters for specific properties below are shorthands for this.
+    fn get_adapter_property(&self, prop_type: BtPropertyType) -> Option<BluetoothProperty>;
+
     /// Gets supported UUIDs by the local adapter.
     fn get_uuids(&self) -> Vec<Uuid>;
 
+    /// Returns the address currently used by the local adapter for LE scanning, advertising and
+    /// initiating, along with its type. The type is Unknown if the adapter isn't enabled.
+    fn get_local_le_address_info(&self) -> LeAddressInfo;
+
+    /// Returns the version and manufacturer of the local controller, or None if the adapter isn't
+    /// enabled.
+    fn get_controller_info(&self) -> Option<ControllerInfo>;
+
     /// Gets the local adapter name.
     fn get_name(&self) -> String;
 
@@ -138,6 +575,11 @@ pub trait IBluetooth {
     /// Sets discoverability. If discoverable, limits the duration with given value.
     fn set_discoverable(&mut self, mode: BtDiscMode, duration: u32) -> bool;
 
+    /// Changes how long the adapter stays discoverable, counting from now, without changing the
+    /// discoverable mode. A duration of 0 keeps it discoverable until told otherwise, which isn't
+    /// allowed in limited discoverable mode. Fails if the adapter isn't discoverable.
+    fn set_discoverable_timeout(&mut self, duration: u32) -> bool;
+
     /// Returns whether multi-advertisement is supported.
     /// A minimum number of 5 advertising instances is required for multi-advertisment support.
     fn is_multi_advertisement_supported(&self) -> bool;
@@ -166,9 +608,20 @@ pub trait IBluetooth {
     /// Removes pairing for given device.
     fn remove_bond(&mut self, device: BluetoothDevice) -> bool;
 
+    /// Drops the cached entry of a found device right away instead of waiting for it to go stale,
+    /// and notifies |on_device_cleared|.
+    ///
+    /// Returns false if the device is unknown, bonded or connected.
+    fn forget_device(&mut self, device: BluetoothDevice) -> bool;
+
     /// Returns a list of known bonded devices.
     fn get_bonded_devices(&self) -> Vec<BluetoothDevice>;
 
+    /// Returns the found devices that aren't bonded, with the time elapsed since each of them was
+    /// last seen. Devices older than |FOUND_DEVICE_FRESHNESS| are cleared by the freshness check
+    /// unless they are connected.
+    fn get_found_devices_with_age(&self) -> Vec<FoundDevice>;
+
     /// Gets the bond state of a single device.
     fn get_bond_state(&self, device: BluetoothDevice) -> BtBondState;
 
@@ -181,6 +634,29 @@ pub trait IBluetooth {
     /// Confirm that a pairing should be completed on a bonding device.
     fn set_pairing_confirmation(&self, device: BluetoothDevice, accept: bool) -> bool;
 
+    /// Sets how SSP requests are answered without an agent.
+    fn set_pairing_policy(&mut self, policy: PairingPolicy);
+
+    /// Sets whether the UUIDs of a newly bonded device are always fetched, even if its services
+    /// were already discovered before bonding.
+    fn set_force_uuid_fetch_on_bond(&mut self, force: bool);
+
+    /// Sets whether property changes of a remote device arriving within a short window are
+    /// reported with a single |on_device_properties_changed|. Disabled by default, since it delays
+    /// the changes for every client; turn it on only if all clients can cope with the delay.
+    fn set_property_change_coalescing(&mut self, enabled: bool);
+
+    /// Sets how many times, and after how long, a connection requested with
+    /// |connect_all_enabled_profiles| is retried when it fails for a transient reason such as a
+    /// page timeout. |on_device_connection_failed| is only sent once the retries are exhausted.
+    /// Zero retries, the default, turns retrying off.
+    fn set_connect_retry_policy(&mut self, max_retries: u32, backoff_ms: u32);
+
+    /// Sets the local IO capability used for BR/EDR Secure Simple Pairing, which determines the
+    /// association model. It is stored and applied again every time the adapter is enabled, also
+    /// after a restart of the daemon.
+    fn set_io_capability(&mut self, io_cap: BtIoCap) -> bool;
+
     /// Gets the name of the remote device.
     fn get_remote_name(&self, device: BluetoothDevice) -> String;
 
@@ -191,7 +667,20 @@ pub trait IBluetooth {
     fn get_remote_alias(&self, device: BluetoothDevice) -> String;
 
     /// Sets the alias of the remote device.
-    fn set_remote_alias(&mut self, device: BluetoothDevice, new_alias: String);
+    ///
+    /// Returns false if the device is unknown or the alias could not be set.
+    fn set_remote_alias(&mut self, device: BluetoothDevice, new_alias: String) -> bool;
+
+    /// Overrides the type reported by the remote device, which decides the transport used to
+    /// bond with it and fetch its UUIDs. Meant for devices that misreport their type. The
+    /// override lasts until the adapter is restarted.
+    ///
+    /// Returns false if the device is unknown.
+    fn set_remote_device_type_override(
+        &mut self,
+        device: BluetoothDevice,
+        device_type: BtDeviceType,
+    ) -> bool;
 
     /// Gets the class of the remote device.
     fn get_remote_class(&self, device: BluetoothDevice) -> u32;
@@ -205,6 +694,22 @@ pub trait IBluetooth {
     /// Gets whether the remote device can wake the system.
     fn get_remote_wake_allowed(&self, device: BluetoothDevice) -> bool;
 
+    /// Sets whether the remote device can wake the system, overriding the default of allowing HID
+    /// and HOGP devices only. The override lasts until the adapter is restarted.
+    fn set_remote_wake_allowed(&mut self, device: BluetoothDevice, allowed: bool);
+
+    /// Gets whether the last ACL connection with the remote device was initiated by us or by the
+    /// remote device. Unknown if it hasn't connected since it was found.
+    fn get_remote_connection_direction(&self, device: BluetoothDevice) -> BtConnectionDirection;
+
+    /// Gets the mechanisms through which the remote device was found since it was last cached.
+    /// Empty if it was only known from a bond or a connection.
+    fn get_remote_discovery_sources(&self, device: BluetoothDevice) -> Vec<DiscoverySource>;
+
+    /// Gets the HCI handles of the ACL connections with the remote device, one per connected
+    /// transport. Useful to match stack events against HCI traces.
+    fn get_connection_handles(&self, device: BluetoothDevice) -> Vec<u16>;
+
     /// Gets the vendor and product information of the remote device.
     fn get_remote_vendor_product_info(&self, device: BluetoothDevice) -> BtVendorProductInfo;
 
@@ -214,6 +719,25 @@ pub trait IBluetooth {
     /// Get the RSSI of the remote device.
     fn get_remote_rssi(&self, device: BluetoothDevice) -> i8;
 
+    /// Gets the RSSI of the remote device smoothed over the samples received so far, see
+    /// |set_rssi_smoothing_factor|.
+    fn get_remote_rssi_smoothed(&self, device: BluetoothDevice) -> i8;
+
+    /// Sets the weight, in (0, 1], of a new RSSI sample in the smoothed RSSI. A factor of 1 turns
+    /// smoothing off. Returns false if the factor is out of range.
+    fn set_rssi_smoothing_factor(&mut self, factor: f64) -> bool;
+
+    /// Sets the minimum interval between two |on_device_found| of the same device. Sightings in
+    /// between are only notified if they change the name or add UUIDs. 0 notifies every sighting.
+    fn set_device_found_min_interval(&mut self, interval_ms: u32);
+
+    /// Gets every cached property of the remote device.
+    fn get_remote_all_properties(&self, device: BluetoothDevice) -> Vec<BluetoothProperty>;
+
+    /// Gets the advertising data parsed from the last advertisement of the remote device seen
+    /// while discovering. It's empty if no advertisement was seen.
+    fn get_remote_advertising_data(&self, device: BluetoothDevice) -> AdvertisingData;
+
     /// Returns a list of connected devices.
     fn get_connected_devices(&self) -> Vec<BluetoothDevice>;
 
@@ -226,8 +750,10 @@ pub trait IBluetooth {
     /// Returns the cached UUIDs of a remote device.
     fn get_remote_uuids(&self, device: BluetoothDevice) -> Vec<Uuid>;
 
-    /// Triggers SDP to get UUIDs of a remote device.
-    fn fetch_remote_uuids(&self, device: BluetoothDevice) -> bool;
+    /// Triggers SDP to get UUIDs of a remote device. If the service discovery fails,
+    /// |on_remote_uuids_fetched| is fired with no UUIDs and a failure status, or a Timeout status
+    /// if the UUIDs aren't reported within |REMOTE_UUIDS_FETCH_TIMEOUT|.
+    fn fetch_remote_uuids(&mut self, device: BluetoothDevice) -> bool;
 
     /// Triggers SDP and searches for a specific UUID on a remote device.
     fn sdp_search(&self, device: BluetoothDevice, uuid: Uuid) -> bool;
@@ -241,11 +767,29 @@ pub trait IBluetooth {
     /// Connect all profiles supported by device and enabled on adapter.
     fn connect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> BtStatus;
 
+    /// Gets the profiles |connect_all_enabled_profiles| would connect for the device right now,
+    /// without connecting anything.
+    fn get_connectable_profiles(&self, device: BluetoothDevice) -> Vec<Profile>;
+
+    /// Stops |connect_all_enabled_profiles| for the device: profiles discovered later won't be
+    /// connected anymore and the profile connections it started are cancelled.
+    fn cancel_connect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool;
+
     /// Disconnect all profiles supported by device and enabled on adapter.
     /// Note that it includes all custom profiles enabled by the users e.g. through SocketManager or
     /// BluetoothGatt interfaces; The device shall be disconnected on baseband eventually.
     fn disconnect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool;
 
+    /// Enables or disables HID or HOGP until the daemon restarts, without changing the admin
+    /// policy. A disabled profile is deactivated, so it is neither connected by
+    /// |connect_all_enabled_profiles| nor accepted from the remote. Returns false for any other
+    /// profile, since media connects and accepts its profiles as a whole.
+    fn set_profile_enabled(&mut self, profile: Profile, enabled: bool) -> bool;
+
+    /// Disconnects the HID or HOGP profile of the device. |reconnect_allowed| tells whether the
+    /// device may reconnect on its own afterwards.
+    fn disconnect_hid(&mut self, device: BluetoothDevice, reconnect_allowed: bool) -> BtStatus;
+
     /// Returns whether WBS is supported.
     fn is_wbs_supported(&self) -> bool;
 
@@ -261,10 +805,18 @@ pub trait IBluetooth {
     /// Returns whether LE Audio is supported.
     fn is_le_audio_supported(&self) -> bool;
 
+    /// Returns the LE Audio related capabilities of the controller.
+    fn get_le_audio_capabilities(&self) -> LeAudioCaps;
+
     /// Returns whether the remote device is a dual mode audio sink device (supports both classic and
     /// LE Audio sink roles).
     fn is_dual_mode_audio_sink_device(&self, device: BluetoothDevice) -> bool;
 
+    /// Requests the connection quality of a connected device. The result is delivered through
+    /// |IBluetoothConnectionCallback::on_connection_quality|. Returns false if the device is not
+    /// connected.
+    fn get_connection_quality(&mut self, device: BluetoothDevice) -> bool;
+
     /// Gets diagnostic output.
     fn get_dumpsys(&self) -> String;
 }
@@ -329,6 +881,24 @@ pub enum AdapterActions {
 
     /// Create bond to the device stored in |pending_create_bond|.
     CreateBond,
+
+    /// Stop waiting for devices to disconnect and disable the adapter.
+    FinishGracefulDisable,
+
+    /// Reset the scan suspend mode if it is still Suspending or Resuming.
+    ScanSuspendModeWatchdog,
+
+    /// Reset the discovery suspend mode if it is still Suspending or Resuming.
+    DiscoverySuspendModeWatchdog,
+
+    /// Report the property changes accumulated for the device.
+    FlushPropertyChanges(RawAddress),
+
+    /// Retry connecting to the device after a transient connection failure.
+    RetryConnect(RawAddress),
+
+    /// The UUIDs of the device weren't reported in time after |fetch_remote_uuids|.
+    RemoteUuidsFetchTimeout(RawAddress),
 }
 
 /// Serializable device used in various apis.
@@ -363,6 +933,45 @@ impl BluetoothDevice {
     }
 }
 
+/// Gets the profiles connected along with |profile|, including itself.
+fn profile_connection_group(profile: Profile) -> Vec<Profile> {
+    match profile {
+        Profile::Hid | Profile::Hogp => vec![Profile::Hid, Profile::Hogp],
+        Profile::LeAudio | Profile::VolumeControl | Profile::CoordinatedSet => {
+            vec![Profile::LeAudio, Profile::VolumeControl, Profile::CoordinatedSet]
+        }
+        Profile::A2dpSink | Profile::A2dpSource | Profile::Hfp | Profile::AvrcpController => {
+            vec![Profile::A2dpSink, Profile::A2dpSource, Profile::Hfp, Profile::AvrcpController]
+        }
+        _ => vec![profile],
+    }
+}
+
+/// Exponential moving average of the RSSI of a remote device.
+#[derive(Clone, Debug, Default, PartialEq)]
+struct SmoothedRssi {
+    average: Option<f64>,
+}
+
+impl SmoothedRssi {
+    /// Adds a sample weighted by |factor| against the current average. The first sample is taken
+    /// as is and invalid samples are ignored.
+    fn update(&mut self, rssi: i8, factor: f64) {
+        if rssi == INVALID_RSSI {
+            return;
+        }
+        let rssi = f64::from(rssi);
+        self.average = Some(match self.average {
+            Some(average) => factor * rssi + (1.0 - factor) * average,
+            None => rssi,
+        });
+    }
+
+    fn get(&self) -> i8 {
+        self.average.map_or(INVALID_RSSI, |average| average.round() as i8)
+    }
+}
+
 /// Internal data structure that keeps a map of cached properties for a remote device.
 struct BluetoothDeviceContext {
     /// Transport type reported by ACL connection (if completed).
@@ -376,6 +985,42 @@ struct BluetoothDeviceContext {
     pub properties: HashMap<BtPropertyType, BluetoothProperty>,
     pub is_hh_connected: bool,
 
+    /// Advertising data of the last advertisement seen while discovering.
+    pub adv_data: Option<AdvertisingData>,
+
+    pub smoothed_rssi: SmoothedRssi,
+
+    /// Explicitly set wake capability, which takes precedence over the one inferred from UUIDs.
+    pub wake_allowed_override: Option<bool>,
+
+    /// Explicitly set device type, which takes precedence over the one the device reported.
+    pub device_type_override: Option<BtDeviceType>,
+
+    /// Last known state of each profile connection, kept to trace the transitions.
+    pub profile_states: HashMap<Profile, ProfileConnectionState>,
+
+    /// Connection state as of the last ACL state change, so it can be served without a native
+    /// call. Only kept once it can't change anymore until the next ACL state change, i.e. once
+    /// every connected transport is encrypted.
+    pub connection_state: Option<BtConnectionState>,
+
+    /// Direction of the last ACL connection, on either transport.
+    pub last_connection_direction: BtConnectionDirection,
+
+    /// HCI handles of the ACL connections, set while connected on each transport.
+    pub bredr_acl_handle: Option<u16>,
+    pub ble_acl_handle: Option<u16>,
+
+    /// Bitmask of the |DiscoverySource| values through which this device was found.
+    pub found_via: u32,
+
+    /// When |on_device_found| was last sent for this device.
+    pub last_found_notified: Option<Instant>,
+
+    /// Whether the UUIDs were reported by a service discovery, as opposed to only being gathered
+    /// from inquiry or advertising data which may list a subset of the services.
+    pub uuids_resolved: bool,
+
     /// If user wants to connect to all profiles, when new profiles are discovered we will also try
     /// to connect them.
     pub connect_to_new_profiles: bool,
@@ -399,6 +1044,18 @@ impl BluetoothDeviceContext {
             last_seen,
             properties: HashMap::new(),
             is_hh_connected: false,
+            adv_data: None,
+            smoothed_rssi: SmoothedRssi::default(),
+            wake_allowed_override: None,
+            device_type_override: None,
+            profile_states: HashMap::new(),
+            connection_state: Some(BtConnectionState::NotConnected),
+            last_connection_direction: BtConnectionDirection::Unknown,
+            bredr_acl_handle: None,
+            ble_acl_handle: None,
+            found_via: 0,
+            last_found_notified: None,
+            uuids_resolved: false,
             connect_to_new_profiles: false,
         };
         device.update_properties(&properties);
@@ -441,29 +1098,227 @@ impl BluetoothDeviceContext {
         }
     }
 
+    /// Adds the RSSI samples among |in_properties| to the smoothed RSSI.
+    pub(crate) fn update_smoothed_rssi(
+        &mut self,
+        in_properties: &Vec<BluetoothProperty>,
+        factor: f64,
+    ) {
+        for prop in in_properties {
+            if let BluetoothProperty::RemoteRssi(rssi) = prop {
+                self.smoothed_rssi.update(*rssi, factor);
+            }
+        }
+    }
+
+    /// Returns whether the UUIDs need to be fetched after the device bonded. Service discovery is
+    /// skipped if it already ran unless |force| is set.
+    fn needs_uuid_fetch_on_bond(&self, force: bool) -> bool {
+        force || !self.uuids_resolved || !self.properties.contains_key(&BtPropertyType::Uuids)
+    }
+
+    /// Returns whether this device can wake the system. Without an override, wake is allowed if
+    /// the device supports HIDP or HOGP only.
+    fn is_wake_allowed(&self) -> bool {
+        if let Some(allowed) = self.wake_allowed_override {
+            return allowed;
+        }
+
+        match self.properties.get(&BtPropertyType::Uuids) {
+            Some(BluetoothProperty::Uuids(uuids)) => uuids.iter().any(|&uuid| {
+                UuidHelper::is_known_profile(&uuid)
+                    .map_or(false, |profile| profile == Profile::Hid || profile == Profile::Hogp)
+            }),
+            _ => false,
+        }
+    }
+
+    /// Records |state| as the new state of |profile| and returns the previous one.
+    fn transition_profile_state(
+        &mut self,
+        profile: Profile,
+        state: ProfileConnectionState,
+    ) -> ProfileConnectionState {
+        self.profile_states.insert(profile, state).unwrap_or(ProfileConnectionState::Disconnected)
+    }
+
+    /// Records a HID host connection state change. HID and HOGP share the connection, so both
+    /// are updated if tracked, and |profile| otherwise.
+    fn set_hid_profile_state(&mut self, profile: Profile, state: &BthhConnectionState) {
+        let state = match state {
+            BthhConnectionState::Connected => ProfileConnectionState::Connected,
+            BthhConnectionState::Connecting | BthhConnectionState::Accepting => {
+                ProfileConnectionState::Connecting
+            }
+            BthhConnectionState::Disconnecting => ProfileConnectionState::Disconnecting,
+            _ => ProfileConnectionState::Disconnected,
+        };
+        let mut tracked: Vec<Profile> = [Profile::Hid, Profile::Hogp]
+            .into_iter()
+            .filter(|p| self.profile_states.contains_key(p))
+            .collect();
+        if tracked.is_empty() {
+            tracked.push(profile);
+        }
+        for p in tracked {
+            self.transition_profile_state(p, state.clone());
+        }
+    }
+
+    /// Records the media profiles in |connected| as connected.
+    fn set_media_profiles_connected(&mut self, connected: &HashSet<Profile>) {
+        for profile in connected {
+            self.transition_profile_state(*profile, ProfileConnectionState::Connected);
+        }
+    }
+
+    /// Returns the UUIDs among |uuids| of profiles whose connection can still be cancelled.
+    /// Profiles sharing a connection are connected and disconnected together, so only groups
+    /// with a profile connecting and none connected yet are returned.
+    fn cancellable_profile_uuids(&self, uuids: Vec<Uuid>) -> Vec<Uuid> {
+        let state = |p: &Profile| self.profile_states.get(p);
+        uuids
+            .into_iter()
+            .filter(|uuid| {
+                UuidHelper::is_known_profile(uuid).map_or(false, |p| {
+                    let group = profile_connection_group(p);
+                    state(&p) == Some(&ProfileConnectionState::Connecting)
+                        && !group
+                            .iter()
+                            .any(|g| state(g) == Some(&ProfileConnectionState::Connected))
+                })
+            })
+            .collect()
+    }
+
+    /// Refreshes the cached connection state after an ACL state change. |native_state| is only
+    /// queried while the device is still connected on some transport.
+    fn update_connection_state<F>(&mut self, native_state: F)
+    where
+        F: FnOnce() -> BtConnectionState,
+    {
+        let state =
+            if self.is_connected() { native_state() } else { BtConnectionState::NotConnected };
+        // A transport that isn't encrypted yet may be encrypted any time.
+        let is_final = match state {
+            BtConnectionState::NotConnected | BtConnectionState::EncryptedBoth => true,
+            BtConnectionState::EncryptedBredr => self.ble_acl_state != BtAclState::Connected,
+            BtConnectionState::EncryptedLe => self.bredr_acl_state != BtAclState::Connected,
+            BtConnectionState::ConnectedOnly => false,
+        };
+        self.connection_state = if is_final { Some(state) } else { None };
+    }
+
+    /// Drops the cached connection state of a connected device, e.g. when its bond changed and
+    /// its links may be encrypted or torn down without an ACL state change yet.
+    fn invalidate_connection_state(&mut self) {
+        if self.is_connected() {
+            self.connection_state = None;
+        }
+    }
+
+    /// Returns the cached connection state, or None if it has to be queried from the stack.
+    fn cached_connection_state(&self) -> Option<BtConnectionState> {
+        self.connection_state.clone()
+    }
+
     /// Mark this device as seen.
     pub(crate) fn seen(&mut self) {
         self.last_seen = Instant::now();
     }
 
-    fn get_default_transport(&self) -> BtTransport {
-        self.properties.get(&BtPropertyType::TypeOfDevice).map_or(BtTransport::Auto, |prop| {
-            match prop {
-                BluetoothProperty::TypeOfDevice(t) => match *t {
-                    BtDeviceType::Bredr => BtTransport::Bredr,
-                    BtDeviceType::Ble => BtTransport::Le,
-                    _ => BtTransport::Auto,
-                },
-                _ => BtTransport::Auto,
-            }
+    /// Returns whether |in_properties| change the name of this device or add UUIDs to it.
+    fn has_significant_changes(&self, in_properties: &Vec<BluetoothProperty>) -> bool {
+        in_properties.iter().any(|prop| match prop {
+            BluetoothProperty::BdName(name) => !name.is_empty() && *name != self.info.name,
+            BluetoothProperty::Uuids(uuids) => match self.properties.get(&BtPropertyType::Uuids) {
+                Some(BluetoothProperty::Uuids(old_uuids)) => {
+                    uuids.iter().any(|uuid| !old_uuids.contains(uuid))
+                }
+                _ => !uuids.is_empty(),
+            },
+            _ => false,
         })
     }
 
+    /// Returns whether a sighting at |now| should be notified, and records it if so. Sightings
+    /// less than |min_interval| after the last notified one are dropped unless |significant|.
+    fn should_notify_found(
+        &mut self,
+        now: Instant,
+        significant: bool,
+        min_interval: Duration,
+    ) -> bool {
+        let notify = significant
+            || self
+                .last_found_notified
+                .map_or(true, |last| now.saturating_duration_since(last) >= min_interval);
+        if notify {
+            self.last_found_notified = Some(now);
+        }
+        notify
+    }
+
+    /// Records that this device was found through |source|.
+    pub(crate) fn mark_found_via(&mut self, source: DiscoverySource) {
+        self.found_via |= source as u32;
+    }
+
+    /// Returns how long ago, relative to |now|, this device was last seen.
+    pub(crate) fn age(&self, now: Instant) -> Duration {
+        now.saturating_duration_since(self.last_seen)
+    }
+
+    /// Returns the type of this device, preferring the override to the reported type.
+    fn get_device_type(&self) -> BtDeviceType {
+        if let Some(device_type) = &self.device_type_override {
+            return device_type.clone();
+        }
+
+        match self.properties.get(&BtPropertyType::TypeOfDevice) {
+            Some(BluetoothProperty::TypeOfDevice(device_type)) => device_type.clone(),
+            _ => BtDeviceType::Unknown,
+        }
+    }
+
+    fn get_default_transport(&self) -> BtTransport {
+        match self.get_device_type() {
+            BtDeviceType::Bredr => BtTransport::Bredr,
+            BtDeviceType::Ble => BtTransport::Le,
+            _ => BtTransport::Auto,
+        }
+    }
+
     /// Check if it is connected in at least one transport.
     fn is_connected(&self) -> bool {
         self.bredr_acl_state == BtAclState::Connected || self.ble_acl_state == BtAclState::Connected
     }
 
+    /// Records the handle of the ACL connection on |transport|, or clears it with None. The
+    /// transport is resolved the same way as in |set_transport_state|.
+    fn set_acl_handle(&mut self, transport: &BtTransport, handle: Option<u16>) {
+        match (transport, self.get_default_transport()) {
+            (BtTransport::Bredr, _) | (BtTransport::Auto, BtTransport::Bredr) => {
+                self.bredr_acl_handle = handle
+            }
+            (BtTransport::Le, _) | (BtTransport::Auto, BtTransport::Le) => {
+                self.ble_acl_handle = handle
+            }
+            // The link can't be attributed to a transport; only clearing is safe.
+            _ => {
+                if handle.is_none() {
+                    self.bredr_acl_handle = None;
+                    self.ble_acl_handle = None;
+                }
+            }
+        }
+    }
+
+    /// Handles of the ACL connections currently up, BR/EDR first.
+    fn connection_handles(&self) -> Vec<u16> {
+        self.bredr_acl_handle.into_iter().chain(self.ble_acl_handle).collect()
+    }
+
     /// Set ACL state given transport. Return true if state changed.
     fn set_transport_state(&mut self, transport: &BtTransport, state: &BtAclState) -> bool {
         match (transport, self.get_default_transport()) {
@@ -539,6 +1394,11 @@ pub trait IBluetoothCallback: RPCProxy {
     /// When the discovery state is changed.
     fn on_discovering_changed(&mut self, discovering: bool);
 
+    /// When the discovery stopped on its own after its timeout, rather than because it was
+    /// cancelled, the adapter is turning off or the stack stopped it early. Sent after
+    /// |on_discovering_changed|, so that clients can start it again.
+    fn on_discovery_timeout(&mut self);
+
     /// When there is a pairing/bonding process and requires agent to display the event to UI.
     fn on_ssp_request(
         &mut self,
@@ -557,6 +1417,9 @@ pub trait IBluetoothCallback: RPCProxy {
     /// When a bonding attempt has completed.
     fn on_bond_state_changed(&mut self, status: u32, device_address: RawAddress, state: u32);
 
+    /// When a device becomes bonded or stops being bonded, with all the bonded devices.
+    fn on_bonded_devices_changed(&mut self, devices: Vec<BluetoothDevice>);
+
     /// When an SDP search has completed.
     fn on_sdp_search_complete(
         &mut self,
@@ -567,6 +1430,16 @@ pub trait IBluetoothCallback: RPCProxy {
 
     /// When an SDP record has been successfully created.
     fn on_sdp_record_created(&mut self, record: BtSdpRecord, handle: i32);
+
+    /// When the service discovery started by |fetch_remote_uuids|, or by bonding, has completed.
+    /// Fired even if the UUIDs didn't change. A failed discovery reports no UUIDs and the failure
+    /// status.
+    fn on_remote_uuids_fetched(
+        &mut self,
+        remote_device: BluetoothDevice,
+        uuids: Vec<Uuid>,
+        status: BtStatus,
+    );
 }
 
 pub trait IBluetoothConnectionCallback: RPCProxy {
@@ -576,8 +1449,17 @@ pub trait IBluetoothConnectionCallback: RPCProxy {
     /// Notification sent when a remote device completes HCI disconnection.
     fn on_device_disconnected(&mut self, remote_device: BluetoothDevice);
 
-    /// Notification sent when a remote device fails to complete HCI connection.
-    fn on_device_connection_failed(&mut self, remote_device: BluetoothDevice, status: BtStatus);
+    /// Notification sent when a remote device fails to complete HCI connection, along with the HCI
+    /// reason of the failure.
+    fn on_device_connection_failed(
+        &mut self,
+        remote_device: BluetoothDevice,
+        status: BtStatus,
+        hci_reason: BtHciErrorCode,
+    );
+
+    /// Notification sent when a connection quality request has completed.
+    fn on_connection_quality(&mut self, remote_device: BluetoothDevice, quality: ConnectionQuality);
 }
 
 /// Implementation of the adapter API.
@@ -606,23 +1488,52 @@ pub struct Bluetooth {
     is_discovering_before_suspend: bool,
     is_discovery_paused: bool,
     discovery_suspend_mode: SuspendMode,
+    scan_suspend_watchdog: Option<JoinHandle<()>>,
+    discovery_suspend_watchdog: Option<JoinHandle<()>>,
+    suspend_mode_watchdog_timeout: Duration,
     local_address: Option<RawAddress>,
     pending_discovery: bool,
+    /// Whether the ongoing discovery was cancelled, either by a client or to pause it.
+    discovery_cancel_requested: bool,
     properties: HashMap<BtPropertyType, BluetoothProperty>,
     profiles_ready: bool,
+    admin_policy: BluetoothAdminPolicyHelper,
+    disabled_profiles: HashSet<Profile>,
     freshness_check: Option<JoinHandle<()>>,
+    controller: Option<controller::Controller>,
     sdp: Option<Sdp>,
     state: BtState,
-    disabling: bool,
+    scan_mode_guard: ScanModeGuard,
+    // Scan mode last requested, held back while BR/EDR is disabled.
+    scan_mode: BtScanMode,
+    is_br_edr_disabled: bool,
+    is_le_disabled: bool,
+    supports_br_edr: bool,
+    supports_ble: bool,
+    graceful_disable: GracefulDisable,
+    connect_retry: ConnectRetry,
+    graceful_disable_timeout: Option<JoinHandle<()>>,
     tx: Sender<Message>,
     api_tx: Sender<APIMessage>,
     // Internal API members
     discoverable_timeout: Option<JoinHandle<()>>,
     cancelling_devices: HashSet<RawAddress>,
+    pending_uuid_fetches: HashMap<RawAddress, JoinHandle<()>>,
     pending_create_bond: Option<(BluetoothDevice, BtTransport)>,
+    pending_connection_quality: HashMap<RawAddress, ConnectionQualityAggregator>,
     active_pairing_address: Option<RawAddress>,
+    pairing_policy: PairingPolicy,
+    auto_accept_just_works: bool,
+    property_changes: PropertyChangeCoalescer,
+    is_property_change_coalescing: bool,
+    force_uuid_fetch_on_bond: bool,
+    io_capability_path: String,
+    io_capability: Option<BtIoCap>,
+    rssi_smoothing_factor: f64,
+    device_found_min_interval: Duration,
     le_supported_states: u64,
     le_local_supported_features: u64,
+    logging: Option<Arc<Mutex<Box<BluetoothLogging>>>>,
 
     /// Used to notify signal handler that we have turned off the stack.
     sig_notifier: Arc<SigData>,
@@ -640,6 +1551,7 @@ impl Bluetooth {
         api_tx: Sender<APIMessage>,
         sig_notifier: Arc<SigData>,
         intf: Arc<Mutex<BluetoothInterface>>,
+        io_capability_path: String,
     ) -> Bluetooth {
         Bluetooth {
             virt_index,
@@ -666,28 +1578,63 @@ impl Bluetooth {
             is_discovering_before_suspend: false,
             is_discovery_paused: false,
             discovery_suspend_mode: SuspendMode::Normal,
+            scan_suspend_watchdog: None,
+            discovery_suspend_watchdog: None,
+            suspend_mode_watchdog_timeout: DEFAULT_SUSPEND_MODE_WATCHDOG_TIMEOUT,
             local_address: None,
             pending_discovery: false,
+            discovery_cancel_requested: false,
             properties: HashMap::new(),
             profiles_ready: false,
+            admin_policy: Default::default(),
+            disabled_profiles: HashSet::new(),
             freshness_check: None,
+            controller: None,
             sdp: None,
             state: BtState::Off,
-            disabling: false,
+            scan_mode_guard: ScanModeGuard::default(),
+            scan_mode: BtScanMode::None_,
+            is_br_edr_disabled: false,
+            is_le_disabled: false,
+            supports_br_edr: false,
+            supports_ble: false,
+            graceful_disable: GracefulDisable::default(),
+            connect_retry: ConnectRetry::default(),
+            graceful_disable_timeout: None,
             tx,
             api_tx,
             // Internal API members
             discoverable_timeout: None,
             cancelling_devices: HashSet::new(),
+            pending_uuid_fetches: HashMap::new(),
             pending_create_bond: None,
+            pending_connection_quality: HashMap::new(),
             active_pairing_address: None,
+            pairing_policy: PairingPolicy::RequireAgent,
+            auto_accept_just_works: true,
+            property_changes: PropertyChangeCoalescer::default(),
+            is_property_change_coalescing: false,
+            force_uuid_fetch_on_bond: false,
+            io_capability_path,
+            io_capability: None,
+            rssi_smoothing_factor: DEFAULT_RSSI_SMOOTHING_FACTOR,
+            device_found_min_interval: DEFAULT_DEVICE_FOUND_MIN_INTERVAL,
             le_supported_states: 0u64,
             le_local_supported_features: 0u64,
+            logging: None,
             sig_notifier,
             uhid_wakeup_source: UHid::new(),
         }
     }
 
+    /// Returns the number of registered adapter and connection callbacks.
+    pub(crate) fn get_callback_counts(&self) -> HashMap<String, CallbackCount> {
+        HashMap::from([
+            (String::from("adapter"), self.callbacks.shared_count()),
+            (String::from("connection"), self.connection_callbacks.shared_count()),
+        ])
+    }
+
     pub(crate) fn set_media(&mut self, bluetooth_media: Arc<Mutex<Box<BluetoothMedia>>>) {
         self.bluetooth_media = Some(bluetooth_media);
     }
@@ -706,9 +1653,128 @@ impl Bluetooth {
         self.ble_scanner_uuid = Some(bluetooth_gatt.lock().unwrap().register_scanner(callback_id));
     }
 
+    /// Writes the scan mode to the controller unless the adapter is disabling, see
+    /// |ScanModeGuard|. All scan mode changes must go through here. While BR/EDR is disabled the
+    /// page and inquiry scans stay off and the mode is only written once BR/EDR is enabled again.
+    fn set_scan_mode(&mut self, mode: BtScanMode) {
+        let effective_mode = if self.is_br_edr_disabled {
+            debug!("Hold back scan mode {:?} while BR/EDR is disabled", mode);
+            BtScanMode::None_
+        } else {
+            mode.clone()
+        };
+        if !self.scan_mode_guard.allow(&effective_mode) {
+            debug!("Skip setting scan mode to {:?} while disabling", effective_mode);
+            return;
+        }
+        self.scan_mode = mode;
+        self.intf.lock().unwrap().set_scan_mode(effective_mode);
+    }
+
+    /// Enables or disables the BR/EDR and LE transports. BR/EDR is disabled by turning off the
+    /// page and inquiry scans, LE with the HCI Write LE Host Support command. Fails if the adapter
+    /// isn't on or the controller doesn't support a transport to enable.
+    pub(crate) fn set_transports_enabled(
+        &mut self,
+        br_edr: bool,
+        le: bool,
+    ) -> Result<(), BtStatus> {
+        if self.state != BtState::On {
+            return Err(BtStatus::NotReady);
+        }
+        if (br_edr && !self.supports_br_edr) || (le && !self.supports_ble) {
+            return Err(BtStatus::Unsupported);
+        }
+        if let Some(controller) = &self.controller {
+            controller.write_le_host_support(le);
+        }
+        self.is_le_disabled = !le;
+
+        if self.is_br_edr_disabled != br_edr {
+            return Ok(());
+        }
+        self.is_br_edr_disabled = !br_edr;
+        // The configured scan mode is kept and written again once BR/EDR is enabled.
+        self.set_scan_mode(self.scan_mode.clone());
+        Ok(())
+    }
+
+    /// Returns the transport to use for |transport| given the enabled transports, or None if it
+    /// is disabled.
+    fn resolve_transport(&self, transport: BtTransport) -> Option<BtTransport> {
+        match transport {
+            BtTransport::Bredr if self.is_br_edr_disabled => None,
+            BtTransport::Le if self.is_le_disabled => None,
+            BtTransport::Auto if self.is_br_edr_disabled => Some(BtTransport::Le),
+            BtTransport::Auto if self.is_le_disabled => Some(BtTransport::Bredr),
+            transport => Some(transport),
+        }
+    }
+
+    /// Returns whether the transport |profile| is connected over is enabled.
+    fn is_profile_transport_enabled(&self, profile: &Profile) -> bool {
+        match profile {
+            Profile::Hid | Profile::A2dpSink | Profile::A2dpSource | Profile::Hfp => {
+                !self.is_br_edr_disabled
+            }
+            Profile::Hogp | Profile::LeAudio | Profile::VolumeControl | Profile::CoordinatedSet => {
+                !self.is_le_disabled
+            }
+            _ => true,
+        }
+    }
+
+    /// Sets the local IO capability in the native stack.
+    fn apply_io_capability(&self, io_cap: BtIoCap) -> bool {
+        self.intf.lock().unwrap().set_adapter_property(BluetoothProperty::LocalIoCaps(io_cap)) == 0
+    }
+
+    /// Sets whether the Just-Works consent of a pairing initiated by us is accepted without
+    /// asking the agent.
+    pub(crate) fn set_auto_accept_just_works(&mut self, enabled: bool) {
+        self.auto_accept_just_works = enabled;
+    }
+
+    /// Returns the devices with a bond cancel that hasn't completed yet.
+    pub(crate) fn get_cancelling_devices(&self) -> Vec<RawAddress> {
+        self.cancelling_devices.iter().copied().collect()
+    }
+
+    /// Writes the inquiry scan parameters to the controller. Fails if the adapter isn't on.
+    pub(crate) fn write_inquiry_scan_params(
+        &self,
+        interval: u16,
+        window: u16,
+        scan_type: u8,
+    ) -> Result<(), BtStatus> {
+        match &self.controller {
+            Some(controller) if self.state == BtState::On => {
+                controller.write_inquiry_scan_params(interval, window, scan_type);
+                Ok(())
+            }
+            _ => Err(BtStatus::NotReady),
+        }
+    }
+
+    /// Writes the page scan parameters to the controller. Fails if the adapter isn't on.
+    pub(crate) fn write_page_scan_params(
+        &self,
+        interval: u16,
+        window: u16,
+        scan_type: u8,
+    ) -> Result<(), BtStatus> {
+        match &self.controller {
+            Some(controller) if self.state == BtState::On => {
+                controller.write_page_scan_params(interval, window, scan_type);
+                Ok(())
+            }
+            _ => Err(BtStatus::NotReady),
+        }
+    }
+
     fn update_connectable_mode(&mut self) {
         // Don't bother if we are disabling. See b/361510982
-        if self.disabling {
+        if self.scan_mode_guard.is_disabling() {
             return;
         }
         if self.get_scan_suspend_mode() != SuspendMode::Normal {
@@ -752,19 +1818,26 @@ impl Bluetooth {
         &mut self,
         admin_policy_helper: BluetoothAdminPolicyHelper,
     ) {
-        match (
-            admin_policy_helper.is_profile_allowed(&Profile::Hid),
-            self.hh.as_ref().unwrap().is_hidp_activated,
-        ) {
+        self.admin_policy = admin_policy_helper;
+        self.update_hid_activation();
+    }
+
+    /// Returns whether |profile| is allowed by the admin policy and not disabled at runtime.
+    fn is_profile_enabled(&self, profile: &Profile) -> bool {
+        self.admin_policy.is_profile_allowed(profile) && !self.disabled_profiles.contains(profile)
+    }
+
+    /// Activates or deactivates HID and HOGP according to |is_profile_enabled|.
+    fn update_hid_activation(&mut self) {
+        match (self.is_profile_enabled(&Profile::Hid), self.hh.as_ref().unwrap().is_hidp_activated)
+        {
             (true, false) => self.hh.as_mut().unwrap().activate_hidp(true),
             (false, true) => self.hh.as_mut().unwrap().activate_hidp(false),
             _ => {}
         }
 
-        match (
-            admin_policy_helper.is_profile_allowed(&Profile::Hogp),
-            self.hh.as_ref().unwrap().is_hogp_activated,
-        ) {
+        match (self.is_profile_enabled(&Profile::Hogp), self.hh.as_ref().unwrap().is_hogp_activated)
+        {
             (true, false) => self.hh.as_mut().unwrap().activate_hogp(true),
             (false, true) => self.hh.as_mut().unwrap().activate_hogp(false),
             _ => {}
@@ -819,6 +1892,32 @@ impl Bluetooth {
         self.connection_callbacks.remove_callback(id);
     }
 
+    /// Sends the connection quality of |addr| to the connection callbacks once every requested
+    /// sub-value has been reported.
+    fn maybe_finish_connection_quality(&mut self, addr: RawAddress) {
+        if !self.pending_connection_quality.get(&addr).map_or(false, |a| a.is_complete()) {
+            return;
+        }
+
+        let quality = self.pending_connection_quality.remove(&addr).unwrap().quality();
+        let device = self
+            .remote_devices
+            .get(&addr)
+            .map_or(BluetoothDevice::new(addr, String::from("")), |d| d.info.clone());
+        self.connection_callbacks.for_all_callbacks(|callback| {
+            callback.on_connection_quality(device.clone(), quality.clone());
+        });
+    }
+
+    /// Reports the HCI Link Quality of |addr| read for a connection quality request. None if it
+    /// couldn't be read, e.g. on an LE link.
+    pub(crate) fn on_link_quality_read(&mut self, addr: RawAddress, link_quality: Option<u8>) {
+        if let Some(aggregator) = self.pending_connection_quality.get_mut(&addr) {
+            aggregator.on_link_quality(link_quality);
+        }
+        self.maybe_finish_connection_quality(addr);
+    }
+
     fn get_remote_device_property(
         &self,
         device: &BluetoothDevice,
@@ -840,9 +1939,17 @@ impl Bluetooth {
             return Err(());
         };
 
+        let status = self
+            .intf
+            .lock()
+            .unwrap()
+            .set_remote_device_property(&mut device.address.clone(), property.clone());
+        if BtStatus::from(status as u32) != BtStatus::Success {
+            return Err(());
+        }
+
         // TODO: Determine why a callback isn't invoked to do this.
-        remote_device.properties.insert(property_type, property.clone());
-        self.intf.lock().unwrap().set_remote_device_property(&mut device.address.clone(), property);
+        remote_device.properties.insert(property_type, property);
         Ok(())
     }
 
@@ -866,15 +1973,37 @@ impl Bluetooth {
             self.is_connectable = mode;
             return true;
         }
-        self.intf.lock().unwrap().set_scan_mode(if mode {
-            BtScanMode::Connectable
-        } else {
-            BtScanMode::None_
-        });
+        self.set_scan_mode(if mode { BtScanMode::Connectable } else { BtScanMode::None_ });
         self.is_connectable = mode;
         true
     }
 
+    /// Replaces the background job that clears stale found devices with a new one.
+    fn restart_freshness_check(&mut self) {
+        if let Some(h) = self.freshness_check.take() {
+            h.abort()
+        }
+        self.freshness_check = Some(spawn_freshness_check(self.tx.clone()));
+    }
+
+    /// Replaces the timer that resets discoverability with one that fires after
+    /// |discoverable_duration|. The old timer is dropped regardless of the mode, and no timer is
+    /// set if not discoverable or if the duration is 0.
+    fn schedule_discoverable_timeout(&mut self) {
+        if let Some(handle) = self.discoverable_timeout.take() {
+            handle.abort();
+        }
+
+        let duration = self.discoverable_duration;
+        if self.discoverable_mode != BtDiscMode::NonDiscoverable && duration != 0 {
+            let txl = self.tx.clone();
+            self.discoverable_timeout = Some(tokio::spawn(async move {
+                time::sleep(Duration::from_secs(duration.into())).await;
+                let _ = txl.send(Message::AdapterActions(AdapterActions::ResetDiscoverable)).await;
+            }));
+        }
+    }
+
     /// Returns adapter's discoverable mode.
     pub(crate) fn get_discoverable_mode_internal(&self) -> BtDiscMode {
         self.discoverable_mode.clone()
@@ -883,6 +2012,13 @@ impl Bluetooth {
     /// Set the suspend mode for scan mode (connectable/discoverable mode).
     pub(crate) fn set_scan_suspend_mode(&mut self, suspend_mode: SuspendMode) {
         if suspend_mode != self.scan_suspend_mode {
+            if let Some(handle) = self.scan_suspend_watchdog.take() {
+                handle.abort();
+            }
+            if suspend_mode.is_transitional() {
+                self.scan_suspend_watchdog =
+                    Some(self.start_suspend_mode_watchdog(AdapterActions::ScanSuspendModeWatchdog));
+            }
             self.scan_suspend_mode = suspend_mode;
         }
     }
@@ -892,6 +2028,12 @@ impl Bluetooth {
         self.scan_suspend_mode.clone()
     }
 
+    /// Returns true if the adapter is entering or in suspend. Outgoing connections and bonding
+    /// would be torn down by the suspend, so they are rejected until resume starts.
+    fn is_suspend_active(&self) -> bool {
+        is_suspend_active(&self.get_scan_suspend_mode(), &self.get_discovery_suspend_mode())
+    }
+
     /// Enters the suspend mode for scan mode (connectable/discoverable mode).
     pub(crate) fn scan_mode_enter_suspend(&mut self) -> BtStatus {
         if self.get_scan_suspend_mode() != SuspendMode::Normal {
@@ -899,7 +2041,7 @@ impl Bluetooth {
         }
         self.set_scan_suspend_mode(SuspendMode::Suspending);
 
-        self.intf.lock().unwrap().set_scan_mode(BtScanMode::None_);
+        self.set_scan_mode(BtScanMode::None_);
 
         self.set_scan_suspend_mode(SuspendMode::Suspended);
 
@@ -921,7 +2063,7 @@ impl Bluetooth {
                 false => BtScanMode::None_,
             },
         };
-        self.intf.lock().unwrap().set_scan_mode(mode);
+        self.set_scan_mode(mode);
 
         self.set_scan_suspend_mode(SuspendMode::Normal);
 
@@ -996,6 +2138,17 @@ impl Bluetooth {
     }
 
     // TODO(b/328675014): Add BtAddrType and BtTransport parameters
+    fn disconnect_hid_internal(&self, mut addr: RawAddress, reconnect_allowed: bool) -> BtStatus {
+        // TODO(b/328675014): Use BtAddrType and BtTransport from BluetoothDevice instead of
+        // default
+        self.hh.as_ref().unwrap().disconnect(
+            &mut addr,
+            BtAddrType::Public,
+            BtTransport::Auto,
+            reconnect_allowed,
+        )
+    }
+
     pub(crate) fn send_hid_virtual_unplug_internal(&mut self, mut addr: RawAddress) -> BtStatus {
         self.hh.as_mut().unwrap().virtual_unplug(&mut addr, BtAddrType::Public, BtTransport::Auto)
     }
@@ -1029,32 +2182,28 @@ impl Bluetooth {
     }
 
     /// Gets whether a single device is connected with its address.
-    fn get_acl_state_by_addr(&self, addr: &RawAddress) -> bool {
+    pub(crate) fn get_acl_state_by_addr(&self, addr: &RawAddress) -> bool {
         self.remote_devices.get(addr).map_or(false, |d| d.is_connected())
     }
 
+    /// Iterates over the found devices that aren't bonded along with their age relative to |now|.
+    fn found_devices_with_age(&self, now: Instant) -> impl Iterator<Item = FoundDevice> + '_ {
+        self.remote_devices.values().filter(|d| d.bond_state == BtBondState::NotBonded).map(
+            move |d| FoundDevice {
+                device: d.info.clone(),
+                age_ms: d.age(now).as_millis().try_into().unwrap_or(u64::MAX),
+            },
+        )
+    }
+
     /// Check whether remote devices are still fresh. If they're outside the
     /// freshness window, send a notification to clear the device from clients.
     fn trigger_freshness_check(&mut self) {
-        // A remote device is considered fresh if:
-        // * It was last seen less than |FOUND_DEVICE_FRESHNESS| ago.
-        // * It is bonded / bonding (i.e., not NotBonded)
-        // * It is currently connected.
-        fn is_fresh(d: &BluetoothDeviceContext, now: &Instant) -> bool {
-            let fresh_at = d.last_seen + FOUND_DEVICE_FRESHNESS;
-            now < &fresh_at || d.is_connected() || d.bond_state != BtBondState::NotBonded
-        }
-
-        let now = Instant::now();
-        let stale_devices: Vec<BluetoothDevice> = self
-            .remote_devices
-            .values()
-            .filter(|d| !is_fresh(d, &now))
-            .map(|d| d.info.clone())
-            .collect();
-
-        // Retain only devices that are fresh.
-        self.remote_devices.retain(|_, d| is_fresh(d, &now));
+        let stale_devices = remove_stale_devices(
+            &mut self.remote_devices,
+            &mut self.cancelling_devices,
+            Instant::now(),
+        );
 
         for d in stale_devices {
             self.callbacks.for_all_callbacks(|callback| {
@@ -1157,7 +2306,9 @@ impl Bluetooth {
                     &properties,
                 );
 
-                self.remote_devices
+                let adv_data = AdvertisingData::from_bytes(&result.adv_data);
+                let device = self
+                    .remote_devices
                     .entry(device_info.address)
                     .and_modify(|d| {
                         d.update_properties(&properties);
@@ -1169,8 +2320,11 @@ impl Bluetooth {
                         BtAclState::Disconnected,
                         device_info,
                         Instant::now(),
-                        properties,
+                        properties.clone(),
                     ));
+                device.adv_data = Some(adv_data);
+                device.mark_found_via(DiscoverySource::LeScan);
+                device.update_smoothed_rssi(&properties, self.rssi_smoothing_factor);
             }
 
             AdapterActions::ResetDiscoverable => {
@@ -1185,6 +2339,116 @@ impl Bluetooth {
                     }
                 }
             }
+
+            AdapterActions::FinishGracefulDisable => {
+                if self.graceful_disable.is_in_progress() {
+                    warn!("Timed out waiting for devices to disconnect, disabling anyway");
+                }
+                self.finish_graceful_disable();
+            }
+
+            AdapterActions::ScanSuspendModeWatchdog => {
+                self.scan_suspend_watchdog = None;
+                if self.scan_suspend_mode.is_transitional() {
+                    error!(
+                        "Scan suspend mode stuck in {:?}, resetting to Normal",
+                        self.scan_suspend_mode
+                    );
+                    self.set_scan_suspend_mode(SuspendMode::Normal);
+                    self.update_connectable_mode();
+                }
+            }
+
+            AdapterActions::DiscoverySuspendModeWatchdog => {
+                self.discovery_suspend_watchdog = None;
+                if self.discovery_suspend_mode.is_transitional() {
+                    error!(
+                        "Discovery suspend mode stuck in {:?}, resetting to Normal",
+                        self.discovery_suspend_mode
+                    );
+                    self.set_discovery_suspend_mode(SuspendMode::Normal);
+                }
+            }
+
+            AdapterActions::FlushPropertyChanges(addr) => {
+                let types = self.property_changes.take(addr);
+                if let Some(info) = self.remote_devices.get(&addr).map(|d| d.info.clone()) {
+                    self.callbacks.for_all_callbacks(|callback| {
+                        callback.on_device_properties_changed(info.clone(), types.clone());
+                    });
+                }
+            }
+
+            AdapterActions::RetryConnect(addr) => {
+                // The connection may have been cancelled while waiting.
+                match self.remote_devices.get(&addr) {
+                    Some(d) if d.connect_to_new_profiles => {
+                        let device = d.info.clone();
+                        let uuids = self.get_remote_uuids(device.clone());
+                        self.connect_profiles_internal(&uuids, device);
+                    }
+                    _ => self.connect_retry.reset(&addr),
+                }
+            }
+
+            AdapterActions::RemoteUuidsFetchTimeout(addr) => {
+                if !self.pending_uuid_fetches.contains_key(&addr) {
+                    return;
+                }
+                warn!("[{}]: Timed out fetching UUIDs", DisplayAddress(&addr));
+                self.notify_remote_uuids_fetch_failed(addr, BtStatus::Timeout);
+            }
+        }
+    }
+
+    /// Reports a service discovery of |addr| that ended without UUIDs, and stops waiting for it.
+    fn notify_remote_uuids_fetch_failed(&mut self, addr: RawAddress, status: BtStatus) {
+        if let Some(timeout) = self.pending_uuid_fetches.remove(&addr) {
+            timeout.abort();
+        }
+        let info = match self.remote_devices.get(&addr) {
+            Some(d) => d.info.clone(),
+            None => BluetoothDevice::new(addr, "".to_string()),
+        };
+        self.callbacks.for_all_callbacks(|callback| {
+            callback.on_remote_uuids_fetched(info.clone(), vec![], status);
+        });
+    }
+
+    /// Disables the adapter after first asking every connected device to disconnect, so that
+    /// e.g. headsets see a clean disconnection rather than a dropped link. The wait for the
+    /// disconnections is bounded by |GRACEFUL_DISABLE_TIMEOUT|.
+    pub fn disable_graceful(&mut self) -> bool {
+        if self.graceful_disable.is_in_progress() {
+            return true;
+        }
+
+        let connected = self.get_connected_devices();
+        if !self.profiles_ready
+            || !self.graceful_disable.start(connected.iter().map(|d| d.address).collect())
+        {
+            return self.disable();
+        }
+
+        info!("Disconnecting {} device(s) before disabling", connected.len());
+        for device in connected {
+            self.disconnect_all_enabled_profiles(device);
+        }
+
+        let txl = self.tx.clone();
+        self.graceful_disable_timeout = Some(tokio::spawn(async move {
+            time::sleep(GRACEFUL_DISABLE_TIMEOUT).await;
+            let _ = txl.send(Message::AdapterActions(AdapterActions::FinishGracefulDisable)).await;
+        }));
+        true
+    }
+
+    fn finish_graceful_disable(&mut self) {
+        if let Some(handle) = self.graceful_disable_timeout.take() {
+            handle.abort();
+        }
+        if self.graceful_disable.finish() {
+            self.disable();
         }
     }
 
@@ -1206,10 +2470,69 @@ impl Bluetooth {
     /// Set the suspend mode.
     pub fn set_discovery_suspend_mode(&mut self, suspend_mode: SuspendMode) {
         if suspend_mode != self.discovery_suspend_mode {
+            if let Some(handle) = self.discovery_suspend_watchdog.take() {
+                handle.abort();
+            }
+            if suspend_mode.is_transitional() {
+                self.discovery_suspend_watchdog = Some(
+                    self.start_suspend_mode_watchdog(AdapterActions::DiscoverySuspendModeWatchdog),
+                );
+            }
             self.discovery_suspend_mode = suspend_mode;
         }
     }
 
+    /// Sets how long the scan and discovery suspend modes may stay in Suspending or Resuming
+    /// before they are reset to Normal.
+    pub fn set_suspend_mode_watchdog_timeout(&mut self, timeout: Duration) {
+        self.suspend_mode_watchdog_timeout = timeout;
+    }
+
+    /// Sets the logging configuration consulted for verbose-only traces.
+    pub fn set_logging(&mut self, logging: Arc<Mutex<Box<BluetoothLogging>>>) {
+        self.logging = Some(logging);
+    }
+
+    fn is_verbose_logging(&self) -> bool {
+        self.logging
+            .as_ref()
+            .map_or(false, |logging| logging.lock().unwrap().get_log_level() == Level::Verbose)
+    }
+
+    /// Tracks |profile| of |addr| moving to |state|, and traces the transition if verbose logging
+    /// is enabled.
+    fn transition_profile_state(
+        &mut self,
+        addr: RawAddress,
+        profile: Profile,
+        state: ProfileConnectionState,
+    ) {
+        let verbose = self.is_verbose_logging();
+        let Some(device) = self.remote_devices.get_mut(&addr) else {
+            return;
+        };
+        let old_state = device.transition_profile_state(profile, state.clone());
+        if verbose {
+            debug!(
+                "[{}] profile={:?} transition {:?}->{:?}",
+                DisplayAddress(&addr),
+                profile,
+                old_state,
+                state
+            );
+        }
+    }
+
+    /// Sends |action| once the suspend mode watchdog timeout expires.
+    fn start_suspend_mode_watchdog(&self, action: AdapterActions) -> JoinHandle<()> {
+        let txl = self.tx.clone();
+        let timeout = self.suspend_mode_watchdog_timeout;
+        tokio::spawn(async move {
+            time::sleep(timeout).await;
+            let _ = txl.send(Message::AdapterActions(action)).await;
+        })
+    }
+
     /// Gets current suspend mode.
     pub fn get_discovery_suspend_mode(&self) -> SuspendMode {
         self.discovery_suspend_mode.clone()
@@ -1353,6 +2676,48 @@ impl Bluetooth {
         });
     }
 
+    /// Returns the profiles among |uuids| that |connect_profiles_internal| connects, i.e. the HID,
+    /// HOGP and media profiles that are supported and enabled. Media profiles are left out while
+    /// media is unavailable, as their connection requests would be dropped.
+    fn get_profiles_to_connect(&self, uuids: &Vec<Uuid>) -> Vec<Profile> {
+        let mut profiles = vec![];
+        for uuid in uuids.iter() {
+            let p = match UuidHelper::is_known_profile(uuid) {
+                Some(p) => p,
+                None => continue,
+            };
+            if !UuidHelper::is_profile_supported(&p)
+                || self.disabled_profiles.contains(&p)
+                || profiles.contains(&p)
+            {
+                continue;
+            }
+            if !self.is_profile_transport_enabled(&p) {
+                debug!("Transport of {} is disabled, not connecting it", p);
+                continue;
+            }
+
+            match p {
+                Profile::Hid | Profile::Hogp => profiles.push(p),
+                Profile::LeAudio
+                | Profile::VolumeControl
+                | Profile::CoordinatedSet
+                | Profile::A2dpSink
+                | Profile::A2dpSource
+                | Profile::Hfp => {
+                    if self.bluetooth_media.is_none() {
+                        warn!("Media is unavailable, not connecting {}", p);
+                        continue;
+                    }
+                    profiles.push(p);
+                }
+                // We don't connect most profiles
+                _ => (),
+            }
+        }
+        profiles
+    }
+
     /// Connect these profiles of a peripheral device
     fn connect_profiles_internal(&mut self, uuids: &Vec<Uuid>, device: BluetoothDevice) {
         let addr = device.address;
@@ -1368,62 +2733,152 @@ impl Bluetooth {
         let mut has_le_media_profile = false;
         let mut has_classic_media_profile = false;
 
+        for p in self.get_profiles_to_connect(uuids) {
+            match p {
+                Profile::Hid | Profile::Hogp => {
+                    has_supported_profile = true;
+                    // TODO(b/328675014): Use BtAddrType
+                    // and BtTransport from
+                    // BluetoothDevice instead of default
+                    let status = self.hh.as_ref().unwrap().connect(
+                        &mut addr.clone(),
+                        BtAddrType::Public,
+                        BtTransport::Auto,
+                    );
+                    metrics::profile_connection_state_changed(
+                        addr,
+                        p as u32,
+                        BtStatus::Success,
+                        BthhConnectionState::Connecting as u32,
+                    );
+                    self.transition_profile_state(addr, p, ProfileConnectionState::Connecting);
+
+                    if status != BtStatus::Success {
+                        metrics::profile_connection_state_changed(
+                            addr,
+                            p as u32,
+                            status,
+                            BthhConnectionState::Disconnected as u32,
+                        );
+                        self.transition_profile_state(
+                            addr,
+                            p,
+                            ProfileConnectionState::Disconnected,
+                        );
+                    }
+                }
+
+                // TODO(b/317682584): implement policy to connect to LEA, VC, and CSIS
+                Profile::LeAudio | Profile::VolumeControl | Profile::CoordinatedSet => {
+                    // The group connection covers all of them.
+                    self.transition_profile_state(addr, p, ProfileConnectionState::Connecting);
+                    if has_le_media_profile {
+                        continue;
+                    }
+                    has_le_media_profile = true;
+                    let txl = self.tx.clone();
+                    topstack::get_runtime().spawn(async move {
+                        let _ = txl
+                            .send(Message::Media(MediaActions::ConnectLeaGroupByMemberAddress(
+                                addr,
+                            )))
+                            .await;
+                    });
+                }
+
+                Profile::A2dpSink | Profile::A2dpSource | Profile::Hfp => {
+                    has_supported_profile = true;
+                    // The media connection covers all of them.
+                    self.transition_profile_state(addr, p, ProfileConnectionState::Connecting);
+                    if has_classic_media_profile {
+                        continue;
+                    }
+                    has_classic_media_profile = true;
+                    let txl = self.tx.clone();
+                    topstack::get_runtime().spawn(async move {
+                        let _ = txl.send(Message::Media(MediaActions::Connect(addr))).await;
+                    });
+                }
+
+                // We don't connect most profiles
+                _ => (),
+            }
+        }
+
+        // If the device does not have a profile that we are interested in connecting to, resume
+        // discovery now. Other cases will be handled in the ACL connection state or bond state
+        // callbacks.
+        if !has_supported_profile {
+            self.resume_discovery();
+        }
+    }
+
+    /// Disconnects the profiles that |connect_profiles_internal| connects for the given UUIDs.
+    /// |reconnect_allowed| tells HID whether the device may reconnect afterwards.
+    fn disconnect_profiles_internal(
+        &mut self,
+        uuids: &Vec<Uuid>,
+        addr: RawAddress,
+        reconnect_allowed: bool,
+    ) {
+        let mut has_classic_media_profile = false;
+        let mut has_le_media_profile = false;
         for uuid in uuids.iter() {
             match UuidHelper::is_known_profile(uuid) {
                 Some(p) => {
                     if UuidHelper::is_profile_supported(&p) {
                         match p {
                             Profile::Hid | Profile::Hogp => {
-                                has_supported_profile = true;
-                                // TODO(b/328675014): Use BtAddrType
-                                // and BtTransport from
-                                // BluetoothDevice instead of default
-                                let status = self.hh.as_ref().unwrap().connect(
-                                    &mut addr.clone(),
-                                    BtAddrType::Public,
-                                    BtTransport::Auto,
-                                );
-                                metrics::profile_connection_state_changed(
+                                // TODO(b/329837967): Determine
+                                // correct reconnection behavior based
+                                // on device instead of the default
+                                self.disconnect_hid_internal(addr, reconnect_allowed);
+                                self.transition_profile_state(
                                     addr,
-                                    p as u32,
-                                    BtStatus::Success,
-                                    BthhConnectionState::Connecting as u32,
+                                    p,
+                                    ProfileConnectionState::Disconnecting,
                                 );
-
-                                if status != BtStatus::Success {
-                                    metrics::profile_connection_state_changed(
-                                        addr,
-                                        p as u32,
-                                        status,
-                                        BthhConnectionState::Disconnected as u32,
-                                    );
-                                }
                             }
 
-                            // TODO(b/317682584): implement policy to connect to LEA, VC, and CSIS
-                            Profile::LeAudio | Profile::VolumeControl | Profile::CoordinatedSet
-                                if !has_le_media_profile =>
-                            {
+                            // TODO(b/317682584): implement policy to disconnect from LEA, VC, and CSIS
+                            Profile::LeAudio | Profile::VolumeControl | Profile::CoordinatedSet => {
+                                self.transition_profile_state(
+                                    addr,
+                                    p,
+                                    ProfileConnectionState::Disconnecting,
+                                );
+                                if has_le_media_profile {
+                                    continue;
+                                }
                                 has_le_media_profile = true;
                                 let txl = self.tx.clone();
                                 topstack::get_runtime().spawn(async move {
                                     let _ = txl
                                         .send(Message::Media(
-                                            MediaActions::ConnectLeaGroupByMemberAddress(addr),
+                                            MediaActions::DisconnectLeaGroupByMemberAddress(addr),
                                         ))
                                         .await;
                                 });
                             }
 
-                            Profile::A2dpSink | Profile::A2dpSource | Profile::Hfp
-                                if !has_classic_media_profile =>
-                            {
-                                has_supported_profile = true;
+                            Profile::A2dpSink
+                            | Profile::A2dpSource
+                            | Profile::Hfp
+                            | Profile::AvrcpController => {
+                                self.transition_profile_state(
+                                    addr,
+                                    p,
+                                    ProfileConnectionState::Disconnecting,
+                                );
+                                if has_classic_media_profile {
+                                    continue;
+                                }
                                 has_classic_media_profile = true;
                                 let txl = self.tx.clone();
                                 topstack::get_runtime().spawn(async move {
-                                    let _ =
-                                        txl.send(Message::Media(MediaActions::Connect(addr))).await;
+                                    let _ = txl
+                                        .send(Message::Media(MediaActions::Disconnect(addr)))
+                                        .await;
                                 });
                             }
 
@@ -1435,13 +2890,6 @@ impl Bluetooth {
                 _ => {}
             }
         }
-
-        // If the device does not have a profile that we are interested in connecting to, resume
-        // discovery now. Other cases will be handled in the ACL connection state or bond state
-        // callbacks.
-        if !has_supported_profile {
-            self.resume_discovery();
-        }
     }
 
     fn fire_device_connection_or_bonded_state_changed(&self, addr: RawAddress) {
@@ -1470,7 +2918,14 @@ impl Bluetooth {
     }
 }
 
-#[btif_callbacks_dispatcher(dispatch_base_callbacks, BaseCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_base_callbacks,
+    BaseCallbacks,
+    [
+        AdapterState, AdapterProperties, DeviceFound, DiscoveryState, SspRequest, BondState,
+        RemoteDeviceProperties, AclState, LeRandCallback, PinRequest, ThreadEvent,
+    ]
+)]
 #[allow(unused_variables)]
 pub(crate) trait BtifBluetoothCallbacks {
     #[btif_callback(AdapterState)]
@@ -1544,7 +2999,11 @@ pub(crate) trait BtifBluetoothCallbacks {
     fn thread_event(&mut self, event: BtThreadEvent) {}
 }
 
-#[btif_callbacks_dispatcher(dispatch_hid_host_callbacks, HHCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_hid_host_callbacks,
+    HHCallbacks,
+    [ConnectionState, VirtualUnplug, HidInfo, ProtocolMode, IdleTime, GetReport, Handshake]
+)]
 pub(crate) trait BtifHHCallbacks {
     #[btif_callback(ConnectionState)]
     fn connection_state(
@@ -1605,7 +3064,7 @@ pub(crate) trait BtifHHCallbacks {
     );
 }
 
-#[btif_callbacks_dispatcher(dispatch_sdp_callbacks, SdpCallbacks)]
+#[btif_callbacks_dispatcher(dispatch_sdp_callbacks, SdpCallbacks, [SdpSearch])]
 pub(crate) trait BtifSdpCallbacks {
     #[btif_callback(SdpSearch)]
     fn sdp_search(
@@ -1618,6 +3077,16 @@ pub(crate) trait BtifSdpCallbacks {
     );
 }
 
+/// Spawns a task that periodically asks the adapter to clear stale found devices.
+fn spawn_freshness_check(tx: Sender<Message>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            time::sleep(FOUND_DEVICE_FRESHNESS).await;
+            let _ = tx.send(Message::AdapterActions(AdapterActions::DeviceFreshnessCheck)).await;
+        }
+    })
+}
+
 pub fn get_bt_dispatcher(tx: Sender<Message>) -> BaseCallbacksDispatcher {
     BaseCallbacksDispatcher { dispatch: make_message_dispatcher(tx, Message::Base) }
 }
@@ -1643,6 +3112,15 @@ impl BtifBluetoothCallbacks for Bluetooth {
 
                 self.clear_uhid();
 
+                // Nothing to check or time out while the adapter is off. These are spawned again
+                // when the adapter is turned back on or discoverable is set again.
+                if let Some(h) = self.freshness_check.take() {
+                    h.abort()
+                }
+                if let Some(h) = self.discoverable_timeout.take() {
+                    h.abort()
+                }
+
                 // Let the signal notifier know we are turned off.
                 *self.sig_notifier.enabled.lock().unwrap() = false;
                 self.sig_notifier.enabled_notify.notify_all();
@@ -1657,26 +3135,23 @@ impl BtifBluetoothCallbacks for Bluetooth {
 
                 // Also need to manually request some properties
                 self.intf.lock().unwrap().get_adapter_property(BtPropertyType::ClassOfDevice);
-                let mut controller = controller::Controller::new();
+                // The controller interface can only be created once per process.
+                let controller = self.controller.get_or_insert_with(controller::Controller::new);
                 self.le_supported_states = controller.get_ble_supported_states();
                 self.le_local_supported_features = controller.get_ble_local_supported_features();
+                self.supports_br_edr = controller.supports_br_edr();
+                self.supports_ble = controller.supports_ble();
 
                 // Update connectable mode so that disconnected bonded classic device can reconnect
                 self.update_connectable_mode();
 
-                // Spawn a freshness check job in the background.
-                if let Some(h) = self.freshness_check.take() {
-                    h.abort()
+                // The IO capability isn't persisted by the native stack, so apply the configured
+                // one again.
+                if let Some(io_cap) = self.io_capability.clone() {
+                    self.apply_io_capability(io_cap);
                 }
-                let txl = self.tx.clone();
-                self.freshness_check = Some(tokio::spawn(async move {
-                    loop {
-                        time::sleep(FOUND_DEVICE_FRESHNESS).await;
-                        let _ = txl
-                            .send(Message::AdapterActions(AdapterActions::DeviceFreshnessCheck))
-                            .await;
-                    }
-                }));
+
+                self.restart_freshness_check();
 
                 if self.get_wake_allowed_device_bonded() {
                     self.create_uhid_for_suspend_wakesource();
@@ -1757,9 +3232,25 @@ impl BtifBluetoothCallbacks for Bluetooth {
 
     fn device_found(&mut self, _n: i32, properties: Vec<BluetoothProperty>) {
         let device_info = BluetoothDevice::from_properties(&properties);
+        let is_le_only = properties.iter().any(|prop| {
+            matches!(prop, BluetoothProperty::TypeOfDevice(device_type)
+                if *device_type == BtDeviceType::Ble)
+        });
+        if is_le_only && self.is_le_disabled {
+            debug!(
+                "Ignoring LE device {} while LE is disabled",
+                DisplayAddress(&device_info.address)
+            );
+            return;
+        }
         self.check_new_property_and_potentially_connect_profiles(device_info.address, &properties);
 
-        let device_info = self
+        let significant = self
+            .remote_devices
+            .get(&device_info.address)
+            .map_or(true, |d| d.has_significant_changes(&properties));
+
+        let device = self
             .remote_devices
             .entry(device_info.address)
             .and_modify(|d| {
@@ -1773,9 +3264,13 @@ impl BtifBluetoothCallbacks for Bluetooth {
                 device_info,
                 Instant::now(),
                 properties,
-            ))
-            .info
-            .clone();
+            ));
+        device.mark_found_via(DiscoverySource::Inquiry);
+        if !device.should_notify_found(Instant::now(), significant, self.device_found_min_interval)
+        {
+            return;
+        }
+        let device_info = device.info.clone();
 
         self.callbacks.for_all_callbacks(|callback| {
             callback.on_device_found(device_info.clone());
@@ -1795,6 +3290,13 @@ impl BtifBluetoothCallbacks for Bluetooth {
         if self.is_discovering {
             self.discovering_started = Instant::now();
         }
+        let timed_out = !self.is_discovering
+            && is_discovery_timeout(
+                self.discovery_cancel_requested,
+                self.state == BtState::On && !self.scan_mode_guard.is_disabling(),
+                self.discovering_started.elapsed(),
+            );
+        self.discovery_cancel_requested = false;
 
         // Prevent sending out discovering changes or freshness checks when
         // suspending. Clients don't need to be notified of discovery pausing
@@ -1809,6 +3311,11 @@ impl BtifBluetoothCallbacks for Bluetooth {
         self.callbacks.for_all_callbacks(|callback| {
             callback.on_discovering_changed(state == BtDiscoveryState::Started);
         });
+        if timed_out {
+            self.callbacks.for_all_callbacks(|callback| {
+                callback.on_discovery_timeout();
+            });
+        }
 
         // Start or stop BLE scanning based on discovering state
         if let (Some(gatt), Some(scanner_id)) = (self.bluetooth_gatt.as_ref(), self.ble_scanner_id)
@@ -1830,13 +3337,33 @@ impl BtifBluetoothCallbacks for Bluetooth {
     }
 
     fn ssp_request(&mut self, remote_addr: RawAddress, variant: BtSspVariant, passkey: u32) {
-        // Accept the Just-Works pairing that we initiated, reject otherwise.
-        if variant == BtSspVariant::Consent {
-            let initiated_by_us = Some(remote_addr) == self.active_pairing_address;
-            self.set_pairing_confirmation(
-                BluetoothDevice::new(remote_addr, "".to_string()),
-                initiated_by_us,
+        let initiated_by_us = Some(remote_addr) == self.active_pairing_address;
+        let mut auto_reply = self.pairing_policy.auto_ssp_reply(&variant, initiated_by_us);
+        if auto_reply == Some(true)
+            && initiated_by_us
+            && variant == BtSspVariant::Consent
+            && !self.auto_accept_just_works
+        {
+            debug!(
+                "Forwarding SSP consent of {} as auto accept is disabled",
+                DisplayAddress(&remote_addr)
+            );
+            auto_reply = None;
+        }
+        if let Some(accept) = auto_reply {
+            debug!(
+                "{} SSP request {:?} of {} by {:?}",
+                if accept { "Accept" } else { "Reject" },
+                variant,
+                DisplayAddress(&remote_addr),
+                self.pairing_policy
             );
+            if variant == BtSspVariant::PasskeyEntry {
+                // libbluetooth doesn't take SSP replies to passkey entry, so cancel the bonding.
+                self.intf.lock().unwrap().cancel_bond(&remote_addr);
+            } else {
+                self.intf.lock().unwrap().ssp_reply(&remote_addr, variant, accept as u8, 0);
+            }
             return;
         }
 
@@ -1908,6 +3435,12 @@ impl BtifBluetoothCallbacks for Bluetooth {
     ) {
         // Get the device type before the device is potentially deleted.
         let device_type = self.get_remote_type(BluetoothDevice::new(addr, "".to_string()));
+        let was_bonded = self.get_bond_state_by_addr(&addr) == BtBondState::Bonded;
+
+        // Pairing encrypts the link, and unpairing disconnects it.
+        if let Some(d) = self.remote_devices.get_mut(&addr) {
+            d.invalidate_connection_state();
+        }
 
         // Clear the pairing lock if this call corresponds to the
         // active pairing device.
@@ -1938,8 +3471,13 @@ impl BtifBluetoothCallbacks for Bluetooth {
                         vec![],
                     ));
                     let device_info = device.info.clone();
-                    // Since this is a newly bonded device, we also need to trigger SDP on it.
-                    self.fetch_remote_uuids(device_info);
+                    // Since this is a newly bonded device, we also need to trigger SDP on it
+                    // unless its services were already discovered.
+                    if device.needs_uuid_fetch_on_bond(self.force_uuid_fetch_on_bond) {
+                        self.fetch_remote_uuids(device_info);
+                    } else {
+                        debug!("[{}]: Skip fetching resolved UUIDs", DisplayAddress(&addr));
+                    }
                     if self.get_wake_allowed_device_bonded() {
                         self.create_uhid_for_suspend_wakesource();
                     }
@@ -1968,6 +3506,13 @@ impl BtifBluetoothCallbacks for Bluetooth {
             );
         });
 
+        if was_bonded != (bond_state == BtBondState::Bonded) {
+            let bonded_devices = self.get_bonded_devices();
+            self.callbacks.for_all_callbacks(|callback| {
+                callback.on_bonded_devices_changed(bonded_devices.clone());
+            });
+        }
+
         // Don't emit the metrics event if we were cancelling the bond.
         // It is ok to not send the pairing complete event as the server should ignore the dangling
         // pairing attempt event.
@@ -1979,11 +3524,19 @@ impl BtifBluetoothCallbacks for Bluetooth {
 
     fn remote_device_properties_changed(
         &mut self,
-        _status: BtStatus,
+        status: BtStatus,
         addr: RawAddress,
         _num_properties: i32,
         properties: Vec<BluetoothProperty>,
     ) {
+        // Only a failed service discovery is reported with a failure status, and it has no
+        // properties to update.
+        if status != BtStatus::Success && properties.is_empty() {
+            warn!("[{}]: Service discovery failed: {:?}", DisplayAddress(&addr), status);
+            self.notify_remote_uuids_fetch_failed(addr, status);
+            return;
+        }
+
         self.check_new_property_and_potentially_connect_profiles(addr, &properties);
         let device = self.remote_devices.entry(addr).or_insert(BluetoothDeviceContext::new(
             BtBondState::NotBonded,
@@ -1995,18 +3548,45 @@ impl BtifBluetoothCallbacks for Bluetooth {
         ));
 
         device.update_properties(&properties);
+        device.update_smoothed_rssi(&properties, self.rssi_smoothing_factor);
         device.seen();
+        // Discovery results are reported as found devices, so UUIDs reported here come from a
+        // service discovery.
+        let fetched_uuids = properties.iter().find_map(|prop| match prop {
+            BluetoothProperty::Uuids(uuids) => Some(uuids.clone()),
+            _ => None,
+        });
+        if fetched_uuids.is_some() {
+            device.uuids_resolved = true;
+        }
 
         Bluetooth::send_metrics_remote_device_info(device);
 
         let info = device.info.clone();
+        let types: Vec<BtPropertyType> = properties.iter().map(|x| x.get_type()).collect();
 
-        self.callbacks.for_all_callbacks(|callback| {
-            callback.on_device_properties_changed(
-                info.clone(),
-                properties.clone().into_iter().map(|x| x.get_type()).collect(),
-            );
-        });
+        if let Some(uuids) = fetched_uuids {
+            if let Some(timeout) = self.pending_uuid_fetches.remove(&addr) {
+                timeout.abort();
+            }
+            self.callbacks.for_all_callbacks(|callback| {
+                callback.on_remote_uuids_fetched(info.clone(), uuids.clone(), status);
+            });
+        }
+
+        if !self.is_property_change_coalescing {
+            self.callbacks.for_all_callbacks(|callback| {
+                callback.on_device_properties_changed(info.clone(), types.clone());
+            });
+        } else if self.property_changes.add(addr, types) {
+            let txl = self.tx.clone();
+            tokio::spawn(async move {
+                time::sleep(PROPERTY_CHANGE_COALESCING_WINDOW).await;
+                let _ = txl
+                    .send(Message::AdapterActions(AdapterActions::FlushPropertyChanges(addr)))
+                    .await;
+            });
+        }
 
         // Only care about device type property changed on bonded device.
         // If the property change happens during bonding, it will be updated after bonding complete anyway.
@@ -2029,7 +3609,7 @@ impl BtifBluetoothCallbacks for Bluetooth {
         link_type: BtTransport,
         hci_reason: BtHciErrorCode,
         conn_direction: BtConnectionDirection,
-        _acl_handle: u16,
+        acl_handle: u16,
     ) {
         // If discovery was previously paused at connect_all_enabled_profiles to avoid an outgoing
         // ACL connection colliding with an ongoing inquiry, resume it.
@@ -2050,15 +3630,34 @@ impl BtifBluetoothCallbacks for Bluetooth {
                 conn_direction,
                 hci_reason,
             );
+
+            // Only the connections requested with |connect_all_enabled_profiles| are retried.
+            let wants_connection =
+                self.remote_devices.get(&addr).map_or(false, |d| d.connect_to_new_profiles);
+            if wants_connection && self.connect_retry.on_failure(addr, hci_reason) {
+                let backoff = self.connect_retry.backoff;
+                info!("Retrying connection to [{}] in {:?}", DisplayAddress(&addr), backoff);
+                let txl = self.tx.clone();
+                tokio::spawn(async move {
+                    time::sleep(backoff).await;
+                    let _ =
+                        txl.send(Message::AdapterActions(AdapterActions::RetryConnect(addr))).await;
+                });
+                return;
+            }
+            self.connect_retry.reset(&addr);
+
             self.connection_callbacks.for_all_callbacks(|callback| {
                 callback.on_device_connection_failed(
                     BluetoothDevice::new(addr, String::from("")),
                     status,
+                    hci_reason,
                 );
             });
             return;
         }
 
+        let intf = self.intf.clone();
         let device = self.remote_devices.entry(addr).or_insert(BluetoothDeviceContext::new(
             BtBondState::NotBonded,
             BtAclState::Disconnected,
@@ -2075,6 +3674,14 @@ impl BtifBluetoothCallbacks for Bluetooth {
 
         let info = device.info.clone();
         device.acl_reported_transport = link_type;
+        device.set_acl_handle(
+            &link_type,
+            if state == BtAclState::Connected { Some(acl_handle) } else { None },
+        );
+        device.update_connection_state(|| intf.lock().unwrap().get_connection_state(&addr));
+        if state == BtAclState::Connected {
+            device.last_connection_direction = conn_direction.clone();
+        }
 
         metrics::acl_connection_state_changed(
             addr,
@@ -2087,6 +3694,7 @@ impl BtifBluetoothCallbacks for Bluetooth {
 
         match state {
             BtAclState::Connected => {
+                self.connect_retry.reset(&addr);
                 Bluetooth::send_metrics_remote_device_info(device);
                 self.connection_callbacks.for_all_callbacks(|callback| {
                     callback.on_device_connected(info.clone());
@@ -2098,6 +3706,12 @@ impl BtifBluetoothCallbacks for Bluetooth {
                         callback.on_device_disconnected(info.clone());
                     });
                     device.connect_to_new_profiles = false;
+                    // No profile can stay connected without an ACL.
+                    device.profile_states.clear();
+                    self.pending_connection_quality.remove(&addr);
+                    if self.graceful_disable.on_disconnected(&addr) {
+                        self.finish_graceful_disable();
+                    }
                 }
             }
         };
@@ -2131,6 +3745,8 @@ impl BtifBluetoothCallbacks for Bluetooth {
     }
 }
 
+#[derive(RPCProxyStub)]
+#[rpc_proxy(object_id = "BLE Discovery Callback")]
 struct BleDiscoveryCallbacks {
     tx: Sender<Message>,
 }
@@ -2170,10 +3786,32 @@ impl IScannerCallback for BleDiscoveryCallbacks {
     fn on_suspend_mode_change(&mut self, _suspend_mode: SuspendMode) {}
 }
 
-impl RPCProxy for BleDiscoveryCallbacks {
-    fn get_object_id(&self) -> String {
-        "BLE Discovery Callback".to_string()
-    }
+/// Tells the clients that a bond attempt to |addr| ended before it started, as the stack won't
+/// fire a bond state callback for it. Clients waiting on |on_bond_state_changed| would otherwise
+/// never leave their bonding state.
+fn notify_create_bond_failed(
+    callbacks: &mut Callbacks<dyn IBluetoothCallback + Send>,
+    addr: RawAddress,
+    status: BtStatus,
+) {
+    callbacks.for_all_callbacks(|callback| {
+        callback.on_bond_state_changed(
+            status.to_u32().unwrap(),
+            addr,
+            BtBondState::NotBonded.to_u32().unwrap(),
+        );
+    });
+}
+
+/// Returns true if |addr| is the device being bonded, either actively or by a bond request
+/// delayed until discovery stops.
+fn is_bonding_device(
+    addr: &RawAddress,
+    active_pairing_address: &Option<RawAddress>,
+    pending_create_bond: &Option<(BluetoothDevice, BtTransport)>,
+) -> bool {
+    active_pairing_address.as_ref() == Some(addr)
+        || pending_create_bond.as_ref().map_or(false, |(device, _)| device.address == *addr)
 }
 
 // TODO: Add unit tests for this implementation
@@ -2198,16 +3836,22 @@ impl IBluetooth for Bluetooth {
     }
 
     fn init(&mut self, hci_index: i32) -> bool {
+        // The IO capability isn't persisted by the native stack, restore the stored one to apply
+        // it once enabled.
+        self.io_capability = read_io_capability(&self.io_capability_path).ok();
         self.intf.lock().unwrap().initialize(get_bt_dispatcher(self.tx.clone()), hci_index)
     }
 
     fn enable(&mut self) -> bool {
-        self.disabling = false;
+        self.scan_mode_guard.set_disabling(false);
         self.intf.lock().unwrap().enable() == 0
     }
 
     fn disable(&mut self) -> bool {
-        self.disabling = true;
+        // From here on only the first write turning the scan off reaches the controller, e.g.
+        // leaving discoverable mode won't make the adapter connectable again before it is turned
+        // off below.
+        self.scan_mode_guard.set_disabling(true);
         if !self.set_discoverable(BtDiscMode::NonDiscoverable, 0) {
             warn!("set_discoverable failed on disabling");
         }
@@ -2225,22 +3869,45 @@ impl IBluetooth for Bluetooth {
         self.local_address.unwrap_or_default()
     }
 
+    fn get_adapter_property(&self, prop_type: BtPropertyType) -> Option<BluetoothProperty> {
+        self.properties.get(&prop_type).cloned()
+    }
+
     fn get_uuids(&self) -> Vec<Uuid> {
-        match self.properties.get(&BtPropertyType::Uuids) {
-            Some(prop) => match prop {
-                BluetoothProperty::Uuids(uuids) => uuids.clone(),
-                _ => vec![],
-            },
+        match self.get_adapter_property(BtPropertyType::Uuids) {
+            Some(BluetoothProperty::Uuids(uuids)) => uuids,
             _ => vec![],
         }
     }
 
+    fn get_local_le_address_info(&self) -> LeAddressInfo {
+        match &self.controller {
+            Some(controller) if self.state == BtState::On => {
+                let (address, address_type) = controller.read_le_addr();
+                LeAddressInfo { address, address_type }
+            }
+            _ => {
+                LeAddressInfo { address: RawAddress::default(), address_type: BtAddrType::Unknown }
+            }
+        }
+    }
+
+    fn get_controller_info(&self) -> Option<ControllerInfo> {
+        match &self.controller {
+            Some(controller) if self.state == BtState::On => Some(ControllerInfo {
+                hci_version: controller.get_hci_version(),
+                hci_revision: controller.get_hci_revision(),
+                lmp_version: controller.get_lmp_version(),
+                subversion: controller.get_lmp_subversion(),
+                manufacturer: controller.get_manufacturer_name(),
+            }),
+            _ => None,
+        }
+    }
+
     fn get_name(&self) -> String {
-        match self.properties.get(&BtPropertyType::BdName) {
-            Some(prop) => match prop {
-                BluetoothProperty::BdName(name) => name.clone(),
-                _ => String::new(),
-            },
+        match self.get_adapter_property(BtPropertyType::BdName) {
+            Some(BluetoothProperty::BdName(name)) => name,
             _ => String::new(),
         }
     }
@@ -2253,11 +3920,8 @@ impl IBluetooth for Bluetooth {
     }
 
     fn get_bluetooth_class(&self) -> u32 {
-        match self.properties.get(&BtPropertyType::ClassOfDevice) {
-            Some(prop) => match prop {
-                BluetoothProperty::ClassOfDevice(cod) => *cod,
-                _ => 0,
-            },
+        match self.get_adapter_property(BtPropertyType::ClassOfDevice) {
+            Some(BluetoothProperty::ClassOfDevice(cod)) => cod,
             _ => 0,
         }
     }
@@ -2275,8 +3939,6 @@ impl IBluetooth for Bluetooth {
     }
 
     fn set_discoverable(&mut self, mode: BtDiscMode, duration: u32) -> bool {
-        let intf = self.intf.lock().unwrap();
-
         // Checks if the duration is valid.
         if mode == BtDiscMode::LimitedDiscoverable && (duration > 60 || duration == 0) {
             warn!("Invalid duration for setting the device into limited discoverable mode. The valid duration is 1~60 seconds.");
@@ -2295,7 +3957,7 @@ impl IBluetooth for Bluetooth {
                     false => BtScanMode::None_,
                 },
             };
-            intf.set_scan_mode(scan_mode);
+            self.set_scan_mode(scan_mode);
         }
 
         self.callbacks.for_all_callbacks(|callback| {
@@ -2303,41 +3965,42 @@ impl IBluetooth for Bluetooth {
         });
         self.discoverable_mode = mode.clone();
         self.discoverable_duration = duration;
+        self.schedule_discoverable_timeout();
 
-        // The old timer should be overwritten regardless of what the new mode is.
-        if let Some(handle) = self.discoverable_timeout.take() {
-            handle.abort();
-        }
+        true
+    }
 
-        if mode != BtDiscMode::NonDiscoverable && duration != 0 {
-            let txl = self.tx.clone();
-            self.discoverable_timeout = Some(tokio::spawn(async move {
-                time::sleep(Duration::from_secs(duration.into())).await;
-                let _ = txl.send(Message::AdapterActions(AdapterActions::ResetDiscoverable)).await;
-            }));
+    fn set_discoverable_timeout(&mut self, duration: u32) -> bool {
+        match self.discoverable_mode {
+            BtDiscMode::NonDiscoverable => {
+                warn!("Can't set the discoverable timeout while not discoverable");
+                return false;
+            }
+            BtDiscMode::LimitedDiscoverable if duration > 60 || duration == 0 => {
+                warn!("Invalid duration for limited discoverable mode. The valid duration is 1~60 seconds.");
+                return false;
+            }
+            _ => (),
         }
 
+        self.discoverable_duration = duration;
+        self.schedule_discoverable_timeout();
+
         true
     }
 
     fn is_multi_advertisement_supported(&self) -> bool {
-        match self.properties.get(&BtPropertyType::LocalLeFeatures) {
-            Some(prop) => match prop {
-                BluetoothProperty::LocalLeFeatures(llf) => {
-                    llf.max_adv_instance >= MIN_ADV_INSTANCES_FOR_MULTI_ADV
-                }
-                _ => false,
-            },
+        match self.get_adapter_property(BtPropertyType::LocalLeFeatures) {
+            Some(BluetoothProperty::LocalLeFeatures(llf)) => {
+                llf.max_adv_instance >= MIN_ADV_INSTANCES_FOR_MULTI_ADV
+            }
             _ => false,
         }
     }
 
     fn is_le_extended_advertising_supported(&self) -> bool {
-        match self.properties.get(&BtPropertyType::LocalLeFeatures) {
-            Some(prop) => match prop {
-                BluetoothProperty::LocalLeFeatures(llf) => llf.le_extended_advertising_supported,
-                _ => false,
-            },
+        match self.get_adapter_property(BtPropertyType::LocalLeFeatures) {
+            Some(BluetoothProperty::LocalLeFeatures(llf)) => llf.le_extended_advertising_supported,
             _ => false,
         }
     }
@@ -2363,6 +4026,12 @@ impl IBluetooth for Bluetooth {
             return false;
         }
 
+        // Discovery always runs an inquiry, which is a BR/EDR procedure.
+        if self.is_br_edr_disabled {
+            log::warn!("start_discovery is not allowed while BR/EDR is disabled.");
+            return false;
+        }
+
         self.intf.lock().unwrap().start_discovery() == 0
     }
 
@@ -2388,7 +4057,11 @@ impl IBluetooth for Bluetooth {
             return false;
         }
 
-        self.intf.lock().unwrap().cancel_discovery() == 0
+        let cancelled = self.intf.lock().unwrap().cancel_discovery() == 0;
+        if cancelled {
+            self.discovery_cancel_requested = true;
+        }
+        cancelled
     }
 
     fn is_discovering(&self) -> bool {
@@ -2409,12 +4082,32 @@ impl IBluetooth for Bluetooth {
     }
 
     fn create_bond(&mut self, device: BluetoothDevice, transport: BtTransport) -> BtStatus {
+        let transport = match self.resolve_transport(transport) {
+            Some(transport) => transport,
+            None => {
+                warn!(
+                    "Bonding requested for {} over disabled transport {:?}, rejecting",
+                    DisplayAddress(&device.address),
+                    transport
+                );
+                notify_create_bond_failed(
+                    &mut self.callbacks,
+                    device.address,
+                    BtStatus::Unsupported,
+                );
+                return BtStatus::Unsupported;
+            }
+        };
         let device_type = match transport {
             BtTransport::Bredr => BtDeviceType::Bredr,
             BtTransport::Le => BtDeviceType::Ble,
             _ => self.get_remote_type(device.clone()),
         };
         let address = device.address;
+        // A repeated request for the device being bonded must not tell the clients that its
+        // ongoing bond failed.
+        let notify_busy =
+            !is_bonding_device(&address, &self.active_pairing_address, &self.pending_create_bond);
 
         if let Some(active_address) = self.active_pairing_address {
             warn!(
@@ -2422,11 +4115,25 @@ impl IBluetooth for Bluetooth {
                 DisplayAddress(&address),
                 DisplayAddress(&active_address)
             );
+            if notify_busy {
+                notify_create_bond_failed(&mut self.callbacks, address, BtStatus::Busy);
+            }
             return BtStatus::Busy;
         }
 
         if self.pending_create_bond.is_some() {
             warn!("Delayed CreateBond is still pending");
+            if notify_busy {
+                notify_create_bond_failed(&mut self.callbacks, address, BtStatus::Busy);
+            }
+            return BtStatus::Busy;
+        }
+
+        if self.is_suspend_active() {
+            warn!("Bonding requested for {} while suspended, rejecting", DisplayAddress(&address));
+            if notify_busy {
+                notify_create_bond_failed(&mut self.callbacks, address, BtStatus::Busy);
+            }
             return BtStatus::Busy;
         }
 
@@ -2453,14 +4160,15 @@ impl IBluetooth for Bluetooth {
         let status = self.intf.lock().unwrap().create_bond(&address, transport);
 
         if status != 0 {
-            metrics::bond_state_changed(
-                address,
-                device_type,
-                BtStatus::from(status as u32),
-                BtBondState::NotBonded,
-                0,
-            );
-            return BtStatus::from(status as u32);
+            let status = BtStatus::from(status as u32);
+            metrics::bond_state_changed(address, device_type, status, BtBondState::NotBonded, 0);
+
+            // The stack doesn't report a bond state for a request it rejected right away, so
+            // finish the attempt here like |bond_state| would.
+            self.active_pairing_address = None;
+            self.resume_discovery();
+            notify_create_bond_failed(&mut self.callbacks, address, status);
+            return status;
         }
 
         // Creating bond automatically create ACL connection as well, therefore also log metrics
@@ -2507,6 +4215,32 @@ impl IBluetooth for Bluetooth {
         true
     }
 
+    fn forget_device(&mut self, device: BluetoothDevice) -> bool {
+        let address = device.address;
+        match self.remote_devices.get(&address) {
+            None => {
+                warn!("Can't forget unknown device {}", DisplayAddress(&address));
+                return false;
+            }
+            Some(d) if d.bond_state != BtBondState::NotBonded || d.is_connected() => {
+                warn!("Can't forget bonded or connected device {}", DisplayAddress(&address));
+                return false;
+            }
+            Some(_) => {}
+        }
+
+        let d = self.remote_devices.remove(&address).unwrap();
+        self.cancelling_devices.remove(&address);
+        if let Some(timeout) = self.pending_uuid_fetches.remove(&address) {
+            timeout.abort();
+        }
+
+        self.callbacks.for_all_callbacks(|callback| {
+            callback.on_device_cleared(d.info.clone());
+        });
+        true
+    }
+
     fn get_bonded_devices(&self) -> Vec<BluetoothDevice> {
         self.remote_devices
             .values()
@@ -2520,6 +4254,10 @@ impl IBluetooth for Bluetooth {
             .collect()
     }
 
+    fn get_found_devices_with_age(&self) -> Vec<FoundDevice> {
+        self.found_devices_with_age(Instant::now()).collect()
+    }
+
     fn get_bond_state(&self, device: BluetoothDevice) -> BtBondState {
         self.get_bond_state_by_addr(&device.address)
     }
@@ -2567,6 +4305,43 @@ impl IBluetooth for Bluetooth {
         ) == 0
     }
 
+    fn set_pairing_policy(&mut self, policy: PairingPolicy) {
+        info!("Pairing policy set to {:?}", policy);
+        self.pairing_policy = policy;
+    }
+
+    fn set_force_uuid_fetch_on_bond(&mut self, force: bool) {
+        self.force_uuid_fetch_on_bond = force;
+    }
+
+    fn set_property_change_coalescing(&mut self, enabled: bool) {
+        self.is_property_change_coalescing = enabled;
+    }
+
+    fn set_connect_retry_policy(&mut self, max_retries: u32, backoff_ms: u32) {
+        info!("Connect retry policy set to {} retries after {}ms", max_retries, backoff_ms);
+        self.connect_retry.max_retries = max_retries;
+        self.connect_retry.backoff = Duration::from_millis(backoff_ms.into());
+    }
+
+    fn set_io_capability(&mut self, io_cap: BtIoCap) -> bool {
+        if io_cap >= BtIoCap::Max {
+            warn!("Invalid IO capability {:?}", io_cap);
+            return false;
+        }
+
+        info!("IO capability set to {:?}", io_cap);
+        self.io_capability = Some(io_cap.clone());
+        if let Err(e) = write_io_capability(&self.io_capability_path, &io_cap) {
+            warn!("Failed to store IO capability {:?}: {}", io_cap, e);
+        }
+        if self.state != BtState::On {
+            // Applied once the adapter is enabled.
+            return true;
+        }
+        self.apply_io_capability(io_cap)
+    }
+
     fn get_remote_name(&self, device: BluetoothDevice) -> String {
         match self.get_remote_device_property(&device, &BtPropertyType::BdName) {
             Some(BluetoothProperty::BdName(name)) => name.clone(),
@@ -2575,10 +4350,9 @@ impl IBluetooth for Bluetooth {
     }
 
     fn get_remote_type(&self, device: BluetoothDevice) -> BtDeviceType {
-        match self.get_remote_device_property(&device, &BtPropertyType::TypeOfDevice) {
-            Some(BluetoothProperty::TypeOfDevice(device_type)) => device_type,
-            _ => BtDeviceType::Unknown,
-        }
+        self.remote_devices
+            .get(&device.address)
+            .map_or(BtDeviceType::Unknown, |d| d.get_device_type())
     }
 
     fn get_remote_alias(&self, device: BluetoothDevice) -> String {
@@ -2588,12 +4362,38 @@ impl IBluetooth for Bluetooth {
         }
     }
 
-    fn set_remote_alias(&mut self, device: BluetoothDevice, new_alias: String) {
-        let _ = self.set_remote_device_property(
+    fn set_remote_alias(&mut self, device: BluetoothDevice, new_alias: String) -> bool {
+        self.set_remote_device_property(
             &device,
             BtPropertyType::RemoteFriendlyName,
             BluetoothProperty::RemoteFriendlyName(new_alias),
-        );
+        )
+        .is_ok()
+    }
+
+    fn set_remote_device_type_override(
+        &mut self,
+        device: BluetoothDevice,
+        device_type: BtDeviceType,
+    ) -> bool {
+        match self.remote_devices.get_mut(&device.address) {
+            Some(d) => {
+                info!(
+                    "[{}]: Overriding device type to {:?}",
+                    DisplayAddress(&device.address),
+                    device_type
+                );
+                d.device_type_override = Some(device_type);
+                true
+            }
+            None => {
+                warn!(
+                    "[{}]: Can't override device type of unknown device",
+                    DisplayAddress(&device.address)
+                );
+                false
+            }
+        }
     }
 
     fn get_remote_class(&self, device: BluetoothDevice) -> u32 {
@@ -2615,16 +4415,42 @@ impl IBluetooth for Bluetooth {
     }
 
     fn get_remote_wake_allowed(&self, device: BluetoothDevice) -> bool {
-        // Wake is allowed if the device supports HIDP or HOGP only.
-        match self.get_remote_device_property(&device, &BtPropertyType::Uuids) {
-            Some(BluetoothProperty::Uuids(uuids)) => {
-                return uuids.iter().any(|&uuid| {
-                    UuidHelper::is_known_profile(&uuid).map_or(false, |profile| {
-                        profile == Profile::Hid || profile == Profile::Hogp
-                    })
-                });
+        self.remote_devices.get(&device.address).map_or(false, |d| d.is_wake_allowed())
+    }
+
+    fn get_remote_connection_direction(&self, device: BluetoothDevice) -> BtConnectionDirection {
+        self.remote_devices
+            .get(&device.address)
+            .map_or(BtConnectionDirection::Unknown, |d| d.last_connection_direction.clone())
+    }
+
+    fn get_remote_discovery_sources(&self, device: BluetoothDevice) -> Vec<DiscoverySource> {
+        self.remote_devices
+            .get(&device.address)
+            .map_or(vec![], |d| DiscoverySource::from_bits(d.found_via))
+    }
+
+    fn get_connection_handles(&self, device: BluetoothDevice) -> Vec<u16> {
+        self.remote_devices.get(&device.address).map_or(vec![], |d| d.connection_handles())
+    }
+
+    fn set_remote_wake_allowed(&mut self, device: BluetoothDevice, allowed: bool) {
+        match self.remote_devices.get_mut(&device.address) {
+            Some(d) => d.wake_allowed_override = Some(allowed),
+            None => {
+                warn!(
+                    "[{}]: Can't set wake allowed of unknown device",
+                    DisplayAddress(&device.address)
+                );
+                return;
             }
-            _ => false,
+        }
+
+        // The suspend wakeup source is only needed while a bonded device can wake the system.
+        if self.get_wake_allowed_device_bonded() {
+            self.create_uhid_for_suspend_wakesource();
+        } else {
+            self.clear_uhid();
         }
     }
 
@@ -2635,18 +4461,47 @@ impl IBluetooth for Bluetooth {
         }
     }
 
-    fn get_remote_address_type(&self, device: BluetoothDevice) -> BtAddrType {
-        match self.get_remote_device_property(&device, &BtPropertyType::RemoteAddrType) {
-            Some(BluetoothProperty::RemoteAddrType(addr_type)) => addr_type,
-            _ => BtAddrType::Unknown,
-        }
+    fn get_remote_address_type(&self, device: BluetoothDevice) -> BtAddrType {
+        match self.get_remote_device_property(&device, &BtPropertyType::RemoteAddrType) {
+            Some(BluetoothProperty::RemoteAddrType(addr_type)) => addr_type,
+            _ => BtAddrType::Unknown,
+        }
+    }
+
+    fn get_remote_rssi(&self, device: BluetoothDevice) -> i8 {
+        match self.get_remote_device_property(&device, &BtPropertyType::RemoteRssi) {
+            Some(BluetoothProperty::RemoteRssi(rssi)) => rssi,
+            _ => INVALID_RSSI,
+        }
+    }
+
+    fn get_remote_rssi_smoothed(&self, device: BluetoothDevice) -> i8 {
+        self.remote_devices.get(&device.address).map_or(INVALID_RSSI, |d| d.smoothed_rssi.get())
+    }
+
+    fn set_rssi_smoothing_factor(&mut self, factor: f64) -> bool {
+        if !(factor > 0.0 && factor <= 1.0) {
+            return false;
+        }
+        self.rssi_smoothing_factor = factor;
+        true
+    }
+
+    fn set_device_found_min_interval(&mut self, interval_ms: u32) {
+        self.device_found_min_interval = Duration::from_millis(interval_ms.into());
+    }
+
+    fn get_remote_all_properties(&self, device: BluetoothDevice) -> Vec<BluetoothProperty> {
+        self.remote_devices
+            .get(&device.address)
+            .map_or(vec![], |d| d.properties.values().cloned().collect())
     }
 
-    fn get_remote_rssi(&self, device: BluetoothDevice) -> i8 {
-        match self.get_remote_device_property(&device, &BtPropertyType::RemoteRssi) {
-            Some(BluetoothProperty::RemoteRssi(rssi)) => rssi,
-            _ => INVALID_RSSI,
-        }
+    fn get_remote_advertising_data(&self, device: BluetoothDevice) -> AdvertisingData {
+        self.remote_devices
+            .get(&device.address)
+            .and_then(|d| d.adv_data.clone())
+            .unwrap_or_default()
     }
 
     fn get_connected_devices(&self) -> Vec<BluetoothDevice> {
@@ -2657,6 +4512,12 @@ impl IBluetooth for Bluetooth {
     }
 
     fn get_connection_state(&self, device: BluetoothDevice) -> BtConnectionState {
+        if let Some(state) =
+            self.remote_devices.get(&device.address).and_then(|d| d.cached_connection_state())
+        {
+            return state;
+        }
+
         // The underlying api adds whether this is ENCRYPTED_BREDR or ENCRYPTED_LE.
         // As long as it is non-zero, it is connected.
         self.intf.lock().unwrap().get_connection_state(&device.address)
@@ -2692,7 +4553,7 @@ impl IBluetooth for Bluetooth {
         }
     }
 
-    fn fetch_remote_uuids(&self, remote_device: BluetoothDevice) -> bool {
+    fn fetch_remote_uuids(&mut self, remote_device: BluetoothDevice) -> bool {
         let Some(device) = self.remote_devices.get(&remote_device.address) else {
             warn!("Won't fetch UUIDs on unknown device");
             return false;
@@ -2704,8 +4565,22 @@ impl IBluetooth for Bluetooth {
             _ => device.acl_reported_transport,
         };
 
-        self.intf.lock().unwrap().get_remote_services(&mut device.info.address.clone(), transport)
-            == 0
+        let mut addr = device.info.address;
+        if self.intf.lock().unwrap().get_remote_services(&mut addr, transport) != 0 {
+            return false;
+        }
+
+        let txl = self.tx.clone();
+        let timeout = tokio::spawn(async move {
+            time::sleep(REMOTE_UUIDS_FETCH_TIMEOUT).await;
+            let _ = txl
+                .send(Message::AdapterActions(AdapterActions::RemoteUuidsFetchTimeout(addr)))
+                .await;
+        });
+        if let Some(previous) = self.pending_uuid_fetches.insert(addr, timeout) {
+            previous.abort();
+        }
+        true
     }
 
     fn sdp_search(&self, mut device: BluetoothDevice, uuid: Uuid) -> bool {
@@ -2740,6 +4615,17 @@ impl IBluetooth for Bluetooth {
             return BtStatus::NotReady;
         }
 
+        if self.is_suspend_active() {
+            warn!(
+                "Connection requested for {} while suspended, rejecting",
+                DisplayAddress(&device.address)
+            );
+            return BtStatus::Busy;
+        }
+
+        // A new request starts over with all the retries of the policy.
+        self.connect_retry.reset(&device.address);
+
         // Check all remote uuids to see if they match enabled profiles and connect them.
         let uuids = self.get_remote_uuids(device.clone());
         self.connect_profiles_internal(&uuids, device.clone());
@@ -2752,80 +4638,74 @@ impl IBluetooth for Bluetooth {
         BtStatus::Success
     }
 
-    fn disconnect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
+    fn get_connectable_profiles(&self, device: BluetoothDevice) -> Vec<Profile> {
+        let uuids = self.get_remote_uuids(device);
+        self.get_profiles_to_connect(&uuids)
+    }
+
+    fn cancel_connect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
         if !self.profiles_ready {
             return false;
         }
         let addr = device.address;
+        let uuids = self.get_remote_uuids(device);
+        let connected_media = self.bluetooth_media.as_ref().map_or(HashSet::new(), |media| {
+            media.lock().unwrap().get_connected_media_profiles(&addr)
+        });
+        let connecting: Vec<Uuid> = match self.remote_devices.get_mut(&addr) {
+            Some(d) => {
+                d.connect_to_new_profiles = false;
+                // Profiles that already connected are left alone.
+                d.set_media_profiles_connected(&connected_media);
+                d.cancellable_profile_uuids(uuids)
+            }
+            None => return false,
+        };
 
-        // log ACL disconnection attempt if it's not already disconnected.
-        if self.get_acl_state_by_addr(&addr) {
-            metrics::acl_connect_attempt(addr, BtAclState::Disconnected);
+        // The user cancelled the connection, so HID must not reconnect on its own.
+        self.disconnect_profiles_internal(&connecting, addr, /*reconnect_allowed=*/ false);
+
+        // Discovery may have been paused for the outgoing connection. A pending bond keeps it
+        // paused until the bond is created.
+        if self.is_discovery_paused && self.pending_create_bond.is_none() {
+            self.resume_discovery();
         }
+        true
+    }
 
-        let uuids = self.get_remote_uuids(device.clone());
-        let mut has_classic_media_profile = false;
-        let mut has_le_media_profile = false;
-        for uuid in uuids.iter() {
-            match UuidHelper::is_known_profile(uuid) {
-                Some(p) => {
-                    if UuidHelper::is_profile_supported(&p) {
-                        match p {
-                            Profile::Hid | Profile::Hogp => {
-                                // TODO(b/328675014): Use BtAddrType
-                                // and BtTransport from
-                                // BluetoothDevice instead of default
+    fn set_profile_enabled(&mut self, profile: Profile, enabled: bool) -> bool {
+        if !matches!(profile, Profile::Hid | Profile::Hogp) {
+            warn!("Profile {} can't be enabled or disabled on its own", profile);
+            return false;
+        }
 
-                                // TODO(b/329837967): Determine
-                                // correct reconnection behavior based
-                                // on device instead of the default
-                                self.hh.as_ref().unwrap().disconnect(
-                                    &mut addr.clone(),
-                                    BtAddrType::Public,
-                                    BtTransport::Auto,
-                                    /*reconnect_allowed=*/ true,
-                                );
-                            }
+        let changed = if enabled {
+            self.disabled_profiles.remove(&profile)
+        } else {
+            self.disabled_profiles.insert(profile)
+        };
+        info!("Profile {} is {}", profile, if enabled { "enabled" } else { "disabled" });
 
-                            // TODO(b/317682584): implement policy to disconnect from LEA, VC, and CSIS
-                            Profile::LeAudio | Profile::VolumeControl | Profile::CoordinatedSet
-                                if !has_le_media_profile =>
-                            {
-                                has_le_media_profile = true;
-                                let txl = self.tx.clone();
-                                topstack::get_runtime().spawn(async move {
-                                    let _ = txl
-                                        .send(Message::Media(
-                                            MediaActions::DisconnectLeaGroupByMemberAddress(addr),
-                                        ))
-                                        .await;
-                                });
-                            }
+        if changed && self.hh.is_some() {
+            self.update_hid_activation();
+        }
+        true
+    }
 
-                            Profile::A2dpSink
-                            | Profile::A2dpSource
-                            | Profile::Hfp
-                            | Profile::AvrcpController
-                                if !has_classic_media_profile =>
-                            {
-                                has_classic_media_profile = true;
-                                let txl = self.tx.clone();
-                                topstack::get_runtime().spawn(async move {
-                                    let _ = txl
-                                        .send(Message::Media(MediaActions::Disconnect(addr)))
-                                        .await;
-                                });
-                            }
+    fn disconnect_all_enabled_profiles(&mut self, device: BluetoothDevice) -> bool {
+        if !self.profiles_ready {
+            return false;
+        }
+        let addr = device.address;
 
-                            // We don't connect most profiles
-                            _ => (),
-                        }
-                    }
-                }
-                _ => {}
-            }
+        // log ACL disconnection attempt if it's not already disconnected.
+        if self.get_acl_state_by_addr(&addr) {
+            metrics::acl_connect_attempt(addr, BtAclState::Disconnected);
         }
 
+        let uuids = self.get_remote_uuids(device.clone());
+        self.disconnect_profiles_internal(&uuids, addr, /*reconnect_allowed=*/ true);
+
         // Disconnect all socket connections
         let txl = self.tx.clone();
         topstack::get_runtime().spawn(async move {
@@ -2846,6 +4726,13 @@ impl IBluetooth for Bluetooth {
         true
     }
 
+    fn disconnect_hid(&mut self, device: BluetoothDevice, reconnect_allowed: bool) -> BtStatus {
+        if !self.profiles_ready || self.hh.is_none() {
+            return BtStatus::NotReady;
+        }
+        self.disconnect_hid_internal(device.address, reconnect_allowed)
+    }
+
     fn is_wbs_supported(&self) -> bool {
         self.intf.lock().unwrap().get_wbs_supported()
     }
@@ -2855,20 +4742,7 @@ impl IBluetooth for Bluetooth {
     }
 
     fn get_supported_roles(&self) -> Vec<BtAdapterRole> {
-        let mut roles: Vec<BtAdapterRole> = vec![];
-
-        // See Core 5.3, Vol 4, Part E, 7.8.27 for detailed state information
-        if self.le_supported_states >> 35 & 1 == 1u64 {
-            roles.push(BtAdapterRole::Central);
-        }
-        if self.le_supported_states >> 38 & 1 == 1u64 {
-            roles.push(BtAdapterRole::Peripheral);
-        }
-        if self.le_supported_states >> 28 & 1 == 1u64 {
-            roles.push(BtAdapterRole::CentralPeripheral);
-        }
-
-        roles
+        BtAdapterRole::from_le_supported_states(self.le_supported_states)
     }
 
     fn is_coding_format_supported(&self, coding_format: EscoCodingFormat) -> bool {
@@ -2876,9 +4750,11 @@ impl IBluetooth for Bluetooth {
     }
 
     fn is_le_audio_supported(&self) -> bool {
-        // We determine LE Audio support by checking CIS Central support
-        // See Core 5.3, Vol 6, 4.6 FEATURE SUPPORT
-        self.le_local_supported_features >> 28 & 1 == 1u64
+        self.get_le_audio_capabilities().is_le_audio_supported()
+    }
+
+    fn get_le_audio_capabilities(&self) -> LeAudioCaps {
+        LeAudioCaps::from_le_features(self.le_local_supported_features)
     }
 
     fn is_dual_mode_audio_sink_device(&self, device: BluetoothDevice) -> bool {
@@ -2907,18 +4783,55 @@ impl IBluetooth for Bluetooth {
         })
     }
 
+    fn get_connection_quality(&mut self, device: BluetoothDevice) -> bool {
+        let addr = device.address;
+        if !self.remote_devices.get(&addr).map_or(false, |d| d.is_connected()) {
+            warn!("Can't get connection quality of {}: not connected", DisplayAddress(&addr));
+            return false;
+        }
+
+        // A request for the same device is already in flight; its result goes to all callbacks.
+        if self.pending_connection_quality.contains_key(&addr) {
+            return true;
+        }
+
+        // The RSSI is cached, the link quality is read from the controller and reported back
+        // through |on_link_quality_read|.
+        self.pending_connection_quality.insert(addr, ConnectionQualityAggregator::new(true, true));
+        let rssi = self.get_remote_rssi(device);
+        if let Some(aggregator) = self.pending_connection_quality.get_mut(&addr) {
+            aggregator.on_rssi(rssi);
+        }
+
+        let txl = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = txl.send(Message::ReadLinkQuality(addr)).await;
+        });
+        true
+    }
+
     fn get_dumpsys(&self) -> String {
-        OpenOptions::new()
-            .write(true)
-            .create(true)
-            .truncate(true)
-            .open(DUMPSYS_LOG)
-            .and_then(|file| {
-                let fd = file.as_raw_fd();
-                self.intf.lock().unwrap().dump(fd);
-                Ok(format!("dump to {}", DUMPSYS_LOG))
-            })
-            .unwrap_or_default()
+        // Dump into an anonymous in-memory file so concurrent dumps never share a path.
+        let fd = match memfd_create(
+            &CString::new("bt_dumpsys").unwrap(),
+            MemFdCreateFlag::MFD_CLOEXEC,
+        ) {
+            Ok(fd) => fd,
+            Err(e) => {
+                error!("Failed to create dumpsys file: {}", e);
+                return String::new();
+            }
+        };
+        let mut file = unsafe { File::from_raw_fd(fd) };
+        self.intf.lock().unwrap().dump(file.as_raw_fd());
+
+        let mut contents = vec![];
+        if let Err(e) = file.seek(SeekFrom::Start(0)).and_then(|_| file.read_to_end(&mut contents))
+        {
+            error!("Failed to read dumpsys: {}", e);
+            return String::new();
+        }
+        String::from_utf8_lossy(&contents).into_owned()
     }
 }
 
@@ -3006,6 +4919,7 @@ impl BtifHHCallbacks for Bluetooth {
 
         let tx = self.tx.clone();
         self.remote_devices.entry(address).and_modify(|context| {
+            context.set_hid_profile_state(profile, &state);
             if context.is_hh_connected && state != BthhConnectionState::Connected {
                 tokio::spawn(async move {
                     let _ = tx.send(Message::ProfileDisconnected(address)).await;
@@ -3156,3 +5070,559 @@ impl IBluetoothQALegacy for Bluetooth {
         self.send_hid_data_internal(addr, data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_test_device(bond: BtBondState, acl: BtAclState) -> BluetoothDeviceContext {
+        BluetoothDeviceContext::new(
+            bond,
+            acl,
+            BtAclState::Disconnected,
+            BluetoothDevice::new(
+                RawAddress::from_string("11:22:33:44:55:66").unwrap(),
+                String::from("Test"),
+            ),
+            Instant::now(),
+            vec![],
+        )
+    }
+
+    #[test]
+    fn test_io_capability_stored() {
+        let path = std::env::temp_dir().join(format!("io_capability_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // The stack default is used until an IO capability is configured.
+        assert!(read_io_capability(path).is_err());
+
+        // A restarted stack reads back the configured IO capability.
+        write_io_capability(path, &BtIoCap::None_).unwrap();
+        assert_eq!(read_io_capability(path).unwrap(), BtIoCap::None_);
+
+        std::fs::write(path, "{\"io_capability\": 5}").unwrap();
+        assert!(read_io_capability(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_advertising_data_from_bytes() {
+        assert_eq!(AdvertisingData::from_bytes(&[]), AdvertisingData::default());
+
+        let mut bytes: Vec<u8> = vec![];
+        // Flags: LE General Discoverable, BR/EDR not supported.
+        bytes.extend([0x02, 0x01, 0x06]);
+        // TX power level: -4 dBm.
+        bytes.extend([0x02, 0x0a, 0xfc]);
+        // Complete list of 16-bit service UUIDs: Battery Service.
+        bytes.extend([0x03, 0x03, 0x0f, 0x18]);
+        // Service data of 16-bit UUID: Battery Service, level 100.
+        bytes.extend([0x04, 0x16, 0x0f, 0x18, 0x64]);
+        // Manufacturer specific data.
+        bytes.extend([0x05, 0xff, 0x00, 0xe0, 0x01, 0x02]);
+        // Appearance: Generic HID.
+        bytes.extend([0x03, 0x19, 0xc0, 0x03]);
+        let adv_data = AdvertisingData::from_bytes(&bytes);
+
+        let battery_uuid = Uuid::from_string("0000180f-0000-1000-8000-00805f9b34fb").unwrap();
+        assert_eq!(
+            adv_data,
+            AdvertisingData {
+                flags: 0x06,
+                tx_power: Some(-4),
+                service_uuids: vec![battery_uuid],
+                service_data: HashMap::from([(battery_uuid.to_string(), vec![0x64])]),
+                manufacturer_data: HashMap::from([(0x00e0, vec![0x01, 0x02])]),
+                appearance: Some(0x03c0),
+            }
+        );
+    }
+
+    #[test]
+    fn test_connect_retry_policy() {
+        let addr = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let mut retry = ConnectRetry::default();
+
+        // Retrying is off by default.
+        assert!(!retry.on_failure(addr, HCI_ERR_PAGE_TIMEOUT));
+
+        retry.max_retries = 2;
+        assert!(retry.on_failure(addr, HCI_ERR_PAGE_TIMEOUT));
+        assert!(retry.on_failure(addr, HCI_ERR_CONNECTION_TIMEOUT));
+        // Retries are exhausted, then the count starts over.
+        assert!(!retry.on_failure(addr, HCI_ERR_PAGE_TIMEOUT));
+        assert!(retry.on_failure(addr, HCI_ERR_PAGE_TIMEOUT));
+
+        // Authentication failures are never retried.
+        retry.reset(&addr);
+        assert!(!retry.on_failure(addr, 0x05));
+    }
+
+    #[test]
+    fn test_profile_state_transitions() {
+        let mut device = new_test_device(BtBondState::Bonded, BtAclState::Connected);
+
+        assert_eq!(
+            device.transition_profile_state(Profile::Hid, ProfileConnectionState::Connecting),
+            ProfileConnectionState::Disconnected
+        );
+        assert_eq!(
+            device.transition_profile_state(Profile::Hfp, ProfileConnectionState::Connecting),
+            ProfileConnectionState::Disconnected
+        );
+        assert_eq!(
+            device.transition_profile_state(Profile::Hid, ProfileConnectionState::Disconnecting),
+            ProfileConnectionState::Connecting
+        );
+        assert_eq!(
+            device.profile_states.get(&Profile::Hfp),
+            Some(&ProfileConnectionState::Connecting)
+        );
+    }
+
+    #[test]
+    fn test_cancel_leaves_connected_profiles() {
+        let uuid = |p: Profile| *UuidHelper::get_profile_uuid(&p).unwrap();
+        let profiles = [
+            Profile::Hid,
+            Profile::A2dpSink,
+            Profile::Hfp,
+            Profile::LeAudio,
+            Profile::VolumeControl,
+        ];
+        let uuids: Vec<Uuid> = profiles.iter().map(|&p| uuid(p)).collect();
+        let mut device = new_test_device(BtBondState::Bonded, BtAclState::Connected);
+        for p in profiles {
+            device.transition_profile_state(p, ProfileConnectionState::Connecting);
+        }
+        assert_eq!(device.cancellable_profile_uuids(uuids.clone()), uuids);
+
+        // A connected HID survives a cancel, even if HID host reports it as HOGP.
+        device.set_hid_profile_state(Profile::Hogp, &BthhConnectionState::Connected);
+        assert_eq!(
+            device.profile_states.get(&Profile::Hid),
+            Some(&ProfileConnectionState::Connected)
+        );
+        assert!(!device.profile_states.contains_key(&Profile::Hogp));
+
+        // Media profiles are disconnected as a group, so one connected member keeps them all.
+        device.set_media_profiles_connected(&HashSet::from([Profile::VolumeControl]));
+        assert_eq!(
+            device.cancellable_profile_uuids(uuids),
+            vec![uuid(Profile::A2dpSink), uuid(Profile::Hfp)]
+        );
+    }
+
+    #[test]
+    fn test_device_found_rate_limit() {
+        let hid = *UuidHelper::get_profile_uuid(&Profile::Hid).unwrap();
+        let interval = Duration::from_secs(1);
+        let start = Instant::now();
+        let mut device = new_test_device(BtBondState::NotBonded, BtAclState::Disconnected);
+        device.update_properties(&vec![BluetoothProperty::BdName(String::from("Test"))]);
+
+        // The first sighting is always notified.
+        assert!(device.should_notify_found(start, false, interval));
+        assert!(!device.should_notify_found(start + Duration::from_millis(500), false, interval));
+        assert!(device.should_notify_found(start + Duration::from_millis(1000), false, interval));
+
+        // A significant change is notified right away and restarts the interval.
+        assert!(device.should_notify_found(start + Duration::from_millis(1100), true, interval));
+        assert!(!device.should_notify_found(start + Duration::from_millis(2000), false, interval));
+
+        // A zero interval notifies every sighting.
+        assert!(device.should_notify_found(
+            start + Duration::from_millis(2000),
+            false,
+            Duration::ZERO
+        ));
+
+        // Only new names and new UUIDs are significant.
+        assert!(!device.has_significant_changes(&vec![BluetoothProperty::RemoteRssi(-40)]));
+        assert!(
+            !device.has_significant_changes(&vec![BluetoothProperty::BdName(String::from("Test"))])
+        );
+        assert!(!device.has_significant_changes(&vec![BluetoothProperty::BdName(String::new())]));
+        assert!(device
+            .has_significant_changes(&vec![BluetoothProperty::BdName(String::from("Renamed"))]));
+        assert!(device.has_significant_changes(&vec![BluetoothProperty::Uuids(vec![hid])]));
+        device.update_properties(&vec![BluetoothProperty::Uuids(vec![hid])]);
+        assert!(!device.has_significant_changes(&vec![BluetoothProperty::Uuids(vec![hid])]));
+    }
+
+    #[test]
+    fn test_wake_allowed_override() {
+        let hid = *UuidHelper::get_profile_uuid(&Profile::Hid).unwrap();
+        let mut device = new_test_device(BtBondState::Bonded, BtAclState::Disconnected);
+        assert!(!device.is_wake_allowed());
+
+        device.wake_allowed_override = Some(true);
+        assert!(device.is_wake_allowed());
+
+        device.wake_allowed_override = None;
+        device.update_properties(&vec![BluetoothProperty::Uuids(vec![hid])]);
+        assert!(device.is_wake_allowed());
+
+        device.wake_allowed_override = Some(false);
+        assert!(!device.is_wake_allowed());
+    }
+
+    #[test]
+    fn test_device_type_override() {
+        let mut device = new_test_device(BtBondState::NotBonded, BtAclState::Disconnected);
+        device.update_properties(&vec![BluetoothProperty::TypeOfDevice(BtDeviceType::Ble)]);
+        assert_eq!(device.get_device_type(), BtDeviceType::Ble);
+        assert_eq!(device.get_default_transport(), BtTransport::Le);
+
+        device.device_type_override = Some(BtDeviceType::Bredr);
+        assert_eq!(device.get_device_type(), BtDeviceType::Bredr);
+        assert_eq!(device.get_default_transport(), BtTransport::Bredr);
+
+        device.device_type_override = Some(BtDeviceType::Dual);
+        assert_eq!(device.get_default_transport(), BtTransport::Auto);
+
+        // A later report from the device doesn't replace the override.
+        device.update_properties(&vec![BluetoothProperty::TypeOfDevice(BtDeviceType::Ble)]);
+        assert_eq!(device.get_device_type(), BtDeviceType::Dual);
+    }
+
+    #[test]
+    fn test_connection_state_cache_tracks_acl() {
+        let mut device = new_test_device(BtBondState::Bonded, BtAclState::Disconnected);
+        assert_eq!(device.cached_connection_state(), Some(BtConnectionState::NotConnected));
+
+        // Not encrypted yet, so the stack has to be asked again later.
+        device.set_transport_state(&BtTransport::Bredr, &BtAclState::Connected);
+        device.update_connection_state(|| BtConnectionState::ConnectedOnly);
+        assert_eq!(device.cached_connection_state(), None);
+
+        // BR/EDR may still be encrypted after LE.
+        device.set_transport_state(&BtTransport::Le, &BtAclState::Connected);
+        device.update_connection_state(|| BtConnectionState::EncryptedLe);
+        assert_eq!(device.cached_connection_state(), None);
+
+        device.update_connection_state(|| BtConnectionState::EncryptedBoth);
+        assert_eq!(device.cached_connection_state(), Some(BtConnectionState::EncryptedBoth));
+
+        // A bond change may encrypt or tear down the links without an ACL state change.
+        device.invalidate_connection_state();
+        assert_eq!(device.cached_connection_state(), None);
+        device.update_connection_state(|| BtConnectionState::EncryptedBoth);
+
+        // Still connected over LE.
+        device.set_transport_state(&BtTransport::Bredr, &BtAclState::Disconnected);
+        device.update_connection_state(|| BtConnectionState::EncryptedLe);
+        assert_eq!(device.cached_connection_state(), Some(BtConnectionState::EncryptedLe));
+
+        device.set_transport_state(&BtTransport::Le, &BtAclState::Disconnected);
+        device.update_connection_state(|| panic!("Stack queried for a disconnected device"));
+        assert_eq!(device.cached_connection_state(), Some(BtConnectionState::NotConnected));
+    }
+
+    #[test]
+    fn test_suspend_active_guards_connections() {
+        assert!(!is_suspend_active(&SuspendMode::Normal, &SuspendMode::Normal));
+        assert!(!is_suspend_active(&SuspendMode::Resuming, &SuspendMode::Normal));
+        assert!(is_suspend_active(&SuspendMode::Suspending, &SuspendMode::Normal));
+        assert!(is_suspend_active(&SuspendMode::Normal, &SuspendMode::Suspended));
+        assert!(is_suspend_active(&SuspendMode::Suspended, &SuspendMode::Suspended));
+    }
+
+    #[test]
+    fn test_discovery_timeout() {
+        let timeout = Duration::from_millis(DEFAULT_DISCOVERY_TIMEOUT_MS);
+        assert!(is_discovery_timeout(false, true, timeout));
+        assert!(is_discovery_timeout(false, true, timeout + Duration::from_secs(1)));
+
+        // A cancelled discovery didn't time out, however long it ran.
+        assert!(!is_discovery_timeout(true, true, timeout));
+
+        // Neither did one stopped because the adapter is being disabled.
+        assert!(!is_discovery_timeout(false, false, timeout));
+
+        // Nor one the stack stopped early on its own.
+        assert!(!is_discovery_timeout(false, true, timeout - Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn test_is_bonding_device() {
+        let addr = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let other = RawAddress::from_string("66:55:44:33:22:11").unwrap();
+        let pending = |addr| Some((BluetoothDevice::new(addr, String::new()), BtTransport::Bredr));
+
+        assert!(!is_bonding_device(&addr, &None, &None));
+
+        // Rejecting a repeated request for the device being bonded isn't a bond failure.
+        assert!(is_bonding_device(&addr, &Some(addr), &None));
+        assert!(is_bonding_device(&addr, &None, &pending(addr)));
+
+        // Rejecting a request for another device is.
+        assert!(!is_bonding_device(&addr, &Some(other), &None));
+        assert!(!is_bonding_device(&addr, &None, &pending(other)));
+    }
+
+    #[test]
+    fn test_stale_device_drops_pending_bond_cancel() {
+        let device = new_test_device(BtBondState::NotBonded, BtAclState::Disconnected);
+        let addr = device.info.address;
+        let last_seen = device.last_seen;
+        let mut remote_devices = HashMap::from([(addr, device)]);
+
+        // A bond cancel whose bond state never arrives.
+        let mut cancelling_devices = HashSet::from([addr]);
+
+        // Still fresh, so the cancel is kept.
+        let stale = remove_stale_devices(&mut remote_devices, &mut cancelling_devices, last_seen);
+        assert!(stale.is_empty());
+        assert!(cancelling_devices.contains(&addr));
+
+        let stale = remove_stale_devices(
+            &mut remote_devices,
+            &mut cancelling_devices,
+            last_seen + FOUND_DEVICE_FRESHNESS,
+        );
+        assert_eq!(stale.len(), 1);
+        assert!(remote_devices.is_empty());
+
+        // |bond_state| only skips the metrics of addresses still cancelling, so a later bond with
+        // the same device is reported.
+        assert!(!cancelling_devices.contains(&addr));
+    }
+
+    #[test]
+    fn test_property_change_coalescing() {
+        let addr1 = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let addr2 = RawAddress::from_string("66:55:44:33:22:11").unwrap();
+        let mut coalescer = PropertyChangeCoalescer::default();
+
+        // Only the first change of a device schedules a flush.
+        assert!(coalescer.add(addr1, vec![BtPropertyType::BdName, BtPropertyType::Uuids]));
+        assert!(!coalescer.add(addr1, vec![BtPropertyType::Uuids, BtPropertyType::RemoteRssi]));
+        assert!(coalescer.add(addr2, vec![BtPropertyType::Uuids]));
+
+        assert_eq!(
+            coalescer.take(addr1),
+            vec![BtPropertyType::BdName, BtPropertyType::Uuids, BtPropertyType::RemoteRssi]
+        );
+        assert_eq!(coalescer.take(addr1), vec![]);
+        assert_eq!(coalescer.take(addr2), vec![BtPropertyType::Uuids]);
+
+        // A new burst after the flush schedules another one.
+        assert!(coalescer.add(addr1, vec![BtPropertyType::Uuids]));
+    }
+
+    #[test]
+    fn test_smoothed_rssi_converges() {
+        let mut rssi = SmoothedRssi::default();
+        assert_eq!(rssi.get(), INVALID_RSSI);
+
+        // The first sample is taken as is.
+        rssi.update(-80, 0.5);
+        assert_eq!(rssi.get(), -80);
+
+        // A steady signal is approached by halving the distance with each sample.
+        let expected = [-70, -65, -63, -61, -61, -60];
+        for value in expected {
+            rssi.update(-60, 0.5);
+            assert_eq!(rssi.get(), value);
+        }
+
+        // Invalid samples don't disturb the average.
+        rssi.update(INVALID_RSSI, 0.5);
+        assert_eq!(rssi.get(), -60);
+
+        // Without smoothing the average follows the samples.
+        rssi.update(-40, 1.0);
+        assert_eq!(rssi.get(), -40);
+    }
+
+    #[test]
+    fn test_pairing_policy_auto_ssp_reply() {
+        let agent_variants = [
+            BtSspVariant::PasskeyConfirmation,
+            BtSspVariant::PasskeyEntry,
+            BtSspVariant::PasskeyNotification,
+        ];
+
+        let policy = PairingPolicy::RequireAgent;
+        assert_eq!(policy.auto_ssp_reply(&BtSspVariant::Consent, true), Some(true));
+        assert_eq!(policy.auto_ssp_reply(&BtSspVariant::Consent, false), Some(false));
+        for variant in agent_variants.iter() {
+            assert_eq!(policy.auto_ssp_reply(variant, true), None);
+        }
+
+        let policy = PairingPolicy::AutoAcceptJustWorks;
+        assert_eq!(policy.auto_ssp_reply(&BtSspVariant::Consent, false), Some(true));
+        for variant in agent_variants.iter() {
+            assert_eq!(policy.auto_ssp_reply(variant, false), None);
+        }
+
+        let policy = PairingPolicy::RejectAll;
+        assert_eq!(policy.auto_ssp_reply(&BtSspVariant::Consent, true), Some(false));
+        for variant in agent_variants.iter() {
+            assert_eq!(policy.auto_ssp_reply(variant, true), Some(false));
+        }
+    }
+
+    #[test]
+    fn test_connection_quality_aggregation() {
+        let mut aggregator = ConnectionQualityAggregator::new(true, true);
+        assert!(!aggregator.is_complete());
+
+        // Sub-values can arrive in any order.
+        aggregator.on_link_quality(Some(230));
+        assert!(!aggregator.is_complete());
+        aggregator.on_rssi(-60);
+        assert!(aggregator.is_complete());
+        assert_eq!(
+            aggregator.quality(),
+            ConnectionQuality { rssi: Some(-60), link_quality: Some(230) }
+        );
+
+        // Values that aren't requested don't hold the result back, and invalid ones are dropped.
+        let mut aggregator = ConnectionQualityAggregator::new(true, false);
+        aggregator.on_rssi(INVALID_RSSI);
+        assert!(aggregator.is_complete());
+        assert_eq!(aggregator.quality(), ConnectionQuality::default());
+
+        // A link quality that couldn't be read still completes the request.
+        let mut aggregator = ConnectionQualityAggregator::new(false, true);
+        aggregator.on_link_quality(None);
+        assert!(aggregator.is_complete());
+        assert_eq!(aggregator.quality(), ConnectionQuality::default());
+    }
+
+    #[test]
+    fn test_device_age() {
+        let device = new_test_device(BtBondState::NotBonded, BtAclState::Disconnected);
+        let now = device.last_seen;
+
+        assert_eq!(device.age(now + Duration::from_millis(1500)), Duration::from_millis(1500));
+        // A timestamp taken before the device was seen doesn't underflow.
+        assert_eq!(device.age(now - Duration::from_millis(10)), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_scan_mode_guard_suppresses_writes_while_disabling() {
+        let mut guard = ScanModeGuard::default();
+        assert!(guard.allow(&BtScanMode::Connectable));
+        assert!(guard.allow(&BtScanMode::None_));
+        assert!(guard.allow(&BtScanMode::None_));
+
+        // Leaving discoverable mode must not make the adapter connectable again, and only the
+        // first write turning the scan off goes through.
+        guard.set_disabling(true);
+        assert!(!guard.allow(&BtScanMode::Connectable));
+        assert!(guard.allow(&BtScanMode::None_));
+        assert!(!guard.allow(&BtScanMode::None_));
+        assert!(!guard.allow(&BtScanMode::ConnectableDiscoverable));
+
+        // Enabling again lifts the guard.
+        guard.set_disabling(false);
+        assert!(guard.allow(&BtScanMode::ConnectableDiscoverable));
+    }
+
+    #[test]
+    fn test_graceful_disable_waits_for_connected_devices() {
+        let addr1 = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let addr2 = RawAddress::from_string("66:55:44:33:22:11").unwrap();
+
+        // Nothing connected: disable right away.
+        let mut graceful = GracefulDisable::default();
+        assert!(!graceful.start(HashSet::new()));
+        assert!(!graceful.is_in_progress());
+
+        // Connected devices have to disconnect before the adapter is disabled.
+        assert!(graceful.start(HashSet::from([addr1, addr2])));
+        assert!(graceful.is_in_progress());
+        assert!(!graceful.on_disconnected(&addr1));
+        assert!(!graceful.on_disconnected(&addr1));
+        assert!(graceful.on_disconnected(&addr2));
+        assert!(graceful.finish());
+
+        // The timeout firing after the last disconnection doesn't disable a second time.
+        assert!(!graceful.finish());
+        assert!(!graceful.on_disconnected(&addr2));
+
+        // Timing out disables even though a device is still connected.
+        assert!(graceful.start(HashSet::from([addr1])));
+        assert!(graceful.finish());
+        assert!(!graceful.is_in_progress());
+    }
+
+    #[test]
+    fn test_le_audio_caps_from_le_features() {
+        assert_eq!(LeAudioCaps::from_le_features(0), LeAudioCaps::default());
+        assert!(!LeAudioCaps::from_le_features(0).is_le_audio_supported());
+
+        let caps = LeAudioCaps::from_le_features(1 << 28 | 1 << 31);
+        assert!(caps.cis_central && caps.iso_sync_receiver);
+        assert!(!caps.cis_peripheral && !caps.iso_broadcaster);
+        assert!(caps.is_le_audio_supported());
+
+        // Only CIS Central support counts.
+        assert!(!LeAudioCaps::from_le_features(1 << 30).is_le_audio_supported());
+        assert!(!LeAudioCaps::from_le_features(1 << 29 | 1 << 30 | 1 << 31).is_le_audio_supported());
+    }
+
+    #[test]
+    fn test_adapter_roles_from_le_supported_states() {
+        assert!(BtAdapterRole::from_le_supported_states(0).is_empty());
+        assert_eq!(BtAdapterRole::from_le_supported_states(1 << 35), vec![BtAdapterRole::Central]);
+        assert_eq!(
+            BtAdapterRole::from_le_supported_states(1 << 38),
+            vec![BtAdapterRole::Peripheral]
+        );
+        assert_eq!(
+            BtAdapterRole::from_le_supported_states(1 << 28),
+            vec![BtAdapterRole::CentralPeripheral]
+        );
+        // The neighbouring states don't count.
+        assert!(BtAdapterRole::from_le_supported_states(!(1 << 28 | 1 << 35 | 1 << 38)).is_empty());
+        // All the states of a typical dual role controller.
+        assert_eq!(
+            BtAdapterRole::from_le_supported_states(0x0000_03ff_ffff_ffff),
+            vec![
+                BtAdapterRole::Central,
+                BtAdapterRole::Peripheral,
+                BtAdapterRole::CentralPeripheral
+            ]
+        );
+    }
+
+    #[test]
+    fn test_device_found_via() {
+        let mut device = new_test_device(BtBondState::NotBonded, BtAclState::Disconnected);
+        assert!(DiscoverySource::from_bits(device.found_via).is_empty());
+
+        device.mark_found_via(DiscoverySource::LeScan);
+        assert_eq!(DiscoverySource::from_bits(device.found_via), vec![DiscoverySource::LeScan]);
+
+        // Being found again through the same source changes nothing.
+        device.mark_found_via(DiscoverySource::LeScan);
+        device.mark_found_via(DiscoverySource::Inquiry);
+        assert_eq!(
+            DiscoverySource::from_bits(device.found_via),
+            vec![DiscoverySource::Inquiry, DiscoverySource::LeScan]
+        );
+    }
+
+    #[test]
+    fn test_device_connection_handles() {
+        let mut device = new_test_device(BtBondState::NotBonded, BtAclState::Disconnected);
+        assert!(device.connection_handles().is_empty());
+
+        device.set_acl_handle(&BtTransport::Le, Some(0x0040));
+        device.set_acl_handle(&BtTransport::Bredr, Some(0x0003));
+        assert_eq!(device.connection_handles(), vec![0x0003, 0x0040]);
+
+        device.set_acl_handle(&BtTransport::Bredr, None);
+        assert_eq!(device.connection_handles(), vec![0x0040]);
+
+        // A disconnection on an unresolved transport clears everything.
+        device.set_acl_handle(&BtTransport::Auto, None);
+        assert!(device.connection_handles().is_empty());
+    }
+}
@@ -12,8 +12,9 @@ use crate::socket_manager::BluetoothSocketManager;
 use crate::uuid::{Profile, UuidHelper};
 use crate::{APIMessage, BluetoothAPI, Message, RPCProxy};
 
-use bt_topshim::btif::{BtPropertyType, BtSspVariant, RawAddress, Uuid};
+use bt_topshim::btif::{BtPropertyType, BtSspVariant, BtStatus, RawAddress, Uuid};
 use bt_topshim::profiles::sdp::BtSdpRecord;
+use btif_macros::RPCProxyStub;
 use log::{info, warn};
 use serde_json::{json, Value};
 use tokio::sync::mpsc::Sender;
@@ -343,6 +344,8 @@ pub enum AdminActions {
 }
 
 /// Handles the callbacks from Bluetooth Device
+#[derive(RPCProxyStub)]
+#[rpc_proxy(object_id = "BluetoothAdmin's Bluetooth Device Callback")]
 struct BluetoothDeviceCallbacks {
     tx: Sender<Message>,
 }
@@ -391,6 +394,7 @@ impl IBluetoothCallback for BluetoothDeviceCallbacks {
     fn on_name_changed(&mut self, _name: String) {}
     fn on_discoverable_changed(&mut self, _discoverable: bool) {}
     fn on_discovering_changed(&mut self, _discovering: bool) {}
+    fn on_discovery_timeout(&mut self) {}
     fn on_ssp_request(
         &mut self,
         _remote_device: BluetoothDevice,
@@ -402,6 +406,7 @@ impl IBluetoothCallback for BluetoothDeviceCallbacks {
     fn on_pin_request(&mut self, _remote_device: BluetoothDevice, _cod: u32, _min_16_digit: bool) {}
     fn on_pin_display(&mut self, _remote_device: BluetoothDevice, _pincode: String) {}
     fn on_bond_state_changed(&mut self, _status: u32, _device_address: RawAddress, _state: u32) {}
+    fn on_bonded_devices_changed(&mut self, _devices: Vec<BluetoothDevice>) {}
     fn on_sdp_search_complete(
         &mut self,
         _remote_device: BluetoothDevice,
@@ -410,11 +415,12 @@ impl IBluetoothCallback for BluetoothDeviceCallbacks {
     ) {
     }
     fn on_sdp_record_created(&mut self, _record: BtSdpRecord, _handle: i32) {}
-}
-
-impl RPCProxy for BluetoothDeviceCallbacks {
-    fn get_object_id(&self) -> String {
-        "BluetoothAdmin's Bluetooth Device Callback".to_string()
+    fn on_remote_uuids_fetched(
+        &mut self,
+        _remote_device: BluetoothDevice,
+        _uuids: Vec<Uuid>,
+        _status: BtStatus,
+    ) {
     }
 }
 
@@ -7,6 +7,7 @@ use bt_topshim::profiles::gatt::{AdvertisingStatus, Gatt, GattAdvCallbacks, LeDi
 
 use itertools::Itertools;
 use log::{debug, error, info, warn};
+use num_derive::{FromPrimitive, ToPrimitive};
 use num_traits::clamp;
 use std::collections::{HashMap, VecDeque};
 use std::sync::{Arc, Mutex};
@@ -53,6 +54,53 @@ pub struct AdvertisingSetParameters {
     /// Own address type for advertising to control public or privacy mode.
     /// The valid types are: -1 (default), 0 (public), 1 (random).
     pub own_address_type: i32,
+    /// Whether the advertisement is directed to |peer_address|, and at which duty cycle. Left
+    /// out by callers that predate directed advertising, which means undirected.
+    pub directed_mode: Option<DirectedAdvertisingMode>,
+    /// The only device a directed advertisement is sent to. Ignored for undirected advertising.
+    pub peer_address: Option<RawAddress>,
+    /// Address type of |peer_address|. The valid types are: 0 (public), 1 (random).
+    pub peer_address_type: Option<i32>,
+}
+
+/// Directed advertising lets a known peer reconnect quickly: the advertisements carry the peer
+/// address and only that peer may connect.
+#[derive(Debug, Default, Clone, Copy, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DirectedAdvertisingMode {
+    /// Advertisements are sent to any device.
+    #[default]
+    Undirected = 0,
+    /// Advertisements are sent at the regular advertising interval until stopped. Suits
+    /// reconnecting in the background.
+    LowDutyCycle = 1,
+    /// Advertisements are sent every 3.75 ms or less for the fastest reconnection, and the
+    /// controller stops after 1.28 s. Only available for legacy advertising.
+    HighDutyCycle = 2,
+}
+
+impl AdvertisingSetParameters {
+    /// Returns the directed advertising mode, which is undirected unless given.
+    fn get_directed_mode(&self) -> DirectedAdvertisingMode {
+        self.directed_mode.unwrap_or_default()
+    }
+
+    /// Checks that directed advertisements are connectable and have a peer, and that the high
+    /// duty cycle mode is only used with legacy advertising.
+    fn is_directed_valid(&self) -> bool {
+        match self.get_directed_mode() {
+            DirectedAdvertisingMode::Undirected => true,
+            DirectedAdvertisingMode::LowDutyCycle | DirectedAdvertisingMode::HighDutyCycle
+                if !self.connectable
+                    || self.peer_address.map_or(true, |a| a == RawAddress::default())
+                    || !matches!(self.peer_address_type, Some(0..=1)) =>
+            {
+                false
+            }
+            DirectedAdvertisingMode::LowDutyCycle => true,
+            DirectedAdvertisingMode::HighDutyCycle => self.is_legacy,
+        }
+    }
 }
 
 /// Represents the data to be advertised and the scan response data for active scans.
@@ -215,6 +263,7 @@ impl From<AdvertisingSetParameters> for bt_topshim::profiles::gatt::AdvertisePar
         let mut props: u16 = 0;
         let mut is_discoverable = false;
         let mut address = RawAddress::default();
+        let mut address_type = 0;
         if val.connectable {
             props |= 0x01;
         }
@@ -230,6 +279,15 @@ impl From<AdvertisingSetParameters> for bt_topshim::profiles::gatt::AdvertisePar
         if val.include_tx_power {
             props |= 0x40;
         }
+        let directed_mode = val.get_directed_mode();
+        if directed_mode != DirectedAdvertisingMode::Undirected {
+            props |= 0x04;
+            address = val.peer_address.unwrap_or_default();
+            address_type = val.peer_address_type.unwrap_or(0);
+        }
+        if directed_mode == DirectedAdvertisingMode::HighDutyCycle {
+            props |= 0x08;
+        }
 
         match val.discoverable {
             LeDiscMode::GeneralDiscoverable => is_discoverable = true,
@@ -249,7 +307,7 @@ impl From<AdvertisingSetParameters> for bt_topshim::profiles::gatt::AdvertisePar
             scan_request_notification_enable: 0_u8, // false
             own_address_type: val.own_address_type as i8,
             peer_address: address,
-            peer_address_type: 0x00 as i8,
+            peer_address_type: address_type as i8,
             discoverable: is_discoverable,
         }
     }
@@ -522,6 +580,25 @@ impl AdvertisingSetInfo {
         self.adv_events
     }
 
+    /// Updates the duration and the maximum number of advertising events.
+    fn set_limits(&mut self, adv_timeout: u16, adv_events: u8) {
+        self.adv_timeout = adv_timeout;
+        self.adv_events = adv_events;
+    }
+
+    /// Returns true if the advertising set is limited by duration or advertising events.
+    fn has_limits(&self) -> bool {
+        self.adv_timeout != 0 || self.adv_events != 0
+    }
+
+    /// Returns true if the controller disabled the advertising set because the configured
+    /// duration or maximum number of advertising events was reached.
+    fn is_limit_reached(&self, enabled: bool, status: AdvertisingStatus) -> bool {
+        !enabled
+            && self.has_limits()
+            && matches!(status, AdvertisingStatus::Timeout | AdvertisingStatus::TooManyAdvertisers)
+    }
+
     /// Returns whether the legacy advertisement will be used.
     fn is_legacy(&self) -> bool {
         self.legacy
@@ -829,6 +906,10 @@ pub trait IBluetoothAdvertiseManager {
     fn get_own_address(&mut self, advertiser_id: i32);
 
     /// Enables or disables an advertising set.
+    ///
+    /// When enabled with a non-zero `duration` or `max_ext_adv_events`, the advertising set is
+    /// stopped once either limit is reached and `on_advertising_set_stopped` is triggered. See
+    /// `start_advertising_set` for the valid ranges of the limits.
     fn enable_advertising_set(
         &mut self,
         advertiser_id: i32,
@@ -877,6 +958,12 @@ pub trait IBluetoothAdvertiseManager {
     );
 }
 
+/// Returns true if the advertising duration (in 10 ms unit) and the maximum number of extended
+/// advertising events are within the range accepted by the controller.
+fn is_valid_adv_limits(duration: i32, max_ext_adv_events: i32) -> bool {
+    (0..=0xffff).contains(&duration) && (0..=0xff).contains(&max_ext_adv_events)
+}
+
 impl IBluetoothAdvertiseManager for AdvertiseManagerImpl {
     fn register_callback(&mut self, callback: Box<dyn IAdvertisingSetCallback + Send>) -> u32 {
         self.callbacks.add_callback(callback)
@@ -910,6 +997,11 @@ impl IBluetoothAdvertiseManager for AdvertiseManagerImpl {
             return INVALID_REG_ID;
         }
 
+        if !parameters.is_directed_valid() {
+            warn!("Failed to start advertising set with invalid directed parameters");
+            return INVALID_REG_ID;
+        }
+
         let device_name = self.get_adapter_name();
         let adv_bytes = advertise_data.make_with(&device_name);
         // TODO(b/311417973): Remove this once we have more robust /device/bluetooth APIs to control extended advertising
@@ -1003,16 +1095,24 @@ impl IBluetoothAdvertiseManager for AdvertiseManagerImpl {
             return;
         }
 
+        if enable && !is_valid_adv_limits(duration, max_ext_adv_events) {
+            warn!(
+                "AdvertiseManagerImpl {}: invalid advertising limits: duration = {}, \
+                max_ext_adv_events = {}",
+                advertiser_id, duration, max_ext_adv_events
+            );
+            return;
+        }
+
         let adv_timeout = clamp(duration, 0, 0xffff) as u16;
         let adv_events = clamp(max_ext_adv_events, 0, 0xff) as u8;
 
-        if let Some(s) = self.get_by_advertiser_id(advertiser_id) {
-            self.gatt.lock().unwrap().advertiser.enable(
-                s.adv_id(),
-                enable,
-                adv_timeout,
-                adv_events,
-            );
+        if let Some(s) = self.get_mut_by_advertiser_id(advertiser_id) {
+            if enable {
+                s.set_limits(adv_timeout, adv_events);
+            }
+            let adv_id = s.adv_id();
+            self.gatt.lock().unwrap().advertiser.enable(adv_id, enable, adv_timeout, adv_events);
         }
     }
 
@@ -1076,6 +1176,11 @@ impl IBluetoothAdvertiseManager for AdvertiseManagerImpl {
             return;
         }
 
+        if !parameters.is_directed_valid() {
+            warn!("set_advertising_parameters: Invalid directed parameters for {}", advertiser_id);
+            return;
+        }
+
         let params = parameters.into();
 
         if let Some(s) = self.get_by_advertiser_id(advertiser_id) {
@@ -1156,7 +1261,15 @@ impl IBluetoothAdvertiseManager for AdvertiseManagerImpl {
     }
 }
 
-#[btif_callbacks_dispatcher(dispatch_le_adv_callbacks, GattAdvCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_le_adv_callbacks,
+    GattAdvCallbacks,
+    [
+        OnAdvertisingSetStarted, OnAdvertisingEnabled, OnAdvertisingDataSet, OnScanResponseDataSet,
+        OnAdvertisingParametersUpdated, OnPeriodicAdvertisingParametersUpdated,
+        OnPeriodicAdvertisingDataSet, OnPeriodicAdvertisingEnabled, OnOwnAddressRead,
+    ]
+)]
 pub(crate) trait BtifGattAdvCallbacks {
     #[btif_callback(OnAdvertisingSetStarted)]
     fn on_advertising_set_started(
@@ -1267,6 +1380,17 @@ impl BtifGattAdvCallbacks for AdvertiseManagerImpl {
             cb.on_advertising_enabled(advertiser_id, enabled, status);
         }
 
+        // The controller terminated the advertising set on its configured limits, so the set
+        // is done and gets released as if it was stopped by the client.
+        if s.is_limit_reached(enabled, status) && !s.is_stopped() {
+            info!("on_advertising_enabled(): adv_id = {} stopped on limits reached", adv_id);
+            self.gatt.lock().unwrap().advertiser.unregister(s.adv_id());
+            if let Some(cb) = self.get_callback(&s) {
+                cb.on_advertising_set_stopped(advertiser_id);
+            }
+            self.remove_by_advertiser_id(advertiser_id);
+        }
+
         if self.suspend_mode() == SuspendMode::Suspending && self.enabled_sets().count() == 0 {
             self.set_suspend_mode(SuspendMode::Suspended);
         }
@@ -1703,6 +1827,11 @@ impl IBluetoothAdvertiseManager for SoftwareRotationAdvertiseManagerImpl {
             return INVALID_REG_ID;
         }
 
+        if !advertising_params.is_directed_valid() {
+            warn!("Failed to start advertising set with invalid directed parameters");
+            return INVALID_REG_ID;
+        }
+
         let is_legacy = advertising_params.is_legacy;
         let device_name = self.get_adapter_name();
 
@@ -1894,6 +2023,11 @@ impl IBluetoothAdvertiseManager for SoftwareRotationAdvertiseManagerImpl {
             return;
         }
 
+        if !params.is_directed_valid() {
+            warn!("set_advertising_parameters: Invalid directed parameters for {}", adv_id);
+            return;
+        }
+
         let current_id = self.current_id();
         let Some(info) = self.adv_info.get_mut(&adv_id) else {
             warn!("set_advertising_parameters: Unknown adv_id {}", adv_id);
@@ -2244,4 +2378,91 @@ mod tests {
         AdvertiseData::append_transport_discovery_data(&mut bytes, &transport_discovery_data);
         assert_eq!(bytes, exp_bytes);
     }
+
+    #[test]
+    fn test_directed_advertising_parameters() {
+        let peer = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let undirected =
+            AdvertisingSetParameters { connectable: true, is_legacy: true, ..Default::default() };
+        let low_duty = AdvertisingSetParameters {
+            directed_mode: Some(DirectedAdvertisingMode::LowDutyCycle),
+            peer_address: Some(peer),
+            peer_address_type: Some(1),
+            ..undirected.clone()
+        };
+        let high_duty = AdvertisingSetParameters {
+            directed_mode: Some(DirectedAdvertisingMode::HighDutyCycle),
+            ..low_duty.clone()
+        };
+
+        // The peer is only passed down for directed advertising.
+        let params: bt_topshim::profiles::gatt::AdvertiseParameters = undirected.clone().into();
+        assert_eq!(params.advertising_event_properties, 0x11);
+        assert_eq!(params.peer_address, RawAddress::default());
+
+        let params: bt_topshim::profiles::gatt::AdvertiseParameters = low_duty.clone().into();
+        assert_eq!(params.advertising_event_properties, 0x15);
+        assert_eq!(params.peer_address, peer);
+        assert_eq!(params.peer_address_type, 1);
+
+        let params: bt_topshim::profiles::gatt::AdvertiseParameters = high_duty.clone().into();
+        assert_eq!(params.advertising_event_properties, 0x1d);
+        assert_eq!(params.peer_address, peer);
+
+        // Parameters from callers that don't know about directed advertising are undirected.
+        let explicit_undirected = AdvertisingSetParameters {
+            directed_mode: Some(DirectedAdvertisingMode::Undirected),
+            ..undirected.clone()
+        };
+        let params: bt_topshim::profiles::gatt::AdvertiseParameters = explicit_undirected.into();
+        assert_eq!(params.advertising_event_properties, 0x11);
+
+        assert!(undirected.is_directed_valid());
+        assert!(low_duty.is_directed_valid());
+        assert!(high_duty.is_directed_valid());
+
+        // Directed advertisements need a connectable set and a peer, and high duty cycle is
+        // legacy only.
+        assert!(!AdvertisingSetParameters { connectable: false, ..low_duty.clone() }
+            .is_directed_valid());
+        assert!(!AdvertisingSetParameters {
+            peer_address: Some(RawAddress::default()),
+            ..low_duty.clone()
+        }
+        .is_directed_valid());
+        assert!(!AdvertisingSetParameters { peer_address: None, ..low_duty.clone() }
+            .is_directed_valid());
+        assert!(!AdvertisingSetParameters { peer_address_type: Some(2), ..low_duty.clone() }
+            .is_directed_valid());
+        assert!(!AdvertisingSetParameters { peer_address_type: None, ..low_duty.clone() }
+            .is_directed_valid());
+        assert!(AdvertisingSetParameters { is_legacy: false, ..low_duty }.is_directed_valid());
+        assert!(!AdvertisingSetParameters { is_legacy: false, ..high_duty }.is_directed_valid());
+    }
+
+    #[test]
+    fn test_adv_limits_validation() {
+        assert!(is_valid_adv_limits(0, 0));
+        assert!(is_valid_adv_limits(0xffff, 0xff));
+        assert!(!is_valid_adv_limits(-1, 0));
+        assert!(!is_valid_adv_limits(0x10000, 0));
+        assert!(!is_valid_adv_limits(0, 0x100));
+    }
+
+    #[test]
+    fn test_auto_stop_on_limits_reached() {
+        let mut s = AdvertisingSetInfo::new(0, 0, 0, false, 1);
+
+        // Without limits, the set is never released by the controller disabling it.
+        assert!(!s.is_limit_reached(false, AdvertisingStatus::Timeout));
+
+        s.set_limits(100, 0);
+        assert!(s.is_limit_reached(false, AdvertisingStatus::Timeout));
+        assert!(!s.is_limit_reached(false, AdvertisingStatus::Success));
+        assert!(!s.is_limit_reached(true, AdvertisingStatus::Timeout));
+
+        s.set_limits(0, 5);
+        assert!(s.is_limit_reached(false, AdvertisingStatus::TooManyAdvertisers));
+        assert!(!s.is_limit_reached(false, AdvertisingStatus::InternalError));
+    }
 }
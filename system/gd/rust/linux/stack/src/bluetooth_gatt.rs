@@ -22,7 +22,7 @@ use crate::bluetooth_adv::{
     AdvertiseData, AdvertiseManager, AdvertiserActions, AdvertisingSetParameters,
     BtifGattAdvCallbacks, IAdvertisingSetCallback, PeriodicAdvertisingParameters,
 };
-use crate::callbacks::Callbacks;
+use crate::callbacks::{CallbackCount, Callbacks};
 use crate::{make_message_dispatcher, APIMessage, BluetoothAPI, Message, RPCProxy, SuspendMode};
 use log::{debug, error, info, warn};
 use num_derive::{FromPrimitive, ToPrimitive};
@@ -42,6 +42,12 @@ struct Client {
 
     // Queued on_characteristic_write callback.
     congestion_queue: Vec<(RawAddress, GattStatus, i32)>,
+
+    // Notification handles per device that are registered again when the device reconnects.
+    persisted_notifications: HashMap<RawAddress, HashSet<i32>>,
+
+    // Devices kept in background connection, which is armed again after each disconnection.
+    auto_connect_addresses: HashSet<RawAddress>,
 }
 
 struct Connection {
@@ -51,6 +57,9 @@ struct Connection {
     // Connections are made to either a client or server
     client_id: i32,
     server_id: i32,
+
+    // Services found by the last discovery on a client connection, if any.
+    services: Option<Vec<BluetoothGattService>>,
 }
 
 struct ContextMap {
@@ -123,6 +132,8 @@ impl ContextMap {
             uuid: *uuid,
             is_congested: false,
             congestion_queue: vec![],
+            persisted_notifications: HashMap::new(),
+            auto_connect_addresses: HashSet::new(),
         });
     }
 
@@ -140,6 +151,56 @@ impl ContextMap {
         self.callbacks.remove_callback(callback_id);
     }
 
+    /// Adds or removes |handle| from the notifications of |client_id| that are registered again
+    /// when |address| reconnects. Returns false if the client doesn't exist.
+    fn set_persisted_notification(
+        &mut self,
+        client_id: i32,
+        address: &RawAddress,
+        handle: i32,
+        persist: bool,
+    ) -> bool {
+        let Some(client) = self.get_by_client_id_mut(client_id) else { return false };
+        if persist {
+            client.persisted_notifications.entry(*address).or_default().insert(handle);
+        } else if let Some(handles) = client.persisted_notifications.get_mut(address) {
+            handles.remove(&handle);
+            if handles.is_empty() {
+                client.persisted_notifications.remove(address);
+            }
+        }
+        true
+    }
+
+    fn get_persisted_notifications(&self, client_id: i32, address: &RawAddress) -> Vec<i32> {
+        let mut handles: Vec<i32> = self
+            .get_by_client_id(client_id)
+            .and_then(|client| client.persisted_notifications.get(address))
+            .map_or(vec![], |handles| handles.iter().cloned().collect());
+        handles.sort_unstable();
+        handles
+    }
+
+    /// Sets whether |client_id| keeps a background connection to |address|. Returns false if the
+    /// client doesn't exist.
+    fn set_auto_connect(&mut self, client_id: i32, address: &RawAddress, enabled: bool) -> bool {
+        let Some(client) = self.get_by_client_id_mut(client_id) else { return false };
+        if enabled {
+            client.auto_connect_addresses.insert(*address);
+        } else {
+            client.auto_connect_addresses.remove(address);
+        }
+        true
+    }
+
+    /// Returns true if the background connection of |client_id| to |address| needs to be armed,
+    /// i.e. auto-connect is enabled and the client isn't connected to the device.
+    fn needs_auto_connect(&self, client_id: i32, address: &RawAddress) -> bool {
+        self.get_by_client_id(client_id)
+            .map_or(false, |client| client.auto_connect_addresses.contains(address))
+            && self.get_conn_id_from_address(client_id, address).is_none()
+    }
+
     fn set_client_id(&mut self, uuid: &Uuid, id: i32) {
         if let Some(client) = self.clients.iter_mut().find(|client| client.uuid == *uuid) {
             client.id = Some(id);
@@ -151,7 +212,13 @@ impl ContextMap {
             return;
         }
 
-        self.connections.push(Connection { conn_id, address: *address, client_id, server_id: 0 });
+        self.connections.push(Connection {
+            conn_id,
+            address: *address,
+            client_id,
+            server_id: 0,
+            services: None,
+        });
     }
 
     fn remove_connection(&mut self, _client_id: i32, conn_id: i32) {
@@ -165,6 +232,30 @@ impl ContextMap {
             .map(|conn| conn.conn_id)
     }
 
+    fn set_discovered_services(
+        &mut self,
+        conn_id: i32,
+        services: Option<Vec<BluetoothGattService>>,
+    ) {
+        if let Some(conn) = self.connections.iter_mut().find(|conn| conn.conn_id == conn_id) {
+            conn.services = services;
+        }
+    }
+
+    /// Returns whether the last discovery on the connection of |client_id| to |address| found a
+    /// service with |uuid|, including services included by other services.
+    fn has_discovered_service(&self, client_id: i32, address: &RawAddress, uuid: &Uuid) -> bool {
+        fn contains(services: &[BluetoothGattService], uuid: &Uuid) -> bool {
+            services.iter().any(|s| s.uuid == *uuid || contains(&s.included_services, uuid))
+        }
+
+        self.connections
+            .iter()
+            .find(|conn| conn.client_id == client_id && conn.address == *address)
+            .and_then(|conn| conn.services.as_ref())
+            .map_or(false, |services| contains(services, uuid))
+    }
+
     fn get_client_ids_from_address(&self, address: &RawAddress) -> Vec<i32> {
         self.connections
             .iter()
@@ -286,6 +377,17 @@ impl ServerContextMap {
         self.callbacks.remove_callback(callback_id);
     }
 
+    /// Removes the callback |callback_id|, with its server if that is registered. Returns the id
+    /// of the removed server, which has to be unregistered from the native stack.
+    fn remove_by_callback_id(&mut self, callback_id: u32) -> Option<i32> {
+        let id = self.get_by_callback_id(callback_id).and_then(|server| server.id);
+        match id {
+            Some(id) => self.remove(id),
+            None => self.remove_callback(callback_id),
+        }
+        id
+    }
+
     fn set_server_id(&mut self, uuid: &Uuid, id: i32) {
         let server = self.servers.iter_mut().find(|server| server.uuid == *uuid);
         if let Some(s) = server {
@@ -305,7 +407,13 @@ impl ServerContextMap {
             return;
         }
 
-        self.connections.push(Connection { conn_id, address: *address, client_id: 0, server_id });
+        self.connections.push(Connection {
+            conn_id,
+            address: *address,
+            client_id: 0,
+            server_id,
+            services: None,
+        });
     }
 
     fn remove_connection(&mut self, conn_id: i32) {
@@ -520,6 +628,11 @@ pub trait IBluetoothGatt {
     /// Disconnects a GATT connection.
     fn client_disconnect(&self, client_id: i32, addr: RawAddress);
 
+    /// Sets whether a background connection to the LE device is maintained, i.e. armed again
+    /// after each disconnection until disabled. Disabling it doesn't drop an existing connection.
+    /// Returns false if the client isn't registered.
+    fn client_set_auto_connect(&mut self, client_id: i32, addr: RawAddress, enabled: bool) -> bool;
+
     /// Clears the attribute cache of a device.
     fn refresh_device(&self, client_id: i32, addr: RawAddress);
 
@@ -532,6 +645,10 @@ pub trait IBluetoothGatt {
     /// Search a GATT service on a connected device based on a UUID.
     fn discover_service_by_uuid(&self, client_id: i32, addr: RawAddress, uuid: String);
 
+    /// Returns whether the last service discovery on a connected device found the service with
+    /// the given UUID. Returns false if services haven't been discovered yet.
+    fn has_service(&self, client_id: i32, addr: RawAddress, service_uuid: String) -> bool;
+
     /// Reads a characteristic on a remote device.
     fn read_characteristic(&self, client_id: i32, addr: RawAddress, handle: i32, auth_req: i32);
 
@@ -579,6 +696,19 @@ pub trait IBluetoothGatt {
         enable: bool,
     );
 
+    /// Sets whether the notification on |handle| is registered again when the bonded device
+    /// reconnects. Returns false if the client isn't registered.
+    fn set_persist_notifications(
+        &mut self,
+        client_id: i32,
+        addr: RawAddress,
+        handle: i32,
+        persist: bool,
+    ) -> bool;
+
+    /// Returns the notification handles that are registered again when the device reconnects.
+    fn get_persisted_notifications(&self, client_id: i32, addr: RawAddress) -> Vec<i32>;
+
     /// Begins reliable write.
     fn begin_reliable_write(&mut self, client_id: i32, addr: RawAddress);
 
@@ -619,6 +749,10 @@ pub trait IBluetoothGatt {
     /// Reads the PHY used by a peer.
     fn client_read_phy(&mut self, client_id: i32, addr: RawAddress);
 
+    /// Reads which LE PHYs a connected peer supports. The result is delivered through
+    /// IBluetoothGattCallback::on_remote_phy_capabilities.
+    fn client_read_remote_phy_capabilities(&mut self, client_id: i32, addr: RawAddress);
+
     // GATT Server
 
     /// Registers a GATT Server.
@@ -963,6 +1097,17 @@ pub trait IBluetoothGattCallback: RPCProxy {
         _status: GattStatus,
     );
 
+    /// The completion of IBluetoothGatt::client_read_remote_phy_capabilities. |le_2m| and
+    /// |le_coded| tell whether the peer supports the LE 2M and LE Coded PHY respectively, and are
+    /// only meaningful if |status| is success.
+    fn on_remote_phy_capabilities(
+        &mut self,
+        _addr: RawAddress,
+        _le_2m: bool,
+        _le_coded: bool,
+        _status: GattStatus,
+    );
+
     /// When GATT db is available.
     fn on_search_complete(
         &mut self,
@@ -1321,6 +1466,10 @@ pub enum GattActions {
     /// This disconnects all server and client connections to the device.
     /// Params: remote_device
     Disconnect(BluetoothDevice),
+    /// Registers the persisted notifications of a client again after it reconnected to a
+    /// bonded device. The bond state is checked in lib.rs as that needs the adapter.
+    /// Params: client_id, address
+    ResubscribeNotifications(i32, RawAddress),
 }
 
 enum MsftCommandQueue {
@@ -1401,6 +1550,15 @@ impl BluetoothGatt {
         }
     }
 
+    /// Returns the number of registered GATT client, GATT server and scanner callbacks.
+    pub(crate) fn get_callback_counts(&self) -> HashMap<String, CallbackCount> {
+        HashMap::from([
+            (String::from("gatt_client"), self.context_map.callbacks.shared_count()),
+            (String::from("gatt_server"), self.server_context_map.callbacks.shared_count()),
+            (String::from("scanner"), self.scanner_callbacks.shared_count()),
+        ])
+    }
+
     pub fn init_profiles(&mut self, api_tx: Sender<APIMessage>) {
         let gatt_client_callbacks_dispatcher = GattClientCallbacksDispatcher {
             dispatch: make_message_dispatcher(self.tx.clone(), Message::GattClient),
@@ -1796,14 +1954,9 @@ impl BluetoothGatt {
 
     pub fn remove_server_callback(&mut self, callback_id: u32) {
         // Unregister server if server id exists.
-        if let Some(server) = self.server_context_map.get_by_callback_id(callback_id) {
-            if let Some(id) = server.id {
-                self.unregister_server(id);
-            }
+        if let Some(id) = self.server_context_map.remove_by_callback_id(callback_id) {
+            self.gatt.lock().unwrap().server.unregister_server(id);
         }
-
-        // Always remove callback.
-        self.context_map.remove_callback(callback_id);
     }
 
     /// Enters suspend mode for LE advertising.
@@ -1874,6 +2027,11 @@ impl BluetoothGatt {
                     }
                 }
             }
+            GattActions::ResubscribeNotifications(client_id, addr) => {
+                for handle in self.context_map.get_persisted_notifications(client_id, &addr) {
+                    self.register_for_notification(client_id, addr, handle, true);
+                }
+            }
         }
     }
 
@@ -2316,6 +2474,21 @@ impl IBluetoothGatt for BluetoothGatt {
         self.gatt.lock().unwrap().client.disconnect(client_id, &addr, conn_id);
     }
 
+    fn client_set_auto_connect(&mut self, client_id: i32, addr: RawAddress, enabled: bool) -> bool {
+        let needed_auto_connect = self.context_map.needs_auto_connect(client_id, &addr);
+        if !self.context_map.set_auto_connect(client_id, &addr, enabled) {
+            return false;
+        }
+
+        if self.context_map.needs_auto_connect(client_id, &addr) {
+            self.client_connect(client_id, addr, false, BtTransport::Le, false, LePhy::Phy1m);
+        } else if needed_auto_connect {
+            // Cancel the pending background connection, which removes it from the accept list.
+            self.gatt.lock().unwrap().client.disconnect(client_id, &addr, 0);
+        }
+        true
+    }
+
     fn refresh_device(&self, client_id: i32, addr: RawAddress) {
         self.gatt.lock().unwrap().client.refresh(client_id, &addr);
     }
@@ -2341,6 +2514,11 @@ impl IBluetoothGatt for BluetoothGatt {
         self.gatt.lock().unwrap().client.search_service(conn_id, uuid);
     }
 
+    fn has_service(&self, client_id: i32, addr: RawAddress, service_uuid: String) -> bool {
+        let Some(uuid) = Uuid::from_string(service_uuid) else { return false };
+        self.context_map.has_discovered_service(client_id, &addr, &uuid)
+    }
+
     fn btif_gattc_discover_service_by_uuid(&self, client_id: i32, addr: RawAddress, uuid: String) {
         let conn_id = match self.context_map.get_conn_id_from_address(client_id, &addr) {
             None => return,
@@ -2478,6 +2656,20 @@ impl IBluetoothGatt for BluetoothGatt {
         }
     }
 
+    fn set_persist_notifications(
+        &mut self,
+        client_id: i32,
+        addr: RawAddress,
+        handle: i32,
+        persist: bool,
+    ) -> bool {
+        self.context_map.set_persisted_notification(client_id, &addr, handle, persist)
+    }
+
+    fn get_persisted_notifications(&self, client_id: i32, addr: RawAddress) -> Vec<i32> {
+        self.context_map.get_persisted_notifications(client_id, &addr)
+    }
+
     fn begin_reliable_write(&mut self, _client_id: i32, addr: RawAddress) {
         self.reliable_queue.insert(addr);
     }
@@ -2568,6 +2760,10 @@ impl IBluetoothGatt for BluetoothGatt {
         self.gatt.lock().unwrap().client.read_phy(client_id, &addr);
     }
 
+    fn client_read_remote_phy_capabilities(&mut self, client_id: i32, addr: RawAddress) {
+        self.gatt.lock().unwrap().client.read_remote_phy_capabilities(client_id, &addr);
+    }
+
     // GATT Server
 
     fn register_server(
@@ -2724,7 +2920,16 @@ impl IBluetoothGatt for BluetoothGatt {
     }
 }
 
-#[btif_callbacks_dispatcher(dispatch_gatt_client_callbacks, GattClientCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_gatt_client_callbacks,
+    GattClientCallbacks,
+    [
+        RegisterClient, Connect, Disconnect, SearchComplete, RegisterForNotification, Notify,
+        ReadCharacteristic, WriteCharacteristic, ReadDescriptor, WriteDescriptor, ExecuteWrite,
+        ReadRemoteRssi, ConfigureMtu, Congestion, GetGattDb, PhyUpdated, ConnUpdated,
+        ServiceChanged, ReadPhy, ReadRemotePhyCapabilities,
+    ]
+)]
 pub(crate) trait BtifGattClientCallbacks {
     #[btif_callback(RegisterClient)]
     fn register_client_cb(&mut self, status: GattStatus, client_id: i32, app_uuid: Uuid);
@@ -2822,6 +3027,16 @@ pub(crate) trait BtifGattClientCallbacks {
         rx_phy: u8,
         status: GattStatus,
     );
+
+    #[btif_callback(ReadRemotePhyCapabilities)]
+    fn read_remote_phy_capabilities_cb(
+        &mut self,
+        client_id: i32,
+        addr: RawAddress,
+        le_2m: bool,
+        le_coded: bool,
+        status: GattStatus,
+    );
 }
 
 impl BtifGattClientCallbacks for BluetoothGatt {
@@ -2845,6 +3060,17 @@ impl BtifGattClientCallbacks for BluetoothGatt {
     fn connect_cb(&mut self, conn_id: i32, status: GattStatus, client_id: i32, addr: RawAddress) {
         if status == GattStatus::Success {
             self.context_map.add_connection(client_id, conn_id, &addr);
+
+            if !self.context_map.get_persisted_notifications(client_id, &addr).is_empty() {
+                let txl = self.tx.clone();
+                tokio::spawn(async move {
+                    let _ = txl
+                        .send(Message::GattActions(GattActions::ResubscribeNotifications(
+                            client_id, addr,
+                        )))
+                        .await;
+                });
+            }
         }
 
         let Some(client) = self.context_map.get_by_client_id(client_id) else { return };
@@ -2866,6 +3092,15 @@ impl BtifGattClientCallbacks for BluetoothGatt {
         }
         self.context_map.remove_connection(client_id, conn_id);
 
+        if self.context_map.needs_auto_connect(client_id, &addr) {
+            debug!(
+                "Re-arming the background connection of client {} to {}",
+                client_id,
+                DisplayAddress(&addr)
+            );
+            self.client_connect(client_id, addr, false, BtTransport::Le, false, LePhy::Phy1m);
+        }
+
         if self.context_map.get_client_ids_from_address(&addr).is_empty() {
             // Cleaning up as no client connects to this address.
             self.write_characteristic_permits.remove(&addr);
@@ -3028,13 +3263,11 @@ impl BtifGattClientCallbacks for BluetoothGatt {
 
     fn get_gatt_db_cb(&mut self, conn_id: i32, elements: Vec<BtGattDbElement>, _count: i32) {
         let Some(addr) = self.context_map.get_address_by_conn_id(conn_id) else { return };
+        let services = BluetoothGattService::from_db(elements, true);
+        self.context_map.set_discovered_services(conn_id, Some(services.clone()));
         let Some(client) = self.context_map.get_client_by_conn_id(conn_id) else { return };
         if let Some(cb) = self.context_map.get_callback_from_callback_id(client.cbid) {
-            cb.on_search_complete(
-                addr,
-                BluetoothGattService::from_db(elements, true),
-                GattStatus::Success,
-            );
+            cb.on_search_complete(addr, services, GattStatus::Success);
         }
     }
 
@@ -3070,6 +3303,20 @@ impl BtifGattClientCallbacks for BluetoothGatt {
         }
     }
 
+    fn read_remote_phy_capabilities_cb(
+        &mut self,
+        client_id: i32,
+        addr: RawAddress,
+        le_2m: bool,
+        le_coded: bool,
+        status: GattStatus,
+    ) {
+        let Some(client) = self.context_map.get_by_client_id(client_id) else { return };
+        if let Some(cb) = self.context_map.get_callback_from_callback_id(client.cbid) {
+            cb.on_remote_phy_capabilities(addr, le_2m, le_coded, status);
+        }
+    }
+
     fn conn_updated_cb(
         &mut self,
         conn_id: i32,
@@ -3087,6 +3334,8 @@ impl BtifGattClientCallbacks for BluetoothGatt {
 
     fn service_changed_cb(&mut self, conn_id: i32) {
         let Some(addr) = self.context_map.get_address_by_conn_id(conn_id) else { return };
+        // The services found before are stale until they are discovered again.
+        self.context_map.set_discovered_services(conn_id, None);
         let Some(client) = self.context_map.get_client_by_conn_id(conn_id) else { return };
         if let Some(cb) = self.context_map.get_callback_from_callback_id(client.cbid) {
             cb.on_service_changed(addr);
@@ -3094,7 +3343,15 @@ impl BtifGattClientCallbacks for BluetoothGatt {
     }
 }
 
-#[btif_callbacks_dispatcher(dispatch_gatt_server_callbacks, GattServerCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_gatt_server_callbacks,
+    GattServerCallbacks,
+    [
+        RegisterServer, Connection, ServiceAdded, ServiceDeleted, RequestReadCharacteristic,
+        RequestReadDescriptor, RequestWriteCharacteristic, RequestWriteDescriptor, RequestExecWrite,
+        IndicationSent, Congestion, MtuChanged, PhyUpdated, ReadPhy, ConnUpdated, SubrateChanged,
+    ]
+)]
 pub(crate) trait BtifGattServerCallbacks {
     #[btif_callback(RegisterServer)]
     fn register_server_cb(&mut self, status: GattStatus, server_id: i32, app_uuid: Uuid);
@@ -3567,7 +3824,11 @@ impl BtifGattServerCallbacks for BluetoothGatt {
     }
 }
 
-#[btif_callbacks_dispatcher(dispatch_le_scanner_callbacks, GattScannerCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_le_scanner_callbacks,
+    GattScannerCallbacks,
+    [OnScannerRegistered, OnScanResult, OnTrackAdvFoundLost]
+)]
 pub(crate) trait BtifGattScannerCallbacks {
     #[btif_callback(OnScannerRegistered)]
     fn on_scanner_registered(&mut self, uuid: Uuid, scanner_id: u8, status: GattStatus);
@@ -3591,7 +3852,16 @@ pub(crate) trait BtifGattScannerCallbacks {
     fn on_track_adv_found_lost(&mut self, adv_track_info: AdvertisingTrackInfo);
 }
 
-#[btif_callbacks_dispatcher(dispatch_le_scanner_inband_callbacks, GattScannerInbandCallbacks)]
+#[btif_callbacks_dispatcher(
+    dispatch_le_scanner_inband_callbacks,
+    GattScannerInbandCallbacks,
+    [
+        RegisterCallback, StatusCallback, EnableCallback, FilterParamSetupCallback,
+        FilterConfigCallback, MsftAdvMonitorAddCallback, MsftAdvMonitorRemoveCallback,
+        MsftAdvMonitorEnableCallback, StartSyncCallback, SyncReportCallback, SyncLostCallback,
+        SyncTransferCallback,
+    ]
+)]
 pub(crate) trait BtifGattScannerInbandCallbacks {
     #[btif_callback(RegisterCallback)]
     fn inband_register_callback(&mut self, app_uuid: Uuid, scanner_id: u8, btm_status: u8);
@@ -4155,6 +4425,15 @@ mod tests {
         ) {
         }
 
+        fn on_remote_phy_capabilities(
+            &mut self,
+            _addr: RawAddress,
+            _le_2m: bool,
+            _le_coded: bool,
+            _status: GattStatus,
+        ) {
+        }
+
         fn on_search_complete(
             &mut self,
             _addr: RawAddress,
@@ -4218,7 +4497,118 @@ mod tests {
         }
     }
 
+    /// GATT server callback that lets the test trigger the client disconnect.
+    struct TestBluetoothGattServerCallback {
+        id: String,
+        on_disconnect: Arc<Mutex<Option<Box<dyn Fn(u32) + Send>>>>,
+    }
+
+    impl IBluetoothGattServerCallback for TestBluetoothGattServerCallback {
+        fn on_server_registered(&mut self, _status: GattStatus, _server_id: i32) {}
+        fn on_server_connection_state(
+            &mut self,
+            _server_id: i32,
+            _connected: bool,
+            _addr: RawAddress,
+        ) {
+        }
+        fn on_service_added(&mut self, _status: GattStatus, _service: BluetoothGattService) {}
+        fn on_service_removed(&mut self, _status: GattStatus, _handle: i32) {}
+        fn on_characteristic_read_request(
+            &mut self,
+            _addr: RawAddress,
+            _trans_id: i32,
+            _offset: i32,
+            _is_long: bool,
+            _handle: i32,
+        ) {
+        }
+        fn on_descriptor_read_request(
+            &mut self,
+            _addr: RawAddress,
+            _trans_id: i32,
+            _offset: i32,
+            _is_long: bool,
+            _handle: i32,
+        ) {
+        }
+        fn on_characteristic_write_request(
+            &mut self,
+            _addr: RawAddress,
+            _trans_id: i32,
+            _offset: i32,
+            _len: i32,
+            _is_prep: bool,
+            _need_rsp: bool,
+            _handle: i32,
+            _value: Vec<u8>,
+        ) {
+        }
+        fn on_descriptor_write_request(
+            &mut self,
+            _addr: RawAddress,
+            _trans_id: i32,
+            _offset: i32,
+            _len: i32,
+            _is_prep: bool,
+            _need_rsp: bool,
+            _handle: i32,
+            _value: Vec<u8>,
+        ) {
+        }
+        fn on_execute_write(&mut self, _addr: RawAddress, _trans_id: i32, _exec_write: bool) {}
+        fn on_notification_sent(&mut self, _addr: RawAddress, _status: GattStatus) {}
+        fn on_mtu_changed(&mut self, _addr: RawAddress, _mtu: i32) {}
+        fn on_phy_update(
+            &mut self,
+            _addr: RawAddress,
+            _tx_phy: LePhy,
+            _rx_phy: LePhy,
+            _status: GattStatus,
+        ) {
+        }
+        fn on_phy_read(
+            &mut self,
+            _addr: RawAddress,
+            _tx_phy: LePhy,
+            _rx_phy: LePhy,
+            _status: GattStatus,
+        ) {
+        }
+        fn on_connection_updated(
+            &mut self,
+            _addr: RawAddress,
+            _interval: i32,
+            _latency: i32,
+            _timeout: i32,
+            _status: GattStatus,
+        ) {
+        }
+        fn on_subrate_change(
+            &mut self,
+            _addr: RawAddress,
+            _subrate_factor: i32,
+            _latency: i32,
+            _cont_num: i32,
+            _timeout: i32,
+            _status: GattStatus,
+        ) {
+        }
+    }
+
+    impl RPCProxy for TestBluetoothGattServerCallback {
+        fn get_object_id(&self) -> String {
+            self.id.clone()
+        }
+        fn register_disconnect(&mut self, f: Box<dyn Fn(u32) + Send>) -> u32 {
+            *self.on_disconnect.lock().unwrap() = Some(f);
+            static CBID: AtomicU32 = AtomicU32::new(1);
+            CBID.fetch_add(1, Ordering::SeqCst)
+        }
+    }
+
     use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
 
     #[test]
     fn test_uuid_from_string() {
@@ -4307,4 +4697,142 @@ mod tests {
         assert!(found.is_some());
         assert_eq!(4, found.unwrap());
     }
+
+    #[test]
+    fn test_context_map_persisted_notifications() {
+        let (tx, _rx) = crate::Stack::create_channel();
+        let mut map = ContextMap::new(tx.clone());
+        let uuid = Uuid::from_string("00000000000000000000000000000001").unwrap();
+        let addr = RawAddress::from_string("aa:bb:cc:dd:ee:ff").unwrap();
+
+        // Unknown clients can't persist notifications.
+        assert!(!map.set_persisted_notification(1, &addr, 10, true));
+
+        map.add(&uuid, Box::new(TestBluetoothGattCallback::new(String::from("Callback 1"))));
+        map.set_client_id(&uuid, 1);
+        map.add_connection(1, 3, &addr);
+        assert!(map.set_persisted_notification(1, &addr, 12, true));
+        assert!(map.set_persisted_notification(1, &addr, 10, true));
+        assert!(map.set_persisted_notification(1, &addr, 14, true));
+        assert!(map.set_persisted_notification(1, &addr, 14, false));
+
+        // The handles to subscribe again survive the disconnection.
+        map.remove_connection(1, 3);
+        map.add_connection(1, 4, &addr);
+        assert_eq!(map.get_persisted_notifications(1, &addr), vec![10, 12]);
+        assert!(map
+            .get_persisted_notifications(1, &RawAddress::from_string("11:22:33:44:55:66").unwrap())
+            .is_empty());
+
+        // They are gone with the client.
+        map.remove(1);
+        assert!(map.get_persisted_notifications(1, &addr).is_empty());
+    }
+
+    #[test]
+    fn test_server_callback_disconnect_removes_server() {
+        tokio::runtime::Runtime::new().unwrap().block_on(async move {
+            let (tx, mut rx) = crate::Stack::create_channel();
+            let mut map = ServerContextMap::new(tx);
+
+            // One server is registered with the native stack, the other one is still pending.
+            let registered = Uuid::from_string("00000000000000000000000000000001").unwrap();
+            let pending = Uuid::from_string("00000000000000000000000000000002").unwrap();
+            let mut on_disconnect = vec![];
+            for uuid in [registered, pending] {
+                let f: Arc<Mutex<Option<Box<dyn Fn(u32) + Send>>>> = Arc::new(Mutex::new(None));
+                map.add(
+                    &uuid,
+                    Box::new(TestBluetoothGattServerCallback {
+                        id: uuid.to_string(),
+                        on_disconnect: f.clone(),
+                    }),
+                );
+                on_disconnect.push((map.get_by_uuid(&uuid).unwrap().cbid, f));
+            }
+            map.set_server_id(&registered, 3);
+            assert_eq!(map.callbacks.count(), 2);
+
+            // Simulate the clients going away and handle the messages like the stack does. Only
+            // the registered server has to be unregistered from the native stack.
+            let mut unregistered = vec![];
+            for (cbid, f) in on_disconnect {
+                (f.lock().unwrap().as_ref().unwrap())(cbid);
+                match rx.recv().await {
+                    Some(Message::GattServerCallbackDisconnected(id)) => {
+                        assert_eq!(id, cbid);
+                        unregistered.extend(map.remove_by_callback_id(id));
+                    }
+                    _ => panic!("Expected the GATT server callback to be disconnected"),
+                }
+                assert!(map.get_callback_from_callback_id(cbid).is_none());
+            }
+            assert_eq!(unregistered, vec![3]);
+            assert!(map.get_by_server_id(3).is_none());
+            assert_eq!(map.callbacks.count(), 0);
+        });
+    }
+
+    #[test]
+    fn test_context_map_auto_connect() {
+        let (tx, _rx) = crate::Stack::create_channel();
+        let mut map = ContextMap::new(tx.clone());
+        let uuid = Uuid::from_string("00000000000000000000000000000001").unwrap();
+        let addr = RawAddress::from_string("aa:bb:cc:dd:ee:ff").unwrap();
+
+        // Unknown clients can't auto-connect.
+        assert!(!map.set_auto_connect(1, &addr, true));
+        assert!(!map.needs_auto_connect(1, &addr));
+
+        map.add(&uuid, Box::new(TestBluetoothGattCallback::new(String::from("Callback 1"))));
+        map.set_client_id(&uuid, 1);
+        assert!(!map.needs_auto_connect(1, &addr));
+        assert!(map.set_auto_connect(1, &addr, true));
+        assert!(map.needs_auto_connect(1, &addr));
+
+        // Nothing to arm while connected, and a disconnection arms the background connection
+        // again.
+        map.add_connection(1, 3, &addr);
+        assert!(!map.needs_auto_connect(1, &addr));
+        map.remove_connection(1, 3);
+        assert!(map.needs_auto_connect(1, &addr));
+        map.add_connection(1, 4, &addr);
+        map.remove_connection(1, 4);
+        assert!(map.needs_auto_connect(1, &addr));
+
+        // Disabled, a disconnection doesn't arm it anymore.
+        map.add_connection(1, 5, &addr);
+        assert!(map.set_auto_connect(1, &addr, false));
+        map.remove_connection(1, 5);
+        assert!(!map.needs_auto_connect(1, &addr));
+    }
+
+    #[test]
+    fn test_context_map_has_discovered_service() {
+        let (tx, _rx) = crate::Stack::create_channel();
+        let mut map = ContextMap::new(tx.clone());
+        let client_id = 1;
+        let addr = RawAddress::from_string("aa:bb:cc:dd:ee:ff").unwrap();
+        let battery = Uuid::from_string("0000180f-0000-1000-8000-00805f9b34fb").unwrap();
+        let hid = Uuid::from_string("00001812-0000-1000-8000-00805f9b34fb").unwrap();
+        let dis = Uuid::from_string("0000180a-0000-1000-8000-00805f9b34fb").unwrap();
+
+        // Discovery hasn't run yet.
+        map.add_connection(client_id, 3, &addr);
+        assert!(!map.has_discovered_service(client_id, &addr, &battery));
+
+        let mut hid_service = BluetoothGattService::new(hid, 2, 0);
+        hid_service.included_services.push(BluetoothGattService::new(battery, 3, 1));
+        map.set_discovered_services(3, Some(vec![hid_service]));
+        assert!(map.has_discovered_service(client_id, &addr, &hid));
+        assert!(map.has_discovered_service(client_id, &addr, &battery));
+        assert!(!map.has_discovered_service(client_id, &addr, &dis));
+
+        // Another client doesn't see the services discovered on this connection.
+        assert!(!map.has_discovered_service(client_id + 1, &addr, &hid));
+
+        // Services are forgotten when they change on the remote.
+        map.set_discovered_services(3, None);
+        assert!(!map.has_discovered_service(client_id, &addr, &hid));
+    }
 }
@@ -5,8 +5,14 @@
 //! two, the |BluetoothLogging| struct will configure both the Rust logging and
 //! the C/C++ logging (via topshim).
 use bt_topshim::syslog::{set_default_log_level, set_log_level_for_tag, Level};
-use log::LevelFilter;
-use syslog::{BasicLogger, Error, Facility, Formatter3164};
+use log::{LevelFilter, Log, Metadata, Record};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use syslog::{BasicLogger, Facility, Formatter3164};
 
 use log_panics;
 
@@ -25,14 +31,27 @@ pub trait IBluetoothLogging {
     fn get_log_level(&self) -> Level;
 }
 
+/// Where the logs of the Rust stack are written. The logs of libbluetooth always go to syslog.
+pub enum LogOutput {
+    Syslog,
+    Stderr,
+    /// A file that is rotated once it reaches |max_size| bytes. Up to |max_rotated| older files
+    /// are kept next to it, suffixed from .1 (newest) to .N (oldest).
+    File {
+        path: PathBuf,
+        max_size: u64,
+        max_rotated: u32,
+    },
+}
+
 /// Logging related implementation.
 pub struct BluetoothLogging {
     /// Current log level
     /// If the level is not verbose, `VERBOSE_ONLY_LOG_TAGS` will be set to emit up to `INFO` only.
     log_level: Level,
 
-    /// Log to stderr?
-    is_stderr: bool,
+    /// Where the Rust logs are written.
+    output: LogOutput,
 
     /// Is logging already initialized?
     is_initialized: bool,
@@ -47,34 +66,125 @@ const VERBOSE_ONLY_LOG_TAGS: &[&str] = &[
     "uipc",      // Userspace IPC implementation
 ];
 
-impl BluetoothLogging {
-    pub fn new(is_debug: bool, is_verbose_debug: bool, log_output: &str) -> Self {
-        let is_stderr = log_output == "stderr";
+/// A log file that moves to |path|.1 once it would exceed |max_size| bytes, shifting the files
+/// rotated before and dropping the oldest one.
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    max_rotated: u32,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, max_size: u64, max_rotated: u32) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile { path, max_size, max_rotated, file, size })
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..self.max_rotated).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        if self.max_rotated > 0 {
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Appends |line|, rotating first if it doesn't fit. A line longer than |max_size| still
+    /// goes to a file of its own.
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.size += line.len() as u64;
+        Ok(())
+    }
+}
+
+/// Writes the Rust logs to a |RotatingFile|, one record per line prefixed with the time since
+/// epoch, the level and the target.
+struct FileLogger {
+    file: Mutex<RotatingFile>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let line = format!(
+            "{}.{:06} {} {}: {}\n",
+            now.as_secs(),
+            now.subsec_micros(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        let _ = self.file.lock().unwrap().write_line(line.as_bytes());
+    }
 
+    fn flush(&self) {
+        let _ = self.file.lock().unwrap().file.flush();
+    }
+}
+
+impl BluetoothLogging {
+    pub fn new(is_debug: bool, is_verbose_debug: bool, output: LogOutput) -> Self {
         let log_level = match (is_debug, is_verbose_debug) {
             (true, true) => Level::Verbose,
             (true, false) => Level::Debug,
             _ => Level::Info,
         };
 
-        Self { log_level, is_stderr, is_initialized: false }
+        Self { log_level, output, is_initialized: false }
     }
 
-    pub fn initialize(&mut self) -> Result<(), Error> {
-        if self.is_stderr {
-            env_logger::Builder::new().filter(None, self.get_log_level_filter()).init();
-        } else {
-            let formatter = Formatter3164 {
-                facility: Facility::LOG_USER,
-                hostname: None,
-                process: "btadapterd".into(),
-                pid: 0,
-            };
-
-            let logger = syslog::unix(formatter)?;
-            let _ = log::set_boxed_logger(Box::new(BasicLogger::new(logger)))
-                .map(|()| self.apply_linux_log_level());
-            log_panics::init();
+    pub fn initialize(&mut self) -> Result<(), Box<dyn Error>> {
+        match &self.output {
+            LogOutput::Stderr => {
+                env_logger::Builder::new().filter(None, self.get_log_level_filter()).init();
+            }
+            LogOutput::Syslog => {
+                let formatter = Formatter3164 {
+                    facility: Facility::LOG_USER,
+                    hostname: None,
+                    process: "btadapterd".into(),
+                    pid: 0,
+                };
+
+                let logger = syslog::unix(formatter)?;
+                let _ = log::set_boxed_logger(Box::new(BasicLogger::new(logger)))
+                    .map(|()| self.apply_linux_log_level());
+                log_panics::init();
+            }
+            LogOutput::File { path, max_size, max_rotated } => {
+                let file = RotatingFile::open(path.clone(), *max_size, *max_rotated)?;
+                let _ = log::set_boxed_logger(Box::new(FileLogger { file: Mutex::new(file) }))
+                    .map(|()| self.apply_linux_log_level());
+                log_panics::init();
+            }
         }
 
         // Set initial log levels and filter out tags if not verbose debug.
@@ -163,3 +273,36 @@ impl IBluetoothLogging for BluetoothLogging {
         self.log_level
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let path = std::env::temp_dir().join(format!("btadapterd_{}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        let rotated = [file.rotated_path(1), file.rotated_path(2), file.rotated_path(3)];
+        for p in &rotated {
+            let _ = fs::remove_file(p);
+        }
+
+        file.write_line(b"first\n").unwrap();
+        file.write_line(b"12345\n").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"12345\n");
+        assert_eq!(fs::read(&rotated[0]).unwrap(), b"first\n");
+
+        file.write_line(b"second\n").unwrap();
+        file.write_line(b"third\n").unwrap();
+        // Only the two newest rotated files are kept.
+        assert_eq!(fs::read(&path).unwrap(), b"third\n");
+        assert_eq!(fs::read(&rotated[0]).unwrap(), b"second\n");
+        assert_eq!(fs::read(&rotated[1]).unwrap(), b"12345\n");
+        assert!(!rotated[2].exists());
+
+        fs::remove_file(&path).unwrap();
+        fs::remove_file(&rotated[0]).unwrap();
+        fs::remove_file(&rotated[1]).unwrap();
+    }
+}
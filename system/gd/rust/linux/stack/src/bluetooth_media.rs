@@ -41,6 +41,7 @@ use bt_utils::uhid_hfp::{
     UHID_OUTPUT_NONE, UHID_OUTPUT_OFF_HOOK, UHID_OUTPUT_RING,
 };
 use bt_utils::uinput::UInput;
+use btif_macros::RPCProxyStub;
 
 use itertools::Itertools;
 use log::{debug, info, warn};
@@ -115,6 +116,11 @@ pub trait IBluetoothMedia {
     ///
     fn register_callback(&mut self, callback: Box<dyn IBluetoothMediaCallback + Send>) -> bool;
 
+    /// Removes the callback registered as `callback_id`.
+    ///
+    /// Returns true if the callback is removed, false if `callback_id` is not recognized.
+    fn unregister_callback(&mut self, callback_id: u32) -> bool;
+
     /// initializes media (both A2dp and AVRCP) stack
     fn initialize(&mut self) -> bool;
 
@@ -231,6 +237,9 @@ pub trait IBluetoothMedia {
 }
 
 pub trait IBluetoothMediaCallback: RPCProxy {
+    /// Triggered when the callback is registered and given an identifier `callback_id`.
+    fn on_callback_registered(&mut self, callback_id: u32);
+
     /// Triggered when a Bluetooth audio device is ready to be used. This should
     /// only be triggered once for a device and send an event to clients. If the
     /// device supports both HFP and A2DP, both should be ready when this is
@@ -321,6 +330,11 @@ pub trait IBluetoothTelephony {
         callback: Box<dyn IBluetoothTelephonyCallback + Send>,
     ) -> bool;
 
+    /// Removes the telephony callback registered as `callback_id`.
+    ///
+    /// Returns true if the callback is removed, false if `callback_id` is not recognized.
+    fn unregister_telephony_callback(&mut self, callback_id: u32) -> bool;
+
     /// Sets whether the device is connected to the cellular network.
     fn set_network_available(&mut self, network_available: bool);
     /// Sets whether the device is roaming.
@@ -357,9 +371,15 @@ pub trait IBluetoothTelephony {
     fn audio_connect(&mut self, address: RawAddress) -> bool;
     /// Stops the audio connection to <address>.
     fn audio_disconnect(&mut self, address: RawAddress);
+    /// Drops all of the calls along with the memory and last call numbers, and resets the network,
+    /// roaming, signal strength and battery level to their defaults.
+    fn clear_calls(&mut self);
 }
 
 pub trait IBluetoothTelephonyCallback: RPCProxy {
+    /// Triggered when the callback is registered and given an identifier `callback_id`.
+    fn on_callback_registered(&mut self, callback_id: u32);
+
     fn on_telephony_event(&mut self, addr: RawAddress, event: u8, state: u8);
 }
 
@@ -613,6 +633,21 @@ impl BluetoothMedia {
         self.connected_profiles.get(device_address).cloned().unwrap_or_default()
     }
 
+    /// Gets the classic and LE media profiles of |addr| that finished connecting.
+    pub(crate) fn get_connected_media_profiles(&self, addr: &RawAddress) -> HashSet<Profile> {
+        let mut profiles = self.get_connected_profiles(addr);
+        if self.le_audio_states.get(addr) == Some(&BtLeAudioConnectionState::Connected) {
+            profiles.insert(Profile::LeAudio);
+        }
+        if self.vc_states.get(addr) == Some(&BtVcConnectionState::Connected) {
+            profiles.insert(Profile::VolumeControl);
+        }
+        if self.csis_states.get(addr) == Some(&BtCsisConnectionState::Connected) {
+            profiles.insert(Profile::CoordinatedSet);
+        }
+        profiles
+    }
+
     fn add_connected_profile(&mut self, addr: RawAddress, profile: Profile) {
         if self.is_profile_connected(&addr, &profile) {
             warn!("[{}]: profile is already connected", DisplayAddress(&addr));
@@ -1936,10 +1971,32 @@ impl BluetoothMedia {
         }
     }
 
+    pub fn callback_registered(&mut self, id: u32) {
+        let found = self
+            .callbacks
+            .lock()
+            .unwrap()
+            .for_one_callback(id, |callback| callback.on_callback_registered(id));
+        if !found {
+            warn!("Media callback {} does not exist", id);
+        }
+    }
+
     pub fn remove_callback(&mut self, id: u32) -> bool {
         self.callbacks.lock().unwrap().remove_callback(id)
     }
 
+    pub fn telephony_callback_registered(&mut self, id: u32) {
+        let found = self
+            .telephony_callbacks
+            .lock()
+            .unwrap()
+            .for_one_callback(id, |callback| callback.on_callback_registered(id));
+        if !found {
+            warn!("Telephony callback {} does not exist", id);
+        }
+    }
+
     pub fn remove_telephony_callback(&mut self, id: u32) -> bool {
         self.telephony_callbacks.lock().unwrap().remove_callback(id)
     }
@@ -3093,10 +3150,20 @@ fn get_csis_dispatcher(tx: Sender<Message>) -> CsisClientCallbacksDispatcher {
 
 impl IBluetoothMedia for BluetoothMedia {
     fn register_callback(&mut self, callback: Box<dyn IBluetoothMediaCallback + Send>) -> bool {
-        let _id = self.callbacks.lock().unwrap().add_callback(callback);
+        let id = self.callbacks.lock().unwrap().add_callback(callback);
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let _result = tx.send(Message::MediaCallbackRegistered(id)).await;
+        });
+
         true
     }
 
+    fn unregister_callback(&mut self, callback_id: u32) -> bool {
+        self.remove_callback(callback_id)
+    }
+
     fn initialize(&mut self) -> bool {
         if self.initialized {
             return false;
@@ -3868,10 +3935,20 @@ impl IBluetoothTelephony for BluetoothMedia {
         &mut self,
         callback: Box<dyn IBluetoothTelephonyCallback + Send>,
     ) -> bool {
-        let _id = self.telephony_callbacks.lock().unwrap().add_callback(callback);
+        let id = self.telephony_callbacks.lock().unwrap().add_callback(callback);
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let _result = tx.send(Message::TelephonyCallbackRegistered(id)).await;
+        });
+
         true
     }
 
+    fn unregister_telephony_callback(&mut self, callback_id: u32) -> bool {
+        self.remove_telephony_callback(callback_id)
+    }
+
     fn set_network_available(&mut self, network_available: bool) {
         if self.telephony_device_status.network_available == network_available {
             return;
@@ -4055,8 +4132,25 @@ impl IBluetoothTelephony for BluetoothMedia {
     fn audio_disconnect(&mut self, address: RawAddress) {
         self.stop_sco_call_impl(address)
     }
+
+    fn clear_calls(&mut self) {
+        info!("Clearing all calls and resetting the telephony status");
+        self.call_list = vec![];
+        self.phone_state.num_active = 0;
+        self.phone_state.num_held = 0;
+        self.phone_state.state = CallState::Idle;
+        self.memory_dialing_number = None;
+        self.last_dialing_number = None;
+        self.telephony_device_status = TelephonyDeviceStatus::new();
+
+        self.phone_state_change("".into());
+        self.device_status_notification();
+        self.try_a2dp_resume();
+    }
 }
 
+#[derive(RPCProxyStub)]
+#[rpc_proxy(object_id = "HFP BatteryProvider Callback")]
 struct BatteryProviderCallback {}
 
 impl BatteryProviderCallback {
@@ -4069,9 +4163,3 @@ impl IBatteryProviderCallback for BatteryProviderCallback {
     // We do not support refreshing HFP battery information.
     fn refresh_battery_info(&mut self) {}
 }
-
-impl RPCProxy for BatteryProviderCallback {
-    fn get_object_id(&self) -> String {
-        "HFP BatteryProvider Callback".to_string()
-    }
-}
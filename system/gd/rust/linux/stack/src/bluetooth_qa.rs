@@ -1,9 +1,24 @@
 //! Anything related to the Qualification API (IBluetoothQA).
 
-use crate::callbacks::Callbacks;
-use crate::{bluetooth::FLOSS_VER, Message, RPCProxy};
-use bt_topshim::btif::{BtDiscMode, BtStatus, RawAddress};
+use crate::bluetooth::{Bluetooth, FLOSS_VER};
+use crate::bluetooth_gatt::BluetoothGatt;
+use crate::callbacks::{CallbackCount, Callbacks};
+use crate::socket_manager::BluetoothSocketManager;
+use crate::{make_message_dispatcher, Message, RPCProxy};
+use bt_topshim::btif::{
+    BtAddrType, BtDiscMode, BtHciErrorCode, BtStatus, DisplayAddress, RawAddress,
+};
+use bt_topshim::link::{Link, LinkCallbacks, LinkCallbacksDispatcher};
+use bt_topshim::metrics;
 use bt_topshim::profiles::hid_host::BthhReportType;
+use bt_topshim::sysprop;
+use log::{info, warn};
+use num_derive::{FromPrimitive, ToPrimitive};
+use num_traits::cast::{FromPrimitive, ToPrimitive};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::io::{Error, ErrorKind};
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc::Sender;
 
 /// Defines the Qualification API
@@ -43,6 +58,295 @@ pub trait IBluetoothQA {
     /// Sends HID virtual unplug to the peer.
     /// Result will be returned in the callback |OnSendHIDVirtualUnplugComplete|
     fn send_hid_virtual_unplug(&self, addr: RawAddress);
+    /// Returns the number of registered callbacks per callback type, e.g. "adapter" or
+    /// "scanner". Useful to spot clients that register callbacks but never unregister.
+    fn get_callback_counts(&self) -> HashMap<String, u32>;
+    /// Returns the most recent metrics events reported by the stack, oldest first.
+    fn get_recent_metrics(&self) -> Vec<String>;
+    /// Returns the number of stack events dropped so far because the message channel was full.
+    fn get_dropped_message_count(&self) -> u64;
+    /// Reads the error counters of the link to a connected device.
+    /// Result will be returned in the callback |OnGetLinkErrorCountersComplete|
+    fn get_link_error_counters(&self, addr: RawAddress);
+    /// Resets the Failed Contact Counter of the link to a connected device.
+    /// Result will be returned in the callback |OnResetFailedContactCounterComplete|
+    fn reset_failed_contact_counter(&self, addr: RawAddress);
+    /// Puts the link to a connected device into |mode|. |params| are only used for sniff mode.
+    /// Returns an error right away if the mode or the parameters are invalid, otherwise the
+    /// result will be returned in the callback |OnLinkPowerModeChanged|
+    fn set_link_power_mode(
+        &self,
+        addr: RawAddress,
+        mode: LinkPowerMode,
+        params: SniffParams,
+    ) -> BtStatus;
+    /// Reads the power mode of the link to a connected device.
+    /// Result will be returned in the callback |OnGetLinkPowerModeComplete|
+    fn get_link_power_mode(&self, addr: RawAddress);
+    /// Adds a device to the LE filter accept list so that the controller connects to it in the
+    /// background. Returns an error right away if the address type isn't public or random or the
+    /// device was already added, otherwise the result will be returned in the callback
+    /// |OnAddToAcceptListComplete|
+    fn add_to_accept_list(&mut self, addr: RawAddress, addr_type: BtAddrType) -> BtStatus;
+    /// Removes a device added with |add_to_accept_list| from the LE filter accept list.
+    fn remove_from_accept_list(&mut self, addr: RawAddress) -> BtStatus;
+    /// Returns the devices added with |add_to_accept_list|, in the order they were added.
+    fn get_accept_list(&self) -> Vec<AcceptListEntry>;
+    /// Configures the transports the adapter operates on. The mode is stored and re-applied
+    /// each time the adapter is enabled. Discovery, bonding and profile connections over a
+    /// disabled transport are refused. Fails if the adapter is off or the controller doesn't
+    /// support the mode.
+    fn set_adapter_mode(&mut self, mode: AdapterMode) -> BtStatus;
+    /// Returns the configured adapter mode.
+    fn get_adapter_mode(&self) -> AdapterMode;
+
+    /// Gets the inquiry scan parameters of the adapter.
+    fn get_scan_params(&self) -> ClassicScanParams;
+    /// Sets the inquiry scan parameters of the adapter. They are kept until the adapter restarts.
+    fn set_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus;
+    /// Gets the page scan parameters of the adapter.
+    fn get_page_scan_params(&self) -> ClassicScanParams;
+    /// Sets the page scan parameters of the adapter. They are kept until the adapter restarts.
+    fn set_page_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus;
+
+    /// Gets the devices with a pending bond cancel, i.e. whose bond state hasn't been reported
+    /// since the cancel was requested.
+    fn get_cancelling_devices(&self) -> Vec<RawAddress>;
+
+    /// Sets whether the Just-Works consent of a pairing initiated by us is accepted
+    /// automatically, which is the default. When disabled, the consent is forwarded to the
+    /// callbacks like the other SSP requests.
+    fn set_auto_accept_just_works(&mut self, enabled: bool);
+}
+
+/// Transports the adapter operates on.
+#[derive(Clone, Copy, Debug, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+#[repr(u32)]
+pub enum AdapterMode {
+    DualMode = 0,
+    BrEdrOnly = 1,
+    LeOnly = 2,
+}
+
+impl AdapterMode {
+    /// Returns whether BR/EDR and LE are enabled, in this order.
+    fn transports(&self) -> (bool, bool) {
+        match self {
+            AdapterMode::DualMode => (true, true),
+            AdapterMode::BrEdrOnly => (true, false),
+            AdapterMode::LeOnly => (false, true),
+        }
+    }
+}
+
+/// Reads the adapter mode stored at |path|.
+fn read_adapter_mode(path: &str) -> std::io::Result<AdapterMode> {
+    let json = serde_json::from_str::<Value>(&std::fs::read_to_string(path)?)?;
+    json.get("adapter_mode")
+        .and_then(Value::as_u64)
+        .and_then(AdapterMode::from_u64)
+        .ok_or(Error::new(ErrorKind::Other, "Invalid adapter mode"))
+}
+
+fn write_adapter_mode(path: &str, mode: AdapterMode) -> std::io::Result<()> {
+    std::fs::write(path, json!({ "adapter_mode": mode.to_u32() }).to_string())
+}
+
+/// Applies |mode| to the enabled |adapter|. Nothing needs to be done in dual mode, which is how
+/// the stack brings up the controller.
+fn apply_adapter_mode(adapter: &mut Bluetooth, mode: AdapterMode) {
+    if mode == AdapterMode::DualMode {
+        return;
+    }
+    let (br_edr, le) = mode.transports();
+    match adapter.set_transports_enabled(br_edr, le) {
+        Ok(()) => info!("Applied adapter mode {:?}", mode),
+        Err(status) => warn!("Failed to apply adapter mode {:?}: {:?}", mode, status),
+    }
+}
+
+/// Error counters maintained by the controller for the link to a remote device.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LinkErrorCounters {
+    /// Number of consecutive failed contacts, see Core 5.3, Vol 4, Part E, 7.5.1.
+    pub failed_contact_counter: u16,
+    /// HCI Link Quality, where 255 is the best. Not available on all links, e.g. LE.
+    pub link_quality: Option<u8>,
+}
+
+impl LinkErrorCounters {
+    /// Builds the counters from the results of the HCI reads. Fails if the Failed Contact Counter
+    /// couldn't be read; the Link Quality is optional.
+    fn from_hci(
+        failed_contact_counter_status: BtHciErrorCode,
+        failed_contact_counter: u16,
+        link_quality_status: BtHciErrorCode,
+        link_quality: u8,
+    ) -> Result<LinkErrorCounters, BtStatus> {
+        hci_status_to_bt_status(failed_contact_counter_status)?;
+        Ok(LinkErrorCounters {
+            failed_contact_counter,
+            link_quality: hci_status_to_bt_status(link_quality_status).ok().map(|_| link_quality),
+        })
+    }
+}
+
+/// Power mode of the ACL link to a remote device, see Core 5.3, Vol 4, Part E, 7.7.20.
+#[derive(Clone, Copy, Debug, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+#[repr(u32)]
+pub enum LinkPowerMode {
+    Active = 0,
+    Hold = 1,
+    Sniff = 2,
+    Park = 3,
+}
+
+/// Parameters of the HCI Sniff Mode command, see Core 5.3, Vol 4, Part E, 7.2.2. Intervals are in
+/// baseband slots of 0.625 ms.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SniffParams {
+    pub max_interval: u16,
+    pub min_interval: u16,
+    pub attempt: u16,
+    pub timeout: u16,
+}
+
+impl SniffParams {
+    fn is_valid(&self) -> bool {
+        let is_valid_interval = |interval: u16| (0x0002..=0xFFFE).contains(&interval);
+        is_valid_interval(self.max_interval)
+            && is_valid_interval(self.min_interval)
+            && self.min_interval <= self.max_interval
+            && (0x0001..=0x7FFF).contains(&self.attempt)
+            && self.timeout <= 0x7FFF
+    }
+}
+
+/// Type of the inquiry or page scan, see Core 5.3, Vol 4, Part E, 7.3.48 and 7.3.52.
+#[derive(Clone, Copy, Debug, FromPrimitive, ToPrimitive, PartialEq, Eq)]
+#[repr(u32)]
+pub enum ClassicScanType {
+    Standard = 0,
+    Interlaced = 1,
+}
+
+/// Parameters of the HCI Write Inquiry or Page Scan Activity and Type commands, see Core 5.3,
+/// Vol 4, Part E, 7.3.20, 7.3.22, 7.3.48 and 7.3.52. Interval and window are in baseband slots of
+/// 0.625 ms, and have the same range for both scans.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassicScanParams {
+    pub interval: u16,
+    pub window: u16,
+    pub scan_type: ClassicScanType,
+}
+
+impl ClassicScanParams {
+    fn is_valid(&self) -> bool {
+        (0x0012..=0x1000).contains(&self.interval)
+            && (0x0011..=0x1000).contains(&self.window)
+            && self.window <= self.interval
+    }
+}
+
+/// Checks whether a link can be put into |mode| with |params|. Only the active and sniff modes are
+/// supported, hold and park are deprecated by the Core spec.
+fn check_link_power_mode(mode: LinkPowerMode, params: &SniffParams) -> Result<(), BtStatus> {
+    match mode {
+        LinkPowerMode::Active => Ok(()),
+        LinkPowerMode::Sniff if params.is_valid() => Ok(()),
+        LinkPowerMode::Sniff => Err(BtStatus::InvalidParam),
+        LinkPowerMode::Hold | LinkPowerMode::Park => Err(BtStatus::Unsupported),
+    }
+}
+
+/// A device in the LE filter accept list.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AcceptListEntry {
+    pub address: RawAddress,
+    pub addr_type: BtAddrType,
+}
+
+/// Devices added to the LE filter accept list through this API. The list is shared with the
+/// stack, which may hold other devices, so only the additions made here are tracked. An addition
+/// stays pending until the stack confirms it since the list may be full.
+#[derive(Debug, Default)]
+struct AcceptList {
+    entries: Vec<AcceptListEntry>,
+    pending: Vec<AcceptListEntry>,
+}
+
+impl AcceptList {
+    fn contains(&self, addr: RawAddress) -> bool {
+        self.entries.iter().chain(self.pending.iter()).any(|entry| entry.address == addr)
+    }
+
+    /// Starts adding |entry|. Fails if the address type can't be used in the accept list or the
+    /// device is already in the list.
+    fn start_add(&mut self, entry: AcceptListEntry) -> Result<(), BtStatus> {
+        if !matches!(entry.addr_type, BtAddrType::Public | BtAddrType::Random) {
+            return Err(BtStatus::InvalidParam);
+        }
+        if self.contains(entry.address) {
+            return Err(BtStatus::Done);
+        }
+        self.pending.push(entry);
+        Ok(())
+    }
+
+    /// Completes the pending addition of |addr| with the |status| reported by the stack. Returns
+    /// None if no addition of |addr| is pending.
+    fn finish_add(&mut self, addr: RawAddress, status: BtHciErrorCode) -> Option<BtStatus> {
+        let index = self.pending.iter().position(|entry| entry.address == addr)?;
+        let entry = self.pending.remove(index);
+        let status = match status {
+            HCI_ERR_MEMORY_FULL => BtStatus::NoMemory,
+            _ => hci_status_to_bt_status(status).err().unwrap_or(BtStatus::Success),
+        };
+        if status == BtStatus::Success {
+            self.entries.push(entry);
+        }
+        Some(status)
+    }
+
+    fn remove(&mut self, addr: RawAddress) -> Option<AcceptListEntry> {
+        let index = self.entries.iter().position(|entry| entry.address == addr)?;
+        Some(self.entries.remove(index))
+    }
+}
+
+/// HCI error code for "Unknown Connection Identifier".
+const HCI_ERR_NO_CONNECTION: BtHciErrorCode = 0x02;
+/// HCI error code for "Memory Capacity Exceeded", reported when the accept list is full.
+const HCI_ERR_MEMORY_FULL: BtHciErrorCode = 0x07;
+
+fn hci_status_to_bt_status(status: BtHciErrorCode) -> Result<(), BtStatus> {
+    match status {
+        0 => Ok(()),
+        HCI_ERR_NO_CONNECTION => Err(BtStatus::RemoteDeviceDown),
+        _ => Err(BtStatus::Fail),
+    }
+}
+
+/// Converts a power mode reported by the stack. A mode change still in progress is reported as
+/// busy, with the link considered active until it completes.
+fn link_power_mode_from_hci(status: BtHciErrorCode, mode: u8) -> (BtStatus, LinkPowerMode) {
+    if let Err(status) = hci_status_to_bt_status(status) {
+        return (status, LinkPowerMode::Active);
+    }
+    match LinkPowerMode::from_u8(mode) {
+        Some(mode) => (BtStatus::Success, mode),
+        None => (BtStatus::Busy, LinkPowerMode::Active),
+    }
 }
 
 pub trait IBluetoothQACallback: RPCProxy {
@@ -54,20 +358,81 @@ pub trait IBluetoothQACallback: RPCProxy {
     fn on_set_hid_report_completed(&mut self, status: BtStatus);
     fn on_send_hid_data_completed(&mut self, status: BtStatus);
     fn on_send_hid_virtual_unplug_completed(&mut self, status: BtStatus);
+    fn on_get_link_error_counters_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        counters: LinkErrorCounters,
+    );
+    fn on_reset_failed_contact_counter_completed(&mut self, addr: RawAddress, status: BtStatus);
+    fn on_get_link_power_mode_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+    );
+    fn on_link_power_mode_changed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+        interval: u16,
+    );
+    fn on_add_to_accept_list_completed(&mut self, addr: RawAddress, status: BtStatus);
 }
 
 pub struct BluetoothQA {
     tx: Sender<Message>,
     callbacks: Callbacks<dyn IBluetoothQACallback + Send>,
+    adapter: Arc<Mutex<Box<Bluetooth>>>,
+    // Callback counts of the other interfaces. They are read without locking the interfaces, as
+    // this object is locked while serving requests.
+    callback_counts: HashMap<String, CallbackCount>,
+    link: Link,
+    // Devices whose error counters are read for QA callbacks, and for the adapter's connection
+    // quality requests. The Link reports both the same way.
+    error_counter_reads: HashSet<RawAddress>,
+    link_quality_reads: HashSet<RawAddress>,
+    accept_list: AcceptList,
+    adapter_mode_path: String,
+    adapter_mode: AdapterMode,
 }
 
 impl BluetoothQA {
-    pub fn new(tx: Sender<Message>) -> BluetoothQA {
+    pub fn new(
+        tx: Sender<Message>,
+        adapter: Arc<Mutex<Box<Bluetooth>>>,
+        gatt: Arc<Mutex<Box<BluetoothGatt>>>,
+        socket_manager: Arc<Mutex<Box<BluetoothSocketManager>>>,
+        adapter_mode_path: String,
+    ) -> BluetoothQA {
+        let adapter_mode = read_adapter_mode(&adapter_mode_path).unwrap_or(AdapterMode::DualMode);
+        let mut callback_counts = adapter.lock().unwrap().get_callback_counts();
+        callback_counts.extend(gatt.lock().unwrap().get_callback_counts());
+        callback_counts.insert(
+            String::from("socket_manager"),
+            socket_manager.lock().unwrap().get_callback_count(),
+        );
         BluetoothQA {
             tx: tx.clone(),
             callbacks: Callbacks::new(tx.clone(), Message::QaCallbackDisconnected),
+            adapter,
+            callback_counts,
+            link: Link::new(LinkCallbacksDispatcher {
+                dispatch: make_message_dispatcher(tx.clone(), Message::QaLink),
+            }),
+            error_counter_reads: HashSet::new(),
+            link_quality_reads: HashSet::new(),
+            accept_list: AcceptList::default(),
+            adapter_mode_path,
+            adapter_mode,
         }
     }
+
+    /// Re-applies the configured adapter mode once the adapter is enabled.
+    pub(crate) fn apply_adapter_mode(&mut self) {
+        apply_adapter_mode(&mut self.adapter.lock().unwrap(), self.adapter_mode);
+    }
     pub fn on_fetch_discoverable_mode_completed(&mut self, mode: BtDiscMode) {
         self.callbacks.for_all_callbacks(|cb| {
             cb.on_fetch_discoverable_mode_completed(mode.clone());
@@ -108,6 +473,152 @@ impl BluetoothQA {
             cb.on_send_hid_virtual_unplug_completed(status);
         });
     }
+
+    fn is_connected(&self, addr: RawAddress) -> bool {
+        self.adapter.lock().unwrap().get_acl_state_by_addr(&addr)
+    }
+
+    pub(crate) fn read_link_error_counters(&mut self, addr: RawAddress) {
+        if !self.is_connected(addr) {
+            self.on_get_link_error_counters_completed(addr, Err(BtStatus::RemoteDeviceDown));
+            return;
+        }
+        self.error_counter_reads.insert(addr);
+        self.link.read_error_counters(addr);
+    }
+
+    /// Reads the HCI Link Quality of |addr| on behalf of the adapter's connection quality request.
+    pub(crate) fn read_link_quality(&mut self, addr: RawAddress) {
+        if !self.is_connected(addr) {
+            self.adapter.lock().unwrap().on_link_quality_read(addr, None);
+            return;
+        }
+        self.link_quality_reads.insert(addr);
+        self.link.read_error_counters(addr);
+    }
+
+    pub(crate) fn reset_link_failed_contact_counter(&mut self, addr: RawAddress) {
+        if !self.is_connected(addr) {
+            self.on_reset_failed_contact_counter_completed(addr, BtStatus::RemoteDeviceDown);
+            return;
+        }
+        self.link.reset_failed_contact_counter(addr);
+    }
+
+    pub(crate) fn set_link_power_mode_internal(
+        &mut self,
+        addr: RawAddress,
+        mode: LinkPowerMode,
+        params: SniffParams,
+    ) {
+        if !self.is_connected(addr) {
+            self.on_link_power_mode_changed(
+                addr,
+                BtStatus::RemoteDeviceDown,
+                LinkPowerMode::Active,
+                0,
+            );
+            return;
+        }
+        self.link.set_power_mode(
+            addr,
+            mode as u8,
+            params.max_interval,
+            params.min_interval,
+            params.attempt,
+            params.timeout,
+        );
+    }
+
+    pub(crate) fn read_link_power_mode(&mut self, addr: RawAddress) {
+        if !self.is_connected(addr) {
+            self.on_get_link_power_mode_completed(
+                addr,
+                BtStatus::RemoteDeviceDown,
+                LinkPowerMode::Active,
+            );
+            return;
+        }
+        self.link.read_power_mode(addr);
+    }
+
+    pub(crate) fn dispatch_link_callbacks(&mut self, cb: LinkCallbacks) {
+        match cb {
+            LinkCallbacks::ErrorCountersRead(addr, fcc_status, fcc, lq_status, lq) => {
+                let result = LinkErrorCounters::from_hci(fcc_status, fcc, lq_status, lq);
+                if self.link_quality_reads.remove(&addr) {
+                    let link_quality = result.as_ref().ok().and_then(|c| c.link_quality);
+                    self.adapter.lock().unwrap().on_link_quality_read(addr, link_quality);
+                }
+                if self.error_counter_reads.remove(&addr) {
+                    self.on_get_link_error_counters_completed(addr, result);
+                }
+            }
+            LinkCallbacks::FailedContactCounterReset(addr, status) => {
+                let status = hci_status_to_bt_status(status).err().unwrap_or(BtStatus::Success);
+                self.on_reset_failed_contact_counter_completed(addr, status);
+            }
+            LinkCallbacks::PowerModeChanged(addr, status, mode, interval) => {
+                let (status, mode) = link_power_mode_from_hci(status, mode);
+                self.on_link_power_mode_changed(addr, status, mode, interval);
+            }
+            LinkCallbacks::PowerModeRead(addr, status, mode) => {
+                let (status, mode) = link_power_mode_from_hci(status, mode);
+                self.on_get_link_power_mode_completed(addr, status, mode);
+            }
+            LinkCallbacks::AcceptListAdd(addr, status) => {
+                match self.accept_list.finish_add(addr, status) {
+                    Some(status) => self.callbacks.for_all_callbacks(|cb| {
+                        cb.on_add_to_accept_list_completed(addr, status);
+                    }),
+                    None => warn!("Unexpected accept list addition of {}", DisplayAddress(&addr)),
+                }
+            }
+        }
+    }
+
+    fn on_get_link_power_mode_completed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+    ) {
+        self.callbacks.for_all_callbacks(|cb| {
+            cb.on_get_link_power_mode_completed(addr, status, mode);
+        });
+    }
+
+    fn on_link_power_mode_changed(
+        &mut self,
+        addr: RawAddress,
+        status: BtStatus,
+        mode: LinkPowerMode,
+        interval: u16,
+    ) {
+        self.callbacks.for_all_callbacks(|cb| {
+            cb.on_link_power_mode_changed(addr, status, mode, interval);
+        });
+    }
+
+    fn on_get_link_error_counters_completed(
+        &mut self,
+        addr: RawAddress,
+        result: Result<LinkErrorCounters, BtStatus>,
+    ) {
+        let (status, counters) = match result {
+            Ok(counters) => (BtStatus::Success, counters),
+            Err(status) => (status, LinkErrorCounters::default()),
+        };
+        self.callbacks.for_all_callbacks(|cb| {
+            cb.on_get_link_error_counters_completed(addr, status, counters.clone());
+        });
+    }
+
+    fn on_reset_failed_contact_counter_completed(&mut self, addr: RawAddress, status: BtStatus) {
+        self.callbacks.for_all_callbacks(|cb| {
+            cb.on_reset_failed_contact_counter_completed(addr, status);
+        });
+    }
 }
 
 impl IBluetoothQA for BluetoothQA {
@@ -181,4 +692,333 @@ impl IBluetoothQA for BluetoothQA {
             let _ = txl.send(Message::QaSendHidVirtualUnplug(addr)).await;
         });
     }
+    fn get_callback_counts(&self) -> HashMap<String, u32> {
+        let mut counts: HashMap<String, u32> = self
+            .callback_counts
+            .iter()
+            .map(|(name, count)| (name.clone(), count.get() as u32))
+            .collect();
+        counts.insert(String::from("qa"), self.callbacks.count() as u32);
+        counts
+    }
+
+    fn get_recent_metrics(&self) -> Vec<String> {
+        metrics::recent_events()
+    }
+
+    fn get_dropped_message_count(&self) -> u64 {
+        crate::get_dropped_message_count()
+    }
+
+    fn get_link_error_counters(&self, addr: RawAddress) {
+        let txl = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = txl.send(Message::QaGetLinkErrorCounters(addr)).await;
+        });
+    }
+
+    fn reset_failed_contact_counter(&self, addr: RawAddress) {
+        let txl = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = txl.send(Message::QaResetFailedContactCounter(addr)).await;
+        });
+    }
+
+    fn set_link_power_mode(
+        &self,
+        addr: RawAddress,
+        mode: LinkPowerMode,
+        params: SniffParams,
+    ) -> BtStatus {
+        if let Err(status) = check_link_power_mode(mode, &params) {
+            return status;
+        }
+        let txl = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = txl.send(Message::QaSetLinkPowerMode(addr, mode, params)).await;
+        });
+        BtStatus::Success
+    }
+
+    fn get_link_power_mode(&self, addr: RawAddress) {
+        let txl = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = txl.send(Message::QaGetLinkPowerMode(addr)).await;
+        });
+    }
+
+    fn add_to_accept_list(&mut self, addr: RawAddress, addr_type: BtAddrType) -> BtStatus {
+        let entry = AcceptListEntry { address: addr, addr_type: addr_type.clone() };
+        if let Err(status) = self.accept_list.start_add(entry) {
+            return status;
+        }
+        self.link.add_to_accept_list(addr, addr_type);
+        BtStatus::Success
+    }
+
+    fn remove_from_accept_list(&mut self, addr: RawAddress) -> BtStatus {
+        match self.accept_list.remove(addr) {
+            Some(entry) => {
+                self.link.remove_from_accept_list(entry.address, entry.addr_type);
+                BtStatus::Success
+            }
+            None => BtStatus::DeviceNotFound,
+        }
+    }
+
+    fn get_accept_list(&self) -> Vec<AcceptListEntry> {
+        self.accept_list.entries.clone()
+    }
+
+    fn set_adapter_mode(&mut self, mode: AdapterMode) -> BtStatus {
+        let (br_edr, le) = mode.transports();
+        if let Err(status) = self.adapter.lock().unwrap().set_transports_enabled(br_edr, le) {
+            return status;
+        }
+        self.adapter_mode = mode;
+        if let Err(e) = write_adapter_mode(&self.adapter_mode_path, mode) {
+            warn!("Failed to store adapter mode {:?}: {}", mode, e);
+        }
+        BtStatus::Success
+    }
+
+    fn get_adapter_mode(&self) -> AdapterMode {
+        self.adapter_mode
+    }
+
+    fn get_scan_params(&self) -> ClassicScanParams {
+        ClassicScanParams {
+            interval: sysprop::get_i32(sysprop::PropertyI32::InquiryScanInterval) as u16,
+            window: sysprop::get_i32(sysprop::PropertyI32::InquiryScanWindow) as u16,
+            scan_type: ClassicScanType::from_i32(sysprop::get_i32(
+                sysprop::PropertyI32::InquiryScanType,
+            ))
+            .unwrap_or(ClassicScanType::Interlaced),
+        }
+    }
+
+    fn set_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus {
+        let params = ClassicScanParams { interval, window, scan_type };
+        if !params.is_valid() {
+            return BtStatus::InvalidParam;
+        }
+        match self.adapter.lock().unwrap().write_inquiry_scan_params(
+            params.interval,
+            params.window,
+            params.scan_type as u8,
+        ) {
+            Ok(()) => BtStatus::Success,
+            Err(status) => status,
+        }
+    }
+
+    fn get_page_scan_params(&self) -> ClassicScanParams {
+        ClassicScanParams {
+            interval: sysprop::get_i32(sysprop::PropertyI32::PageScanInterval) as u16,
+            window: sysprop::get_i32(sysprop::PropertyI32::PageScanWindow) as u16,
+            scan_type: ClassicScanType::from_i32(sysprop::get_i32(
+                sysprop::PropertyI32::PageScanType,
+            ))
+            .unwrap_or(ClassicScanType::Interlaced),
+        }
+    }
+
+    fn set_page_scan_params(
+        &mut self,
+        interval: u16,
+        window: u16,
+        scan_type: ClassicScanType,
+    ) -> BtStatus {
+        let params = ClassicScanParams { interval, window, scan_type };
+        if !params.is_valid() {
+            return BtStatus::InvalidParam;
+        }
+        match self.adapter.lock().unwrap().write_page_scan_params(
+            params.interval,
+            params.window,
+            params.scan_type as u8,
+        ) {
+            Ok(()) => BtStatus::Success,
+            Err(status) => status,
+        }
+    }
+
+    fn get_cancelling_devices(&self) -> Vec<RawAddress> {
+        self.adapter.lock().unwrap().get_cancelling_devices()
+    }
+
+    fn set_auto_accept_just_works(&mut self, enabled: bool) {
+        self.adapter.lock().unwrap().set_auto_accept_just_works(enabled);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_error_counters_from_hci() {
+        assert_eq!(
+            LinkErrorCounters::from_hci(0, 3, 0, 200),
+            Ok(LinkErrorCounters { failed_contact_counter: 3, link_quality: Some(200) })
+        );
+
+        // Link Quality isn't supported on LE links, the counter alone is still reported.
+        assert_eq!(
+            LinkErrorCounters::from_hci(0, 0, 0x01, 0),
+            Ok(LinkErrorCounters { failed_contact_counter: 0, link_quality: None })
+        );
+
+        assert_eq!(
+            LinkErrorCounters::from_hci(HCI_ERR_NO_CONNECTION, 0, HCI_ERR_NO_CONNECTION, 0),
+            Err(BtStatus::RemoteDeviceDown)
+        );
+        assert_eq!(LinkErrorCounters::from_hci(0x0c, 0, 0, 0), Err(BtStatus::Fail));
+    }
+
+    #[test]
+    fn test_reset_status_from_hci() {
+        assert_eq!(hci_status_to_bt_status(0), Ok(()));
+        assert_eq!(hci_status_to_bt_status(HCI_ERR_NO_CONNECTION), Err(BtStatus::RemoteDeviceDown));
+        assert_eq!(hci_status_to_bt_status(0x0c), Err(BtStatus::Fail));
+    }
+
+    #[test]
+    fn test_enter_and_exit_sniff_mode() {
+        let params = SniffParams { max_interval: 800, min_interval: 400, attempt: 4, timeout: 1 };
+        assert_eq!(check_link_power_mode(LinkPowerMode::Sniff, &params), Ok(()));
+        assert_eq!(link_power_mode_from_hci(0, 2), (BtStatus::Success, LinkPowerMode::Sniff));
+
+        // Exiting sniff mode doesn't need any parameters.
+        assert_eq!(check_link_power_mode(LinkPowerMode::Active, &SniffParams::default()), Ok(()));
+        assert_eq!(link_power_mode_from_hci(0, 0), (BtStatus::Success, LinkPowerMode::Active));
+
+        // A pending mode change and a disconnected link.
+        assert_eq!(link_power_mode_from_hci(0, 5), (BtStatus::Busy, LinkPowerMode::Active));
+        assert_eq!(
+            link_power_mode_from_hci(HCI_ERR_NO_CONNECTION, 0xef),
+            (BtStatus::RemoteDeviceDown, LinkPowerMode::Active)
+        );
+    }
+
+    #[test]
+    fn test_classic_scan_params_validation() {
+        let params = |interval, window| ClassicScanParams {
+            interval,
+            window,
+            scan_type: ClassicScanType::Standard,
+        };
+        assert!(params(0x0800, 0x0012).is_valid());
+        assert!(params(0x0012, 0x0012).is_valid());
+        assert!(params(0x1000, 0x1000).is_valid());
+        assert!(params(0x0012, 0x0011).is_valid());
+        // The window can't be longer than the interval.
+        assert!(!params(0x0012, 0x0013).is_valid());
+        // Out of the HCI range.
+        assert!(!params(0x0011, 0x0011).is_valid());
+        assert!(!params(0x1001, 0x0012).is_valid());
+        assert!(!params(0x0800, 0x0010).is_valid());
+    }
+
+    #[test]
+    fn test_sniff_params_validation() {
+        let valid = SniffParams { max_interval: 800, min_interval: 400, attempt: 4, timeout: 1 };
+        let check = |params: SniffParams| check_link_power_mode(LinkPowerMode::Sniff, &params);
+
+        assert_eq!(check(SniffParams::default()), Err(BtStatus::InvalidParam));
+        assert_eq!(
+            check(SniffParams { min_interval: 802, ..valid.clone() }),
+            Err(BtStatus::InvalidParam)
+        );
+        assert_eq!(
+            check(SniffParams { max_interval: 0xFFFF, ..valid.clone() }),
+            Err(BtStatus::InvalidParam)
+        );
+        assert_eq!(check(SniffParams { attempt: 0, ..valid.clone() }), Err(BtStatus::InvalidParam));
+        assert_eq!(
+            check(SniffParams { timeout: 0x8000, ..valid.clone() }),
+            Err(BtStatus::InvalidParam)
+        );
+        assert_eq!(check(SniffParams { min_interval: 800, ..valid.clone() }), Ok(()));
+
+        assert_eq!(check_link_power_mode(LinkPowerMode::Hold, &valid), Err(BtStatus::Unsupported));
+        assert_eq!(check_link_power_mode(LinkPowerMode::Park, &valid), Err(BtStatus::Unsupported));
+    }
+
+    #[test]
+    fn test_accept_list_add_remove_show() {
+        let addr1 = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let addr2 = RawAddress::from_string("C0:11:22:33:44:55").unwrap();
+        let entry1 = AcceptListEntry { address: addr1, addr_type: BtAddrType::Public };
+        let entry2 = AcceptListEntry { address: addr2, addr_type: BtAddrType::Random };
+        let mut list = AcceptList::default();
+
+        assert_eq!(list.start_add(entry1.clone()), Ok(()));
+        assert_eq!(list.start_add(entry2.clone()), Ok(()));
+        assert_eq!(list.start_add(entry1.clone()), Err(BtStatus::Done));
+
+        // Additions only show up once the stack confirms them.
+        assert!(list.entries.is_empty());
+        assert_eq!(list.finish_add(addr2, 0), Some(BtStatus::Success));
+        assert_eq!(list.finish_add(addr1, 0), Some(BtStatus::Success));
+        assert_eq!(list.finish_add(addr1, 0), None);
+        assert_eq!(list.entries, vec![entry2.clone(), entry1.clone()]);
+
+        assert_eq!(list.remove(addr2), Some(entry2.clone()));
+        assert_eq!(list.remove(addr2), None);
+        assert_eq!(list.entries, vec![entry1.clone()]);
+
+        // A removed device can be added again.
+        assert_eq!(list.start_add(entry2.clone()), Ok(()));
+        assert_eq!(list.finish_add(addr2, 0), Some(BtStatus::Success));
+        assert_eq!(list.entries, vec![entry1, entry2]);
+    }
+
+    #[test]
+    fn test_accept_list_full() {
+        let addr = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let entry = AcceptListEntry { address: addr, addr_type: BtAddrType::Public };
+        let mut list = AcceptList::default();
+
+        assert_eq!(list.start_add(entry.clone()), Ok(()));
+        assert_eq!(list.finish_add(addr, HCI_ERR_MEMORY_FULL), Some(BtStatus::NoMemory));
+        assert!(list.entries.is_empty());
+        assert!(!list.contains(addr));
+
+        // The device can be retried once room was made.
+        assert_eq!(list.start_add(entry.clone()), Ok(()));
+        assert_eq!(list.finish_add(addr, 0), Some(BtStatus::Success));
+        assert_eq!(list.entries, vec![entry]);
+
+        let anonymous = AcceptListEntry { address: addr, addr_type: BtAddrType::Anonymous };
+        assert_eq!(list.start_add(anonymous), Err(BtStatus::InvalidParam));
+    }
+
+    #[test]
+    fn test_adapter_mode_reapplied_after_restart() {
+        let path = std::env::temp_dir().join(format!("adapter_mode_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path);
+
+        // Nothing is stored before the mode is configured.
+        assert!(read_adapter_mode(path).is_err());
+
+        write_adapter_mode(path, AdapterMode::LeOnly).unwrap();
+        // A restarted stack reads back the mode and turns off BR/EDR only.
+        let mode = read_adapter_mode(path).unwrap();
+        assert_eq!(mode, AdapterMode::LeOnly);
+        assert_eq!(mode.transports(), (false, true));
+
+        write_adapter_mode(path, AdapterMode::BrEdrOnly).unwrap();
+        assert_eq!(read_adapter_mode(path).unwrap().transports(), (true, false));
+
+        std::fs::write(path, "{\"adapter_mode\": 7}").unwrap();
+        assert!(read_adapter_mode(path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
 }
@@ -1,13 +1,31 @@
 //! Provides utilities for managing callbacks.
 
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc::Sender;
 
 use crate::{Message, RPCProxy};
 
+/// Number of callbacks in a `Callbacks`, readable without locking the object owning them.
+#[derive(Clone, Default)]
+pub struct CallbackCount(Arc<AtomicUsize>);
+
+impl CallbackCount {
+    /// Returns the number of registered callbacks.
+    pub fn get(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, count: usize) {
+        self.0.store(count, Ordering::Relaxed);
+    }
+}
+
 /// Utility for managing callbacks conveniently.
 pub struct Callbacks<T: Send + ?Sized> {
     callbacks: HashMap<u32, Box<T>>,
+    shared_count: CallbackCount,
     object_id_to_cbid: HashMap<String, u32>,
     tx: Sender<Message>,
     disconnected_message: fn(u32) -> Message,
@@ -22,6 +40,7 @@ impl<T: RPCProxy + Send + ?Sized> Callbacks<T> {
     pub fn new(tx: Sender<Message>, disconnected_message: fn(u32) -> Message) -> Self {
         Self {
             callbacks: HashMap::new(),
+            shared_count: CallbackCount::default(),
             object_id_to_cbid: HashMap::new(),
             tx,
             disconnected_message,
@@ -51,6 +70,7 @@ impl<T: RPCProxy + Send + ?Sized> Callbacks<T> {
 
         self.object_id_to_cbid.insert(callback.get_object_id(), id);
         self.callbacks.insert(id, callback);
+        self.shared_count.set(self.callbacks.len());
         id
     }
 
@@ -68,12 +88,23 @@ impl<T: RPCProxy + Send + ?Sized> Callbacks<T> {
                 // Remove the proxy object.
                 self.object_id_to_cbid.remove(&callback.get_object_id());
                 self.callbacks.remove(&id);
+                self.shared_count.set(self.callbacks.len());
                 true
             }
             None => false,
         }
     }
 
+    /// Returns the number of registered callbacks.
+    pub fn count(&self) -> usize {
+        self.callbacks.len()
+    }
+
+    /// Returns a handle following the number of registered callbacks.
+    pub fn shared_count(&self) -> CallbackCount {
+        self.shared_count.clone()
+    }
+
     /// Returns the callback object based on the given id.
     pub fn get_by_id(&self, id: u32) -> Option<&Box<T>> {
         self.callbacks.get(&id)
@@ -90,6 +121,19 @@ impl<T: RPCProxy + Send + ?Sized> Callbacks<T> {
             f(callback);
         }
     }
+
+    /// Applies the given function on the callback with the given id.
+    ///
+    /// Returns true if the callback is found, false if there is no such id.
+    pub fn for_one_callback<F: FnOnce(&mut T)>(&mut self, id: u32, f: F) -> bool {
+        match self.callbacks.get_mut(&id) {
+            Some(callback) => {
+                f(callback);
+                true
+            }
+            None => false,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -152,4 +196,50 @@ mod tests {
         let cbid2 = callbacks.add_callback(Box::new(TestCallback::new(cb_string.clone())));
         assert_ne!(cbid, cbid2);
     }
+
+    #[test]
+    fn test_for_one_callback() {
+        let (tx, _rx) = crate::Stack::create_channel();
+        let mut callbacks = Callbacks::new(tx.clone(), Message::AdapterCallbackDisconnected);
+
+        let cbid1 = callbacks.add_callback(Box::new(TestCallback::new(String::from("First"))));
+        let cbid2 = callbacks.add_callback(Box::new(TestCallback::new(String::from("Second"))));
+
+        let mut invoked = vec![];
+        assert!(callbacks.for_one_callback(cbid2, |cb| invoked.push(cb.get_object_id())));
+        assert_eq!(invoked, vec![String::from("Second")]);
+
+        // Unknown ids don't invoke anything.
+        let unknown = cbid1.max(cbid2) + 1;
+        assert!(!callbacks.for_one_callback(unknown, |cb| invoked.push(cb.get_object_id())));
+        assert_eq!(invoked, vec![String::from("Second")]);
+    }
+
+    #[test]
+    fn test_count() {
+        let (tx, _rx) = crate::Stack::create_channel();
+        let mut callbacks = Callbacks::new(tx.clone(), Message::AdapterCallbackDisconnected);
+        let shared_count = callbacks.shared_count();
+        assert_eq!(callbacks.count(), 0);
+
+        let cbid1 = callbacks.add_callback(Box::new(TestCallback::new(String::from("First"))));
+        let cbid2 = callbacks.add_callback(Box::new(TestCallback::new(String::from("Second"))));
+        assert_eq!(callbacks.count(), 2);
+
+        // Registering the same object again doesn't add a new callback.
+        callbacks.add_callback(Box::new(TestCallback::new(String::from("First"))));
+        assert_eq!(callbacks.count(), 2);
+
+        assert_eq!(shared_count.get(), 2);
+
+        assert!(callbacks.remove_callback(cbid1));
+        assert_eq!(callbacks.count(), 1);
+        assert_eq!(shared_count.get(), 1);
+        assert!(callbacks.remove_callback(cbid2));
+        assert_eq!(callbacks.count(), 0);
+
+        // Removing an unknown callback leaves the count alone.
+        assert!(!callbacks.remove_callback(cbid2));
+        assert_eq!(callbacks.count(), 0);
+    }
 }
@@ -0,0 +1,115 @@
+//! Names of Bluetooth SIG company identifiers.
+//!
+//! Company identifiers are reported as the manufacturer of a controller and prefix manufacturer
+//! specific data. Only a subset of the Bluetooth Assigned Numbers is listed here, mostly
+//! controller vendors.
+
+use std::fmt::{Display, Formatter, Result};
+
+/// Known company identifiers, sorted by identifier.
+const COMPANIES: &[(u16, &str)] = &[
+    (0x0000, "Ericsson"),
+    (0x0001, "Nokia"),
+    (0x0002, "Intel"),
+    (0x0003, "IBM"),
+    (0x0004, "Toshiba"),
+    (0x0005, "3Com"),
+    (0x0006, "Microsoft"),
+    (0x0007, "Lucent"),
+    (0x0008, "Motorola"),
+    (0x0009, "Infineon Technologies"),
+    (0x000A, "Qualcomm Technologies International"),
+    (0x000B, "Silicon Wave"),
+    (0x000C, "Digianswer"),
+    (0x000D, "Texas Instruments"),
+    (0x000E, "Parthus Technologies"),
+    (0x000F, "Broadcom"),
+    (0x0010, "Mitel Semiconductor"),
+    (0x0011, "Widcomm"),
+    (0x0012, "Zeevo"),
+    (0x0013, "Atmel"),
+    (0x0014, "Mitsubishi Electric"),
+    (0x0015, "RTX Telecom"),
+    (0x0016, "KC Technology"),
+    (0x0017, "Newlogic"),
+    (0x0018, "Transilica"),
+    (0x0019, "Rohde & Schwarz"),
+    (0x001A, "TTPCom"),
+    (0x001B, "Signia Technologies"),
+    (0x001C, "Conexant Systems"),
+    (0x001D, "Qualcomm"),
+    (0x001E, "Inventel"),
+    (0x001F, "AVM Berlin"),
+    (0x0020, "BandSpeed"),
+    (0x0021, "Mansella"),
+    (0x0022, "NEC"),
+    (0x0023, "WavePlus Technology"),
+    (0x0024, "Alcatel"),
+    (0x0025, "NXP Semiconductors"),
+    (0x0026, "C Technologies"),
+    (0x0027, "Open Interface"),
+    (0x0028, "RF Micro Devices"),
+    (0x0029, "Hitachi"),
+    (0x002A, "Symbol Technologies"),
+    (0x002B, "Tenovis"),
+    (0x002C, "Macronix International"),
+    (0x002D, "GCT Semiconductor"),
+    (0x002E, "Norwood Systems"),
+    (0x002F, "MewTel Technology"),
+    (0x0030, "STMicroelectronics"),
+    (0x0031, "Synopsys"),
+    (0x0046, "MediaTek"),
+    (0x0048, "Marvell"),
+    (0x004C, "Apple"),
+    (0x0059, "Nordic Semiconductor"),
+    (0x005D, "Realtek Semiconductor"),
+    (0x0075, "Samsung Electronics"),
+    (0x00D2, "Dialog Semiconductor"),
+    (0x00E0, "Google"),
+    (0x0131, "Cypress Semiconductor"),
+    (0x073F, "Unisoc"),
+    (0x0A76, "Synaptics"),
+];
+
+/// Returns the name of the company with identifier |id|, or None if it isn't known.
+pub fn company_name(id: u16) -> Option<&'static str> {
+    COMPANIES.binary_search_by_key(&id, |(c, _)| *c).ok().map(|i| COMPANIES[i].1)
+}
+
+/// Wrapper for printing a company identifier with its name, e.g. "Intel (0x0002)".
+pub struct DisplayCompany(pub u16);
+
+impl Display for DisplayCompany {
+    fn fmt(&self, f: &mut Formatter) -> Result {
+        match company_name(self.0) {
+            Some(name) => write!(f, "{} (0x{:04X})", name, self.0),
+            None => write!(f, "0x{:04X}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_companies_sorted() {
+        assert!(COMPANIES.windows(2).all(|w| w[0].0 < w[1].0));
+    }
+
+    #[test]
+    fn test_company_name() {
+        assert_eq!(company_name(0x0000), Some("Ericsson"));
+        assert_eq!(company_name(0x0002), Some("Intel"));
+        assert_eq!(company_name(0x005D), Some("Realtek Semiconductor"));
+        assert_eq!(company_name(0x0A76), Some("Synaptics"));
+        assert_eq!(company_name(0x0032), None);
+        assert_eq!(company_name(0xFFFF), None);
+    }
+
+    #[test]
+    fn test_display_company() {
+        assert_eq!(DisplayCompany(0x000F).to_string(), "Broadcom (0x000F)");
+        assert_eq!(DisplayCompany(0xFFFF).to_string(), "0xFFFF");
+    }
+}
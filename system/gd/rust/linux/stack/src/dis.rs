@@ -13,6 +13,7 @@ use crate::{Message, RPCProxy};
 use bt_topshim::btif::{RawAddress, Uuid};
 use bt_topshim::profiles::gatt::{GattStatus, LePhy};
 use bt_topshim::sysprop;
+use btif_macros::RPCProxyStub;
 
 /// Random uuid generated for registering against gatt server.
 const DIS_APP_RANDOM_UUID: &str = "1b518948-fd77-4459-906f-4923104bb639";
@@ -146,6 +147,8 @@ pub enum ServiceCallbacks {
 }
 
 // Handle callbacks for DIS to register
+#[derive(RPCProxyStub)]
+#[rpc_proxy(object_id = "DIS Gatt Server Callback")]
 struct DeviceInformationServerCallbacks {
     // Sender to the main loop
     tx: Sender<Message>,
@@ -267,9 +270,3 @@ impl IBluetoothGattServerCallback for DeviceInformationServerCallbacks {
     ) {
     }
 }
-
-impl RPCProxy for DeviceInformationServerCallbacks {
-    fn get_object_id(&self) -> String {
-        "DIS Gatt Server Callback".to_string()
-    }
-}
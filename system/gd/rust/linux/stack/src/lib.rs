@@ -3,6 +3,7 @@
 //! This crate provides the API implementation of the Fluoride/GD Bluetooth
 //! stack, independent of any RPC projection.
 
+pub mod appearance;
 pub mod battery_manager;
 pub mod battery_provider_manager;
 pub mod battery_service;
@@ -14,16 +15,18 @@ pub mod bluetooth_logging;
 pub mod bluetooth_media;
 pub mod bluetooth_qa;
 pub mod callbacks;
+pub mod company;
 pub mod dis;
 pub mod socket_manager;
 pub mod suspend;
 pub mod uuid;
 
-use bluetooth_qa::{BluetoothQA, IBluetoothQA};
-use log::{debug, info};
+use bluetooth_qa::{BluetoothQA, IBluetoothQA, LinkPowerMode, SniffParams};
+use log::{debug, info, warn};
 use num_derive::{FromPrimitive, ToPrimitive};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use tokio::sync::mpsc::channel;
+use tokio::sync::mpsc::{channel, unbounded_channel};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tokio::time::{sleep, Duration};
 
@@ -48,6 +51,7 @@ use crate::socket_manager::{BluetoothSocketManager, SocketActions};
 use crate::suspend::Suspend;
 use bt_topshim::{
     btif::{BaseCallbacks, BtAclState, BtBondState, BtTransport, DisplayAddress, RawAddress, Uuid},
+    link::LinkCallbacks,
     profiles::{
         a2dp::A2dpCallbacks,
         avrcp::AvrcpCallbacks,
@@ -72,6 +76,8 @@ pub enum Message {
     InterfaceShutdown,
     /// Disable the adapter by calling btif disable.
     AdapterShutdown,
+    /// Disconnect connected devices first and disable the adapter once they are gone.
+    AdapterShutdownGraceful,
     /// Clean up the adapter by calling btif cleanup.
     Cleanup,
     /// Clean up the media by calling profile cleanup.
@@ -100,7 +106,9 @@ pub enum Message {
 
     // Actions within the stack
     Media(MediaActions),
+    MediaCallbackRegistered(u32),
     MediaCallbackDisconnected(u32),
+    TelephonyCallbackRegistered(u32),
     TelephonyCallbackDisconnected(u32),
 
     // Client callback disconnections
@@ -160,6 +168,9 @@ pub enum Message {
     // Device removal
     DisconnectDevice(BluetoothDevice),
 
+    // Reads the HCI Link Quality of a connection quality request
+    ReadLinkQuality(RawAddress),
+
     // Qualification Only
     QaCallbackDisconnected(u32),
     QaAddMediaPlayer(String, bool),
@@ -172,6 +183,11 @@ pub enum Message {
     QaSetHidReport(RawAddress, BthhReportType, String),
     QaSendHidData(RawAddress, String),
     QaSendHidVirtualUnplug(RawAddress),
+    QaGetLinkErrorCounters(RawAddress),
+    QaResetFailedContactCounter(RawAddress),
+    QaSetLinkPowerMode(RawAddress, LinkPowerMode, SniffParams),
+    QaGetLinkPowerMode(RawAddress),
+    QaLink(LinkCallbacks),
 
     // UHid callbacks
     UHidHfpOutputCallback(RawAddress, u8, u8),
@@ -183,11 +199,49 @@ pub enum Message {
     ProfileDisconnected(RawAddress),
 }
 
+/// What a message dispatcher does with a message when its queue is full.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FullQueuePolicy {
+    /// Queue the message anyway, it is sent once the queue drains.
+    Queue,
+    /// Drop the message. Only for events that are superseded by later ones of the same kind.
+    Drop,
+}
+
+impl Message {
+    /// Returns how this message is handled by the message dispatchers when their queue is full.
+    pub(crate) fn full_queue_policy(&self) -> FullQueuePolicy {
+        match self {
+            // Advertisements are repeated by the remote, so a missed scan result is reported again.
+            Message::LeScanner(GattScannerCallbacks::OnScanResult(..)) => FullQueuePolicy::Drop,
+            _ => FullQueuePolicy::Queue,
+        }
+    }
+}
+
+/// Number of messages a dispatcher queues before dropping those with |FullQueuePolicy::Drop|.
+const DISPATCH_QUEUE_CAPACITY: usize = 256;
+
+/// Number of queued messages of a dispatcher at which a warning is logged.
+const DISPATCH_QUEUE_HIGH_WATER_MARK: usize = 64;
+
+/// Number of messages dropped by the message dispatchers because their queue was full.
+static DROPPED_MESSAGES: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of messages dropped by the message dispatchers so far.
+pub fn get_dropped_message_count() -> u64 {
+    DROPPED_MESSAGES.load(Ordering::Relaxed)
+}
+
 /// Returns a callable object that dispatches a BTIF callback to Message
 ///
 /// The returned object would make sure the order of how the callbacks arrive the same as how they
 /// goes to Message.
 ///
+/// Messages go through a queue of the dispatcher and are forwarded in order by a single task, so
+/// the BTIF thread never blocks on the message channel. When the queue is full, messages are
+/// handled per |Message::full_queue_policy|: either queued anyway or dropped.
+///
 /// Example
 /// ```ignore
 /// // Create a dispatcher in btstack
@@ -206,23 +260,64 @@ where
     Cb: Send + 'static,
     F: Fn(Cb) -> Message + Send + Copy + 'static,
 {
-    let async_mutex = Arc::new(tokio::sync::Mutex::new(()));
-    let dispatch_queue = Arc::new(Mutex::new(std::collections::VecDeque::new()));
+    make_filtered_message_dispatcher(tx, move |cb| Some(f(cb)))
+}
+
+/// Returns a callable object that dispatches a BTIF callback to Message, or drops it.
+///
+/// Similar to |make_message_dispatcher|, but the mapper may return None to drop callbacks that are
+/// of no interest. Dropped callbacks never reach the channel, which is useful for high-frequency
+/// callbacks only a few of which need to be handled.
+///
+/// Example
+/// ```ignore
+/// let dispatcher = make_filtered_message_dispatcher(tx.clone(), |cb| match cb {
+///     GattClientCallbacks::ReadRemoteRssi(..) => None,
+///     cb => Some(Message::GattClient(cb)),
+/// });
+/// ```
+pub(crate) fn make_filtered_message_dispatcher<F, Cb>(
+    tx: Sender<Message>,
+    mapper: F,
+) -> Box<dyn Fn(Cb) + Send>
+where
+    Cb: Send + 'static,
+    F: Fn(Cb) -> Option<Message> + Send + 'static,
+{
+    let (queue_tx, mut queue_rx) = unbounded_channel::<Message>();
+    // Number of messages queued or being forwarded.
+    let pending = Arc::new(AtomicUsize::new(0));
+
+    let forwarder_pending = pending.clone();
+    bt_topshim::topstack::get_runtime().spawn(async move {
+        while let Some(message) = queue_rx.recv().await {
+            let _ = tx.send(message).await;
+            forwarder_pending.fetch_sub(1, Ordering::SeqCst);
+        }
+    });
 
     Box::new(move |cb| {
-        let tx = tx.clone();
-        let async_mutex = async_mutex.clone();
-        let dispatch_queue = dispatch_queue.clone();
-        // Enqueue the callbacks at the synchronized block to ensure the order.
-        dispatch_queue.lock().unwrap().push_back(cb);
-        bt_topshim::topstack::get_runtime().spawn(async move {
-            // Acquire the lock first to ensure |pop_front| and |tx.send| not
-            // interrupted by the other async threads.
-            let _guard = async_mutex.lock().await;
-            // Consume exactly one callback.
-            let cb = dispatch_queue.lock().unwrap().pop_front().unwrap();
-            let _ = tx.send(f(cb)).await;
-        });
+        let Some(message) = mapper(cb) else {
+            return;
+        };
+
+        if message.full_queue_policy() == FullQueuePolicy::Drop
+            && pending.load(Ordering::SeqCst) >= DISPATCH_QUEUE_CAPACITY
+        {
+            let dropped = DROPPED_MESSAGES.fetch_add(1, Ordering::Relaxed) + 1;
+            if dropped.is_power_of_two() {
+                warn!("Message queue is full, {} messages dropped so far", dropped);
+            }
+            return;
+        }
+
+        let queued = pending.fetch_add(1, Ordering::SeqCst) + 1;
+        if queued % DISPATCH_QUEUE_HIGH_WATER_MARK == 0 {
+            warn!("Message channel is busy, {} messages are waiting to be sent", queued);
+        }
+        if queue_tx.send(message).is_err() {
+            pending.fetch_sub(1, Ordering::SeqCst);
+        }
     })
 }
 
@@ -254,6 +349,13 @@ pub enum SuspendMode {
     Resuming = 3,
 }
 
+impl SuspendMode {
+    /// Whether the module is between the normal and the suspended mode.
+    pub fn is_transitional(&self) -> bool {
+        matches!(self, SuspendMode::Suspending | SuspendMode::Resuming)
+    }
+}
+
 /// Umbrella class for the Bluetooth stack.
 pub struct Stack {}
 
@@ -301,6 +403,11 @@ impl Stack {
                     bluetooth.lock().unwrap().disable();
                 }
 
+                Message::AdapterShutdownGraceful => {
+                    bluetooth_gatt.lock().unwrap().enable(false);
+                    bluetooth.lock().unwrap().disable_graceful();
+                }
+
                 Message::Cleanup => {
                     bluetooth.lock().unwrap().cleanup();
                 }
@@ -329,6 +436,8 @@ impl Stack {
                     battery_service.lock().unwrap().init();
                     // Initialize Admin. This toggles the enabled profiles.
                     bluetooth_admin.lock().unwrap().initialize(api_tx.clone());
+                    // Re-apply the adapter mode configured through the QA API.
+                    bluetooth_qa.lock().unwrap().apply_adapter_mode();
                 }
 
                 Message::A2dp(a) => {
@@ -426,10 +535,18 @@ impl Stack {
                     bluetooth_media.lock().unwrap().dispatch_media_actions(action);
                 }
 
+                Message::MediaCallbackRegistered(cb_id) => {
+                    bluetooth_media.lock().unwrap().callback_registered(cb_id);
+                }
+
                 Message::MediaCallbackDisconnected(cb_id) => {
                     bluetooth_media.lock().unwrap().remove_callback(cb_id);
                 }
 
+                Message::TelephonyCallbackRegistered(cb_id) => {
+                    bluetooth_media.lock().unwrap().telephony_callback_registered(cb_id);
+                }
+
                 Message::TelephonyCallbackDisconnected(cb_id) => {
                     bluetooth_media.lock().unwrap().remove_telephony_callback(cb_id);
                 }
@@ -520,6 +637,17 @@ impl Stack {
                 Message::BatteryManagerCallbackDisconnected(id) => {
                     battery_manager.lock().unwrap().remove_callback(id);
                 }
+                Message::GattActions(GattActions::ResubscribeNotifications(client_id, addr)) => {
+                    // Only bonded devices are re-subscribed, as they keep the CCC values.
+                    if bluetooth.lock().unwrap().get_bond_state_by_addr(&addr)
+                        == BtBondState::Bonded
+                    {
+                        bluetooth_gatt
+                            .lock()
+                            .unwrap()
+                            .handle_action(GattActions::ResubscribeNotifications(client_id, addr));
+                    }
+                }
                 Message::GattActions(action) => {
                     bluetooth_gatt.lock().unwrap().handle_action(action);
                 }
@@ -544,6 +672,9 @@ impl Stack {
                 Message::DisconnectDevice(addr) => {
                     bluetooth.lock().unwrap().disconnect_all_enabled_profiles(addr);
                 }
+                Message::ReadLinkQuality(addr) => {
+                    bluetooth_qa.lock().unwrap().read_link_quality(addr);
+                }
                 // Qualification Only
                 Message::QaAddMediaPlayer(name, browsing_supported) => {
                     bluetooth_media.lock().unwrap().add_player(name, browsing_supported);
@@ -594,6 +725,21 @@ impl Stack {
                     let status = bluetooth.lock().unwrap().send_hid_virtual_unplug_internal(addr);
                     bluetooth_qa.lock().unwrap().on_send_hid_virtual_unplug_completed(status);
                 }
+                Message::QaGetLinkErrorCounters(addr) => {
+                    bluetooth_qa.lock().unwrap().read_link_error_counters(addr);
+                }
+                Message::QaResetFailedContactCounter(addr) => {
+                    bluetooth_qa.lock().unwrap().reset_link_failed_contact_counter(addr);
+                }
+                Message::QaSetLinkPowerMode(addr, mode, params) => {
+                    bluetooth_qa.lock().unwrap().set_link_power_mode_internal(addr, mode, params);
+                }
+                Message::QaGetLinkPowerMode(addr) => {
+                    bluetooth_qa.lock().unwrap().read_link_power_mode(addr);
+                }
+                Message::QaLink(callbacks) => {
+                    bluetooth_qa.lock().unwrap().dispatch_link_callbacks(callbacks);
+                }
 
                 // UHid callbacks
                 Message::UHidHfpOutputCallback(addr, id, data) => {
@@ -658,3 +804,132 @@ pub trait RPCProxy {
     /// Makes this object available for remote call.
     fn export_for_rpc(self: Box<Self>) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use btif_macros::RPCProxyStub;
+
+    #[test]
+    fn test_filtered_message_dispatcher_drops_events() {
+        let (tx, mut rx) = channel::<Message>(10);
+        let dispatcher = make_filtered_message_dispatcher(tx, |id: u32| {
+            if id % 2 == 0 {
+                Some(Message::SuspendCallbackRegistered(id))
+            } else {
+                None
+            }
+        });
+
+        for id in 1..=4 {
+            dispatcher(id);
+        }
+
+        let runtime = bt_topshim::topstack::get_runtime();
+        for expected in [2, 4] {
+            match runtime.block_on(rx.recv()) {
+                Some(Message::SuspendCallbackRegistered(id)) => assert_eq!(id, expected),
+                _ => panic!("Unexpected message"),
+            }
+        }
+        assert!(rx.try_recv().is_err());
+    }
+
+    fn make_scan_result() -> GattScannerCallbacks {
+        GattScannerCallbacks::OnScanResult(0, 0, RawAddress::default(), 0, 0, 0, 0, 0, 0, vec![])
+    }
+
+    #[test]
+    fn test_message_dispatcher_full_queue_policy() {
+        let (tx, mut rx) = channel::<Message>(1);
+        let dispatcher = make_message_dispatcher(tx, Message::LeScanner);
+
+        // Nothing is received, so this fills the channel and the queue of the dispatcher.
+        let queued = DISPATCH_QUEUE_CAPACITY as i32 + 1;
+        for value in 0..queued {
+            dispatcher(GattScannerCallbacks::OnBatchScanThresholdCrossed(value));
+        }
+
+        // Scan results are dropped rather than queued while the queue is full.
+        let dropped_before = get_dropped_message_count();
+        for _ in 0..10 {
+            dispatcher(make_scan_result());
+        }
+        assert!(get_dropped_message_count() - dropped_before >= 10);
+
+        // Other messages are still queued, and everything is delivered in order.
+        dispatcher(GattScannerCallbacks::OnBatchScanThresholdCrossed(queued));
+
+        let runtime = bt_topshim::topstack::get_runtime();
+        for expected in 0..=queued {
+            match runtime.block_on(rx.recv()) {
+                Some(Message::LeScanner(GattScannerCallbacks::OnBatchScanThresholdCrossed(v))) => {
+                    assert_eq!(v, expected)
+                }
+                _ => panic!("Unexpected message"),
+            }
+        }
+
+        // Once the queue drained, scan results go through again.
+        dispatcher(make_scan_result());
+        assert!(matches!(
+            runtime.block_on(rx.recv()),
+            Some(Message::LeScanner(GattScannerCallbacks::OnScanResult(..)))
+        ));
+        assert!(rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_message_dispatcher_keeps_scan_results_behind_queued_messages() {
+        let (tx, mut rx) = channel::<Message>(2);
+        let dispatcher = make_message_dispatcher(tx, Message::LeScanner);
+
+        // Fills the channel, so that the next message is queued.
+        dispatcher(GattScannerCallbacks::OnBatchScanThresholdCrossed(1));
+        dispatcher(GattScannerCallbacks::OnBatchScanThresholdCrossed(2));
+        dispatcher(GattScannerCallbacks::OnBatchScanThresholdCrossed(3));
+
+        // Once the channel has room again, a scan result is queued behind the pending message
+        // instead of being dropped.
+        let runtime = bt_topshim::topstack::get_runtime();
+        assert!(matches!(
+            runtime.block_on(rx.recv()),
+            Some(Message::LeScanner(GattScannerCallbacks::OnBatchScanThresholdCrossed(1)))
+        ));
+        assert!(matches!(
+            runtime.block_on(rx.recv()),
+            Some(Message::LeScanner(GattScannerCallbacks::OnBatchScanThresholdCrossed(2)))
+        ));
+        dispatcher(make_scan_result());
+
+        assert!(matches!(
+            runtime.block_on(rx.recv()),
+            Some(Message::LeScanner(GattScannerCallbacks::OnBatchScanThresholdCrossed(3)))
+        ));
+        assert!(matches!(
+            runtime.block_on(rx.recv()),
+            Some(Message::LeScanner(GattScannerCallbacks::OnScanResult(..)))
+        ));
+    }
+
+    #[test]
+    fn test_derive_rpc_proxy_stub() {
+        #[derive(RPCProxyStub)]
+        struct FieldCallback {
+            #[rpc_proxy(object_id)]
+            objpath: String,
+        }
+
+        #[derive(RPCProxyStub)]
+        #[rpc_proxy(object_id = "Fixed Callback")]
+        struct FixedCallback {}
+
+        let mut field_cb = FieldCallback { objpath: String::from("/org/chromium/test/callback") };
+        assert_eq!(field_cb.get_object_id(), "/org/chromium/test/callback");
+        assert_eq!(FixedCallback {}.get_object_id(), "Fixed Callback");
+
+        // Everything else falls back to the RPCProxy defaults.
+        assert_eq!(field_cb.register_disconnect(Box::new(|_| {})), 0);
+        assert!(!field_cb.unregister(0));
+    }
+}
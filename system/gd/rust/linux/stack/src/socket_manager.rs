@@ -3,6 +3,7 @@
 use bt_topshim::btif::{
     BluetoothInterface, BtStatus, DisplayAddress, DisplayUuid, RawAddress, Uuid,
 };
+use bt_topshim::link::{L2capEcho, L2capEchoCallbacks, L2capEchoCallbacksDispatcher};
 use bt_topshim::profiles::socket;
 use log;
 use nix::sys::socket::{recvmsg, ControlMessageOwned};
@@ -13,7 +14,7 @@ use std::fmt;
 use std::os::unix;
 use std::os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, RawFd};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use tokio::net::UnixStream;
 use tokio::runtime::Runtime;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
@@ -22,9 +23,9 @@ use tokio::time;
 
 use crate::bluetooth::{Bluetooth, BluetoothDevice};
 use crate::bluetooth_admin::BluetoothAdminPolicyHelper;
-use crate::callbacks::Callbacks;
-use crate::Message;
+use crate::callbacks::{CallbackCount, Callbacks};
 use crate::RPCProxy;
+use crate::{make_message_dispatcher, Message};
 
 /// Type for unique identifier for each opened socket.
 pub type SocketId = u64;
@@ -54,6 +55,9 @@ pub const DYNAMIC_PSM_NO_SDP: i32 = -2;
 /// Use this to select a dynamic channel when creating socket.
 pub const DYNAMIC_CHANNEL: i32 = -1;
 
+/// Highest RFCOMM server channel number.
+const MAX_RFCOMM_CHANNEL: i32 = 30;
+
 /// Socket ids are unsigned so make zero an invalid value.
 pub const INVALID_SOCKET_ID: SocketId = 0;
 
@@ -265,6 +269,76 @@ impl fmt::Display for BluetoothSocket {
     }
 }
 
+/// Snapshot of a socket tracked by the socket manager.
+#[derive(Clone, Debug)]
+pub struct SocketInfo {
+    pub id: SocketId,
+    pub sock_type: SocketType,
+    /// True for a listening socket, false for an outgoing socket that is still connecting.
+    pub is_listening: bool,
+    /// Remote device of an outgoing socket. Not set for listening sockets.
+    pub remote_address: Option<RawAddress>,
+    pub psm: Option<i32>,
+    pub channel: Option<i32>,
+    pub uuid: Option<Uuid>,
+}
+
+impl SocketInfo {
+    fn from_server_socket(socket: &BluetoothServerSocket) -> Self {
+        SocketInfo {
+            id: socket.id,
+            sock_type: socket.sock_type.clone(),
+            is_listening: true,
+            remote_address: None,
+            psm: socket.psm,
+            channel: socket.channel,
+            uuid: socket.uuid,
+        }
+    }
+
+    fn from_connecting_socket(socket: &BluetoothSocket) -> Self {
+        let (psm, channel) = match socket.sock_type {
+            SocketType::Rfcomm => (None, Some(socket.port)),
+            _ => (Some(socket.port), None),
+        };
+        SocketInfo {
+            id: socket.id,
+            sock_type: socket.sock_type.clone(),
+            is_listening: false,
+            remote_address: Some(socket.remote_device.address),
+            psm,
+            channel,
+            uuid: socket.uuid,
+        }
+    }
+}
+
+impl fmt::Display for SocketInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "id={}, {}, type={:?}, port={}, remote={}, uuid={}",
+            self.id,
+            if self.is_listening { "listening" } else { "connecting" },
+            self.sock_type,
+            match (self.psm, self.channel) {
+                (Some(psm), Some(cn)) => format!("psm {} | cn {}", psm, cn),
+                (None, Some(cn)) => format!("cn {}", cn),
+                (Some(psm), None) => format!("psm {}", psm),
+                (None, None) => "none".to_string(),
+            },
+            match self.remote_address {
+                Some(addr) => DisplayAddress(&addr).to_string(),
+                None => "".to_string(),
+            },
+            match self.uuid {
+                Some(u) => DisplayUuid(&u).to_string(),
+                None => "".to_string(),
+            }
+        )
+    }
+}
+
 pub trait IBluetoothSocketManagerCallbacks: RPCProxy {
     /// Listening socket is ready to listen. This is sent each time a listening socket
     /// transitions to a non-listening state (i.e. a new listener opened or accept timed-out). The
@@ -287,6 +361,10 @@ pub trait IBluetoothSocketManagerCallbacks: RPCProxy {
         result: BtStatus,
         socket: Option<BluetoothSocket>,
     );
+
+    /// Result of an L2CAP ping started with `l2cap_ping`. The round-trip time is only meaningful
+    /// when status is success.
+    fn on_l2cap_ping_result(&mut self, addr: RawAddress, status: BtStatus, rtt_ms: u32);
 }
 
 pub trait IBluetoothSocketManager {
@@ -332,6 +410,9 @@ pub trait IBluetoothSocketManager {
     /// Generic method for setting up an RFCOMM listening socket.  Prefer to use one of the other
     /// RFCOMM listen methods when possible as they reflect the more preferred RFCOMM flows, but
     /// this method exposes all of the options that the stack supports.
+    ///
+    /// If |channel| is None or |DYNAMIC_CHANNEL|, a free channel is allocated. The channel in use
+    /// is reported in the socket passed to |on_incoming_socket_ready|.
     fn listen_using_rfcomm(
         &mut self,
         callback: CallbackId,
@@ -394,6 +475,14 @@ pub trait IBluetoothSocketManager {
 
     /// Close a listening socket.
     fn close(&mut self, callback: CallbackId, id: SocketId) -> BtStatus;
+
+    /// Get the sockets of a callback that are listening or still connecting.
+    fn get_active_sockets(&self, callback: CallbackId) -> Vec<SocketInfo>;
+
+    /// Send an L2CAP echo request with |size| bytes of payload to a connected BR/EDR device. The
+    /// result is reported with `on_l2cap_ping_result`. Returns false if a ping to the device is
+    /// already outstanding.
+    fn l2cap_ping(&mut self, callback: CallbackId, addr: RawAddress, size: u16) -> bool;
 }
 
 /// Internal listening socket data.
@@ -409,6 +498,9 @@ struct InternalListeningSocket {
 
     /// Used for tracing task status
     joinhandle: JoinHandle<()>,
+
+    /// Used for listing active sockets
+    info: SocketInfo,
 }
 
 impl InternalListeningSocket {
@@ -418,8 +510,9 @@ impl InternalListeningSocket {
         tx: Sender<SocketRunnerActions>,
         uuid: Option<Uuid>,
         joinhandle: JoinHandle<()>,
+        info: SocketInfo,
     ) -> Self {
-        InternalListeningSocket { _callback_id, socket_id, tx, uuid, joinhandle }
+        InternalListeningSocket { _callback_id, socket_id, tx, uuid, joinhandle, info }
     }
 }
 
@@ -430,11 +523,19 @@ struct InternalConnectingSocket {
 
     /// Used for cleaning up
     joinhandle: JoinHandle<()>,
+
+    /// Used for listing active sockets
+    info: SocketInfo,
 }
 
 impl InternalConnectingSocket {
-    fn new(_callback_id: CallbackId, socket_id: SocketId, joinhandle: JoinHandle<()>) -> Self {
-        InternalConnectingSocket { _callback_id, socket_id, joinhandle }
+    fn new(
+        _callback_id: CallbackId,
+        socket_id: SocketId,
+        joinhandle: JoinHandle<()>,
+        info: SocketInfo,
+    ) -> Self {
+        InternalConnectingSocket { _callback_id, socket_id, joinhandle, info }
     }
 }
 
@@ -467,6 +568,51 @@ fn file_to_unixstream(fd: std::fs::File) -> Option<UnixStream> {
 /// TODO(abps) - Should this be configurable?
 const CONNECT_COMPLETE_TIMEOUT_MS: u64 = 10000;
 
+/// Time to wait for the peer to answer an L2CAP echo request before timing out.
+const L2CAP_PING_TIMEOUT_MS: u64 = 5000;
+
+/// Outstanding L2CAP echo requests. At most one request per device is outstanding since the
+/// native stack can't tell the responses of concurrent requests apart.
+#[derive(Default)]
+struct L2capPings {
+    pending: HashMap<RawAddress, (CallbackId, Instant)>,
+}
+
+impl L2capPings {
+    /// Starts tracking a ping to |addr| sent at |now|. Returns false if one is already pending.
+    fn start(&mut self, addr: RawAddress, callback: CallbackId, now: Instant) -> bool {
+        if self.pending.contains_key(&addr) {
+            return false;
+        }
+        self.pending.insert(addr, (callback, now));
+        true
+    }
+
+    /// Completes the ping to |addr|. Returns the requester and the round-trip time.
+    fn finish(&mut self, addr: RawAddress, now: Instant) -> Option<(CallbackId, Duration)> {
+        self.pending
+            .remove(&addr)
+            .map(|(callback, sent_at)| (callback, now.saturating_duration_since(sent_at)))
+    }
+
+    /// Expires the ping to |addr| sent at |sent_at|. Timers of pings that were already answered
+    /// are ignored, even if a newer ping to the same device is pending.
+    fn expire(&mut self, addr: RawAddress, sent_at: Instant) -> Option<CallbackId> {
+        match self.pending.get(&addr) {
+            Some(&(callback, pending_sent_at)) if pending_sent_at == sent_at => {
+                self.pending.remove(&addr);
+                Some(callback)
+            }
+            _ => None,
+        }
+    }
+
+    /// Drops the pings started by |callback|.
+    fn remove_callback(&mut self, callback: CallbackId) {
+        self.pending.retain(|_, (cbid, _)| *cbid != callback);
+    }
+}
+
 /// Actions to take on the socket in the socket runner.
 pub(crate) enum SocketRunnerActions {
     /// Accept connections on a listening socket with an optional timeout.
@@ -489,6 +635,10 @@ pub enum SocketActions {
 
     // Request to disconnect all sockets, e.g. when user disconnects the peer device.
     DisconnectAll(RawAddress),
+
+    // Events for L2CAP echo requests.
+    L2capEchoResponse(L2capEchoCallbacks),
+    L2capPingTimeout(RawAddress, Instant),
 }
 
 /// Implementation of the `IBluetoothSocketManager` api.
@@ -521,6 +671,12 @@ pub struct BluetoothSocketManager {
 
     /// Admin helper
     admin_helper: BluetoothAdminPolicyHelper,
+
+    /// Topshim interface for L2CAP echo requests.
+    l2cap_echo: L2capEcho,
+
+    /// Outstanding L2CAP echo requests.
+    l2cap_pings: L2capPings,
 }
 
 impl BluetoothSocketManager {
@@ -535,6 +691,11 @@ impl BluetoothSocketManager {
         let socket_counter: u64 = 1000;
         let connecting = HashMap::new();
         let listening = HashMap::new();
+        let l2cap_echo = L2capEcho::new(L2capEchoCallbacksDispatcher {
+            dispatch: make_message_dispatcher(tx.clone(), |cb| {
+                Message::SocketManagerActions(SocketActions::L2capEchoResponse(cb))
+            }),
+        });
 
         BluetoothSocketManager {
             callbacks,
@@ -546,9 +707,16 @@ impl BluetoothSocketManager {
             tx,
             adapter,
             admin_helper: Default::default(),
+            l2cap_echo,
+            l2cap_pings: Default::default(),
         }
     }
 
+    /// Returns the number of registered socket manager callbacks.
+    pub(crate) fn get_callback_count(&self) -> CallbackCount {
+        self.callbacks.shared_count()
+    }
+
     /// Check if there is any listening socket.
     fn is_listening(&self) -> bool {
         self.listening.values().any(|vs| !vs.is_empty())
@@ -614,6 +782,7 @@ impl BluetoothSocketManager {
                 socket_info.id = id;
                 let (runner_tx, runner_rx) = channel::<SocketRunnerActions>(10);
                 let uuid = socket_info.uuid;
+                let info = SocketInfo::from_server_socket(&socket_info);
 
                 // Push a listening task to local runtime to wait for device to
                 // start accepting or get closed.
@@ -643,10 +812,9 @@ impl BluetoothSocketManager {
                 });
 
                 // Keep track of active listener sockets.
-                self.listening
-                    .entry(cbid)
-                    .or_default()
-                    .push(InternalListeningSocket::new(cbid, id, runner_tx, uuid, joinhandle));
+                self.listening.entry(cbid).or_default().push(InternalListeningSocket::new(
+                    cbid, id, runner_tx, uuid, joinhandle, info,
+                ));
 
                 // Update the connectable mode since the list of listening socket has changed.
                 self.adapter.lock().unwrap().set_socket_listening(true);
@@ -700,6 +868,7 @@ impl BluetoothSocketManager {
                 // callbacks.
                 let id = self.next_socket_id();
                 socket_info.id = id;
+                let info = SocketInfo::from_connecting_socket(&socket_info);
 
                 // Push a connecting task to local runtime to wait for connection
                 // completion.
@@ -721,7 +890,7 @@ impl BluetoothSocketManager {
                 self.connecting
                     .entry(cbid)
                     .or_default()
-                    .push(InternalConnectingSocket::new(cbid, id, joinhandle));
+                    .push(InternalConnectingSocket::new(cbid, id, joinhandle, info));
 
                 SocketResult::new(status, id)
             }
@@ -1137,6 +1306,16 @@ impl BluetoothSocketManager {
     pub fn handle_actions(&mut self, action: SocketActions) {
         match action {
             SocketActions::OnIncomingSocketReady(cbid, server_socket, status) => {
+                // Keep track of the port assigned to the listening socket.
+                if let Some(socket) = self
+                    .listening
+                    .get_mut(&cbid)
+                    .and_then(|v| v.iter_mut().find(|s| s.socket_id == server_socket.id))
+                {
+                    socket.info.psm = server_socket.psm;
+                    socket.info.channel = server_socket.channel;
+                }
+
                 if let Some(callback) = self.callbacks.get_by_id_mut(cbid) {
                     callback.on_incoming_socket_ready(server_socket, status);
                 }
@@ -1178,6 +1357,38 @@ impl BluetoothSocketManager {
             SocketActions::DisconnectAll(addr) => {
                 self.sock.disconnect_all(addr);
             }
+
+            SocketActions::L2capEchoResponse(L2capEchoCallbacks::Response(addr, status, _)) => {
+                let Some((cbid, rtt)) = self.l2cap_pings.finish(addr, Instant::now()) else {
+                    log::debug!("Ignoring L2CAP echo response from {}", DisplayAddress(&addr));
+                    return;
+                };
+                // The native stack reports the outcome of the echo request as an HCI error code.
+                let status = match status {
+                    0x00 => BtStatus::Success,
+                    0x1A => BtStatus::Unsupported,
+                    0x22 => BtStatus::Timeout,
+                    _ => BtStatus::RemoteDeviceDown,
+                };
+                let rtt_ms = if status == BtStatus::Success {
+                    u32::try_from(rtt.as_millis()).unwrap_or(u32::MAX)
+                } else {
+                    0
+                };
+                if let Some(callback) = self.callbacks.get_by_id_mut(cbid) {
+                    callback.on_l2cap_ping_result(addr, status, rtt_ms);
+                }
+            }
+
+            SocketActions::L2capPingTimeout(addr, sent_at) => {
+                let Some(cbid) = self.l2cap_pings.expire(addr, sent_at) else {
+                    return;
+                };
+                log::warn!("L2CAP ping to {} timed out", DisplayAddress(&addr));
+                if let Some(callback) = self.callbacks.get_by_id_mut(cbid) {
+                    callback.on_l2cap_ping_result(addr, BtStatus::Timeout, 0);
+                }
+            }
         }
     }
 
@@ -1233,6 +1444,7 @@ impl BluetoothSocketManager {
             self.adapter.lock().unwrap().set_socket_listening(false);
         }
 
+        self.l2cap_pings.remove_callback(callback);
         self.callbacks.remove_callback(callback);
     }
 
@@ -1347,6 +1559,14 @@ impl IBluetoothSocketManager for BluetoothSocketManager {
             None => socket::SOCK_FLAG_NONE,
         };
 
+        let channel = channel.filter(|&c| c != DYNAMIC_CHANNEL);
+        if let Some(c) = channel {
+            if !(1..=MAX_RFCOMM_CHANNEL).contains(&c) {
+                log::warn!("Invalid RFCOMM channel {}", c);
+                return SocketResult::new(BtStatus::InvalidParam, INVALID_SOCKET_ID);
+            }
+        }
+
         self.socket_listen(
             BluetoothServerSocket::make_rfcomm_channel(flags, name, channel, application_uuid),
             callback,
@@ -1480,4 +1700,74 @@ impl IBluetoothSocketManager for BluetoothSocketManager {
 
         BtStatus::InvalidParam
     }
+
+    fn get_active_sockets(&self, callback: CallbackId) -> Vec<SocketInfo> {
+        let listening = self.listening.get(&callback).into_iter().flatten().map(|s| s.info.clone());
+        let connecting =
+            self.connecting.get(&callback).into_iter().flatten().map(|s| s.info.clone());
+
+        listening.chain(connecting).collect()
+    }
+
+    fn l2cap_ping(&mut self, callback: CallbackId, addr: RawAddress, size: u16) -> bool {
+        if self.callbacks.get_by_id(callback).is_none() {
+            return false;
+        }
+
+        let sent_at = Instant::now();
+        if !self.l2cap_pings.start(addr, callback, sent_at) {
+            log::warn!("L2CAP ping to {} is already in progress", DisplayAddress(&addr));
+            return false;
+        }
+        self.l2cap_echo.ping(addr, size);
+
+        let tx = self.tx.clone();
+        self.runtime.spawn(async move {
+            time::sleep(Duration::from_millis(L2CAP_PING_TIMEOUT_MS)).await;
+            let _ = tx
+                .send(Message::SocketManagerActions(SocketActions::L2capPingTimeout(addr, sent_at)))
+                .await;
+        });
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_l2cap_ping_success() {
+        let mut pings = L2capPings::default();
+        let addr = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let sent_at = Instant::now();
+
+        assert!(pings.start(addr, 1, sent_at));
+        // Only one ping per device may be outstanding.
+        assert!(!pings.start(addr, 2, sent_at));
+
+        let rtt = Duration::from_millis(12);
+        assert_eq!(pings.finish(addr, sent_at + rtt), Some((1, rtt)));
+        // The timer of an answered ping is ignored.
+        assert_eq!(pings.expire(addr, sent_at), None);
+        assert!(pings.start(addr, 2, sent_at + rtt));
+    }
+
+    #[test]
+    fn test_l2cap_ping_timeout() {
+        let mut pings = L2capPings::default();
+        let addr = RawAddress::from_string("11:22:33:44:55:66").unwrap();
+        let first = Instant::now();
+        let second = first + Duration::from_millis(L2CAP_PING_TIMEOUT_MS);
+
+        assert!(pings.start(addr, 1, first));
+        assert_eq!(pings.expire(addr, first), Some(1));
+        // A late response after the timeout is not reported again.
+        assert_eq!(pings.finish(addr, second), None);
+
+        // The timer of an earlier ping doesn't expire a newer one.
+        assert!(pings.start(addr, 1, second));
+        assert_eq!(pings.expire(addr, first), None);
+        assert_eq!(pings.expire(addr, second), Some(1));
+    }
 }
@@ -14,7 +14,9 @@ const INCOMPLETE_LIST_128_BIT_SERVICE_UUIDS: u8 = 0x06;
 const COMPLETE_LIST_128_BIT_SERVICE_UUIDS: u8 = 0x07;
 const SHORTENED_LOCAL_NAME: u8 = 0x08;
 const COMPLETE_LOCAL_NAME: u8 = 0x09;
+const TX_POWER_LEVEL: u8 = 0x0a;
 const SERVICE_DATA_16_BIT_UUID: u8 = 0x16;
+const APPEARANCE: u8 = 0x19;
 const SERVICE_DATA_32_BIT_UUID: u8 = 0x20;
 const SERVICE_DATA_128_BIT_UUID: u8 = 0x21;
 const MANUFACTURER_SPECIFIC_DATA: u8 = 0xff;
@@ -53,7 +55,19 @@ fn iterate_adv_data(data: &[u8], data_type: u8) -> AdvDataIterator {
 
 // Helper function to extract flags from advertising data
 pub fn extract_flags(bytes: &[u8]) -> u8 {
-    iterate_adv_data(bytes, FLAGS).next().map_or(0, |v| v[0])
+    iterate_adv_data(bytes, FLAGS).next().and_then(|v| v.first().copied()).unwrap_or(0)
+}
+
+// Helper function to extract TX power level (in dBm) from advertising data
+pub fn extract_tx_power(bytes: &[u8]) -> Option<i8> {
+    iterate_adv_data(bytes, TX_POWER_LEVEL).next().and_then(|v| v.first().map(|b| *b as i8))
+}
+
+// Helper function to extract appearance from advertising data
+pub fn extract_appearance(bytes: &[u8]) -> Option<u16> {
+    iterate_adv_data(bytes, APPEARANCE)
+        .next()
+        .and_then(|v| v.get(0..2)?.try_into().ok().map(u16::from_le_bytes))
 }
 
 // Helper function to extract service uuids (128bit) from advertising data
@@ -160,6 +174,38 @@ mod tests {
         assert_eq!(flags, 3);
     }
 
+    #[test]
+    fn test_extract_tx_power() {
+        let payload: Vec<u8> = vec![2, FLAGS, 3];
+        assert_eq!(extract_tx_power(payload.as_slice()), None);
+
+        let payload: Vec<u8> = vec![2, FLAGS, 3, 2, TX_POWER_LEVEL, 0xf9];
+        assert_eq!(extract_tx_power(payload.as_slice()), Some(-7));
+    }
+
+    #[test]
+    fn test_truncated_elements() {
+        // Elements of length 1 carry only their type, with no value.
+        let payload: Vec<u8> = vec![1, TX_POWER_LEVEL];
+        assert_eq!(extract_tx_power(payload.as_slice()), None);
+
+        let payload: Vec<u8> = vec![1, FLAGS, 1, TX_POWER_LEVEL, 0];
+        assert_eq!(extract_flags(payload.as_slice()), 0);
+        assert_eq!(extract_tx_power(payload.as_slice()), None);
+    }
+
+    #[test]
+    fn test_extract_appearance() {
+        let payload: Vec<u8> = vec![2, FLAGS, 3];
+        assert_eq!(extract_appearance(payload.as_slice()), None);
+
+        let payload: Vec<u8> = vec![2, APPEARANCE, 0xc1];
+        assert_eq!(extract_appearance(payload.as_slice()), None);
+
+        let payload: Vec<u8> = vec![2, FLAGS, 3, 3, APPEARANCE, 0xc1, 0x03];
+        assert_eq!(extract_appearance(payload.as_slice()), Some(0x03c1));
+    }
+
     #[test]
     fn test_extract_service_uuids() {
         let payload: Vec<u8> = vec![2, FLAGS, 3];
@@ -16,11 +16,23 @@
 
 #include "rust/topshim/controller/controller_shim.h"
 
+#include <base/functional/bind.h>
+#include <bluetooth/log.h>
+
 #include <memory>
 
+#include "hci/acl_manager.h"
+#include "hci/le_address_manager.h"
+#include "main/shim/entry.h"
 #include "main/shim/helpers.h"
+#include "osi/include/properties.h"
 #include "rust/cxx.h"
 #include "src/controller.rs.h"
+#include "stack/include/bt_types.h"
+#include "stack/include/btu_hcif.h"
+#include "stack/include/hci_error_code.h"
+#include "stack/include/hcidefs.h"
+#include "stack/include/main_thread.h"
 #include "types/raw_address.h"
 
 namespace bluetooth {
@@ -28,6 +40,68 @@ namespace topshim {
 namespace rust {
 namespace internal {
 static ControllerIntf* g_controller_intf;
+
+static void OnWriteLeHostSupportComplete(bool enabled, uint8_t* p, uint16_t /* len */) {
+  uint8_t status;
+  STREAM_TO_UINT8(status, p);
+  if (status != HCI_SUCCESS) {
+    log::warn("Failed to write LE host support {}, status {}", enabled, status);
+  }
+}
+
+static void WriteLeHostSupport(bool enabled) {
+  uint8_t param[2];
+  uint8_t* p = param;
+  UINT8_TO_STREAM(p, enabled ? 0x01 : 0x00);
+  // The Simultaneous LE and BR/EDR to Same Device Capable (Host) bit is deprecated.
+  UINT8_TO_STREAM(p, 0x00);
+  btu_hcif_send_cmd_with_cb(FROM_HERE, HCI_WRITE_LE_HOST_SUPPORT, param, sizeof(param),
+                            base::BindOnce(&OnWriteLeHostSupportComplete, enabled));
+}
+
+static void OnWriteScanParamsComplete(uint16_t opcode, uint8_t* p, uint16_t /* len */) {
+  uint8_t status;
+  STREAM_TO_UINT8(status, p);
+  if (status != HCI_SUCCESS) {
+    log::warn("Failed to write scan parameters, opcode {:#x} status {}", opcode, status);
+  }
+}
+
+static void WriteInquiryScanParams(uint16_t interval, uint16_t window, uint8_t scan_type) {
+  // The stack rewrites the inquiry scan activity from these properties whenever the discoverable
+  // mode changes, so update them to keep the new parameters.
+  osi_property_set("bluetooth.core.classic.inq_scan_interval", std::to_string(interval).c_str());
+  osi_property_set("bluetooth.core.classic.inq_scan_window", std::to_string(window).c_str());
+  osi_property_set("bluetooth.core.classic.inq_scan_type", std::to_string(scan_type).c_str());
+
+  uint8_t activity[4];
+  uint8_t* p = activity;
+  UINT16_TO_STREAM(p, interval);
+  UINT16_TO_STREAM(p, window);
+  btu_hcif_send_cmd_with_cb(FROM_HERE, HCI_WRITE_INQUIRYSCAN_CFG, activity, sizeof(activity),
+                            base::BindOnce(&OnWriteScanParamsComplete, HCI_WRITE_INQUIRYSCAN_CFG));
+
+  btu_hcif_send_cmd_with_cb(FROM_HERE, HCI_WRITE_INQSCAN_TYPE, &scan_type, sizeof(scan_type),
+                            base::BindOnce(&OnWriteScanParamsComplete, HCI_WRITE_INQSCAN_TYPE));
+}
+
+static void WritePageScanParams(uint16_t interval, uint16_t window, uint8_t scan_type) {
+  // The stack rewrites the page scan activity from these properties whenever the connectable mode
+  // changes, so update them to keep the new parameters.
+  osi_property_set("bluetooth.core.classic.page_scan_interval", std::to_string(interval).c_str());
+  osi_property_set("bluetooth.core.classic.page_scan_window", std::to_string(window).c_str());
+  osi_property_set("bluetooth.core.classic.page_scan_type", std::to_string(scan_type).c_str());
+
+  uint8_t activity[4];
+  uint8_t* p = activity;
+  UINT16_TO_STREAM(p, interval);
+  UINT16_TO_STREAM(p, window);
+  btu_hcif_send_cmd_with_cb(FROM_HERE, HCI_WRITE_PAGESCAN_CFG, activity, sizeof(activity),
+                            base::BindOnce(&OnWriteScanParamsComplete, HCI_WRITE_PAGESCAN_CFG));
+
+  btu_hcif_send_cmd_with_cb(FROM_HERE, HCI_WRITE_PAGESCAN_TYPE, &scan_type, sizeof(scan_type),
+                            base::BindOnce(&OnWriteScanParamsComplete, HCI_WRITE_PAGESCAN_TYPE));
+}
 }  // namespace internal
 
 ControllerIntf::~ControllerIntf() {}
@@ -48,6 +122,12 @@ RawAddress ControllerIntf::read_local_addr() const {
   return ToRawAddress(controller_->GetMacAddress());
 }
 
+RawAddress ControllerIntf::read_le_addr(uint8_t& addr_type) const {
+  auto le_address = shim::GetAclManager()->GetLeAddressManager()->GetInitiatorAddress();
+  addr_type = static_cast<uint8_t>(le_address.GetAddressType());
+  return ToRawAddress(le_address.GetAddress());
+}
+
 uint64_t ControllerIntf::get_ble_supported_states() const {
   if (!controller_) {
     std::abort();
@@ -62,6 +142,70 @@ uint64_t ControllerIntf::get_ble_local_supported_features() const {
   return controller_->GetControllerLeLocalSupportedFeatures();
 }
 
+bool ControllerIntf::supports_br_edr() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return controller_->SupportsBrEdr();
+}
+
+bool ControllerIntf::supports_ble() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return controller_->SupportsBle();
+}
+
+uint8_t ControllerIntf::get_hci_version() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return static_cast<uint8_t>(controller_->GetLocalVersionInformation().hci_version_);
+}
+
+uint16_t ControllerIntf::get_hci_revision() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return controller_->GetLocalVersionInformation().hci_revision_;
+}
+
+uint8_t ControllerIntf::get_lmp_version() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return static_cast<uint8_t>(controller_->GetLocalVersionInformation().lmp_version_);
+}
+
+uint16_t ControllerIntf::get_lmp_subversion() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return controller_->GetLocalVersionInformation().lmp_subversion_;
+}
+
+uint16_t ControllerIntf::get_manufacturer_name() const {
+  if (!controller_) {
+    std::abort();
+  }
+  return controller_->GetLocalVersionInformation().manufacturer_name_;
+}
+
+void ControllerIntf::write_le_host_support(bool enabled) const {
+  do_in_main_thread(base::BindOnce(&internal::WriteLeHostSupport, enabled));
+}
+
+void ControllerIntf::write_inquiry_scan_params(uint16_t interval, uint16_t window,
+                                               uint8_t scan_type) const {
+  do_in_main_thread(
+          base::BindOnce(&internal::WriteInquiryScanParams, interval, window, scan_type));
+}
+
+void ControllerIntf::write_page_scan_params(uint16_t interval, uint16_t window,
+                                            uint8_t scan_type) const {
+  do_in_main_thread(base::BindOnce(&internal::WritePageScanParams, interval, window, scan_type));
+}
+
 }  // namespace rust
 }  // namespace topshim
 }  // namespace bluetooth
@@ -33,8 +33,19 @@ public:
   ~ControllerIntf();
 
   RawAddress read_local_addr() const;
+  RawAddress read_le_addr(uint8_t& addr_type) const;
   uint64_t get_ble_supported_states() const;
   uint64_t get_ble_local_supported_features() const;
+  bool supports_br_edr() const;
+  bool supports_ble() const;
+  uint8_t get_hci_version() const;
+  uint16_t get_hci_revision() const;
+  uint8_t get_lmp_version() const;
+  uint16_t get_lmp_subversion() const;
+  uint16_t get_manufacturer_name() const;
+  void write_le_host_support(bool enabled) const;
+  void write_inquiry_scan_params(uint16_t interval, uint16_t window, uint8_t scan_type) const;
+  void write_page_scan_params(uint16_t interval, uint16_t window, uint8_t scan_type) const;
 
 private:
   const hci::ControllerInterface* controller_;
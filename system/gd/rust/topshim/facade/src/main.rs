@@ -9,6 +9,7 @@ use futures::stream::StreamExt;
 use grpcio::*;
 use log::debug;
 use nix::sys::signal;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::{Arc, Mutex};
 use tokio::runtime::Runtime;
 
@@ -43,6 +44,13 @@ fn main() {
 fn clap_command() -> Command {
     Command::new("bluetooth_topshim_facade")
         .about("The bluetooth topshim stack, with testing facades enabled and exposed via gRPC.")
+        .arg(
+            Arg::new("grpc-bind-address")
+                .long("grpc-bind-address")
+                .help("The IP address the gRPC server listens on")
+                .value_parser(value_parser!(IpAddr))
+                .default_value("0.0.0.0"),
+        )
         .arg(
             Arg::new("grpc-port")
                 .long("grpc-port")
@@ -76,6 +84,7 @@ fn clap_command() -> Command {
 async fn async_main(rt: Arc<Runtime>, mut sigint: mpsc::UnboundedReceiver<()>) {
     let matches = clap_command().get_matches();
 
+    let grpc_bind_address = *matches.get_one::<IpAddr>("grpc-bind-address").unwrap();
     let grpc_port = *matches.get_one::<u16>("grpc-port").unwrap();
     let _rootcanal_port = matches.get_one::<u16>("rootcanal-port").cloned();
     let env = Arc::new(Environment::new(2));
@@ -113,7 +122,7 @@ async fn async_main(rt: Arc<Runtime>, mut sigint: mpsc::UnboundedReceiver<()>) {
         .register_service(media_service_impl)
         .build()
         .unwrap();
-    let addr = format!("0.0.0.0:{}", grpc_port);
+    let addr = SocketAddr::new(grpc_bind_address, grpc_port).to_string();
     let creds = ServerCredentials::insecure();
     server.add_listening_port(addr, creds).unwrap();
     server.start();
@@ -165,4 +174,24 @@ mod tests {
     fn verify_comand() {
         clap_command().debug_assert();
     }
+
+    #[test]
+    fn grpc_bind_address() {
+        let matches = clap_command().get_matches_from(["facade"]);
+        assert_eq!(
+            matches.get_one::<IpAddr>("grpc-bind-address"),
+            Some(&IpAddr::from([0, 0, 0, 0]))
+        );
+
+        let matches =
+            clap_command().get_matches_from(["facade", "--grpc-bind-address", "127.0.0.1"]);
+        assert_eq!(
+            matches.get_one::<IpAddr>("grpc-bind-address"),
+            Some(&IpAddr::from([127, 0, 0, 1]))
+        );
+
+        assert!(clap_command()
+            .try_get_matches_from(["facade", "--grpc-bind-address", "localhost"])
+            .is_err());
+    }
 }
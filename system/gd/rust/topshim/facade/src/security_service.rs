@@ -1,14 +1,17 @@
 //! Security service facade
 
 use bt_topshim::btif::{BluetoothInterface, BtTransport, RawAddress};
+use bt_topshim::link::{Link, LinkCallbacksDispatcher};
 
 use bt_topshim_facade_protobuf::empty::Empty;
 use bt_topshim_facade_protobuf::facade::{
-    CreateBondRequest, CreateBondResponse, GenerateOobDataRequest, RemoveBondRequest,
+    CreateBondRequest, CreateBondResponse, ForceDisconnectRequest, GenerateOobDataRequest,
+    RemoveBondRequest,
 };
 use bt_topshim_facade_protobuf::facade_grpc::{create_security_service, SecurityService};
 use grpcio::*;
 
+use std::convert::TryFrom;
 use std::sync::{Arc, Mutex};
 use tokio::runtime::Runtime;
 
@@ -19,13 +22,17 @@ pub struct SecurityServiceImpl {
     rt: Arc<Runtime>,
     #[allow(dead_code)]
     btif_intf: Arc<Mutex<BluetoothInterface>>,
+    link: Arc<Mutex<Link>>,
 }
 
 #[allow(dead_code)]
 impl SecurityServiceImpl {
     /// Create a new instance of the root facade service
     pub fn create(rt: Arc<Runtime>, btif_intf: Arc<Mutex<BluetoothInterface>>) -> grpcio::Service {
-        create_security_service(Self { rt, btif_intf })
+        // The link results aren't reported by this facade.
+        let link =
+            Arc::new(Mutex::new(Link::new(LinkCallbacksDispatcher { dispatch: Box::new(|_| {}) })));
+        create_security_service(Self { rt, btif_intf, link })
     }
 }
 
@@ -75,4 +82,30 @@ impl SecurityService for SecurityServiceImpl {
             }
         });
     }
+
+    fn force_disconnect(
+        &mut self,
+        ctx: RpcContext<'_>,
+        req: ForceDisconnectRequest,
+        sink: UnarySink<Empty>,
+    ) {
+        let link = self.link.clone();
+        ctx.spawn(async move {
+            let bt_addr = &req.address;
+            match (RawAddress::from_string(bt_addr), u8::try_from(req.hci_reason)) {
+                (Some(addr), Ok(reason)) => {
+                    link.lock().unwrap().force_disconnect(addr, reason);
+                    sink.success(Empty::default()).await.unwrap();
+                }
+                _ => {
+                    sink.fail(RpcStatus::with_message(
+                        RpcStatusCode::INVALID_ARGUMENT,
+                        format!("Invalid Request Address {} or reason {}", bt_addr, req.hci_reason),
+                    ))
+                    .await
+                    .unwrap();
+                }
+            }
+        });
+    }
 }
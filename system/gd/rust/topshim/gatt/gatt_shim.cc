@@ -19,7 +19,19 @@
 #include <base/functional/bind.h>
 #include <base/functional/callback.h>
 
+#include <map>
+#include <utility>
+#include <vector>
+
+#include "stack/include/acl_api.h"
+#include "stack/include/btm_ble_api.h"
+#include "stack/include/btm_client_interface.h"
+#include "stack/include/gatt_api.h"
+#include "stack/include/hci_error_code.h"
+#include "stack/include/hcidefs.h"
+#include "stack/include/main_thread.h"
 #include "src/profiles/gatt.rs.h"
+#include "types/bt_transport.h"
 
 namespace bluetooth {
 namespace topshim {
@@ -37,12 +49,62 @@ void ServerReadPhyCallback(int server_if, RawAddress addr, uint8_t tx_phy, uint8
   bluetooth::topshim::rust::server_read_phy_callback(server_if, addr, tx_phy, rx_phy, status);
 }
 
+// Clients waiting for the LE features of the peer, by HCI handle. Only accessed on the main
+// thread.
+static std::map<uint16_t, std::vector<std::pair<int, RawAddress>>> pending_phy_capabilities;
+
+static void OnRemoteFeaturesRead(uint16_t handle, tHCI_STATUS status) {
+  auto it = pending_phy_capabilities.find(handle);
+  if (it == pending_phy_capabilities.end()) {
+    return;
+  }
+  std::vector<std::pair<int, RawAddress>> clients = std::move(it->second);
+  pending_phy_capabilities.erase(it);
+
+  tGATT_STATUS gatt_status;
+  switch (status) {
+    case HCI_SUCCESS:
+      gatt_status = GATT_SUCCESS;
+      break;
+    case HCI_ERR_UNSUPPORTED_REM_FEATURE:
+      gatt_status = GATT_REQ_NOT_SUPPORTED;
+      break;
+    default:
+      gatt_status = GATT_ERROR;
+      break;
+  }
+
+  bool le_2m = gatt_status == GATT_SUCCESS && acl_peer_supports_ble_2m_phy(handle);
+  bool le_coded = gatt_status == GATT_SUCCESS && acl_peer_supports_ble_coded_phy(handle);
+  for (const auto& [client_if, addr] : clients) {
+    bluetooth::topshim::rust::read_remote_phy_capabilities_callback(client_if, addr, le_2m,
+                                                                    le_coded, gatt_status);
+  }
+}
+
+// Reads the LE features from the peer instead of using the ones cached when the link came up,
+// which may be missing if the peripheral could not start the feature exchange.
+void ReadRemotePhyCapabilities(int client_if, RawAddress addr) {
+  uint16_t handle = get_btm_client_interface().peer.BTM_GetHCIConnHandle(addr, BT_TRANSPORT_LE);
+  if (handle == HCI_INVALID_HANDLE) {
+    bluetooth::topshim::rust::read_remote_phy_capabilities_callback(client_if, addr, false, false,
+                                                                    GATT_WRONG_STATE);
+    return;
+  }
+  pending_phy_capabilities[handle].emplace_back(client_if, addr);
+  BTM_BleReadRemoteFeatures(handle, &OnRemoteFeaturesRead);
+}
+
 }  // namespace internal
 
 int GattClientIntf::read_phy(int client_if, RawAddress addr) {
   return client_intf_->read_phy(addr, base::Bind(&internal::ReadPhyCallback, client_if, addr));
 }
 
+int GattClientIntf::read_remote_phy_capabilities(int client_if, RawAddress addr) {
+  return do_in_main_thread(base::BindOnce(&internal::ReadRemotePhyCapabilities, client_if, addr));
+}
+
 std::unique_ptr<GattClientIntf> GetGattClientProfile(const unsigned char* gatt_intf) {
   return std::make_unique<GattClientIntf>(
           reinterpret_cast<const btgatt_interface_t*>(gatt_intf)->client);
@@ -31,6 +31,7 @@ public:
   ~GattClientIntf() = default;
 
   int read_phy(int client_if, RawAddress bt_addr);
+  int read_remote_phy_capabilities(int client_if, RawAddress bt_addr);
 
 private:
   const btgatt_client_interface_t* client_intf_;
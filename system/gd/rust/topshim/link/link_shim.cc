@@ -0,0 +1,284 @@
+/*
+ * Copyright 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+#include "rust/topshim/link/link_shim.h"
+
+#include <base/functional/bind.h>
+#include <bluetooth/log.h>
+
+#include <memory>
+
+#include "btif/include/btif_dm.h"
+#include "main/shim/acl_api.h"
+#include "stack/btm/power_mode.h"
+#include "stack/include/acl_api.h"
+#include "stack/include/bt_types.h"
+#include "stack/include/btm_client_interface.h"
+#include "stack/include/btu_hcif.h"
+#include "stack/include/hci_error_code.h"
+#include "stack/include/hcidefs.h"
+#include "stack/include/l2cap_interface.h"
+#include "stack/include/main_thread.h"
+#include "src/link.rs.h"
+#include "types/ble_address_with_type.h"
+#include "types/bt_transport.h"
+#include "types/raw_address.h"
+
+namespace bluetooth {
+namespace topshim {
+namespace rust {
+namespace internal {
+static LinkIntf* g_link_intf;
+static L2capEchoIntf* g_l2cap_echo_intf;
+
+// Returns the handle of the ACL connection to |addr|, preferring BR/EDR over LE.
+static uint16_t GetAclHandle(const RawAddress& addr) {
+  auto& peer = get_btm_client_interface().peer;
+  uint16_t handle = peer.BTM_GetHCIConnHandle(addr, BT_TRANSPORT_BR_EDR);
+  if (handle == HCI_INVALID_HANDLE) {
+    handle = peer.BTM_GetHCIConnHandle(addr, BT_TRANSPORT_LE);
+  }
+  return handle;
+}
+
+static void SendHandleCommand(uint16_t opcode, uint16_t handle,
+                              base::OnceCallback<void(uint8_t*, uint16_t)> cb) {
+  uint8_t param[2];
+  uint8_t* p = param;
+  UINT16_TO_STREAM(p, handle);
+  btu_hcif_send_cmd_with_cb(FROM_HERE, opcode, param, sizeof(param), std::move(cb));
+}
+
+static void OnReadLinkQualityComplete(RawAddress addr, uint8_t fcc_status,
+                                      uint16_t failed_contact_counter, uint8_t* p,
+                                      uint16_t /* len */) {
+  uint8_t status;
+  uint16_t handle;
+  uint8_t link_quality = 0;
+  STREAM_TO_UINT8(status, p);
+  STREAM_TO_UINT16(handle, p);
+  if (status == HCI_SUCCESS) {
+    STREAM_TO_UINT8(link_quality, p);
+  }
+  link_error_counters_read_callback(addr, fcc_status, failed_contact_counter, status,
+                                    link_quality);
+}
+
+static void OnReadFailedContactCounterComplete(RawAddress addr, uint8_t* p, uint16_t /* len */) {
+  uint8_t status;
+  uint16_t handle;
+  uint16_t failed_contact_counter = 0;
+  STREAM_TO_UINT8(status, p);
+  STREAM_TO_UINT16(handle, p);
+  if (status != HCI_SUCCESS) {
+    link_error_counters_read_callback(addr, status, 0, status, 0);
+    return;
+  }
+  STREAM_TO_UINT16(failed_contact_counter, p);
+
+  // Link Quality is read right after so that both values describe the same moment.
+  SendHandleCommand(HCI_GET_LINK_QUALITY, handle,
+                    base::BindOnce(&OnReadLinkQualityComplete, addr, status,
+                                   failed_contact_counter));
+}
+
+static void OnResetFailedContactCounterComplete(RawAddress addr, uint8_t* p, uint16_t /* len */) {
+  uint8_t status;
+  STREAM_TO_UINT8(status, p);
+  link_failed_contact_counter_reset_callback(addr, status);
+}
+
+static void ReadErrorCounters(RawAddress addr) {
+  uint16_t handle = GetAclHandle(addr);
+  if (handle == HCI_INVALID_HANDLE) {
+    link_error_counters_read_callback(addr, HCI_ERR_NO_CONNECTION, 0, HCI_ERR_NO_CONNECTION, 0);
+    return;
+  }
+  SendHandleCommand(HCI_READ_FAILED_CONTACT_COUNTER, handle,
+                    base::BindOnce(&OnReadFailedContactCounterComplete, addr));
+}
+
+static void ResetFailedContactCounter(RawAddress addr) {
+  uint16_t handle = GetAclHandle(addr);
+  if (handle == HCI_INVALID_HANDLE) {
+    link_failed_contact_counter_reset_callback(addr, HCI_ERR_NO_CONNECTION);
+    return;
+  }
+  SendHandleCommand(HCI_RESET_FAILED_CONTACT_COUNTER, handle,
+                    base::BindOnce(&OnResetFailedContactCounterComplete, addr));
+}
+
+static void OnPowerModeChanged(const RawAddress& addr, tBTM_PM_STATUS status, uint16_t interval,
+                               tHCI_STATUS hci_status) {
+  link_power_mode_changed_callback(addr, hci_status, status, interval);
+}
+
+static void SetPowerMode(RawAddress addr, tBTM_PM_PWR_MD mode) {
+  tBTM_STATUS status =
+          get_btm_client_interface().link_policy.BTM_SetPowerMode(BTM_PM_SET_ONLY_ID, addr, &mode);
+  switch (status) {
+    case tBTM_STATUS::BTM_CMD_STARTED:
+    case tBTM_STATUS::BTM_CMD_STORED:
+      // The result is reported by the Mode Change event.
+      return;
+    case tBTM_STATUS::BTM_SUCCESS: {
+      // The link is already in the requested mode so there won't be a Mode Change event.
+      tBTM_PM_MODE current = mode.mode;
+      if (!BTM_ReadPowerMode(addr, &current)) {
+        link_power_mode_changed_callback(addr, HCI_ERR_NO_CONNECTION, BTM_PM_MD_ACTIVE, 0);
+        return;
+      }
+      link_power_mode_changed_callback(addr, HCI_SUCCESS, current, 0);
+      return;
+    }
+    case tBTM_STATUS::BTM_UNKNOWN_ADDR:
+      link_power_mode_changed_callback(addr, HCI_ERR_NO_CONNECTION, BTM_PM_MD_ACTIVE, 0);
+      return;
+    default:
+      link_power_mode_changed_callback(addr, HCI_ERR_ILLEGAL_PARAMETER_FMT, BTM_PM_MD_ACTIVE, 0);
+      return;
+  }
+}
+
+static void ReadPowerMode(RawAddress addr) {
+  tBTM_PM_MODE mode = BTM_PM_MD_UNKNOWN;
+  if (!BTM_ReadPowerMode(addr, &mode)) {
+    link_power_mode_read_callback(addr, HCI_ERR_NO_CONNECTION, BTM_PM_MD_UNKNOWN);
+    return;
+  }
+  link_power_mode_read_callback(addr, HCI_SUCCESS, mode);
+}
+
+static void AddToAcceptList(RawAddress addr, tBLE_ADDR_TYPE addr_type) {
+  tBLE_BD_ADDR address_with_type = {.type = addr_type, .bda = addr};
+  // The only reason for the shim to refuse a device is that the accept list is full.
+  bool added = bluetooth::shim::ACL_AcceptLeConnectionFrom(address_with_type, /*is_direct=*/false);
+  link_accept_list_add_callback(addr, added ? HCI_SUCCESS : HCI_ERR_MEMORY_FULL);
+}
+
+static void RemoveFromAcceptList(RawAddress addr, tBLE_ADDR_TYPE addr_type) {
+  tBLE_BD_ADDR address_with_type = {.type = addr_type, .bda = addr};
+  bluetooth::shim::ACL_IgnoreLeConnectionFrom(address_with_type);
+}
+
+static void ForceDisconnect(RawAddress addr, tHCI_REASON reason) {
+  uint16_t handle = GetAclHandle(addr);
+  if (handle == HCI_INVALID_HANDLE) {
+    log::warn("No ACL to {} to disconnect", addr);
+    return;
+  }
+  // A local disconnect completes with the local host reason, so make the stack report the
+  // requested reason instead.
+  btif_dm_override_next_disc_reason(addr, reason);
+  acl_disconnect_from_handle(handle, reason, "Topshim force disconnect");
+}
+
+static void OnL2capEchoResponse(const RawAddress& addr, tL2CAP_ECHO_RESULT result,
+                                uint16_t data_len) {
+  switch (result) {
+    case tL2CAP_ECHO_RESULT::SUCCESS:
+      link_l2cap_echo_callback(addr, HCI_SUCCESS, data_len);
+      break;
+    case tL2CAP_ECHO_RESULT::REJECTED:
+      link_l2cap_echo_callback(addr, HCI_ERR_UNSUPPORTED_REM_FEATURE, 0);
+      break;
+    case tL2CAP_ECHO_RESULT::TIMEOUT:
+      link_l2cap_echo_callback(addr, HCI_ERR_LMP_RESPONSE_TIMEOUT, 0);
+      break;
+    case tL2CAP_ECHO_RESULT::NO_LINK:
+      link_l2cap_echo_callback(addr, HCI_ERR_NO_CONNECTION, 0);
+      break;
+  }
+}
+
+static void L2capPing(RawAddress addr, uint16_t data_len) {
+  if (!stack::l2cap::get_interface().L2CA_Ping(addr, data_len, &OnL2capEchoResponse)) {
+    link_l2cap_echo_callback(addr, HCI_ERR_NO_CONNECTION, 0);
+  }
+}
+}  // namespace internal
+
+LinkIntf::LinkIntf() { BTM_PmSetModeChangeObserver(&internal::OnPowerModeChanged); }
+
+LinkIntf::~LinkIntf() {
+  BTM_PmSetModeChangeObserver(nullptr);
+  internal::g_link_intf = nullptr;
+}
+
+std::unique_ptr<LinkIntf> GetLinkInterface() {
+  if (internal::g_link_intf) {
+    std::abort();
+  }
+  auto link_intf = std::make_unique<LinkIntf>();
+  internal::g_link_intf = link_intf.get();
+  return link_intf;
+}
+
+void LinkIntf::read_error_counters(RawAddress addr) const {
+  do_in_main_thread(base::BindOnce(&internal::ReadErrorCounters, addr));
+}
+
+void LinkIntf::reset_failed_contact_counter(RawAddress addr) const {
+  do_in_main_thread(base::BindOnce(&internal::ResetFailedContactCounter, addr));
+}
+
+void LinkIntf::set_power_mode(RawAddress addr, uint8_t mode, uint16_t max_interval,
+                              uint16_t min_interval, uint16_t attempt, uint16_t timeout) const {
+  tBTM_PM_PWR_MD pm_mode = {
+          .max = max_interval,
+          .min = min_interval,
+          .attempt = attempt,
+          .timeout = timeout,
+          .mode = mode,
+  };
+  do_in_main_thread(base::BindOnce(&internal::SetPowerMode, addr, pm_mode));
+}
+
+void LinkIntf::read_power_mode(RawAddress addr) const {
+  do_in_main_thread(base::BindOnce(&internal::ReadPowerMode, addr));
+}
+
+void LinkIntf::add_to_accept_list(RawAddress addr, uint8_t addr_type) const {
+  do_in_main_thread(base::BindOnce(&internal::AddToAcceptList, addr, addr_type));
+}
+
+void LinkIntf::remove_from_accept_list(RawAddress addr, uint8_t addr_type) const {
+  do_in_main_thread(base::BindOnce(&internal::RemoveFromAcceptList, addr, addr_type));
+}
+
+void LinkIntf::force_disconnect(RawAddress addr, uint8_t reason) const {
+  do_in_main_thread(
+          base::BindOnce(&internal::ForceDisconnect, addr, static_cast<tHCI_REASON>(reason)));
+}
+
+L2capEchoIntf::~L2capEchoIntf() { internal::g_l2cap_echo_intf = nullptr; }
+
+std::unique_ptr<L2capEchoIntf> GetL2capEchoInterface() {
+  if (internal::g_l2cap_echo_intf) {
+    std::abort();
+  }
+  auto echo_intf = std::make_unique<L2capEchoIntf>();
+  internal::g_l2cap_echo_intf = echo_intf.get();
+  return echo_intf;
+}
+
+void L2capEchoIntf::ping(RawAddress addr, uint16_t data_len) const {
+  do_in_main_thread(base::BindOnce(&internal::L2capPing, addr, data_len));
+}
+
+}  // namespace rust
+}  // namespace topshim
+}  // namespace bluetooth
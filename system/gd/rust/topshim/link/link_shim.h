@@ -0,0 +1,63 @@
+/*
+ * Copyright 2024 The Android Open Source Project
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *      http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#ifndef GD_RUST_TOPSHIM_LINK_SHIM
+#define GD_RUST_TOPSHIM_LINK_SHIM
+
+#include <memory>
+
+#include "rust/cxx.h"
+#include "types/raw_address.h"
+
+namespace bluetooth {
+namespace topshim {
+namespace rust {
+
+// Issues link level HCI commands to the ACL connection of a remote device. Results are reported
+// asynchronously through the callbacks declared in src/link.rs.
+class LinkIntf {
+public:
+  LinkIntf();
+  ~LinkIntf();
+
+  void read_error_counters(RawAddress addr) const;
+  void reset_failed_contact_counter(RawAddress addr) const;
+  void set_power_mode(RawAddress addr, uint8_t mode, uint16_t max_interval, uint16_t min_interval,
+                      uint16_t attempt, uint16_t timeout) const;
+  void read_power_mode(RawAddress addr) const;
+  void add_to_accept_list(RawAddress addr, uint8_t addr_type) const;
+  void remove_from_accept_list(RawAddress addr, uint8_t addr_type) const;
+  void force_disconnect(RawAddress addr, uint8_t reason) const;
+};
+
+std::unique_ptr<LinkIntf> GetLinkInterface();
+
+// Sends L2CAP echo requests over the BR/EDR link of a remote device. Responses are reported
+// asynchronously through the callbacks declared in src/link.rs.
+class L2capEchoIntf {
+public:
+  L2capEchoIntf() {}
+  ~L2capEchoIntf();
+
+  void ping(RawAddress addr, uint16_t data_len) const;
+};
+
+std::unique_ptr<L2capEchoIntf> GetL2capEchoInterface();
+
+}  // namespace rust
+}  // namespace topshim
+}  // namespace bluetooth
+
+#endif  // GD_RUST_TOPSHIM_LINK_SHIM
@@ -7,14 +7,45 @@ use quote::{format_ident, quote};
 use syn::parse::{Parse, ParseStream, Result};
 use syn::{parse_macro_input, Block, Ident, Path, Stmt, Token, Type};
 
+/// Parsed argument of a callback variant
+enum CbArg {
+    /// A single C argument, optionally converted to another type. No target type means the
+    /// argument is consumed and not passed forward.
+    Single(Type, Option<Type>),
+
+    /// A pointer and length pair of C arguments, in the order they are declared, converted into
+    /// a single Vec with `ptr_to_vec`.
+    PtrLenPair { ptr: Type, len: Type, ptr_first: bool, vec: Box<Type> },
+}
+
 /// Parsed structure for callback variant
 struct CbVariant {
     dispatcher: Type,
     fn_pair: (Ident, Path),
-    arg_pairs: Vec<(Type, Option<Type>)>,
+    args: Vec<CbArg>,
     stmts: Vec<Stmt>,
 }
 
+/// Splits a `(pointer, length)` tuple type into its pointer and length types, and whether the
+/// pointer comes first. Returns None if the type isn't such a pair.
+fn split_ptr_len_pair(ty: &Type) -> Option<(Type, Type, bool)> {
+    let Type::Tuple(tuple) = ty else {
+        return None;
+    };
+    if tuple.elems.len() != 2 {
+        return None;
+    }
+
+    let first = tuple.elems[0].clone();
+    let second = tuple.elems[1].clone();
+    match (&first, &second) {
+        (Type::Ptr(_), Type::Ptr(_)) => None,
+        (Type::Ptr(_), _) => Some((first, second, true)),
+        (_, Type::Ptr(_)) => Some((second, first, false)),
+        _ => None,
+    }
+}
+
 impl Parse for CbVariant {
     fn parse(input: ParseStream) -> Result<Self> {
         // First thing should be the dispatcher
@@ -26,7 +57,7 @@ impl Parse for CbVariant {
         input.parse::<Token![->]>()?;
         let rpath: Path = input.parse()?;
 
-        let mut arg_pairs: Vec<(Type, Option<Type>)> = Vec::new();
+        let mut args: Vec<CbArg> = Vec::new();
         let mut stmts: Vec<Stmt> = Vec::new();
 
         while input.peek(Token![,]) {
@@ -44,6 +75,14 @@ impl Parse for CbVariant {
             // Grab the next type argument
             let start_type: Type = input.parse()?;
 
+            // A (pointer, length) pair must be converted into a Vec.
+            if let Some((ptr, len, ptr_first)) = split_ptr_len_pair(&start_type) {
+                input.parse::<Token![->]>()?;
+                let vec: Box<Type> = Box::new(input.parse()?);
+                args.push(CbArg::PtrLenPair { ptr, len, ptr_first, vec });
+                continue;
+            }
+
             if input.peek(Token![->]) {
                 // Discard ->
                 input.parse::<Token![->]>()?;
@@ -54,18 +93,18 @@ impl Parse for CbVariant {
                 // conversion.
                 if input.peek(Token![_]) {
                     input.parse::<Token![_]>()?;
-                    arg_pairs.push((start_type, None));
+                    args.push(CbArg::Single(start_type, None));
                 } else {
                     let end_type: Type = input.parse()?;
-                    arg_pairs.push((start_type, Some(end_type)));
+                    args.push(CbArg::Single(start_type, Some(end_type)));
                 }
             } else {
-                arg_pairs.push((start_type.clone(), Some(start_type)));
+                args.push(CbArg::Single(start_type.clone(), Some(start_type)));
             }
         }
 
         // TODO: Validate there are no more tokens; currently they are ignored.
-        Ok(CbVariant { dispatcher, fn_pair: (name, rpath), arg_pairs, stmts })
+        Ok(CbVariant { dispatcher, fn_pair: (name, rpath), args, stmts })
     }
 }
 
@@ -91,6 +130,13 @@ impl Parse for CbVariant {
 ///
 /// Example:
 ///     u32 -> _
+///
+/// A pointer and its length can also be converted into a single Vec with "(Ptr, Len) -> Vec<T>".
+/// The pair takes a single argument index and `ptr_to_vec` is called before the statements, so
+/// the Vec is available by that index. The pointer and length may be declared in either order.
+///
+/// Example:
+///     (*const u8, usize) -> Vec<u8>
 pub fn cb_variant(input: TokenStream) -> TokenStream {
     let parsed_cptr = parse_macro_input!(input as CbVariant);
 
@@ -99,21 +145,39 @@ pub fn cb_variant(input: TokenStream) -> TokenStream {
 
     let mut params = proc_macro2::TokenStream::new();
     let mut args = proc_macro2::TokenStream::new();
-    for (i, (start, end)) in parsed_cptr.arg_pairs.iter().enumerate() {
+    let mut stmts = proc_macro2::TokenStream::new();
+    for (i, arg) in parsed_cptr.args.iter().enumerate() {
         let ident = format_ident!("_{}", i);
-        params.extend(quote! { #ident: #start, });
+        match arg {
+            CbArg::Single(start, end) => {
+                params.extend(quote! { #ident: #start, });
+
+                if let Some(v) = end {
+                    // Argument needs an into translation if it doesn't match the start
+                    if start != v {
+                        args.extend(quote! { #end::from(#ident), });
+                    } else {
+                        args.extend(quote! {#ident,});
+                    }
+                }
+            }
+            CbArg::PtrLenPair { ptr, len, ptr_first, vec } => {
+                let ptr_ident = format_ident!("_{}_ptr", i);
+                let len_ident = format_ident!("_{}_len", i);
+                if *ptr_first {
+                    params.extend(quote! { #ptr_ident: #ptr, #len_ident: #len, });
+                } else {
+                    params.extend(quote! { #len_ident: #len, #ptr_ident: #ptr, });
+                }
 
-        if let Some(v) = end {
-            // Argument needs an into translation if it doesn't match the start
-            if start != v {
-                args.extend(quote! { #end::from(#ident), });
-            } else {
+                stmts.extend(quote! {
+                    let #ident: #vec = crate::btif::ptr_to_vec(#ptr_ident, #len_ident as usize);
+                });
                 args.extend(quote! {#ident,});
             }
         }
     }
 
-    let mut stmts = proc_macro2::TokenStream::new();
     for stmt in parsed_cptr.stmts {
         stmts.extend(quote! { #stmt });
     }
@@ -126,9 +190,7 @@ pub fn cb_variant(input: TokenStream) -> TokenStream {
                 (get_dispatchers()
                     .lock()
                     .expect("Couldn't lock dispatchers!")
-                    .get::<#dispatcher>()
-                    .expect(concat!("Couldn't find dispatcher type: ", #dispatcher_str))
-                    .clone()
+                    .get_or_panic::<#dispatcher>()
                     .lock()
                     .expect(concat!("Couldn't lock specific dispatcher: ", #dispatcher_str))
                     .dispatch)(#rpath(#args));
@@ -185,36 +247,72 @@ pub fn cb_variant(input: TokenStream) -> TokenStream {
 ///     }
 ///     ```
 pub fn profile_enabled_or(attr: TokenStream, item: TokenStream) -> TokenStream {
-    generate_profile_enabled_or_tokenstream(item, attr.to_string())
+    generate_profile_enabled_or_tokenstream(item.into(), attr.to_string(), LogLevel::Warn).into()
 }
 
 /// Similar to profile_enabled_or but return Default::default() when profile is not enabled.
 #[proc_macro_attribute]
 pub fn profile_enabled_or_default(_attr: TokenStream, item: TokenStream) -> TokenStream {
-    generate_profile_enabled_or_tokenstream(item, String::from("Default::default()"))
+    generate_profile_enabled_or_tokenstream(
+        item.into(),
+        String::from("Default::default()"),
+        LogLevel::Warn,
+    )
+    .into()
+}
+
+/// Similar to profile_enabled_or but logs with debug! instead of warn!.
+///
+/// Meant for frequently called methods where the profile not being enabled is expected. Function
+/// who applies this macro should include log::debug instead of log::warn.
+#[proc_macro_attribute]
+pub fn profile_enabled_or_quiet(attr: TokenStream, item: TokenStream) -> TokenStream {
+    generate_profile_enabled_or_tokenstream(item.into(), attr.to_string(), LogLevel::Debug).into()
+}
+
+/// Log level used when a profile_enabled_or guarded function is called on a disabled profile.
+enum LogLevel {
+    Warn,
+    Debug,
 }
 
-fn generate_profile_enabled_or_tokenstream(item: TokenStream, attr_string: String) -> TokenStream {
-    let mut input = syn::parse_macro_input!(item as syn::ItemFn);
+impl LogLevel {
+    fn log_macro(&self) -> proc_macro2::Ident {
+        let name = match self {
+            LogLevel::Warn => "warn",
+            LogLevel::Debug => "debug",
+        };
+        proc_macro2::Ident::new(name, proc_macro2::Span::call_site())
+    }
+}
+
+fn generate_profile_enabled_or_tokenstream(
+    item: proc_macro2::TokenStream,
+    attr_string: String,
+    log_level: LogLevel,
+) -> proc_macro2::TokenStream {
+    let mut input: syn::ItemFn = match syn::parse2(item) {
+        Ok(input) => input,
+        Err(e) => return e.to_compile_error(),
+    };
 
     let fn_name = input.sig.ident.to_string();
 
     let ret_stmt: proc_macro2::TokenStream = format!("return {};", attr_string).parse().unwrap();
+    let log_macro = log_level.log_macro();
 
     let check_block = quote::quote! {
         if !self.is_enabled() {
-            warn!("Tried to {} but internal hasn't been enabled", #fn_name);
+            #log_macro!("Tried to {} but internal hasn't been enabled", #fn_name);
             #ret_stmt
         }
     };
 
-    input.block.stmts.insert(0, syn::parse(check_block.into()).unwrap());
+    input.block.stmts.insert(0, syn::parse2(check_block).unwrap());
 
-    let output = quote::quote! {
+    quote::quote! {
         #input
-    };
-
-    output.into()
+    }
 }
 
 /// Generate impl cxx::ExternType for the trivial types in bindings.
@@ -235,6 +333,12 @@ fn generate_profile_enabled_or_tokenstream(item: TokenStream, attr_string: Strin
 /// }
 /// ```
 ///
+/// Types that are not under `bindings::` need the C++ type named explicitly:
+/// ```ignore
+/// #[gen_cxx_extern_trivial(cxx = "some::ns::sample_t")]
+/// type SampleType = some_module::SampleType;
+/// ```
+///
 /// To use the binding type in a cxx::bridge block, include the header and (optionally) assign
 /// the namespace and name for the C++ type.
 /// ```ignore
@@ -250,12 +354,42 @@ fn generate_profile_enabled_or_tokenstream(item: TokenStream, attr_string: Strin
 /// }
 /// ```
 #[proc_macro_attribute]
-pub fn gen_cxx_extern_trivial(_attr: TokenStream, item: TokenStream) -> TokenStream {
+pub fn gen_cxx_extern_trivial(attr: TokenStream, item: TokenStream) -> TokenStream {
     let input = syn::parse_macro_input!(item as syn::ItemType);
 
     let ident = input.ident.clone();
+    let cxx_ident = get_cxx_ident(attr.into(), &input.ty);
+
+    quote! {
+        #input
+
+        unsafe impl cxx::ExternType for #ident {
+            type Id = cxx::type_id!(#cxx_ident);
+            type Kind = cxx::kind::Trivial;
+        }
+    }
+    .into()
+}
 
-    let segs = match *input.ty {
+/// Gets the C++ type name for `gen_cxx_extern_trivial`, either from the `cxx = "..."` argument or
+/// derived from the `bindings::` path of the type.
+fn get_cxx_ident(attr: proc_macro2::TokenStream, ty: &Type) -> String {
+    if !attr.is_empty() {
+        let arg: syn::MetaNameValue = syn::parse2(attr).expect("Expected cxx = \"...\"");
+        if !arg.path.is_ident("cxx") {
+            panic!("Unsupported argument: Only cxx = \"...\" is allowed");
+        }
+        let cxx_ident = match arg.lit {
+            syn::Lit::Str(name) => name.value(),
+            _ => panic!("Unexpected cxx value: Must be a string literal"),
+        };
+        if cxx_ident.is_empty() {
+            panic!("Empty cxx ident");
+        }
+        return cxx_ident;
+    }
+
+    let segs = match *ty {
         Type::Path(syn::TypePath {
             qself: None,
             path: Path { leading_colon: None, ref segments },
@@ -267,7 +401,7 @@ pub fn gen_cxx_extern_trivial(_attr: TokenStream, item: TokenStream) -> TokenStr
 
     match iter.next() {
         Some(seg) if seg.ident == "bindings" => {}
-        _ => panic!("Unexpected type: Must starts with \"bindings::\""),
+        _ => panic!("Unexpected type: Must starts with \"bindings::\" or specify cxx = \"...\""),
     }
 
     match iter.clone().next() {
@@ -284,13 +418,62 @@ pub fn gen_cxx_extern_trivial(_attr: TokenStream, item: TokenStream) -> TokenStr
         panic!("Empty cxx ident");
     }
 
-    quote! {
-        #input
+    cxx_ident
+}
 
-        unsafe impl cxx::ExternType for #ident {
-            type Id = cxx::type_id!(#cxx_ident);
-            type Kind = cxx::kind::Trivial;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cxx_ident_from_bindings_path() {
+        let ty: Type = syn::parse_str("bindings::root::some::ns::sample_t").unwrap();
+        assert_eq!(get_cxx_ident(quote! {}, &ty), "some::ns::sample_t");
+
+        let ty: Type = syn::parse_str("bindings::RawAddress").unwrap();
+        assert_eq!(get_cxx_ident(quote! {}, &ty), "RawAddress");
+    }
+
+    #[test]
+    fn test_cxx_ident_from_argument() {
+        let ty: Type = syn::parse_str("some_module::SampleType").unwrap();
+        assert_eq!(get_cxx_ident(quote! { cxx = "some::ns::sample_t" }, &ty), "some::ns::sample_t");
+    }
+
+    #[test]
+    #[should_panic(expected = "Must starts with")]
+    fn test_cxx_ident_missing_argument() {
+        let ty: Type = syn::parse_str("some_module::SampleType").unwrap();
+        get_cxx_ident(quote! {}, &ty);
+    }
+
+    #[test]
+    fn test_profile_enabled_or_log_level() {
+        let item = quote! {
+            fn foo(&self) -> bool {
+                true
+            }
+        };
+
+        let warn_output =
+            generate_profile_enabled_or_tokenstream(item.clone(), "false".into(), LogLevel::Warn);
+        let expected_check = quote! {
+            if !self.is_enabled() {
+                warn!("Tried to {} but internal hasn't been enabled", "foo");
+                return false;
+            }
+        };
+        assert!(warn_output.to_string().contains(&expected_check.to_string()));
+
+        let debug_output =
+            generate_profile_enabled_or_tokenstream(item, "false".into(), LogLevel::Debug);
+        let expected_check = quote! {
+            if !self.is_enabled() {
+                debug!("Tried to {} but internal hasn't been enabled", "foo");
+                return false;
+            }
+        };
+        assert!(debug_output.to_string().contains(&expected_check.to_string()));
+        assert!(!debug_output.to_string().contains("warn !"));
     }
-    .into()
 }
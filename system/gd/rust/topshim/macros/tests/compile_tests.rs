@@ -0,0 +1,9 @@
+#[test]
+fn compile_tests() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/cb_variant_ptr_len_pair.rs");
+    t.compile_fail("tests/ui/cb_variant_ptr_len_pair_no_vec.rs");
+    t.pass("tests/ui/gen_cxx_extern_trivial.rs");
+    t.compile_fail("tests/ui/gen_cxx_extern_trivial_missing_cxx.rs");
+    t.compile_fail("tests/ui/gen_cxx_extern_trivial_bad_argument.rs");
+}
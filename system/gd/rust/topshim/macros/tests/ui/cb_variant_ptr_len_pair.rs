@@ -0,0 +1,64 @@
+// Mirrors the parts of bt_topshim that the code generated by cb_variant refers to.
+mod btif {
+    pub(crate) fn ptr_to_vec<T: Copy, U: From<T>>(start: *const T, length: usize) -> Vec<U> {
+        unsafe { (0..length).map(|i| U::from(start.add(i).read_unaligned())).collect() }
+    }
+}
+
+use std::sync::{Arc, Mutex};
+use topshim_macros::cb_variant;
+
+#[derive(Debug, PartialEq)]
+enum BufferCallbacks {
+    PtrFirst(Vec<u8>, i32),
+    LenFirst(i32, Vec<u16>),
+}
+
+struct BufferCallbacksDispatcher {
+    dispatch: Box<dyn Fn(BufferCallbacks) + Send>,
+}
+
+type BufferCb = Arc<Mutex<BufferCallbacksDispatcher>>;
+
+struct DispatchContainer {
+    dispatcher: Option<BufferCb>,
+}
+
+impl DispatchContainer {
+    fn get_or_panic<T: From<BufferCb>>(&self) -> T {
+        T::from(self.dispatcher.clone().expect("No dispatcher"))
+    }
+}
+
+static DISPATCHERS: Mutex<DispatchContainer> = Mutex::new(DispatchContainer { dispatcher: None });
+
+fn get_dispatchers() -> &'static Mutex<DispatchContainer> {
+    &DISPATCHERS
+}
+
+cb_variant!(BufferCb, ptr_first_cb -> BufferCallbacks::PtrFirst, (*const u8, usize) -> Vec<u8>, i32);
+
+cb_variant!(BufferCb, len_first_cb -> BufferCallbacks::LenFirst, i32, (u32, *const u16) -> Vec<u16>);
+
+fn main() {
+    let received = Arc::new(Mutex::new(vec![]));
+    let received_clone = received.clone();
+    get_dispatchers().lock().unwrap().dispatcher =
+        Some(Arc::new(Mutex::new(BufferCallbacksDispatcher {
+            dispatch: Box::new(move |cb| received_clone.lock().unwrap().push(cb)),
+        })));
+
+    let bytes: [u8; 3] = [1, 2, 3];
+    ptr_first_cb(bytes.as_ptr(), bytes.len(), 7);
+
+    let words: [u16; 2] = [0x1234, 0x5678];
+    len_first_cb(9, words.len() as u32, words.as_ptr());
+
+    assert_eq!(
+        *received.lock().unwrap(),
+        vec![
+            BufferCallbacks::PtrFirst(vec![1, 2, 3], 7),
+            BufferCallbacks::LenFirst(9, vec![0x1234, 0x5678]),
+        ]
+    );
+}
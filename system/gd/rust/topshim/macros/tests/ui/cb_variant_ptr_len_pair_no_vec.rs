@@ -0,0 +1,6 @@
+use topshim_macros::cb_variant;
+
+// A (pointer, length) pair must name the Vec it is converted into.
+cb_variant!(BufferCb, buffer_cb -> BufferCallbacks::Buffer, (*const u8, usize), i32);
+
+fn main() {}
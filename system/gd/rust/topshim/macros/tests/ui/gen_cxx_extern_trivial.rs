@@ -0,0 +1,34 @@
+use topshim_macros::gen_cxx_extern_trivial;
+
+mod bindings {
+    pub mod root {
+        pub mod some {
+            pub mod ns {
+                #[repr(C)]
+                pub struct sample_t {
+                    pub value: u32,
+                }
+            }
+        }
+    }
+}
+
+mod some_module {
+    #[repr(C)]
+    pub struct SampleType {
+        pub value: u32,
+    }
+}
+
+#[gen_cxx_extern_trivial]
+type BindingsSampleType = bindings::root::some::ns::sample_t;
+
+#[gen_cxx_extern_trivial(cxx = "some::ns::other_sample_t")]
+type ExplicitSampleType = some_module::SampleType;
+
+fn assert_trivial<T: cxx::ExternType<Kind = cxx::kind::Trivial>>() {}
+
+fn main() {
+    assert_trivial::<BindingsSampleType>();
+    assert_trivial::<ExplicitSampleType>();
+}
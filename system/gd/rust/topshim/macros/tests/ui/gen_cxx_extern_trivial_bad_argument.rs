@@ -0,0 +1,13 @@
+use topshim_macros::gen_cxx_extern_trivial;
+
+mod some_module {
+    #[repr(C)]
+    pub struct SampleType {
+        pub value: u32,
+    }
+}
+
+#[gen_cxx_extern_trivial(name = "some::ns::sample_t")]
+type SampleType = some_module::SampleType;
+
+fn main() {}
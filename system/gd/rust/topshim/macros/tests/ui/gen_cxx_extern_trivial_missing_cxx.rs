@@ -0,0 +1,14 @@
+use topshim_macros::gen_cxx_extern_trivial;
+
+mod some_module {
+    #[repr(C)]
+    pub struct SampleType {
+        pub value: u32,
+    }
+}
+
+// Types outside of bindings:: must name their C++ type.
+#[gen_cxx_extern_trivial]
+type SampleType = some_module::SampleType;
+
+fn main() {}
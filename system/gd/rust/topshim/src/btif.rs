@@ -170,6 +170,11 @@ pub enum BtPropertyType {
     //  BT_PROPERTY_REMOTE_ASHA_TRUNCATED_HISYNCID,
     //  BT_PROPERTY_REMOTE_MODEL_NUM,
     RemoteAddrType = 0x18,
+    // Unimplemented:
+    //  BT_PROPERTY_REMOTE_SECURE_CONNECTIONS_SUPPORTED,
+    //  BT_PROPERTY_REMOTE_MAX_SESSION_KEY_SIZE,
+    //  BT_PROPERTY_LPP_OFFLOAD_FEATURES,
+    RemoteEir = 0x1C,
 
     Unknown = 0xFE,
     RemoteDeviceTimestamp = 0xFF,
@@ -609,6 +614,7 @@ pub enum BluetoothProperty {
     Appearance(u16),
     VendorProductInfo(BtVendorProductInfo),
     RemoteAddrType(BtAddrType),
+    RemoteEir(Vec<u8>),
     RemoteDeviceTimestamp(),
 
     Unknown(),
@@ -652,10 +658,59 @@ impl BluetoothProperty {
             BluetoothProperty::VendorProductInfo(_) => BtPropertyType::VendorProductInfo,
             BluetoothProperty::RemoteDeviceTimestamp() => BtPropertyType::RemoteDeviceTimestamp,
             BluetoothProperty::RemoteAddrType(_) => BtPropertyType::RemoteAddrType,
+            BluetoothProperty::RemoteEir(_) => BtPropertyType::RemoteEir,
             BluetoothProperty::Unknown() => BtPropertyType::Unknown,
         }
     }
 
+    /// Returns the value of this property serialized as it is laid out in `bt_property_t`.
+    ///
+    /// This is used to send properties over IPC, see [`BluetoothProperty::from_raw_value`].
+    pub fn to_raw_value(&self) -> Vec<u8> {
+        let (data, _): (Box<[u8]>, bindings::bt_property_t) = self.clone().into();
+        data.into_vec()
+    }
+
+    /// Builds a property from its type and a value serialized by
+    /// [`BluetoothProperty::to_raw_value`].
+    ///
+    /// Returns `BluetoothProperty::Unknown` if the value doesn't have a valid size for the given
+    /// type.
+    pub fn from_raw_value(prop_type: BtPropertyType, mut value: Vec<u8>) -> BluetoothProperty {
+        // Fixed-size values must have the exact size of their type, variable-size ones at least
+        // the size of their fixed part.
+        let (len, is_fixed_size) = match prop_type {
+            BtPropertyType::ServiceRecord => {
+                (mem::size_of::<bindings::bt_service_record_t>(), false)
+            }
+            BtPropertyType::ClassOfDevice | BtPropertyType::AdapterDiscoverableTimeout => {
+                (mem::size_of::<u32>(), true)
+            }
+            BtPropertyType::TypeOfDevice => (mem::size_of::<BtDeviceType>(), true),
+            BtPropertyType::RemoteRssi => (mem::size_of::<i8>(), true),
+            BtPropertyType::RemoteVersionInfo => (mem::size_of::<BtRemoteVersion>(), true),
+            BtPropertyType::LocalLeFeatures => (mem::size_of::<BtLocalLeFeatures>(), true),
+            BtPropertyType::LocalIoCaps | BtPropertyType::LocalIoCapsBle => {
+                (mem::size_of::<BtIoCap>(), true)
+            }
+            BtPropertyType::RemoteIsCoordinatedSetMember => (mem::size_of::<bool>(), true),
+            BtPropertyType::Appearance => (mem::size_of::<u16>(), true),
+            BtPropertyType::VendorProductInfo => (mem::size_of::<BtVendorProductInfo>(), true),
+            BtPropertyType::RemoteAddrType => (mem::size_of::<BtAddrType>(), true),
+            _ => (0, false),
+        };
+        if value.len() < len || (is_fixed_size && value.len() != len) {
+            return BluetoothProperty::Unknown();
+        }
+
+        bindings::bt_property_t {
+            type_: prop_type.into(),
+            len: value.len() as i32,
+            val: value.as_mut_ptr() as *mut std::os::raw::c_void,
+        }
+        .into()
+    }
+
     fn get_len(&self) -> usize {
         match &*self {
             BluetoothProperty::BdName(name) => cmp::min(PROPERTY_NAME_MAX, name.len() + 1),
@@ -682,6 +737,7 @@ impl BluetoothProperty {
             BluetoothProperty::Appearance(_) => mem::size_of::<u16>(),
             BluetoothProperty::VendorProductInfo(_) => mem::size_of::<BtVendorProductInfo>(),
             BluetoothProperty::RemoteAddrType(_) => mem::size_of::<BtAddrType>(),
+            BluetoothProperty::RemoteEir(eir) => eir.len(),
 
             // TODO(abps) - Figure out sizes for these
             BluetoothProperty::DynamicAudioBuffer() => 0,
@@ -792,6 +848,9 @@ impl BluetoothProperty {
                     &BtAddrType::to_u32(addr_type).unwrap_or_default().to_ne_bytes(),
                 );
             }
+            BluetoothProperty::RemoteEir(eir) => {
+                data.copy_from_slice(eir);
+            }
 
             BluetoothProperty::DynamicAudioBuffer() => (),
             BluetoothProperty::RemoteDeviceTimestamp() => (),
@@ -871,6 +930,7 @@ impl From<bindings::bt_property_t> for BluetoothProperty {
             BtPropertyType::RemoteAddrType => BluetoothProperty::RemoteAddrType(
                 BtAddrType::from_u32(u32_from_bytes(slice)).unwrap_or(BtAddrType::Unknown),
             ),
+            BtPropertyType::RemoteEir => BluetoothProperty::RemoteEir(slice.to_vec()),
             // TODO(abps) - Figure out if these values should actually have contents
             BtPropertyType::DynamicAudioBuffer => BluetoothProperty::DynamicAudioBuffer(),
             BtPropertyType::RemoteDeviceTimestamp => BluetoothProperty::RemoteDeviceTimestamp(),
@@ -1546,6 +1606,39 @@ mod tests {
         assert_eq!(expected, vec);
     }
 
+    #[derive(Debug, PartialEq)]
+    enum TestBufferCallbacks {
+        Buffer(i32, Vec<u8>, u8),
+    }
+
+    struct TestBufferCallbacksDispatcher {
+        dispatch: Box<dyn Fn(TestBufferCallbacks) + Send>,
+    }
+
+    type TestBufferCb = Arc<Mutex<TestBufferCallbacksDispatcher>>;
+
+    cb_variant!(TestBufferCb, test_buffer_cb -> TestBufferCallbacks::Buffer,
+    i32, (usize, *const u8) -> Vec<u8>, u8);
+
+    #[test]
+    fn test_cb_variant_ptr_len_pair() {
+        let received = Arc::new(Mutex::new(vec![]));
+        let received_clone = received.clone();
+        get_dispatchers().lock().unwrap().set::<TestBufferCb>(Arc::new(Mutex::new(
+            TestBufferCallbacksDispatcher {
+                dispatch: Box::new(move |cb| received_clone.lock().unwrap().push(cb)),
+            },
+        )));
+
+        let buffer: [u8; 4] = [1, 2, 3, 4];
+        test_buffer_cb(7, buffer.len(), buffer.as_ptr(), 9);
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![TestBufferCallbacks::Buffer(7, vec![1, 2, 3, 4], 9)]
+        );
+    }
+
     #[test]
     fn test_property_with_string_conversions() {
         {
@@ -1588,6 +1681,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_property_raw_value_conversions() {
+        let uuids = vec![Uuid::from([1; 16]), Uuid::from([2; 16])];
+        let prop = BluetoothProperty::Uuids(uuids.clone());
+        let converted = BluetoothProperty::from_raw_value(prop.get_type(), prop.to_raw_value());
+        assert!(match converted {
+            BluetoothProperty::Uuids(converted_uuids) => converted_uuids == uuids,
+            _ => false,
+        });
+
+        let prop = BluetoothProperty::ClassOfDevice(0x2540);
+        let converted = BluetoothProperty::from_raw_value(prop.get_type(), prop.to_raw_value());
+        assert!(matches!(converted, BluetoothProperty::ClassOfDevice(0x2540)));
+
+        let eir = vec![0x04, 0x09, 0x61, 0x62, 0x63, 0x00];
+        let prop = BluetoothProperty::RemoteEir(eir.clone());
+        let converted = BluetoothProperty::from_raw_value(prop.get_type(), prop.to_raw_value());
+        assert!(match converted {
+            BluetoothProperty::RemoteEir(converted_eir) => converted_eir == eir,
+            _ => false,
+        });
+
+        // A value that is too short for its type is not converted.
+        let converted =
+            BluetoothProperty::from_raw_value(BtPropertyType::VendorProductInfo, vec![0; 1]);
+        assert!(matches!(converted, BluetoothProperty::Unknown()));
+
+        // Neither is one that is too long for its fixed-size type.
+        for prop_type in [
+            BtPropertyType::ClassOfDevice,
+            BtPropertyType::TypeOfDevice,
+            BtPropertyType::AdapterDiscoverableTimeout,
+            BtPropertyType::Appearance,
+            BtPropertyType::RemoteRssi,
+            BtPropertyType::VendorProductInfo,
+        ] {
+            let converted = BluetoothProperty::from_raw_value(prop_type, vec![0; 64]);
+            assert!(matches!(converted, BluetoothProperty::Unknown()));
+        }
+    }
+
     #[test]
     fn test_display_address() {
         assert_eq!(
@@ -1,3 +1,5 @@
+use crate::btif::{BtAddrType, RawAddress};
+
 #[cxx::bridge(namespace = bluetooth::topshim::rust)]
 mod ffi {
     unsafe extern "C++" {
@@ -13,8 +15,24 @@ mod ffi {
 
         fn GetControllerInterface() -> UniquePtr<ControllerIntf>;
         fn read_local_addr(self: &ControllerIntf) -> RawAddress;
+        fn read_le_addr(self: &ControllerIntf, addr_type: &mut u8) -> RawAddress;
         fn get_ble_supported_states(self: &ControllerIntf) -> u64;
         fn get_ble_local_supported_features(self: &ControllerIntf) -> u64;
+        fn supports_br_edr(self: &ControllerIntf) -> bool;
+        fn supports_ble(self: &ControllerIntf) -> bool;
+        fn get_hci_version(self: &ControllerIntf) -> u8;
+        fn get_hci_revision(self: &ControllerIntf) -> u16;
+        fn get_lmp_version(self: &ControllerIntf) -> u8;
+        fn get_lmp_subversion(self: &ControllerIntf) -> u16;
+        fn get_manufacturer_name(self: &ControllerIntf) -> u16;
+        fn write_le_host_support(self: &ControllerIntf, enabled: bool);
+        fn write_inquiry_scan_params(
+            self: &ControllerIntf,
+            interval: u16,
+            window: u16,
+            scan_type: u8,
+        );
+        fn write_page_scan_params(self: &ControllerIntf, interval: u16, window: u16, scan_type: u8);
     }
 }
 
@@ -34,6 +52,14 @@ impl Controller {
         self.internal.read_local_addr().address
     }
 
+    /// Reads the address currently used by LE initiating, scanning and advertising, which is a
+    /// random (private) address if LE privacy is enabled.
+    pub fn read_le_addr(&self) -> (RawAddress, BtAddrType) {
+        let mut addr_type: u8 = 0;
+        let addr = self.internal.read_le_addr(&mut addr_type);
+        (addr, BtAddrType::from(addr_type as u32))
+    }
+
     pub fn get_ble_supported_states(&mut self) -> u64 {
         self.internal.get_ble_supported_states()
     }
@@ -41,4 +67,55 @@ impl Controller {
     pub fn get_ble_local_supported_features(&mut self) -> u64 {
         self.internal.get_ble_local_supported_features()
     }
+
+    /// Whether the controller supports BR/EDR, i.e. isn't an LE only controller.
+    pub fn supports_br_edr(&self) -> bool {
+        self.internal.supports_br_edr()
+    }
+
+    pub fn supports_ble(&self) -> bool {
+        self.internal.supports_ble()
+    }
+
+    /// The HCI version from the controller's HCI Read Local Version Information response.
+    pub fn get_hci_version(&self) -> u8 {
+        self.internal.get_hci_version()
+    }
+
+    pub fn get_hci_revision(&self) -> u16 {
+        self.internal.get_hci_revision()
+    }
+
+    pub fn get_lmp_version(&self) -> u8 {
+        self.internal.get_lmp_version()
+    }
+
+    pub fn get_lmp_subversion(&self) -> u16 {
+        self.internal.get_lmp_subversion()
+    }
+
+    /// The Bluetooth SIG company identifier of the controller's manufacturer.
+    pub fn get_manufacturer_name(&self) -> u16 {
+        self.internal.get_manufacturer_name()
+    }
+
+    /// Enables or disables LE on the host side with the HCI Write LE Host Support command. A
+    /// failure is only logged.
+    pub fn write_le_host_support(&self, enabled: bool) {
+        self.internal.write_le_host_support(enabled);
+    }
+
+    /// Writes the inquiry scan activity and type with the HCI Write Inquiry Scan Activity and
+    /// Write Inquiry Scan Type commands, and keeps them for later discoverable mode changes. A
+    /// failure is only logged.
+    pub fn write_inquiry_scan_params(&self, interval: u16, window: u16, scan_type: u8) {
+        self.internal.write_inquiry_scan_params(interval, window, scan_type);
+    }
+
+    /// Writes the page scan activity and type with the HCI Write Page Scan Activity and Write
+    /// Page Scan Type commands, and keeps them for later connectable mode changes. A failure is
+    /// only logged.
+    pub fn write_page_scan_params(&self, interval: u16, window: u16, scan_type: u8) {
+        self.internal.write_page_scan_params(interval, window, scan_type);
+    }
 }
@@ -18,6 +18,7 @@ pub mod btif;
 
 /// Helper module for the topshim facade.
 pub mod controller;
+pub mod link;
 pub mod metrics;
 pub mod profiles;
 pub mod syslog;
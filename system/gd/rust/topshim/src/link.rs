@@ -0,0 +1,205 @@
+//! Link level HCI commands for the ACL connection of a remote device.
+
+use crate::btif::{BtAddrType, BtHciErrorCode, RawAddress};
+use crate::topstack::get_dispatchers;
+
+use std::sync::{Arc, Mutex};
+use topshim_macros::cb_variant;
+
+#[cxx::bridge(namespace = bluetooth::topshim::rust)]
+mod ffi {
+    unsafe extern "C++" {
+        include!("types/raw_address.h");
+        #[namespace = ""]
+        type RawAddress = crate::btif::RawAddress;
+    }
+
+    unsafe extern "C++" {
+        include!("link/link_shim.h");
+
+        type LinkIntf;
+
+        fn GetLinkInterface() -> UniquePtr<LinkIntf>;
+        fn read_error_counters(self: &LinkIntf, addr: RawAddress);
+        fn reset_failed_contact_counter(self: &LinkIntf, addr: RawAddress);
+        fn set_power_mode(
+            self: &LinkIntf,
+            addr: RawAddress,
+            mode: u8,
+            max_interval: u16,
+            min_interval: u16,
+            attempt: u16,
+            timeout: u16,
+        );
+        fn read_power_mode(self: &LinkIntf, addr: RawAddress);
+        fn add_to_accept_list(self: &LinkIntf, addr: RawAddress, addr_type: u8);
+        fn remove_from_accept_list(self: &LinkIntf, addr: RawAddress, addr_type: u8);
+        fn force_disconnect(self: &LinkIntf, addr: RawAddress, reason: u8);
+
+        type L2capEchoIntf;
+
+        fn GetL2capEchoInterface() -> UniquePtr<L2capEchoIntf>;
+        fn ping(self: &L2capEchoIntf, addr: RawAddress, data_len: u16);
+    }
+
+    extern "Rust" {
+        fn link_error_counters_read_callback(
+            addr: RawAddress,
+            failed_contact_counter_status: u8,
+            failed_contact_counter: u16,
+            link_quality_status: u8,
+            link_quality: u8,
+        );
+        fn link_failed_contact_counter_reset_callback(addr: RawAddress, status: u8);
+        fn link_power_mode_changed_callback(addr: RawAddress, status: u8, mode: u8, interval: u16);
+        fn link_power_mode_read_callback(addr: RawAddress, status: u8, mode: u8);
+        fn link_accept_list_add_callback(addr: RawAddress, status: u8);
+        fn link_l2cap_echo_callback(addr: RawAddress, status: u8, data_len: u16);
+    }
+}
+
+#[derive(Debug)]
+pub enum LinkCallbacks {
+    /// Address, Failed Contact Counter status and value, Link Quality status and value.
+    ErrorCountersRead(RawAddress, BtHciErrorCode, u16, BtHciErrorCode, u8),
+    /// Address, status.
+    FailedContactCounterReset(RawAddress, BtHciErrorCode),
+    /// Address, status, HCI power mode, interval in baseband slots.
+    PowerModeChanged(RawAddress, BtHciErrorCode, u8, u16),
+    /// Address, status, HCI power mode.
+    PowerModeRead(RawAddress, BtHciErrorCode, u8),
+    /// Address, status.
+    AcceptListAdd(RawAddress, BtHciErrorCode),
+}
+
+pub struct LinkCallbacksDispatcher {
+    pub dispatch: Box<dyn Fn(LinkCallbacks) + Send>,
+}
+
+type LinkCb = Arc<Mutex<LinkCallbacksDispatcher>>;
+
+cb_variant!(LinkCb,
+            link_error_counters_read_callback -> LinkCallbacks::ErrorCountersRead,
+            RawAddress, u8, u16, u8, u8);
+
+cb_variant!(LinkCb,
+            link_failed_contact_counter_reset_callback -> LinkCallbacks::FailedContactCounterReset,
+            RawAddress, u8);
+
+cb_variant!(LinkCb,
+            link_power_mode_changed_callback -> LinkCallbacks::PowerModeChanged,
+            RawAddress, u8, u8, u16);
+
+cb_variant!(LinkCb,
+            link_power_mode_read_callback -> LinkCallbacks::PowerModeRead,
+            RawAddress, u8, u8);
+
+cb_variant!(LinkCb,
+            link_accept_list_add_callback -> LinkCallbacks::AcceptListAdd,
+            RawAddress, u8);
+
+pub struct Link {
+    internal: cxx::UniquePtr<ffi::LinkIntf>,
+}
+
+unsafe impl Send for Link {}
+
+impl Link {
+    pub fn new(callbacks: LinkCallbacksDispatcher) -> Link {
+        if get_dispatchers().lock().unwrap().set::<LinkCb>(Arc::new(Mutex::new(callbacks))) {
+            panic!("Tried to set dispatcher for Link callbacks while it already exists");
+        }
+
+        Link { internal: ffi::GetLinkInterface() }
+    }
+
+    /// Reads the Failed Contact Counter and the Link Quality of the connection to |addr|. The
+    /// result is reported with |LinkCallbacks::ErrorCountersRead|.
+    pub fn read_error_counters(&self, addr: RawAddress) {
+        self.internal.read_error_counters(addr);
+    }
+
+    /// Resets the Failed Contact Counter of the connection to |addr|. The result is reported with
+    /// |LinkCallbacks::FailedContactCounterReset|.
+    pub fn reset_failed_contact_counter(&self, addr: RawAddress) {
+        self.internal.reset_failed_contact_counter(addr);
+    }
+
+    /// Puts the connection to |addr| into the HCI power |mode|. The sniff parameters are in
+    /// baseband slots and ignored for the other modes. The result is reported with
+    /// |LinkCallbacks::PowerModeChanged|.
+    pub fn set_power_mode(
+        &self,
+        addr: RawAddress,
+        mode: u8,
+        max_interval: u16,
+        min_interval: u16,
+        attempt: u16,
+        timeout: u16,
+    ) {
+        self.internal.set_power_mode(addr, mode, max_interval, min_interval, attempt, timeout);
+    }
+
+    /// Reads the power mode of the connection to |addr|. The result is reported with
+    /// |LinkCallbacks::PowerModeRead|.
+    pub fn read_power_mode(&self, addr: RawAddress) {
+        self.internal.read_power_mode(addr);
+    }
+
+    /// Adds |addr| to the LE filter accept list so that the controller connects to it in the
+    /// background. The result is reported with |LinkCallbacks::AcceptListAdd|.
+    pub fn add_to_accept_list(&self, addr: RawAddress, addr_type: BtAddrType) {
+        self.internal.add_to_accept_list(addr, addr_type as u8);
+    }
+
+    /// Removes |addr| from the LE filter accept list.
+    pub fn remove_from_accept_list(&self, addr: RawAddress, addr_type: BtAddrType) {
+        self.internal.remove_from_accept_list(addr, addr_type as u8);
+    }
+
+    /// Disconnects the connection to |addr| with the HCI |reason|. The disconnection is reported
+    /// through the ACL state callback with |reason| rather than the local host termination, which
+    /// lets tests simulate a link loss of their choice.
+    pub fn force_disconnect(&self, addr: RawAddress, reason: BtHciErrorCode) {
+        self.internal.force_disconnect(addr, reason);
+    }
+}
+
+#[derive(Debug)]
+pub enum L2capEchoCallbacks {
+    /// Address, status, length of the echoed payload.
+    Response(RawAddress, BtHciErrorCode, u16),
+}
+
+pub struct L2capEchoCallbacksDispatcher {
+    pub dispatch: Box<dyn Fn(L2capEchoCallbacks) + Send>,
+}
+
+type L2capEchoCb = Arc<Mutex<L2capEchoCallbacksDispatcher>>;
+
+cb_variant!(L2capEchoCb,
+            link_l2cap_echo_callback -> L2capEchoCallbacks::Response,
+            RawAddress, u8, u16);
+
+pub struct L2capEcho {
+    internal: cxx::UniquePtr<ffi::L2capEchoIntf>,
+}
+
+unsafe impl Send for L2capEcho {}
+
+impl L2capEcho {
+    pub fn new(callbacks: L2capEchoCallbacksDispatcher) -> L2capEcho {
+        if get_dispatchers().lock().unwrap().set::<L2capEchoCb>(Arc::new(Mutex::new(callbacks))) {
+            panic!("Tried to set dispatcher for L2capEcho callbacks while it already exists");
+        }
+
+        L2capEcho { internal: ffi::GetL2capEchoInterface() }
+    }
+
+    /// Sends an L2CAP echo request with |data_len| bytes of payload to |addr|. The result is
+    /// reported with |L2capEchoCallbacks::Response|. A status other than success means the peer
+    /// rejected the request, did not answer in time, or there was no link to send it on.
+    pub fn ping(&self, addr: RawAddress, data_len: u16) {
+        self.internal.ping(addr, data_len);
+    }
+}
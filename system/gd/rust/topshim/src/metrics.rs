@@ -1,7 +1,16 @@
 use crate::btif::{
     BtAclState, BtBondState, BtConnectionDirection, BtDeviceType, BtHciErrorCode, BtState,
-    BtStatus, BtTransport, RawAddress,
+    BtStatus, BtTransport, DisplayAddress, RawAddress,
 };
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Number of events kept in |RECENT_EVENTS|.
+const MAX_RECENT_EVENTS: usize = 100;
+
+/// The latest metrics events, oldest first. The metrics backend isn't observable from the stack,
+/// so these are kept around to let developers check which events fired and in which order.
+static RECENT_EVENTS: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
 
 #[cxx::bridge(namespace = bluetooth::topshim::rust)]
 mod ffi {
@@ -52,11 +61,33 @@ mod ffi {
     }
 }
 
+fn push_event(events: &mut VecDeque<String>, event: String) {
+    if events.len() >= MAX_RECENT_EVENTS {
+        events.pop_front();
+    }
+    events.push_back(event);
+}
+
+fn record(event: String) {
+    push_event(&mut RECENT_EVENTS.lock().unwrap(), event);
+}
+
+/// Returns the most recent metrics events, oldest first.
+pub fn recent_events() -> Vec<String> {
+    RECENT_EVENTS.lock().unwrap().iter().cloned().collect()
+}
+
 pub fn adapter_state_changed(state: BtState) {
+    record(format!("adapter_state_changed: state={:?}", state));
     ffi::adapter_state_changed(state as u32);
 }
 
 pub fn bond_create_attempt(addr: RawAddress, device_type: BtDeviceType) {
+    record(format!(
+        "bond_create_attempt: addr={}, device_type={:?}",
+        DisplayAddress(&addr),
+        device_type
+    ));
     ffi::bond_create_attempt(addr, device_type as u32);
 }
 
@@ -67,6 +98,15 @@ pub fn bond_state_changed(
     bond_state: BtBondState,
     fail_reason: i32,
 ) {
+    record(format!(
+        "bond_state_changed: addr={}, device_type={:?}, status={:?}, bond_state={:?}, \
+         fail_reason={}",
+        DisplayAddress(&addr),
+        device_type,
+        status,
+        bond_state,
+        fail_reason
+    ));
     ffi::bond_state_changed(
         addr,
         device_type as u32,
@@ -86,6 +126,12 @@ pub fn device_info_report(
     product_id: u16,
     version: u16,
 ) {
+    record(format!(
+        "device_info_report: addr={}, device_type={:?}, class_of_device={:#08x}",
+        DisplayAddress(&addr),
+        device_type,
+        class_of_device
+    ));
     ffi::device_info_report(
         addr,
         device_type as u32,
@@ -104,10 +150,22 @@ pub fn profile_connection_state_changed(
     status: BtStatus,
     state: u32,
 ) {
+    record(format!(
+        "profile_connection_state_changed: addr={}, profile={}, status={:?}, state={}",
+        DisplayAddress(&addr),
+        profile,
+        status,
+        state
+    ));
     ffi::profile_connection_state_changed(addr, profile, status as u32, state);
 }
 
 pub fn acl_connect_attempt(addr: RawAddress, acl_state: BtAclState) {
+    record(format!(
+        "acl_connect_attempt: addr={}, acl_state={:?}",
+        DisplayAddress(&addr),
+        acl_state
+    ));
     ffi::acl_connect_attempt(addr, acl_state as u32);
 }
 
@@ -119,6 +177,16 @@ pub fn acl_connection_state_changed(
     direction: BtConnectionDirection,
     hci_reason: BtHciErrorCode,
 ) {
+    record(format!(
+        "acl_connection_state_changed: addr={}, transport={:?}, status={:?}, acl_state={:?}, \
+         direction={:?}, hci_reason={:#04x}",
+        DisplayAddress(&addr),
+        transport,
+        status,
+        acl_state,
+        direction,
+        hci_reason
+    ));
     ffi::acl_connection_state_changed(
         addr,
         transport as u32,
@@ -130,5 +198,23 @@ pub fn acl_connection_state_changed(
 }
 
 pub fn suspend_complete_state(state: u32) {
+    record(format!("suspend_complete_state: state={}", state));
     ffi::suspend_complete_state(state);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_push_event_drops_oldest() {
+        let mut events = VecDeque::new();
+        for i in 0..MAX_RECENT_EVENTS + 2 {
+            push_event(&mut events, i.to_string());
+        }
+
+        assert_eq!(events.len(), MAX_RECENT_EVENTS);
+        assert_eq!(events.front().unwrap(), "2");
+        assert_eq!(events.back().unwrap(), &(MAX_RECENT_EVENTS + 1).to_string());
+    }
+}
@@ -109,6 +109,12 @@ pub mod ffi {
 
         fn read_phy(self: Pin<&mut GattClientIntf>, client_if: i32, bt_addr: RawAddress) -> i32;
 
+        fn read_remote_phy_capabilities(
+            self: Pin<&mut GattClientIntf>,
+            client_if: i32,
+            bt_addr: RawAddress,
+        ) -> i32;
+
         type GattServerIntf;
 
         unsafe fn GetGattServerProfile(btif: *const u8) -> UniquePtr<GattServerIntf>;
@@ -124,6 +130,14 @@ pub mod ffi {
         // Generated by cb_variant! below.
         fn read_phy_callback(client_if: i32, addr: RawAddress, tx_phy: u8, rx_phy: u8, status: u8);
 
+        fn read_remote_phy_capabilities_callback(
+            client_if: i32,
+            addr: RawAddress,
+            le_2m: bool,
+            le_coded: bool,
+            status: u8,
+        );
+
         fn server_read_phy_callback(
             server_if: i32,
             addr: RawAddress,
@@ -555,6 +569,7 @@ pub enum AdvertisingStatus {
     AlreadyStarted = 0x3,
     InternalError = 0x4,
     FeatureUnsupported = 0x5,
+    Timeout = 0x6,
 }
 
 impl From<u8> for AdvertisingStatus {
@@ -587,6 +602,7 @@ pub enum GattClientCallbacks {
     ConnUpdated(i32, u16, u16, u16, GattStatus),
     ServiceChanged(i32),
     ReadPhy(i32, RawAddress, u8, u8, GattStatus),
+    ReadRemotePhyCapabilities(i32, RawAddress, bool, bool, GattStatus),
 }
 
 #[derive(Debug)]
@@ -751,6 +767,11 @@ cb_variant!(
     read_phy_callback -> GattClientCallbacks::ReadPhy,
     i32, RawAddress, u8, u8, u8 -> GattStatus);
 
+cb_variant!(
+    GattClientCb,
+    read_remote_phy_capabilities_callback -> GattClientCallbacks::ReadRemotePhyCapabilities,
+    i32, RawAddress, bool, bool, u8 -> GattStatus);
+
 cb_variant!(
     GattServerCb,
     gs_register_server_cb -> GattServerCallbacks::RegisterServer,
@@ -912,13 +933,8 @@ cb_variant!(
 cb_variant!(
     GDScannerCb,
     gdscan_on_scan_result -> GattScannerCallbacks::OnScanResult,
-    u16, u8, *const RawAddress, u8, u8, u8, i8, i8, u16, *const u8, usize -> _, {
+    u16, u8, *const RawAddress, u8, u8, u8, i8, i8, u16, (*const u8, usize) -> Vec<u8>, {
         let _2 = unsafe { *_2 };
-
-        // Convert the vec! at the end. Since this cb is being called via cxx
-        // ffi, we do the vector separation at the cxx layer. The usize is consumed during
-        // conversion.
-        let _9 : Vec<u8> = ptr_to_vec(_9, _10);
     }
 );
 
@@ -930,10 +946,7 @@ cb_variant!(
 cb_variant!(
     GDScannerCb,
     gdscan_on_batch_scan_reports -> GattScannerCallbacks::OnBatchScanReports,
-    i32, i32, i32, i32, *const u8, usize -> _, {
-        // Write the vector to the output and consume the usize in the input.
-        let _4 : Vec<u8> = ptr_to_vec(_4, _5);
-    }
+    i32, i32, i32, i32, (*const u8, usize) -> Vec<u8>
 );
 
 cb_variant!(GDScannerCb, gdscan_on_batch_scan_threshold_crossed -> GattScannerCallbacks::OnBatchScanThresholdCrossed, i32);
@@ -1016,9 +1029,7 @@ u8, u16, u8, u8, *const RawAddress, u8, u16, {
 });
 cb_variant!(GDScannerInbandCb,
 gdscan_sync_report_callback -> GattScannerInbandCallbacks::SyncReportCallback,
-u16, i8, i8, u8, *const u8, usize -> _, {
-    let _4 = ptr_to_vec(_4, _5 as usize);
-});
+u16, i8, i8, u8, (*const u8, usize) -> Vec<u8>);
 cb_variant!(GDScannerInbandCb, gdscan_sync_lost_callback -> GattScannerInbandCallbacks::SyncLostCallback, u16);
 cb_variant!(GDScannerInbandCb, gdscan_sync_transfer_callback -> GattScannerInbandCallbacks::SyncTransferCallback,
 u8, *const RawAddress, {
@@ -1352,6 +1363,11 @@ impl GattClient {
         BtStatus::from_i32(mutcxxcall!(self, read_phy, client_if, *addr)).unwrap()
     }
 
+    pub fn read_remote_phy_capabilities(&mut self, client_if: i32, addr: &RawAddress) -> BtStatus {
+        BtStatus::from_i32(mutcxxcall!(self, read_remote_phy_capabilities, client_if, *addr))
+            .unwrap()
+    }
+
     pub fn test_command(&self, command: i32, params: &BtGattTestParams) -> BtStatus {
         BtStatus::from(ccall!(self, test_command, command, params))
     }
@@ -14,6 +14,14 @@ pub enum PropertyI32 {
     LeAdvMonScanInterval,
     LeAdvMonScanWindow,
 
+    // bluetooth.core.classic
+    InquiryScanInterval,
+    InquiryScanWindow,
+    InquiryScanType,
+    PageScanInterval,
+    PageScanWindow,
+    PageScanType,
+
     // bluetooth.device_id
     ProductId,
     ProductVersion,
@@ -37,6 +45,24 @@ impl Into<(CString, i32)> for PropertyI32 {
             // Adv Mon scan window = N * 0.625 ms; value of 20 = 12.5ms
             PropertyI32::LeAdvMonScanWindow => ("bluetooth.core.le.adv_mon_scan_window", 20),
 
+            // Inquiry scan interval = N * 0.625 ms; value of 2048 = 1.28s
+            PropertyI32::InquiryScanInterval => ("bluetooth.core.classic.inq_scan_interval", 2048),
+
+            // Inquiry scan window = N * 0.625 ms; value of 18 = 11.25ms
+            PropertyI32::InquiryScanWindow => ("bluetooth.core.classic.inq_scan_window", 18),
+
+            // Inquiry scan type defaults to interlaced (0x1)
+            PropertyI32::InquiryScanType => ("bluetooth.core.classic.inq_scan_type", 0x1),
+
+            // Page scan interval = N * 0.625 ms; value of 1024 = 640ms
+            PropertyI32::PageScanInterval => ("bluetooth.core.classic.page_scan_interval", 1024),
+
+            // Page scan window = N * 0.625 ms; value of 18 = 11.25ms
+            PropertyI32::PageScanWindow => ("bluetooth.core.classic.page_scan_window", 18),
+
+            // Page scan type defaults to interlaced (0x1)
+            PropertyI32::PageScanType => ("bluetooth.core.classic.page_scan_type", 0x1),
+
             PropertyI32::ProductId => ("bluetooth.device_id.product_id", 0),
             PropertyI32::ProductVersion => ("bluetooth.device_id.product_version", 0),
 
@@ -2,7 +2,7 @@
 //!
 //! Helpers for dealing with the stack on top of the Bluetooth interface.
 
-use std::any::{Any, TypeId};
+use std::any::{type_name, Any, TypeId};
 use std::collections::HashMap;
 use std::sync::{Arc, LazyLock, Mutex};
 use tokio::runtime::{Builder, Runtime};
@@ -84,6 +84,17 @@ impl DispatchContainer {
         None
     }
 
+    /// Find registered dispatcher for enum specialization, panicking if it is missing.
+    ///
+    /// This is used by the generated callbacks, which have no way to recover
+    /// from a dispatcher that was never registered.
+    pub fn get_or_panic<T: 'static + Clone + Send + Sync>(&self) -> T {
+        match self.get::<T>() {
+            Some(value) => value,
+            None => panic!("Couldn't find dispatcher type: {}", type_name::<T>()),
+        }
+    }
+
     /// Set dispatcher for an enum specialization.
     ///
     /// # Arguments
@@ -103,3 +114,29 @@ impl DispatchContainer {
 pub fn get_dispatchers() -> Arc<Mutex<DispatchContainer>> {
     CB_DISPATCHER.clone()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, PartialEq)]
+    struct TestDispatcher(u32);
+
+    #[test]
+    fn test_get_or_panic_registered() {
+        let mut container = DispatchContainer { instances: HashMap::new() };
+        assert!(!container.set::<TestDispatcher>(TestDispatcher(1)));
+        assert_eq!(container.get_or_panic::<TestDispatcher>(), TestDispatcher(1));
+
+        // Replacing the dispatcher returns the latest one.
+        assert!(container.set::<TestDispatcher>(TestDispatcher(2)));
+        assert_eq!(container.get_or_panic::<TestDispatcher>(), TestDispatcher(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Couldn't find dispatcher type")]
+    fn test_get_or_panic_missing() {
+        let container = DispatchContainer { instances: HashMap::new() };
+        container.get_or_panic::<TestDispatcher>();
+    }
+}
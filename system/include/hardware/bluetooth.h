@@ -426,6 +426,24 @@ typedef enum {
    */
   BT_PROPERTY_LPP_OFFLOAD_FEATURES,
 
+  /**
+   * Description - Raw Extended Inquiry Response received from the remote device
+   * Access mode - GET.
+   * Data Type - uint8_t array.
+   */
+  BT_PROPERTY_REMOTE_EIR,
+
+#ifdef TARGET_FLOSS
+  /**
+   * Description - Local IO capability used for BR/EDR Secure Simple Pairing.
+   *               Not persisted by the stack; the stack default is used after
+   *               restart.
+   * Access mode - SET.
+   * Data type   - bt_io_cap_t.
+   */
+  BT_PROPERTY_LOCAL_IO_CAPS = BT_PROPERTY_RESERVED_0E,
+#endif
+
   BT_PROPERTY_REMOTE_DEVICE_TIMESTAMP = 0xFF,
 } bt_property_type_t;
 
@@ -73,6 +73,8 @@ tBTM_PM_MCB* btm_pm_get_power_manager_from_address(const RawAddress& bda) {
 
 tBTM_PM_RCB pm_reg_db; /* per application/module */
 
+tBTM_PM_STATUS_CBACK* pm_mode_change_observer = nullptr;
+
 uint8_t pm_pend_id = 0; /* the id pf the module, which has a pending PM cmd */
 
 constexpr char kBtmLogTag[] = "ACL";
@@ -149,6 +151,8 @@ tBTM_STATUS BTM_PmRegister(uint8_t mask, uint8_t* p_pm_id, tBTM_PM_STATUS_CBACK*
   return tBTM_STATUS::BTM_NO_RESOURCES;
 }
 
+void BTM_PmSetModeChangeObserver(tBTM_PM_STATUS_CBACK* p_cb) { pm_mode_change_observer = p_cb; }
+
 void BTM_PM_OnConnected(uint16_t handle, const RawAddress& remote_bda) {
   if (pm_mode_db.find(handle) != pm_mode_db.end()) {
     log::error("Overwriting power mode db entry handle:{} peer:{}", handle, remote_bda);
@@ -731,6 +735,9 @@ void btm_pm_proc_mode_change(tHCI_STATUS hci_status, uint16_t hci_handle, tHCI_M
   if (pm_reg_db.mask & BTM_PM_REG_SET) {
     (*pm_reg_db.cback)(p_cb->bda_, mode, interval, hci_status);
   }
+  if (pm_mode_change_observer != nullptr) {
+    (*pm_mode_change_observer)(p_cb->bda_, mode, interval, hci_status);
+  }
   /*check if sco disconnect  is waiting for the mode change */
   btm_sco_disc_chk_pend_for_modechange(hci_handle);
 
@@ -33,6 +33,7 @@
 #include <bitset>
 #include <cstdint>
 #include <list>
+#include <map>
 #include <memory>
 #include <type_traits>
 #include <vector>
@@ -2644,6 +2645,25 @@ static void btm_ble_inquiry_timer_timeout(void* /* data */) { btm_ble_stop_inqui
 
 static void btm_ble_observer_timer_timeout(void* /* data */) { btm_ble_stop_observe(); }
 
+/* Reads of the peer LE features requested with BTM_BleReadRemoteFeatures,
+ * by HCI handle */
+static std::map<uint16_t, tBTM_BLE_READ_REMOTE_FEATURES_CBACK*> btm_ble_remote_features_reads;
+
+void BTM_BleReadRemoteFeatures(uint16_t handle, tBTM_BLE_READ_REMOTE_FEATURES_CBACK* p_cb) {
+  btm_ble_remote_features_reads[handle] = p_cb;
+  btsnd_hcic_ble_read_remote_feat(handle);
+}
+
+static tBTM_BLE_READ_REMOTE_FEATURES_CBACK* btm_ble_take_remote_features_read(uint16_t handle) {
+  auto it = btm_ble_remote_features_reads.find(handle);
+  if (it == btm_ble_remote_features_reads.end()) {
+    return nullptr;
+  }
+  tBTM_BLE_READ_REMOTE_FEATURES_CBACK* p_cb = it->second;
+  btm_ble_remote_features_reads.erase(it);
+  return p_cb;
+}
+
 /*******************************************************************************
  *
  * Function         btm_ble_read_remote_features_complete
@@ -2656,8 +2676,9 @@ static void btm_ble_observer_timer_timeout(void* /* data */) { btm_ble_stop_obse
  *
  ******************************************************************************/
 void btm_ble_read_remote_features_complete(uint8_t* p, uint8_t length) {
-  uint16_t handle;
+  uint16_t handle = 0;
   uint8_t status;
+  tBTM_BLE_READ_REMOTE_FEATURES_CBACK* p_cb = nullptr;
 
   if (length < 3) {
     goto err_out;
@@ -2666,11 +2687,15 @@ void btm_ble_read_remote_features_complete(uint8_t* p, uint8_t length) {
   STREAM_TO_UINT8(status, p);
   STREAM_TO_UINT16(handle, p);
   handle = handle & 0x0FFF;  // only 12 bits meaningful
+  p_cb = btm_ble_take_remote_features_read(handle);
 
   if (status != HCI_SUCCESS) {
     if (status != HCI_ERR_UNSUPPORTED_REM_FEATURE) {
       log::error("Failed to read remote features status:{}",
                  hci_error_code_text(static_cast<tHCI_STATUS>(status)));
+      if (p_cb != nullptr) {
+        (*p_cb)(handle, static_cast<tHCI_STATUS>(status));
+      }
       return;
     }
     log::warn("Remote does not support reading remote feature");
@@ -2685,16 +2710,28 @@ void btm_ble_read_remote_features_complete(uint8_t* p, uint8_t length) {
 
     if (!acl_set_peer_le_features_from_handle(handle, p)) {
       log::error("Unable to find existing connection after read remote features");
+      if (p_cb != nullptr) {
+        (*p_cb)(handle, HCI_ERR_NO_CONNECTION);
+      }
       return;
     }
   }
 
+  // A read requested on an established link does not continue the link setup.
+  if (p_cb != nullptr) {
+    (*p_cb)(handle, static_cast<tHCI_STATUS>(status));
+    return;
+  }
+
   btsnd_hcic_rmt_ver_req(handle);
 
   return;
 
 err_out:
   log::error("Bogus event packet, too short");
+  if (p_cb != nullptr) {
+    (*p_cb)(handle, HCI_ERR_UNSPECIFIED);
+  }
 }
 
 /*******************************************************************************
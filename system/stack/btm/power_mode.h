@@ -214,6 +214,19 @@ inline void set_le_scan_mode_to_ctrl_state(uint32_t duty_cycle, tBTM_CONTRL_STAT
  ******************************************************************************/
 tBTM_STATUS BTM_PmRegister(uint8_t mask, uint8_t* p_pm_id, tBTM_PM_STATUS_CBACK* p_cb);
 
+/*******************************************************************************
+ *
+ * Function         BTM_PmSetModeChangeObserver
+ *
+ * Description      Set a callback that observes the mode changes of all links,
+ *                  in addition to the module registered with BTM_PmRegister.
+ *                  Pass nullptr to stop observing.
+ *
+ * Returns          void
+ *
+ ******************************************************************************/
+void BTM_PmSetModeChangeObserver(tBTM_PM_STATUS_CBACK* p_cb);
+
 // Notified by ACL that a new link is connected
 void BTM_PM_OnConnected(uint16_t handle, const RawAddress& remote_bda);
 
@@ -182,6 +182,23 @@ void BTM_BleSetPrefConnParams(const RawAddress& bd_addr, uint16_t min_conn_int,
  ******************************************************************************/
 void BTM_BleReadControllerFeatures(tBTM_BLE_CTRL_FEATURES_CBACK* p_vsc_cback);
 
+/******************************************************************************
+ *
+ * Function         BTM_BleReadRemoteFeatures
+ *
+ * Description      Reads the LE features of the peer on an established LE
+ *                  link again. The ACL connection is updated with the
+ *                  features before |p_cb| is called.
+ *
+ * Parameters:      handle : HCI handle of the LE link
+ *                  tBTM_BLE_READ_REMOTE_FEATURES_CBACK : Callback to notify
+ *                  when the features are read or the read failed
+ *
+ * Returns          void
+ *
+ ******************************************************************************/
+void BTM_BleReadRemoteFeatures(uint16_t handle, tBTM_BLE_READ_REMOTE_FEATURES_CBACK* p_cb);
+
 /*******************************************************************************
  *
  * Function         BTM_ReadDevInfo
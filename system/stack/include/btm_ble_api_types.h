@@ -517,6 +517,8 @@ typedef struct {
 
 typedef void(tBTM_BLE_CTRL_FEATURES_CBACK)(tHCI_STATUS status);
 
+typedef void(tBTM_BLE_READ_REMOTE_FEATURES_CBACK)(uint16_t handle, tHCI_STATUS status);
+
 namespace std {
 template <>
 struct formatter<tBTM_BLE_CONN_TYPE> : enum_formatter<tBTM_BLE_CONN_TYPE> {};
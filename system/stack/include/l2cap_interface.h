@@ -35,6 +35,14 @@ enum class tL2CAP_DW_RESULT : uint8_t {
   CONGESTED = 2,
 };
 
+/* Outcome of an echo request sent with L2CA_Ping() */
+enum class tL2CAP_ECHO_RESULT : uint8_t {
+  SUCCESS = 0,  /* The peer answered with an echo response */
+  REJECTED = 1, /* The peer answered with a command reject */
+  TIMEOUT = 2,  /* The peer did not answer in time */
+  NO_LINK = 3,  /* The link went down before the peer answered */
+};
+
 /*********************************
  *  Callback Functions Prototypes
  *********************************/
@@ -111,6 +119,14 @@ typedef void(tL2CA_TX_COMPLETE_CB)(uint16_t, uint16_t);
  */
 typedef void(tL2CA_ERROR_CB)(uint16_t, uint16_t);
 
+/* Echo response callback prototype. Called once for every echo request sent
+ * with L2CA_Ping, whether or not the peer answered. The parameters are:
+ *              BD Address of remote
+ *              Outcome of the echo request
+ *              Length of the data echoed back by the peer
+ */
+typedef void(tL2CA_ECHO_RSP_CB)(const RawAddress&, tL2CAP_ECHO_RESULT, uint16_t);
+
 /* Create credit based connection request callback prototype. Parameters are
  *              BD Address of remote
  *              Vector of allocated local cids to accept
@@ -654,6 +670,24 @@ public:
    ******************************************************************************/
   virtual bool L2CA_IsLinkEstablished(const RawAddress& bd_addr, tBT_TRANSPORT transport) = 0;
 
+  /*******************************************************************************
+   **
+   ** Function         L2CA_Ping
+   **
+   ** Description      Send an L2CAP echo request over an established BR/EDR
+   **                  link. Only one echo request may be outstanding per link.
+   **
+   ** Parameters:      bd_addr: Peer bluetooth device address
+   **                  data_len: Number of bytes of payload to echo
+   **                  p_cb: Called when the peer answers the echo request,
+   **                        or when it times out or the link goes down
+   **
+   ** Returns          true if the echo request was sent, false otherwise
+   **
+   ******************************************************************************/
+  virtual bool L2CA_Ping(const RawAddress& bd_addr, uint16_t data_len,
+                         tL2CA_ECHO_RSP_CB* p_cb) = 0;
+
   /*******************************************************************************
    **
    ** Function         L2CA_SubrateRequest
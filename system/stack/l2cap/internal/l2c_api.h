@@ -716,6 +716,14 @@ void L2CA_SetEcosystemBaseInterval(uint32_t base_interval);
  */
 [[nodiscard]] bool L2CA_IsLinkEstablished(const RawAddress& bd_addr, tBT_TRANSPORT transport);
 
+/**
+ * Send an L2CAP echo request to the remote device over an established BR/EDR
+ * link. |p_cb| is called once the peer answers, or once the request times out
+ * or the link goes down.
+ */
+[[nodiscard]] bool L2CA_Ping(const RawAddress& bd_addr, uint16_t data_len,
+                             tL2CA_ECHO_RSP_CB* p_cb);
+
 /*******************************************************************************
  *
  *  Function        L2CA_SetDefaultSubrate
@@ -1613,6 +1613,37 @@ bool L2CA_IsLinkEstablished(const RawAddress& bd_addr, tBT_TRANSPORT transport)
   return l2cu_find_lcb_by_bd_addr(bd_addr, transport) != nullptr;
 }
 
+/*******************************************************************************
+ *
+ * Function         L2CA_Ping
+ *
+ * Description      Send an L2CAP echo request to the peer over an established
+ *                  BR/EDR link. |p_cb| is called when the peer responds.
+ *
+ * Returns          true if the echo request was sent, false if there is no
+ *                  connected link or a request is already outstanding
+ *
+ ******************************************************************************/
+bool L2CA_Ping(const RawAddress& bd_addr, uint16_t data_len, tL2CA_ECHO_RSP_CB* p_cb) {
+  tL2C_LCB* p_lcb = l2cu_find_lcb_by_bd_addr(bd_addr, BT_TRANSPORT_BR_EDR);
+  if (p_lcb == nullptr || p_lcb->link_state != LST_CONNECTED) {
+    log::warn("No connected BR/EDR link to {}", bd_addr);
+    return false;
+  }
+
+  if (p_lcb->p_echo_rsp_cb != nullptr) {
+    log::warn("Echo request already pending to {}", bd_addr);
+    return false;
+  }
+
+  p_lcb->p_echo_rsp_cb = p_cb;
+  if (!l2cu_send_peer_echo_req(p_lcb, data_len)) {
+    p_lcb->p_echo_rsp_cb = nullptr;
+    return false;
+  }
+  return true;
+}
+
 /*******************************************************************************
 **
 ** Function         L2CA_SetMediaStreamChannel
@@ -59,6 +59,8 @@ public:
   // Methods used for both BR/EDR and BLE
   [[nodiscard]] bool L2CA_IsLinkEstablished(const RawAddress& bd_addr,
                                             tBT_TRANSPORT transport) override;
+  [[nodiscard]] bool L2CA_Ping(const RawAddress& bd_addr, uint16_t data_len,
+                               tL2CA_ECHO_RSP_CB* p_cb) override;
   [[nodiscard]] bool L2CA_SetIdleTimeoutByBdAddr(const RawAddress& bd_addr, uint16_t timeout,
                                                  tBT_TRANSPORT transport) override;
   [[nodiscard]] bool L2CA_GetRemoteChannelId(uint16_t lcid, uint16_t* rcid) override;
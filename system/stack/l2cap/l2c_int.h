@@ -64,6 +64,7 @@ constexpr uint16_t L2CAP_CREDIT_BASED_MIN_MPS = 64;
 #define L2CAP_CHNL_DISCONNECT_TIMEOUT_MS (10 * 1000)   /* 10 seconds */
 #define L2CAP_DELAY_CHECK_SM4_TIMEOUT_MS (2 * 1000)    /* 2 seconds */
 #define L2CAP_WAIT_INFO_RSP_TIMEOUT_MS (3 * 1000)      /* 3 seconds */
+#define L2CAP_ECHO_RSP_TIMEOUT_MS (5 * 1000)           /* 5 seconds */
 #define L2CAP_BLE_LINK_CONNECT_TIMEOUT_MS (30 * 1000)  /* 30 seconds */
 #define L2CAP_FCR_ACK_TIMEOUT_MS 200                   /* 200 milliseconds */
 
@@ -425,6 +426,7 @@ public:
 
   tL2C_CCB* p_pending_ccb;   /* ccb of waiting channel during link disconnect */
   alarm_t* info_resp_timer;  /* Timer entry for info resp timeout evt */
+  alarm_t* echo_rsp_timer;   /* Timer entry for echo resp timeout evt */
   RawAddress remote_bd_addr; /* The BD address of the remote */
 
 private:
@@ -441,6 +443,9 @@ public:
   uint8_t cur_echo_id;   /* Current id value for echo request */
   uint16_t idle_timeout; /* Idle timeout */
 
+  uint8_t echo_req_id;              /* Id of our outstanding echo request, if any */
+  tL2CA_ECHO_RSP_CB* p_echo_rsp_cb; /* Called when the peer answers our echo request */
+
 private:
   bool is_bonding_{false}; /* True - link active only for bonding */
 
@@ -724,6 +729,8 @@ void l2cu_send_peer_info_rsp(tL2C_LCB* p_lcb, uint8_t id, uint16_t info_type);
 void l2cu_reject_connection(tL2C_LCB* p_lcb, uint16_t remote_cid, uint8_t rem_id,
                             tL2CAP_CONN result);
 void l2cu_send_peer_info_req(tL2C_LCB* p_lcb, uint16_t info_type);
+bool l2cu_send_peer_echo_req(tL2C_LCB* p_lcb, uint16_t data_len);
+void l2cu_complete_echo_req(tL2C_LCB* p_lcb, tL2CAP_ECHO_RESULT result, uint16_t data_len);
 void l2cu_set_acl_hci_header(BT_HDR* p_buf, tL2C_CCB* p_ccb);
 void l2cu_check_channel_congestion(tL2C_CCB* p_ccb);
 void l2cu_disconnect_chnl(tL2C_CCB* p_ccb);
@@ -787,6 +794,7 @@ void l2cu_adjust_out_mps(tL2C_CCB* p_ccb);
 
 void l2c_link_timeout(tL2C_LCB* p_lcb);
 void l2c_info_resp_timer_timeout(void* data);
+void l2c_echo_rsp_timer_timeout(void* data);
 void l2c_link_check_send_pkts(tL2C_LCB* p_lcb, uint16_t local_cid, BT_HDR* p_buf);
 void l2c_link_adjust_allocation(void);
 void l2c_link_hci_conn_comp(tHCI_STATUS status, uint16_t handle, const RawAddress& p_bda);
@@ -506,6 +506,26 @@ void l2c_link_timeout(tL2C_LCB* p_lcb) {
   }
 }
 
+/*******************************************************************************
+ *
+ * Function         l2c_echo_rsp_timer_timeout
+ *
+ * Description      This function is called when an echo request times out
+ *
+ * Returns          void
+ *
+ ******************************************************************************/
+void l2c_echo_rsp_timer_timeout(void* data) {
+  tL2C_LCB* p_lcb = (tL2C_LCB*)data;
+
+  if (p_lcb->p_echo_rsp_cb == nullptr) {
+    return;
+  }
+
+  log::warn("Echo request to {} timed out", p_lcb->remote_bd_addr);
+  l2cu_complete_echo_req(p_lcb, tL2CAP_ECHO_RESULT::TIMEOUT, 0);
+}
+
 /*******************************************************************************
  *
  * Function         l2c_info_resp_timer_timeout
@@ -368,6 +368,10 @@ static void process_l2cap_cmd(tL2C_LCB* p_lcb, uint8_t* p, uint16_t pkt_len) {
             l2c_csm_execute(p_ccb, L2CEVT_L2CAP_INFO_RSP, &ci);
           }
         }
+        if (p_lcb->p_echo_rsp_cb != nullptr && id == p_lcb->echo_req_id) {
+          log::warn("Echo request rejected, reason:{}", rej_reason);
+          l2cu_complete_echo_req(p_lcb, tL2CAP_ECHO_RESULT::REJECTED, 0);
+        }
         break;
 
       case L2CAP_CMD_CONN_REQ: {
@@ -764,6 +768,15 @@ static void process_l2cap_cmd(tL2C_LCB* p_lcb, uint8_t* p, uint16_t pkt_len) {
         l2cu_send_peer_echo_rsp(p_lcb, id, p, cmd_len);
         break;
 
+      case L2CAP_CMD_ECHO_RSP: {
+        if (p_lcb->p_echo_rsp_cb == nullptr || id != p_lcb->echo_req_id) {
+          log::warn("Unexpected echo response id:{}", id);
+          break;
+        }
+        l2cu_complete_echo_req(p_lcb, tL2CAP_ECHO_RESULT::SUCCESS, cmd_len);
+        break;
+      }
+
       case L2CAP_CMD_INFO_REQ: {
         uint16_t info_type;
         if (p + 2 > p_next_cmd) {
@@ -75,6 +75,7 @@ tL2C_LCB* l2cu_allocate_lcb(const RawAddress& p_bd_addr, bool is_bonding, tBT_TR
     if (!p_lcb->in_use) {
       alarm_free(p_lcb->l2c_lcb_timer);
       alarm_free(p_lcb->info_resp_timer);
+      alarm_free(p_lcb->echo_rsp_timer);
       *p_lcb = {};
 
       p_lcb->remote_bd_addr = p_bd_addr;
@@ -85,6 +86,7 @@ tL2C_LCB* l2cu_allocate_lcb(const RawAddress& p_bd_addr, bool is_bonding, tBT_TR
       p_lcb->InvalidateHandle();
       p_lcb->l2c_lcb_timer = alarm_new("l2c_lcb.l2c_lcb_timer");
       p_lcb->info_resp_timer = alarm_new("l2c_lcb.info_resp_timer");
+      p_lcb->echo_rsp_timer = alarm_new("l2c_lcb.echo_rsp_timer");
       p_lcb->idle_timeout = l2cb.idle_timeout;
       p_lcb->signal_id = 1; /* spec does not allow '0' */
       if (is_bonding) {
@@ -158,11 +160,18 @@ void l2cu_release_lcb(tL2C_LCB* p_lcb) {
   p_lcb->in_use = false;
   p_lcb->ResetBonding();
 
+  /* Report an unanswered echo request before its timer goes away */
+  if (p_lcb->p_echo_rsp_cb != nullptr) {
+    l2cu_complete_echo_req(p_lcb, tL2CAP_ECHO_RESULT::NO_LINK, 0);
+  }
+
   /* Stop and free timers */
   alarm_free(p_lcb->l2c_lcb_timer);
   p_lcb->l2c_lcb_timer = NULL;
   alarm_free(p_lcb->info_resp_timer);
   p_lcb->info_resp_timer = NULL;
+  alarm_free(p_lcb->echo_rsp_timer);
+  p_lcb->echo_rsp_timer = NULL;
 
   if (p_lcb->transport == BT_TRANSPORT_BR_EDR) { /* Release all SCO links */
     get_btm_client_interface().sco.BTM_RemoveScoByBdaddr(p_lcb->remote_bd_addr);
@@ -1004,6 +1013,84 @@ void l2cu_send_peer_echo_rsp(tL2C_LCB* p_lcb, uint8_t signal_id, uint8_t* p_data
   l2c_link_check_send_pkts(p_lcb, 0, p_buf);
 }
 
+/*******************************************************************************
+ *
+ * Function         l2cu_send_peer_echo_req
+ *
+ * Description      Build and send an L2CAP "echo request" message carrying
+ *                  |data_len| bytes of payload to the peer.
+ *
+ * Returns          true if the request was queued, false otherwise
+ *
+ ******************************************************************************/
+bool l2cu_send_peer_echo_req(tL2C_LCB* p_lcb, uint16_t data_len) {
+  BT_HDR* p_buf;
+  uint8_t* p;
+  uint16_t maxlen;
+
+  constexpr int kHciDataPreambleSize = 4;
+  uint16_t acl_data_size = bluetooth::shim::GetController()->GetAclPacketLength();
+  uint16_t acl_packet_size =
+          bluetooth::shim::GetController()->GetAclPacketLength() + kHciDataPreambleSize;
+  /* Keep the payload within a single command buffer and ACL packet */
+  maxlen = (L2CAP_CMD_BUF_SIZE > acl_packet_size) ? acl_data_size : (uint16_t)L2CAP_CMD_BUF_SIZE;
+  maxlen -= (uint16_t)(BT_HDR_SIZE + HCI_DATA_PREAMBLE_SIZE + L2CAP_PKT_OVERHEAD +
+                       L2CAP_CMD_OVERHEAD + L2CAP_ECHO_REQ_LEN);
+
+  if (data_len > maxlen) {
+    log::warn("Echo request payload {} truncated to {}", data_len, maxlen);
+    data_len = maxlen;
+  }
+
+  /* Create an identifier for this packet */
+  p_lcb->signal_id++;
+  l2cu_adj_id(p_lcb);
+
+  p_buf = l2cu_build_header(p_lcb, (uint16_t)(L2CAP_ECHO_REQ_LEN + data_len), L2CAP_CMD_ECHO_REQ,
+                            p_lcb->signal_id);
+  if (p_buf == NULL) {
+    log::warn("L2CAP - no buffer for echo_req");
+    return false;
+  }
+
+  p = (uint8_t*)(p_buf + 1) + L2CAP_SEND_CMD_OFFSET + HCI_DATA_PREAMBLE_SIZE + L2CAP_PKT_OVERHEAD +
+      L2CAP_CMD_OVERHEAD;
+
+  for (uint16_t i = 0; i < data_len; i++) {
+    UINT8_TO_STREAM(p, (uint8_t)i);
+  }
+
+  p_lcb->echo_req_id = p_lcb->signal_id;
+  alarm_set_on_mloop(p_lcb->echo_rsp_timer, L2CAP_ECHO_RSP_TIMEOUT_MS, l2c_echo_rsp_timer_timeout,
+                     p_lcb);
+
+  l2c_link_check_send_pkts(p_lcb, 0, p_buf);
+  return true;
+}
+
+/*******************************************************************************
+ *
+ * Function         l2cu_complete_echo_req
+ *
+ * Description      Finish the outstanding echo request on the link and report
+ *                  |result| to its callback. A new echo request may be sent
+ *                  once this returns.
+ *
+ * Returns          void
+ *
+ ******************************************************************************/
+void l2cu_complete_echo_req(tL2C_LCB* p_lcb, tL2CAP_ECHO_RESULT result, uint16_t data_len) {
+  tL2CA_ECHO_RSP_CB* p_cb = p_lcb->p_echo_rsp_cb;
+
+  alarm_cancel(p_lcb->echo_rsp_timer);
+  p_lcb->p_echo_rsp_cb = nullptr;
+  p_lcb->echo_req_id = 0;
+
+  if (p_cb != nullptr) {
+    (*p_cb)(p_lcb->remote_bd_addr, result, data_len);
+  }
+}
+
 /*******************************************************************************
  *
  * Function         l2cu_send_peer_info_req
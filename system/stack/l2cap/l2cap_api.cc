@@ -229,6 +229,12 @@ void bluetooth::stack::l2cap::Impl::L2CA_SetEcosystemBaseInterval(uint32_t base_
   return ::L2CA_IsLinkEstablished(bd_addr, transport);
 }
 
+[[nodiscard]] bool bluetooth::stack::l2cap::Impl::L2CA_Ping(const RawAddress& bd_addr,
+                                                            uint16_t data_len,
+                                                            tL2CA_ECHO_RSP_CB* p_cb) {
+  return ::L2CA_Ping(bd_addr, data_len, p_cb);
+}
+
 void bluetooth::stack::l2cap::Impl::L2CA_SetDefaultSubrate(uint16_t subrate_min,
                                                            uint16_t subrate_max,
                                                            uint16_t max_latency, uint16_t cont_num,
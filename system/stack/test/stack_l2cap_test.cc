@@ -20,6 +20,9 @@
 #include <gtest/gtest.h>
 #include <sys/socket.h>
 
+#include <cstring>
+#include <vector>
+
 #include "hci/controller_interface_mock.h"
 #include "osi/include/allocator.h"
 #include "stack/btm/btm_int_types.h"
@@ -299,3 +302,85 @@ TEST_F(StackL2capTest, bt_psm_text) {
     bluetooth::log::info("{} {} ", bt_psm_text(it.first), it.second);
   }
 }
+
+namespace {
+constexpr uint16_t kAclHandle = 0x0123;
+const RawAddress kPingAddress = RawAddress({0x11, 0x22, 0x33, 0x44, 0x55, 0x66});
+
+std::vector<tL2CAP_ECHO_RESULT> echo_results;
+
+void OnEchoResponse(const RawAddress& /* bd_addr */, tL2CAP_ECHO_RESULT result,
+                    uint16_t /* data_len */) {
+  echo_results.push_back(result);
+}
+}  // namespace
+
+class StackL2capPingTest : public StackL2capTest {
+protected:
+  void SetUp() override {
+    StackL2capTest::SetUp();
+    ON_CALL(controller_interface_, GetAclPacketLength).WillByDefault(Return(1021));
+    echo_results.clear();
+
+    p_lcb_ = l2cu_allocate_lcb(kPingAddress, false, BT_TRANSPORT_BR_EDR);
+    ASSERT_NE(nullptr, p_lcb_);
+    p_lcb_->link_state = LST_CONNECTED;
+    p_lcb_->SetHandle(kAclHandle);
+  }
+
+  void TearDown() override {
+    alarm_free(p_lcb_->l2c_lcb_timer);
+    alarm_free(p_lcb_->info_resp_timer);
+    alarm_free(p_lcb_->echo_rsp_timer);
+    p_lcb_->in_use = false;
+    StackL2capTest::TearDown();
+  }
+
+  // Delivers a signalling command reject for |id| as if it came from the peer.
+  void ReceiveCommandReject(uint8_t id) {
+    const uint8_t packet[] = {
+            (uint8_t)(kAclHandle & 0xff), (uint8_t)(0x20 | (kAclHandle >> 8)),  // start packet
+            0x0a, 0x00,                                                         // HCI length
+            0x06, 0x00,                                                         // L2CAP length
+            0x01, 0x00,                                                         // signalling CID
+            L2CAP_CMD_REJECT, id, 0x02, 0x00,                                   // command header
+            0x00, 0x00,  // reason: command not understood
+    };
+    BT_HDR* p_buf = (BT_HDR*)osi_calloc(sizeof(BT_HDR) + sizeof(packet));
+    p_buf->len = sizeof(packet);
+    memcpy(p_buf->data, packet, sizeof(packet));
+    l2c_rcv_acl_data(p_buf);
+  }
+
+  tL2C_LCB* p_lcb_{nullptr};
+};
+
+TEST_F(StackL2capPingTest, L2CA_Ping__unanswered_request_times_out) {
+  ASSERT_TRUE(L2CA_Ping(kPingAddress, 4, &OnEchoResponse));
+  ASSERT_FALSE(L2CA_Ping(kPingAddress, 4, &OnEchoResponse));
+
+  // The peer never answers.
+  l2c_echo_rsp_timer_timeout(p_lcb_);
+
+  ASSERT_EQ(1UL, echo_results.size());
+  ASSERT_EQ(tL2CAP_ECHO_RESULT::TIMEOUT, echo_results[0]);
+  ASSERT_EQ(nullptr, p_lcb_->p_echo_rsp_cb);
+
+  // The link can be pinged again.
+  ASSERT_TRUE(L2CA_Ping(kPingAddress, 4, &OnEchoResponse));
+}
+
+TEST_F(StackL2capPingTest, L2CA_Ping__rejected_request_completes) {
+  ASSERT_TRUE(L2CA_Ping(kPingAddress, 4, &OnEchoResponse));
+  uint8_t echo_req_id = p_lcb_->echo_req_id;
+
+  // A reject for some other command leaves the echo request pending.
+  ReceiveCommandReject((uint8_t)(echo_req_id + 1));
+  ASSERT_TRUE(echo_results.empty());
+
+  ReceiveCommandReject(echo_req_id);
+
+  ASSERT_EQ(1UL, echo_results.size());
+  ASSERT_EQ(tL2CAP_ECHO_RESULT::REJECTED, echo_results[0]);
+  ASSERT_TRUE(L2CA_Ping(kPingAddress, 4, &OnEchoResponse));
+}
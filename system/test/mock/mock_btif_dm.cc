@@ -120,6 +120,8 @@ void btif_dm_proc_io_rsp(const RawAddress& /* bd_addr */, tBTM_IO_CAP /* io_cap
 void btif_dm_read_energy_info() { inc_func_call_count(__func__); }
 void btif_dm_remove_ble_bonding_keys(void) { inc_func_call_count(__func__); }
 void btif_dm_remove_bond(const RawAddress /* bd_addr */) { inc_func_call_count(__func__); }
+void btif_dm_set_local_io_caps(tBTM_IO_CAP /* io_cap */) { inc_func_call_count(__func__); }
+void btif_dm_set_io_cap_for_io_req(tBTM_IO_CAP* /* p_io_cap */) { inc_func_call_count(__func__); }
 void btif_dm_set_oob_for_io_req(tBTM_OOB_DATA* /* p_has_oob_data */) {
   inc_func_call_count(__func__);
 }
@@ -32,6 +32,9 @@ bool BTM_ReadPowerMode(const RawAddress& /* remote_bda */, tBTM_PM_MODE* /* p_mo
   inc_func_call_count(__func__);
   return false;
 }
+void BTM_PmSetModeChangeObserver(tBTM_PM_STATUS_CBACK* /* p_cb */) {
+  inc_func_call_count(__func__);
+}
 bool BTM_SetLinkPolicyActiveMode(const RawAddress& /* remote_bda */) {
   inc_func_call_count(__func__);
   return false;
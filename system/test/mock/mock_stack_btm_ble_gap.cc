@@ -144,6 +144,10 @@ void btm_ble_process_adv_pkt_cont_for_inquiry(
 void btm_ble_read_remote_features_complete(uint8_t* /* p */, uint8_t /* length */) {
   inc_func_call_count(__func__);
 }
+void BTM_BleReadRemoteFeatures(uint16_t /* handle */,
+                               tBTM_BLE_READ_REMOTE_FEATURES_CBACK* /* p_cb */) {
+  inc_func_call_count(__func__);
+}
 void btm_ble_read_remote_name_cmpl(bool /* status */, const RawAddress& /* bda */,
                                    uint16_t /* length */, char* /* p_name */) {
   inc_func_call_count(__func__);
@@ -71,6 +71,7 @@ struct L2CA_LECocDataWrite L2CA_LECocDataWrite;
 struct L2CA_SetChnlFlushability L2CA_SetChnlFlushability;
 struct L2CA_FlushChannel L2CA_FlushChannel;
 struct L2CA_IsLinkEstablished L2CA_IsLinkEstablished;
+struct L2CA_Ping L2CA_Ping;
 struct L2CA_SetMediaStreamChannel L2CA_SetMediaStreamChannel;
 struct L2CA_isMediaChannel L2CA_isMediaChannel;
 struct L2CA_LeCreditDefault L2CA_LeCreditDefault;
@@ -235,6 +236,10 @@ bool L2CA_IsLinkEstablished(const RawAddress& bd_addr, tBT_TRANSPORT transport)
   inc_func_call_count(__func__);
   return test::mock::stack_l2cap_api::L2CA_IsLinkEstablished(bd_addr, transport);
 }
+bool L2CA_Ping(const RawAddress& bd_addr, uint16_t data_len, tL2CA_ECHO_RSP_CB* p_cb) {
+  inc_func_call_count(__func__);
+  return test::mock::stack_l2cap_api::L2CA_Ping(bd_addr, data_len, p_cb);
+}
 void L2CA_SetMediaStreamChannel(uint16_t local_media_cid, bool status) {
   inc_func_call_count(__func__);
   return test::mock::stack_l2cap_api::L2CA_SetMediaStreamChannel(local_media_cid, status);
@@ -418,6 +418,18 @@ struct L2CA_IsLinkEstablished {
   }
 };
 extern struct L2CA_IsLinkEstablished L2CA_IsLinkEstablished;
+// Name: L2CA_Ping
+// Params: const RawAddress& bd_addr, uint16_t data_len, tL2CA_ECHO_RSP_CB* p_cb
+// Returns: bool
+struct L2CA_Ping {
+  std::function<bool(const RawAddress& bd_addr, uint16_t data_len, tL2CA_ECHO_RSP_CB* p_cb)> body{
+          [](const RawAddress& /* bd_addr */, uint16_t /* data_len */,
+             tL2CA_ECHO_RSP_CB* /* p_cb */) { return false; }};
+  bool operator()(const RawAddress& bd_addr, uint16_t data_len, tL2CA_ECHO_RSP_CB* p_cb) {
+    return body(bd_addr, data_len, p_cb);
+  }
+};
+extern struct L2CA_Ping L2CA_Ping;
 // Name: L2CA_SetMediaStreamChannel
 // Params: uint16_t handle, uint16_t channel_id, bool is_local_cid
 // Returns: void
@@ -51,6 +51,8 @@ public:
 
   // Methods used for, both BR/EDR and BLE
   MOCK_METHOD(bool, L2CA_IsLinkEstablished, (const RawAddress& bd_addr, tBT_TRANSPORT transport));
+  MOCK_METHOD(bool, L2CA_Ping,
+              (const RawAddress& bd_addr, uint16_t data_len, tL2CA_ECHO_RSP_CB* p_cb));
   MOCK_METHOD(bool, L2CA_SetIdleTimeoutByBdAddr,
               (const RawAddress& bd_addr, uint16_t timeout, tBT_TRANSPORT transport));
   MOCK_METHOD(bool, L2CA_GetRemoteChannelId, (uint16_t lcid, uint16_t* rcid));